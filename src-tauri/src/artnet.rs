@@ -0,0 +1,458 @@
+use crate::dmx::{DmxSharedState, DmxState};
+use crate::merge::{Contributions, MergeSource};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddrV4, UdpSocket};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+
+pub const ARTNET_PORT: u16 = 6454;
+const ARTNET_ID: &[u8; 8] = b"Art-Net\0";
+const OP_DMX: u16 = 0x5000;
+const PROTOCOL_VERSION: u16 = 14;
+const ART_DMX_HEADER_LEN: usize = 18;
+// Tiempo tras el cual el estándar da por perdida una fuente en el merge
+const DEFAULT_SOURCE_TIMEOUT_MS: u64 = 10_000;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ArtNetConfig {
+    target_ip: Ipv4Addr,
+    net: u8,
+    subnet: u8,
+    universe: u8,
+    enabled: bool,
+}
+
+impl ArtNetConfig {
+    pub(crate) fn new(
+        target_ip: Ipv4Addr,
+        net: u8,
+        subnet: u8,
+        universe: u8,
+    ) -> Result<Self, String> {
+        if net > 0x7F {
+            return Err("El net Art-Net debe estar entre 0 y 127".to_string());
+        }
+        if subnet > 0x0F {
+            return Err("La subnet Art-Net debe estar entre 0 y 15".to_string());
+        }
+        if universe > 0x0F {
+            return Err("El universo Art-Net debe estar entre 0 y 15".to_string());
+        }
+
+        Ok(Self {
+            target_ip,
+            net,
+            subnet,
+            universe,
+            enabled: true,
+        })
+    }
+}
+
+pub(crate) fn export_config(shared: &DmxSharedState) -> Option<ArtNetConfig> {
+    shared
+        .artnet
+        .lock()
+        .ok()
+        .and_then(|artnet| artnet.as_ref().map(|sender| sender.config.clone()))
+}
+
+// Se vuelve a validar porque la configuración puede venir de un archivo de proyecto
+pub(crate) fn restore_config(shared: &DmxSharedState, config: ArtNetConfig) -> Result<(), String> {
+    let mut restored =
+        ArtNetConfig::new(config.target_ip, config.net, config.subnet, config.universe)?;
+    restored.enabled = config.enabled;
+
+    let mut artnet = shared
+        .artnet
+        .lock()
+        .map_err(|e| format!("No se pudo bloquear la salida Art-Net: {e}"))?;
+    *artnet = Some(ArtNetSender::new(restored));
+    Ok(())
+}
+
+pub(crate) struct ArtNetSender {
+    config: ArtNetConfig,
+    socket: Option<UdpSocket>,
+    sequence: u8,
+    // Se reutiliza en cada frame
+    packet: Vec<u8>,
+}
+
+impl ArtNetSender {
+    fn new(config: ArtNetConfig) -> Self {
+        Self {
+            config,
+            socket: None,
+            sequence: 0,
+            packet: Vec::new(),
+        }
+    }
+
+    pub(crate) fn send(&mut self, channels: &[u8]) -> Result<(), String> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        if self.socket.is_none() {
+            let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+                .map_err(|e| format!("No se pudo abrir el socket Art-Net: {e}"))?;
+            socket
+                .set_broadcast(true)
+                .map_err(|e| format!("No se pudo habilitar broadcast Art-Net: {e}"))?;
+            self.socket = Some(socket);
+        }
+
+        // La secuencia 0 desactiva el reordenado en el nodo, así que se salta.
+        self.sequence = self.sequence.wrapping_add(1).max(1);
+        build_art_dmx(
+            &mut self.packet,
+            self.sequence,
+            self.config.net,
+            self.config.subnet,
+            self.config.universe,
+            channels,
+        );
+
+        let target = SocketAddrV4::new(self.config.target_ip, ARTNET_PORT);
+        if let Some(socket) = &self.socket {
+            if let Err(err) = socket.send_to(&self.packet, target) {
+                self.socket = None;
+                return Err(format!("Error al enviar ArtDMX a {target}: {err}"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn build_art_dmx(
+    packet: &mut Vec<u8>,
+    sequence: u8,
+    net: u8,
+    subnet: u8,
+    universe: u8,
+    channels: &[u8],
+) {
+    // El largo debe ser par y estar entre 2 y 512
+    let mut length = channels.len().clamp(2, 512);
+    length += length % 2;
+
+    packet.clear();
+    packet.extend_from_slice(ARTNET_ID);
+    packet.extend_from_slice(&OP_DMX.to_le_bytes());
+    packet.extend_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+    packet.push(sequence);
+    packet.push(0); // Physical
+    packet.push((subnet << 4) | universe);
+    packet.push(net);
+    packet.extend_from_slice(&(length as u16).to_be_bytes());
+    packet.extend_from_slice(&channels[..channels.len().min(length)]);
+    packet.resize(ART_DMX_HEADER_LEN + length, 0);
+}
+
+#[tauri::command]
+pub fn artnet_configure(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    target_ip: String,
+    net: u8,
+    subnet: u8,
+    artnet_universe: u8,
+) -> Result<(), String> {
+    let target_ip = target_ip
+        .parse::<Ipv4Addr>()
+        .map_err(|e| format!("IP de destino Art-Net inválida ({target_ip}): {e}"))?;
+    let config = ArtNetConfig::new(target_ip, net, subnet, artnet_universe)?;
+
+    state.with_universe(app_handle, universe, |shared| {
+        let mut artnet = shared
+            .artnet
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la salida Art-Net: {e}"))?;
+        info!(
+            "Universo {universe} enviando Art-Net a {} ({}:{}:{})",
+            config.target_ip, config.net, config.subnet, config.universe
+        );
+        *artnet = Some(ArtNetSender::new(config));
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn artnet_set_enabled(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    enabled: bool,
+) -> Result<(), String> {
+    state.with_universe(app_handle, universe, |shared| {
+        let mut artnet = shared
+            .artnet
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la salida Art-Net: {e}"))?;
+        let sender = artnet
+            .as_mut()
+            .ok_or_else(|| format!("El universo {universe} no tiene salida Art-Net configurada"))?;
+        sender.config.enabled = enabled;
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn artnet_get_config(
+    state: State<'_, DmxState>,
+    universe: u16,
+) -> Result<Option<ArtNetConfig>, String> {
+    state.read_universe(universe, |shared| {
+        shared
+            .artnet
+            .lock()
+            .map(|artnet| artnet.as_ref().map(|sender| sender.config.clone()))
+            .map_err(|e| format!("No se pudo bloquear la salida Art-Net: {e}"))
+    })
+}
+
+pub(crate) struct ArtNetInput {
+    net: u8,
+    subnet: u8,
+    universe: u8,
+    timeout: Duration,
+    sources: HashMap<IpAddr, ArtNetSource>,
+}
+
+struct ArtNetSource {
+    levels: Vec<u8>,
+    last_seen: Instant,
+}
+
+#[derive(Serialize)]
+pub struct ArtNetSourceInfo {
+    address: String,
+    channels: usize,
+    idle_ms: u64,
+}
+
+impl ArtNetInput {
+    fn accepts(&self, net: u8, sub_uni: u8) -> bool {
+        self.net == net && (self.subnet << 4 | self.universe) == sub_uni
+    }
+
+    fn update_source(&mut self, address: IpAddr, data: &[u8]) {
+        let source = self.sources.entry(address).or_insert_with(|| {
+            info!("Nueva fuente Art-Net en el merge: {address}");
+            ArtNetSource {
+                levels: Vec::new(),
+                last_seen: Instant::now(),
+            }
+        });
+        source.levels.clear();
+        source.levels.extend_from_slice(data);
+        source.last_seen = Instant::now();
+    }
+
+    // Aportes al merge de cada fuente externa que siga viva
+    pub(crate) fn contribute(&mut self, contributions: &mut Contributions) {
+        let timeout = self.timeout;
+        self.sources.retain(|address, source| {
+            let alive = source.last_seen.elapsed() < timeout;
+            if !alive {
+                info!("Fuente Art-Net {address} expirada, se quita del merge");
+            }
+            alive
+        });
+
+        for (address, source) in &self.sources {
+            contributions
+                .next(MergeSource::ArtNet(*address))
+                .extend(source.levels.iter().map(|value| Some(*value)));
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ArtNetInputState {
+    receiver: Mutex<Option<(Sender<()>, thread::JoinHandle<()>)>>,
+}
+
+// Devuelve (net, sub-uni, datos) si el paquete es un ArtDMX válido
+pub(crate) fn parse_art_dmx(packet: &[u8]) -> Option<(u8, u8, &[u8])> {
+    if packet.len() < ART_DMX_HEADER_LEN || &packet[..8] != ARTNET_ID {
+        return None;
+    }
+    if u16::from_le_bytes([packet[8], packet[9]]) != OP_DMX {
+        return None;
+    }
+
+    let sub_uni = packet[14];
+    let net = packet[15];
+    let length = u16::from_be_bytes([packet[16], packet[17]]) as usize;
+    let end = (ART_DMX_HEADER_LEN + length).min(packet.len());
+    Some((net, sub_uni, &packet[ART_DMX_HEADER_LEN..end]))
+}
+
+fn run_receiver(app_handle: AppHandle, socket: UdpSocket, rx: Receiver<()>) {
+    let mut buffer = [0u8; 1024];
+
+    while rx.try_recv().is_err() {
+        let (len, from) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            // El timeout de lectura permite revisar la señal de parada
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                continue
+            }
+            Err(err) => {
+                error!("Error al recibir Art-Net: {err}");
+                thread::sleep(Duration::from_millis(200));
+                continue;
+            }
+        };
+
+        let Some((net, sub_uni, data)) = parse_art_dmx(&buffer[..len]) else {
+            continue;
+        };
+
+        let dmx = app_handle.state::<DmxState>();
+        dmx.for_each_universe(|_, shared| {
+            if let Ok(mut input) = shared.artnet_input.lock() {
+                if let Some(input) = input.as_mut().filter(|i| i.accepts(net, sub_uni)) {
+                    input.update_source(from.ip(), data);
+                }
+            }
+        });
+    }
+
+    info!("Receptor Art-Net detenido");
+}
+
+#[tauri::command]
+pub fn artnet_listen_start(
+    app_handle: AppHandle,
+    input: State<'_, ArtNetInputState>,
+    bind_ip: Option<String>,
+) -> Result<(), String> {
+    let mut receiver = input
+        .receiver
+        .lock()
+        .map_err(|e| format!("No se pudo bloquear el receptor Art-Net: {e}"))?;
+    if receiver.is_some() {
+        return Ok(());
+    }
+
+    let bind_ip = match bind_ip {
+        Some(ip) => ip
+            .parse::<Ipv4Addr>()
+            .map_err(|e| format!("IP de escucha Art-Net inválida ({ip}): {e}"))?,
+        None => Ipv4Addr::UNSPECIFIED,
+    };
+
+    let socket = UdpSocket::bind((bind_ip, ARTNET_PORT))
+        .map_err(|e| format!("No se pudo escuchar Art-Net en {bind_ip}:{ARTNET_PORT}: {e}"))?;
+    socket
+        .set_read_timeout(Some(Duration::from_millis(200)))
+        .map_err(|e| format!("No se pudo configurar el socket Art-Net: {e}"))?;
+
+    info!("Escuchando Art-Net en {bind_ip}:{ARTNET_PORT}");
+    let (tx, rx) = mpsc::channel();
+    let handle = thread::spawn(move || run_receiver(app_handle, socket, rx));
+    *receiver = Some((tx, handle));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn artnet_listen_stop(input: State<'_, ArtNetInputState>) -> Result<(), String> {
+    let receiver = input
+        .receiver
+        .lock()
+        .map_err(|e| format!("No se pudo bloquear el receptor Art-Net: {e}"))?
+        .take();
+
+    if let Some((tx, handle)) = receiver {
+        let _ = tx.send(());
+        if handle.join().is_err() {
+            error!("El hilo receptor Art-Net terminó con pánico");
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn artnet_input_map(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    net: u8,
+    subnet: u8,
+    artnet_universe: u8,
+    timeout_ms: Option<u64>,
+) -> Result<(), String> {
+    // Reutiliza la validación de direccionamiento de la salida
+    let address = ArtNetConfig::new(Ipv4Addr::UNSPECIFIED, net, subnet, artnet_universe)?;
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_SOURCE_TIMEOUT_MS));
+
+    state.with_universe(app_handle, universe, |shared| {
+        let mut input = shared
+            .artnet_input
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la entrada Art-Net: {e}"))?;
+        *input = Some(ArtNetInput {
+            net: address.net,
+            subnet: address.subnet,
+            universe: address.universe,
+            timeout,
+            sources: HashMap::new(),
+        });
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn artnet_input_unmap(state: State<'_, DmxState>, universe: u16) -> Result<(), String> {
+    state.read_universe(universe, |shared| {
+        let mut input = shared
+            .artnet_input
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la entrada Art-Net: {e}"))?;
+        *input = None;
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn artnet_input_sources(
+    state: State<'_, DmxState>,
+    universe: u16,
+) -> Result<Vec<ArtNetSourceInfo>, String> {
+    state.read_universe(universe, |shared| {
+        let input = shared
+            .artnet_input
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la entrada Art-Net: {e}"))?;
+
+        Ok(input
+            .as_ref()
+            .map(|input| {
+                input
+                    .sources
+                    .iter()
+                    .map(|(address, source)| ArtNetSourceInfo {
+                        address: address.to_string(),
+                        channels: source.levels.len(),
+                        idle_ms: source.last_seen.elapsed().as_millis() as u64,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    })
+}
@@ -0,0 +1,992 @@
+use crate::beat_detect::BeatDetector;
+use crate::latency::Calibration;
+use crate::looper::Looper;
+use crate::ltc::LtcDecoder;
+use crate::meters::Meters;
+use crate::plugin_host;
+use crate::sampler::Sampler;
+use crate::settings::SettingsState;
+use crate::spectrum::Spectrum;
+use crate::storage;
+use crate::transport::TransportClock;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{
+    BufferSize, SampleFormat, SampleRate, StreamConfig, SupportedBufferSize,
+    SupportedStreamConfigRange,
+};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub const AUDIO_ERROR_EVENT: &str = "audio://error";
+const AUDIO_CONFIG_FILE: &str = "audio.json";
+// Entrada que se guarda como mucho mientras el callback de salida no la consume; lo más viejo
+// se descarta para que la latencia no crezca si los relojes de los dispositivos derivan
+const MAX_INPUT_BACKLOG: usize = 8192;
+// Frecuencias que se ofrecen si el dispositivo las admite
+const COMMON_SAMPLE_RATES: [u32; 6] = [44_100, 48_000, 88_200, 96_000, 176_400, 192_000];
+const MAX_MONITOR_GAIN: f32 = 4.0;
+// Lo que tarda el monitoreo en pasar de silencio a ganancia 1, para que mutear no haga click
+// (ms)
+const MONITOR_RAMP_MS: u32 = 10;
+
+// Los dispositivos con que arranca el motor, desde la configuración general
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AudioStartup {
+    #[serde(default)]
+    host: Option<String>,
+    #[serde(default)]
+    input_device: Option<String>,
+    #[serde(default)]
+    output_device: Option<String>,
+    #[serde(default)]
+    buffer_size: Option<u32>,
+}
+
+impl AudioStartup {
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if self.buffer_size == Some(0) {
+            return Err("El tamaño de bloque tiene que ser mayor que 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AudioConfig {
+    // Backend de audio (p. ej. "JACK" o "ALSA"); sin nombre se usa el predeterminado del
+    // sistema
+    #[serde(default)]
+    host: Option<String>,
+    // Sin nombre se usa el dispositivo predeterminado
+    #[serde(default)]
+    input_device: Option<String>,
+    #[serde(default)]
+    output_device: Option<String>,
+    // Sin frecuencia se usa la predeterminada de la salida
+    #[serde(default)]
+    sample_rate: Option<u32>,
+    // Muestras por bloque del driver; sin valor deja el del driver. Más chico es menos
+    // latencia y más carga
+    #[serde(default)]
+    buffer_size: Option<u32>,
+    // Canal de la entrada (desde 0) que usa el motor
+    #[serde(default)]
+    input_channel: u16,
+    #[serde(default)]
+    routing: AudioRouting,
+    // Latencia de ida y vuelta medida (muestras); alinea lo que graba el looper
+    #[serde(default)]
+    latency_samples: Option<u32>,
+    #[serde(default)]
+    monitor: InputMonitor,
+}
+
+impl AudioConfig {
+    fn startup(&self) -> AudioStartup {
+        AudioStartup {
+            host: self.host.clone(),
+            input_device: self.input_device.clone(),
+            output_device: self.output_device.clone(),
+            buffer_size: self.buffer_size,
+        }
+    }
+}
+
+// Canales de la salida (desde 0) de cada bus
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct AudioRouting {
+    // Primer canal del par del click (p. ej. 2 para las salidas 3/4); sin canal el click va a
+    // la mezcla principal
+    #[serde(default)]
+    click_output: Option<u16>,
+}
+
+// Escucha de la entrada por la mezcla principal, para tocar sin consola. Arranca muteada:
+// con un micrófono frente a los parlantes se acopla.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct InputMonitor {
+    gain: f32,
+    muted: bool,
+}
+
+impl Default for InputMonitor {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            muted: true,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Monitor {
+    settings: InputMonitor,
+    // Ganancia que se aplica ahora; va en rampa hacia la pedida
+    level: f32,
+}
+
+impl Monitor {
+    fn process(&mut self, block: &mut AudioBlock) {
+        let target = if self.settings.muted {
+            0.0
+        } else {
+            self.settings.gain
+        };
+        if target == 0.0 && self.level == 0.0 {
+            return;
+        }
+        let step = 1.0 / (MONITOR_RAMP_MS * block.sample_rate / 1000).max(1) as f32;
+        for (input, out) in block.input.iter().zip(block.main.iter_mut()) {
+            self.level += (target - self.level).clamp(-step, step);
+            out[0] += input * self.level;
+            out[1] += input * self.level;
+        }
+    }
+}
+
+#[derive(Clone, Default, Serialize)]
+pub struct AudioStatus {
+    running: bool,
+    host: Option<String>,
+    input_device: Option<String>,
+    output_device: Option<String>,
+    sample_rate: Option<u32>,
+    buffer_size: Option<u32>,
+    input_channels: u16,
+    output_channels: u16,
+}
+
+// Compartido entre los callbacks de entrada y de salida y los comandos
+#[derive(Default)]
+pub(crate) struct AudioShared {
+    // Muestras del canal de entrada que todavía no usó el callback de salida
+    input: Mutex<VecDeque<f32>>,
+    looper: Mutex<Looper>,
+    transport: Mutex<TransportClock>,
+    beats: Mutex<BeatDetector>,
+    meters: Mutex<Meters>,
+    spectrum: Mutex<Spectrum>,
+    sampler: Mutex<Sampler>,
+    routing: Mutex<AudioRouting>,
+    monitor: Mutex<Monitor>,
+    calibration: Mutex<Calibration>,
+    ltc: Mutex<LtcDecoder>,
+}
+
+impl AudioShared {
+    pub(crate) fn lock_looper(&self) -> Result<std::sync::MutexGuard<'_, Looper>, String> {
+        self.looper
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el looper: {e}"))
+    }
+
+    pub(crate) fn lock_transport(
+        &self,
+    ) -> Result<std::sync::MutexGuard<'_, TransportClock>, String> {
+        self.transport
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el transporte: {e}"))
+    }
+
+    pub(crate) fn lock_beats(&self) -> Result<std::sync::MutexGuard<'_, BeatDetector>, String> {
+        self.beats
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la detección de golpes: {e}"))
+    }
+
+    pub(crate) fn lock_meters(&self) -> Result<std::sync::MutexGuard<'_, Meters>, String> {
+        self.meters
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear los medidores de audio: {e}"))
+    }
+
+    pub(crate) fn lock_spectrum(&self) -> Result<std::sync::MutexGuard<'_, Spectrum>, String> {
+        self.spectrum
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el análisis de espectro: {e}"))
+    }
+
+    pub(crate) fn lock_sampler(&self) -> Result<std::sync::MutexGuard<'_, Sampler>, String> {
+        self.sampler
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el sampler: {e}"))
+    }
+
+    pub(crate) fn lock_calibration(
+        &self,
+    ) -> Result<std::sync::MutexGuard<'_, Calibration>, String> {
+        self.calibration
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la calibración de latencia: {e}"))
+    }
+
+    pub(crate) fn lock_ltc(&self) -> Result<std::sync::MutexGuard<'_, LtcDecoder>, String> {
+        self.ltc
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el lector de LTC: {e}"))
+    }
+
+    fn set_routing(&self, routing: AudioRouting) -> Result<(), String> {
+        *self
+            .routing
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el ruteo de audio: {e}"))? = routing;
+        Ok(())
+    }
+
+    fn set_monitor(&self, settings: InputMonitor) -> Result<(), String> {
+        self.monitor
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el monitoreo de la entrada: {e}"))?
+            .settings = settings;
+        Ok(())
+    }
+
+    fn click_output(&self) -> Option<usize> {
+        self.routing
+            .lock()
+            .ok()
+            .and_then(|routing| routing.click_output)
+            .map(usize::from)
+    }
+
+    fn process(&self, block: &mut AudioBlock, click_routed: bool) {
+        if let Ok(mut beats) = self.beats.lock() {
+            beats.process(block);
+        }
+        if let Ok(mut spectrum) = self.spectrum.lock() {
+            spectrum.process(block);
+        }
+        if let Ok(mut transport) = self.transport.lock() {
+            transport.process(block);
+        }
+        if let Ok(mut looper) = self.looper.lock() {
+            looper.process(block);
+        }
+        if let Ok(mut sampler) = self.sampler.lock() {
+            sampler.process(block);
+        }
+        if let Ok(mut monitor) = self.monitor.lock() {
+            monitor.process(block);
+        }
+        if !click_routed {
+            for (out, click) in block.main.iter_mut().zip(block.click.iter()) {
+                out[0] += click[0];
+                out[1] += click[1];
+            }
+        }
+        if let Ok(mut calibration) = self.calibration.lock() {
+            calibration.process(block);
+        }
+        if let Ok(mut meters) = self.meters.lock() {
+            meters.add_main(block.main);
+        }
+    }
+}
+
+// Lo que procesa cada callback de salida
+pub(crate) struct AudioBlock<'a> {
+    pub(crate) sample_rate: u32,
+    // Entrada mono, del canal elegido, alineada con la salida
+    pub(crate) input: &'a [f32],
+    // Mezcla estéreo; va a los dos primeros canales de la salida
+    pub(crate) main: &'a mut [[f32; 2]],
+    // Bus del click del metrónomo
+    pub(crate) click: &'a mut [[f32; 2]],
+    // Primer tiempo del transporte que cae en el bloque; lo marca el transporte
+    pub(crate) beat: Option<BeatMark>,
+    // Tempo del transporte, también parado; lo pone el transporte
+    pub(crate) bpm: Option<f64>,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct BeatMark {
+    // Muestra del bloque donde empieza el tiempo
+    pub(crate) offset: usize,
+    // Primer tiempo del compás
+    pub(crate) bar: bool,
+}
+
+// Vive dentro del callback de salida; los buffers se reutilizan para no reservar memoria en
+// el hilo de audio
+struct Renderer {
+    shared: Arc<AudioShared>,
+    sample_rate: u32,
+    channels: usize,
+    input: Vec<f32>,
+    main: Vec<[f32; 2]>,
+    click: Vec<[f32; 2]>,
+}
+
+impl Renderer {
+    fn render(&mut self, data: &mut [f32]) {
+        let frames = data.len() / self.channels;
+        self.input.clear();
+        if let Ok(mut queue) = self.shared.input.lock() {
+            let available = queue.len().min(frames);
+            self.input.extend(queue.drain(..available));
+        }
+        self.input.resize(frames, 0.0);
+        self.main.clear();
+        self.main.resize(frames, [0.0; 2]);
+        self.click.clear();
+        self.click.resize(frames, [0.0; 2]);
+        // Un canal que la salida no tiene deja el click en la mezcla
+        let click_output = self
+            .shared
+            .click_output()
+            .filter(|channel| *channel < self.channels);
+        self.shared.process(
+            &mut AudioBlock {
+                sample_rate: self.sample_rate,
+                input: &self.input,
+                main: &mut self.main,
+                click: &mut self.click,
+                beat: None,
+                bpm: None,
+            },
+            click_output.is_some(),
+        );
+
+        for ((frame, [left, right]), click) in data
+            .chunks_mut(self.channels)
+            .zip(&self.main)
+            .zip(&self.click)
+        {
+            match frame {
+                [mono] => *mono = (left + right) * 0.5,
+                [l, r, rest @ ..] => {
+                    *l = *left;
+                    *r = *right;
+                    rest.fill(0.0);
+                }
+                [] => {}
+            }
+            if let Some(channel) = click_output {
+                // En el último canal de la salida el click sale en mono
+                match &mut frame[channel..] {
+                    [mono] => *mono += (click[0] + click[1]) * 0.5,
+                    [l, r, ..] => {
+                        *l += click[0];
+                        *r += click[1];
+                    }
+                    [] => {}
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct AudioDeviceInfo {
+    name: String,
+    default: bool,
+    sample_rates: Vec<u32>,
+    channels: Vec<u16>,
+    // Muestras por bloque que admite; sin rango el driver no lo informa
+    buffer_sizes: Option<BufferRange>,
+}
+
+#[derive(Clone, Copy, Serialize)]
+pub struct BufferRange {
+    min: u32,
+    max: u32,
+}
+
+#[derive(Clone, Serialize)]
+pub struct AudioHostInfo {
+    name: String,
+    default: bool,
+}
+
+#[derive(Clone, Serialize)]
+pub struct AudioDevices {
+    inputs: Vec<AudioDeviceInfo>,
+    outputs: Vec<AudioDeviceInfo>,
+}
+
+struct Engine {
+    stop: Sender<()>,
+    handle: thread::JoinHandle<()>,
+    status: AudioStatus,
+}
+
+// Solo aparecen los backends compilados: JACK hace falta activarlo con la feature `jack`
+fn find_host(name: Option<&str>) -> Result<cpal::Host, String> {
+    let Some(name) = name else {
+        return Ok(cpal::default_host());
+    };
+    let id = cpal::available_hosts()
+        .into_iter()
+        .find(|id| id.name().eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("El backend de audio \"{name}\" no está disponible"))?;
+    // JACK, por ejemplo, falla si el servidor no está corriendo
+    cpal::host_from_id(id).map_err(|e| format!("No se pudo abrir el backend de audio {name}: {e}"))
+}
+
+fn device_name(device: &cpal::Device) -> String {
+    device.name().unwrap_or_else(|_| "(sin nombre)".to_string())
+}
+
+fn find_device(
+    host: &cpal::Host,
+    name: Option<&str>,
+    input: bool,
+) -> Result<Option<cpal::Device>, String> {
+    let Some(name) = name else {
+        return Ok(if input {
+            host.default_input_device()
+        } else {
+            host.default_output_device()
+        });
+    };
+    let devices = if input {
+        host.input_devices()
+    } else {
+        host.output_devices()
+    }
+    .map_err(|e| format!("No se pudieron listar los dispositivos de audio: {e}"))?;
+    let device = devices
+        .into_iter()
+        .find(|device| device.name().is_ok_and(|device| device == name))
+        .ok_or_else(|| format!("El dispositivo de audio \"{name}\" no existe"))?;
+    Ok(Some(device))
+}
+
+fn buffer_fits(range: &SupportedStreamConfigRange, frames: u32) -> bool {
+    match *range.buffer_size() {
+        SupportedBufferSize::Range { min, max } => (min..=max).contains(&frames),
+        // Sin rango informado se prueba igual; si no lo admite falla al abrir el stream
+        SupportedBufferSize::Unknown => true,
+    }
+}
+
+// El motor trabaja en f32; de los formatos que lo admiten a esa frecuencia (y con ese tamaño
+// de bloque), el de más canales
+fn stream_config(
+    ranges: impl Iterator<Item = SupportedStreamConfigRange>,
+    sample_rate: u32,
+    buffer_size: Option<u32>,
+    device: &str,
+) -> Result<StreamConfig, String> {
+    let ranges = ranges
+        .filter(|range| {
+            range.sample_format() == SampleFormat::F32
+                && (range.min_sample_rate().0..=range.max_sample_rate().0).contains(&sample_rate)
+        })
+        .collect::<Vec<_>>();
+    let rate_supported = !ranges.is_empty();
+    let range = ranges
+        .into_iter()
+        .filter(|range| match buffer_size {
+            Some(frames) => buffer_fits(range, frames),
+            None => true,
+        })
+        .max_by_key(|range| range.channels());
+    let Some(range) = range else {
+        return Err(match buffer_size {
+            Some(frames) if rate_supported => {
+                format!("{device} no admite bloques de {frames} muestras a {sample_rate} Hz")
+            }
+            _ => format!("{device} no admite audio f32 a {sample_rate} Hz"),
+        });
+    };
+    let mut config = range.with_sample_rate(SampleRate(sample_rate)).config();
+    if let Some(frames) = buffer_size {
+        config.buffer_size = BufferSize::Fixed(frames);
+    }
+    Ok(config)
+}
+
+// Solo cuentan los formatos f32, que son los que puede abrir el motor
+fn device_info(
+    device: &cpal::Device,
+    ranges: Vec<SupportedStreamConfigRange>,
+    default: Option<&str>,
+) -> AudioDeviceInfo {
+    let ranges = ranges
+        .into_iter()
+        .filter(|range| range.sample_format() == SampleFormat::F32)
+        .collect::<Vec<_>>();
+    let sample_rates = COMMON_SAMPLE_RATES
+        .into_iter()
+        .filter(|rate| {
+            ranges
+                .iter()
+                .any(|range| (range.min_sample_rate().0..=range.max_sample_rate().0).contains(rate))
+        })
+        .collect();
+    let channels = ranges
+        .iter()
+        .map(SupportedStreamConfigRange::channels)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let buffer_sizes = ranges
+        .iter()
+        .filter_map(|range| match *range.buffer_size() {
+            SupportedBufferSize::Range { min, max } => Some(BufferRange { min, max }),
+            SupportedBufferSize::Unknown => None,
+        })
+        .reduce(|all, range| BufferRange {
+            min: all.min.min(range.min),
+            max: all.max.max(range.max),
+        });
+    let name = device_name(device);
+    AudioDeviceInfo {
+        default: default == Some(name.as_str()),
+        name,
+        sample_rates,
+        channels,
+        buffer_sizes,
+    }
+}
+
+fn list_devices(host: &cpal::Host, input: bool) -> Result<Vec<AudioDeviceInfo>, String> {
+    let (devices, default) = if input {
+        (host.input_devices(), host.default_input_device())
+    } else {
+        (host.output_devices(), host.default_output_device())
+    };
+    let devices =
+        devices.map_err(|e| format!("No se pudieron listar los dispositivos de audio: {e}"))?;
+    let default = default.map(|device| device_name(&device));
+
+    Ok(devices
+        .into_iter()
+        .map(|device| {
+            let ranges = if input {
+                device.supported_input_configs().map(Iterator::collect)
+            } else {
+                device.supported_output_configs().map(Iterator::collect)
+            };
+            // Un dispositivo ocupado por otra app puede no informar sus formatos
+            device_info(&device, ranges.unwrap_or_default(), default.as_deref())
+        })
+        .collect())
+}
+
+fn error_callback(app_handle: &AppHandle) -> impl FnMut(cpal::StreamError) + Send + 'static {
+    let app_handle = app_handle.clone();
+    move |err| {
+        error!("Error en el stream de audio: {err}");
+        if let Err(err) = app_handle.emit(AUDIO_ERROR_EVENT, err.to_string()) {
+            error!("No se pudo emitir el error de audio: {err}");
+        }
+    }
+}
+
+fn open_streams(
+    app_handle: &AppHandle,
+    shared: &Arc<AudioShared>,
+    config: &AudioConfig,
+) -> Result<(Vec<cpal::Stream>, AudioStatus), String> {
+    let host = find_host(config.host.as_deref())?;
+    let output = find_device(&host, config.output_device.as_deref(), false)?
+        .ok_or_else(|| "No hay una salida de audio".to_string())?;
+    let sample_rate = match config.sample_rate {
+        Some(rate) => rate,
+        None => {
+            output
+                .default_output_config()
+                .map_err(|e| format!("No se pudo leer la configuración de la salida: {e}"))?
+                .sample_rate()
+                .0
+        }
+    };
+
+    let output_ranges = output
+        .supported_output_configs()
+        .map_err(|e| format!("No se pudieron leer los formatos de la salida: {e}"))?;
+    let output_config = stream_config(output_ranges, sample_rate, config.buffer_size, "La salida")?;
+    let mut renderer = Renderer {
+        shared: shared.clone(),
+        sample_rate,
+        channels: output_config.channels as usize,
+        input: Vec::new(),
+        main: Vec::new(),
+        click: Vec::new(),
+    };
+    let output_stream = output
+        .build_output_stream(
+            &output_config,
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| renderer.render(data),
+            error_callback(app_handle),
+            None,
+        )
+        .map_err(|e| format!("No se pudo abrir la salida de audio: {e}"))?;
+
+    let mut status = AudioStatus {
+        running: true,
+        host: Some(host.id().name().to_string()),
+        input_device: None,
+        output_device: Some(device_name(&output)),
+        sample_rate: Some(sample_rate),
+        buffer_size: config.buffer_size,
+        input_channels: 0,
+        output_channels: output_config.channels,
+    };
+    let mut streams = vec![output_stream];
+
+    match find_device(&host, config.input_device.as_deref(), true)? {
+        Some(input) => {
+            let input_ranges = input
+                .supported_input_configs()
+                .map_err(|e| format!("No se pudieron leer los formatos de la entrada: {e}"))?;
+            let input_config =
+                stream_config(input_ranges, sample_rate, config.buffer_size, "La entrada")?;
+            let channels = input_config.channels as usize;
+            let channel = config.input_channel as usize;
+            if channel >= channels {
+                return Err(format!(
+                    "La entrada tiene {channels} canales, no existe el {}",
+                    channel + 1
+                ));
+            }
+
+            shared.lock_meters()?.set_input_channels(channels);
+            let shared = shared.clone();
+            let input_stream = input
+                .build_input_stream(
+                    &input_config,
+                    move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                        if let Ok(mut queue) = shared.input.lock() {
+                            queue.extend(data.chunks(channels).map(|frame| frame[channel]));
+                            let excess = queue.len().saturating_sub(MAX_INPUT_BACKLOG);
+                            queue.drain(..excess);
+                        }
+                        if let Ok(mut meters) = shared.meters.lock() {
+                            meters.add_input(data);
+                        }
+                        if let Ok(mut ltc) = shared.ltc.lock() {
+                            ltc.add_input(data, channels, sample_rate);
+                        }
+                    },
+                    error_callback(app_handle),
+                    None,
+                )
+                .map_err(|e| format!("No se pudo abrir la entrada de audio: {e}"))?;
+            status.input_device = Some(device_name(&input));
+            status.input_channels = input_config.channels;
+            streams.push(input_stream);
+        }
+        None => {
+            shared.lock_meters()?.set_input_channels(0);
+            warn!("No hay una entrada de audio; el motor arranca solo con salida");
+        }
+    }
+
+    for stream in &streams {
+        stream
+            .play()
+            .map_err(|e| format!("No se pudo arrancar el audio: {e}"))?;
+    }
+    Ok((streams, status))
+}
+
+// Los streams de cpal no se pueden pasar entre hilos: los abre y los cierra este hilo, que
+// queda esperando la orden de parar
+fn run_engine(
+    app_handle: AppHandle,
+    shared: Arc<AudioShared>,
+    config: AudioConfig,
+    ready: Sender<Result<AudioStatus, String>>,
+    stop: Receiver<()>,
+) {
+    let streams = match open_streams(&app_handle, &shared, &config) {
+        Ok((streams, status)) => {
+            let _ = ready.send(Ok(status));
+            streams
+        }
+        Err(err) => {
+            let _ = ready.send(Err(err));
+            return;
+        }
+    };
+
+    let _ = stop.recv();
+    drop(streams);
+    info!("Motor de audio detenido");
+}
+
+#[derive(Default)]
+pub struct AudioState {
+    shared: Arc<AudioShared>,
+    config: Mutex<AudioConfig>,
+    engine: Mutex<Option<Engine>>,
+    path: Mutex<Option<PathBuf>>,
+}
+
+impl AudioState {
+    // Con una configuración guardada el motor arranca solo al abrir la app
+    pub fn load(&self, app_handle: &AppHandle) {
+        let Some(path) = storage::config_file(app_handle, AUDIO_CONFIG_FILE) else {
+            return;
+        };
+
+        let stored = storage::load_json::<AudioConfig>(&path);
+        if let Ok(mut current) = self.path.lock() {
+            *current = Some(path);
+        }
+        // Los dispositivos de la configuración general mandan sobre los de `audio.json`
+        let config = match (stored, app_handle.state::<SettingsState>().audio_startup()) {
+            (stored, Some(devices)) => {
+                let mut config = stored.unwrap_or_default();
+                config.host = devices.host;
+                config.input_device = devices.input_device;
+                config.output_device = devices.output_device;
+                config.buffer_size = devices.buffer_size;
+                config
+            }
+            (Some(config), None) => config,
+            (None, None) => return,
+        };
+        if let Ok(mut current) = self.config.lock() {
+            *current = config;
+        }
+        if let Err(err) = self.start(app_handle) {
+            error!("No se pudo arrancar el audio: {err}");
+        }
+    }
+
+    pub(crate) fn shared(&self) -> &AudioShared {
+        &self.shared
+    }
+
+    fn lock_engine(&self) -> Result<std::sync::MutexGuard<'_, Option<Engine>>, String> {
+        self.engine
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el motor de audio: {e}"))
+    }
+
+    fn lock_config(&self) -> Result<std::sync::MutexGuard<'_, AudioConfig>, String> {
+        self.config
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la configuración de audio: {e}"))
+    }
+
+    fn save(&self, config: &AudioConfig) -> Result<(), String> {
+        let path = self
+            .path
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la configuración de audio: {e}"))?
+            .clone();
+
+        match path {
+            Some(path) => storage::save_json(&path, config),
+            None => Ok(()),
+        }
+    }
+
+    // (Re)arranca el motor con la configuración actual
+    fn start(&self, app_handle: &AppHandle) -> Result<AudioStatus, String> {
+        let config = self.lock_config()?.clone();
+        self.shared.set_routing(config.routing)?;
+        self.shared.set_monitor(config.monitor)?;
+        self.shared
+            .lock_looper()?
+            .set_latency(config.latency_samples.unwrap_or(0) as usize);
+        let mut engine = self.lock_engine()?;
+        if let Some(previous) = engine.take() {
+            stop_engine(previous);
+        }
+
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let shared = self.shared.clone();
+        let thread_handle = app_handle.clone();
+        let handle =
+            thread::spawn(move || run_engine(thread_handle, shared, config, ready_tx, stop_rx));
+        let status = ready_rx
+            .recv()
+            .map_err(|_| "El hilo de audio terminó sin arrancar".to_string())??;
+
+        info!(
+            "Audio en marcha ({}): salida {}, entrada {}, {} Hz",
+            status.host.as_deref().unwrap_or("-"),
+            status.output_device.as_deref().unwrap_or("-"),
+            status.input_device.as_deref().unwrap_or("-"),
+            status.sample_rate.unwrap_or_default()
+        );
+        *engine = Some(Engine {
+            stop: stop_tx,
+            handle,
+            status: status.clone(),
+        });
+        drop(engine);
+        // Los insertos de las pistas se activan a la frecuencia del motor, en el hilo principal
+        if let Some(sample_rate) = status.sample_rate {
+            plugin_host::activate_all(app_handle, sample_rate)?;
+        }
+        Ok(status)
+    }
+
+    pub(crate) fn has_input(&self) -> Result<bool, String> {
+        Ok(self
+            .lock_engine()?
+            .as_ref()
+            .is_some_and(|engine| engine.status.input_device.is_some()))
+    }
+
+    pub(crate) fn sample_rate(&self) -> Result<Option<u32>, String> {
+        Ok(self
+            .lock_engine()?
+            .as_ref()
+            .and_then(|engine| engine.status.sample_rate))
+    }
+
+    pub(crate) fn set_latency(&self, samples: Option<u32>) -> Result<(), String> {
+        let mut config = self.lock_config()?;
+        config.latency_samples = samples;
+        self.save(&config)?;
+        self.shared
+            .lock_looper()?
+            .set_latency(samples.unwrap_or(0) as usize);
+        Ok(())
+    }
+
+    pub fn shutdown(&self) {
+        if let Ok(mut engine) = self.lock_engine() {
+            if let Some(engine) = engine.take() {
+                stop_engine(engine);
+            }
+        }
+    }
+}
+
+fn stop_engine(engine: Engine) {
+    let _ = engine.stop.send(());
+    if engine.handle.join().is_err() {
+        error!("El hilo de audio terminó con pánico");
+    }
+}
+
+// Sin `config` arranca con la última usada; una nueva se guarda en `audio.json`
+#[tauri::command]
+pub fn audio_start(
+    app_handle: AppHandle,
+    state: State<'_, AudioState>,
+    config: Option<AudioConfig>,
+) -> Result<AudioStatus, String> {
+    if let Some(config) = config {
+        config.startup().validate()?;
+        state.save(&config)?;
+        *state.lock_config()? = config;
+    }
+    state.start(&app_handle)
+}
+
+#[tauri::command]
+pub fn audio_stop(state: State<'_, AudioState>) -> Result<(), String> {
+    if let Some(engine) = state.lock_engine()?.take() {
+        stop_engine(engine);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn audio_status(state: State<'_, AudioState>) -> Result<AudioStatus, String> {
+    Ok(state
+        .lock_engine()?
+        .as_ref()
+        .map(|engine| engine.status.clone())
+        .unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn audio_get_config(state: State<'_, AudioState>) -> Result<AudioConfig, String> {
+    Ok(state.lock_config()?.clone())
+}
+
+// Vale al instante, sin reiniciar el motor; se guarda con la configuración
+#[tauri::command]
+pub fn audio_set_routing(
+    state: State<'_, AudioState>,
+    routing: AudioRouting,
+) -> Result<AudioRouting, String> {
+    if let (Some(channel), Some(engine)) = (routing.click_output, state.lock_engine()?.as_ref()) {
+        let channels = engine.status.output_channels;
+        if channel >= channels {
+            return Err(format!(
+                "La salida tiene {channels} canales, no existe el {}",
+                channel + 1
+            ));
+        }
+    }
+    let mut config = state.lock_config()?;
+    config.routing = routing;
+    state.save(&config)?;
+    state.shared.set_routing(routing)?;
+    Ok(routing)
+}
+
+#[tauri::command]
+pub fn audio_get_routing(state: State<'_, AudioState>) -> Result<AudioRouting, String> {
+    Ok(state.lock_config()?.routing)
+}
+
+// Sin valor cada parámetro queda como estaba; vale al instante y se guarda
+#[tauri::command]
+pub fn audio_set_monitor(
+    state: State<'_, AudioState>,
+    gain: Option<f32>,
+    muted: Option<bool>,
+) -> Result<InputMonitor, String> {
+    if gain.is_some_and(|gain| !(0.0..=MAX_MONITOR_GAIN).contains(&gain)) {
+        return Err(format!(
+            "La ganancia del monitoreo va de 0 a {MAX_MONITOR_GAIN}"
+        ));
+    }
+    let mut config = state.lock_config()?;
+    if let Some(gain) = gain {
+        config.monitor.gain = gain;
+    }
+    if let Some(muted) = muted {
+        config.monitor.muted = muted;
+    }
+    state.save(&config)?;
+    state.shared.set_monitor(config.monitor)?;
+    info!(
+        "Monitoreo de la entrada {} (ganancia {})",
+        if config.monitor.muted {
+            "muteado"
+        } else {
+            "activo"
+        },
+        config.monitor.gain
+    );
+    Ok(config.monitor)
+}
+
+#[tauri::command]
+pub fn audio_get_monitor(state: State<'_, AudioState>) -> Result<InputMonitor, String> {
+    Ok(state.lock_config()?.monitor)
+}
+
+#[tauri::command]
+pub fn audio_list_hosts() -> Vec<AudioHostInfo> {
+    let default = cpal::default_host().id();
+    cpal::available_hosts()
+        .into_iter()
+        .map(|id| AudioHostInfo {
+            name: id.name().to_string(),
+            default: id == default,
+        })
+        .collect()
+}
+
+// Sin `host` lista los del backend predeterminado
+#[tauri::command]
+pub fn audio_list_devices(host: Option<String>) -> Result<AudioDevices, String> {
+    let host = find_host(host.as_deref())?;
+    Ok(AudioDevices {
+        inputs: list_devices(&host, true)?,
+        outputs: list_devices(&host, false)?,
+    })
+}
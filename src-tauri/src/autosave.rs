@@ -0,0 +1,226 @@
+use crate::dmx::DmxState;
+use crate::project;
+use crate::storage;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State};
+
+// Lo escribe el hilo de autoguardado mientras la app corre; al salir bien se borra
+const AUTOSAVE_FILE: &str = "session_autosave.json";
+// Copia de la sesión anterior si la app no se cerró bien
+const RECOVERY_FILE: &str = "session_recovery.json";
+const DEFAULT_INTERVAL_SECS: u64 = 30;
+const MIN_INTERVAL_SECS: u64 = 5;
+const MAX_INTERVAL_SECS: u64 = 3600;
+
+#[derive(Serialize, Deserialize)]
+struct Session {
+    // Segundos desde epoch
+    saved_at: u64,
+    // Como en el archivo de show: se lee pasando por las migraciones
+    project: Value,
+    // Buffer de cada universo (512 canales, sin start code)
+    levels: BTreeMap<u16, Vec<u8>>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct RecoveryInfo {
+    saved_at: u64,
+    universes: usize,
+}
+
+pub struct AutosaveState {
+    interval_secs: AtomicU64,
+    stopped: AtomicBool,
+    autosave_path: Mutex<Option<PathBuf>>,
+    recovery_path: Mutex<Option<PathBuf>>,
+}
+
+impl Default for AutosaveState {
+    fn default() -> Self {
+        Self {
+            interval_secs: AtomicU64::new(DEFAULT_INTERVAL_SECS),
+            stopped: AtomicBool::new(false),
+            autosave_path: Mutex::new(None),
+            recovery_path: Mutex::new(None),
+        }
+    }
+}
+
+impl AutosaveState {
+    // Si quedó un autoguardado de la sesión anterior la app no se cerró bien: se aparta
+    // como recuperación antes de que el hilo nuevo lo pise
+    pub fn start(&self, app_handle: &AppHandle) {
+        let (Some(autosave), Some(recovery)) = (
+            storage::config_file(app_handle, AUTOSAVE_FILE),
+            storage::config_file(app_handle, RECOVERY_FILE),
+        ) else {
+            return;
+        };
+
+        if autosave.exists() {
+            match fs::rename(&autosave, &recovery) {
+                Ok(()) => warn!("La sesión anterior no se cerró bien; se puede recuperar"),
+                Err(err) => error!("No se pudo apartar el autoguardado anterior: {err}"),
+            }
+        }
+
+        if let Ok(mut path) = self.autosave_path.lock() {
+            *path = Some(autosave);
+        }
+        if let Ok(mut path) = self.recovery_path.lock() {
+            *path = Some(recovery);
+        }
+
+        let app_handle = app_handle.clone();
+        thread::spawn(move || run_autosave(app_handle));
+    }
+
+    // Salida limpia: se detiene el hilo y se borra el autoguardado
+    pub fn shutdown(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        if let Some(path) = self.autosave_path() {
+            if let Err(err) = fs::remove_file(&path) {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    error!("No se pudo borrar el autoguardado: {err}");
+                }
+            }
+        }
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.load(Ordering::Relaxed))
+    }
+
+    fn autosave_path(&self) -> Option<PathBuf> {
+        self.autosave_path.lock().ok().and_then(|path| path.clone())
+    }
+
+    fn recovery_path(&self) -> Option<PathBuf> {
+        self.recovery_path.lock().ok().and_then(|path| path.clone())
+    }
+
+    fn recovery(&self) -> Option<Session> {
+        storage::load_json::<Session>(&self.recovery_path()?)
+    }
+}
+
+fn capture_session(app_handle: &AppHandle) -> Result<Session, String> {
+    let dmx = app_handle.state::<DmxState>();
+    let project = serde_json::to_value(project::capture(app_handle)?)
+        .map_err(|e| format!("No se pudo serializar el proyecto: {e}"))?;
+
+    let mut levels = BTreeMap::new();
+    dmx.for_each_universe(|universe, shared| {
+        levels.insert(universe, shared.channel_levels());
+    });
+
+    let saved_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    Ok(Session {
+        saved_at,
+        project,
+        levels,
+    })
+}
+
+fn run_autosave(app_handle: AppHandle) {
+    let mut last_save = Instant::now();
+
+    loop {
+        // Se duerme de a poco para reaccionar rápido a la salida y a los cambios de intervalo
+        thread::sleep(Duration::from_secs(1));
+
+        let state = app_handle.state::<AutosaveState>();
+        if state.stopped.load(Ordering::SeqCst) {
+            break;
+        }
+        if last_save.elapsed() < state.interval() {
+            continue;
+        }
+        last_save = Instant::now();
+
+        let Some(path) = state.autosave_path() else {
+            break;
+        };
+        let result =
+            capture_session(&app_handle).and_then(|session| storage::save_json(&path, &session));
+        if let Err(err) = result {
+            error!("No se pudo autoguardar la sesión: {err}");
+        }
+    }
+}
+
+#[tauri::command]
+pub fn autosave_set_interval(
+    autosave: State<'_, AutosaveState>,
+    seconds: u64,
+) -> Result<(), String> {
+    if !(MIN_INTERVAL_SECS..=MAX_INTERVAL_SECS).contains(&seconds) {
+        return Err(format!(
+            "El intervalo de autoguardado debe estar entre {MIN_INTERVAL_SECS} y {MAX_INTERVAL_SECS} s"
+        ));
+    }
+    autosave.interval_secs.store(seconds, Ordering::Relaxed);
+    Ok(())
+}
+
+// Sesión que quedó de un cierre inesperado, para que el front ofrezca recuperarla
+#[tauri::command]
+pub fn last_session_info(autosave: State<'_, AutosaveState>) -> Option<RecoveryInfo> {
+    autosave.recovery().map(|session| RecoveryInfo {
+        saved_at: session.saved_at,
+        universes: session.levels.len(),
+    })
+}
+
+// Restaura el proyecto y los niveles de la sesión anterior al cierre inesperado
+#[tauri::command]
+pub fn recover_last_session(
+    app_handle: AppHandle,
+    autosave: State<'_, AutosaveState>,
+    dmx: State<'_, DmxState>,
+) -> Result<(), String> {
+    let session = autosave
+        .recovery()
+        .ok_or_else(|| "No hay una sesión anterior para recuperar".to_string())?;
+
+    let project = project::from_value(session.project)
+        .map_err(|e| format!("La sesión guardada no es válida: {e}"))?;
+    project::apply(&app_handle, project)?;
+    for (universe, levels) in &session.levels {
+        dmx.with_universe(app_handle.clone(), *universe, |shared| {
+            shared.update_levels(levels)
+        })?;
+    }
+
+    discard_recovery(&autosave);
+    info!("Sesión recuperada ({} universos)", session.levels.len());
+    Ok(())
+}
+
+#[tauri::command]
+pub fn discard_last_session(autosave: State<'_, AutosaveState>) {
+    discard_recovery(&autosave);
+}
+
+fn discard_recovery(autosave: &AutosaveState) {
+    if let Some(path) = autosave.recovery_path() {
+        if let Err(err) = fs::remove_file(&path) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                error!("No se pudo borrar la sesión recuperada: {err}");
+            }
+        }
+    }
+}
@@ -0,0 +1,433 @@
+use crate::cues::{cue_release, CueState};
+use crate::dmx::DmxState;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub const BACKUP_EVENT: &str = "backup://state";
+pub const DEFAULT_BACKUP_PORT: u16 = 6970;
+// El principal manda el estado (y con él el heartbeat) cada 100 ms
+const SEND_INTERVAL: Duration = Duration::from_millis(100);
+const DEFAULT_TIMEOUT_MS: u64 = 1000;
+// Con menos, un par de paquetes perdidos ya dispara el cambio
+const MIN_TIMEOUT_MS: u64 = 300;
+// Un universo en JSON ocupa unos 2 KB
+const MAX_PACKET: usize = 8 * 1024;
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupMode {
+    /// Manda su estado al backup
+    Primary,
+    /// Sigue al principal con la salida DMX retenida
+    Tracking,
+    /// Tomó la salida: el principal dejó de responder o se pidió a mano
+    Active,
+}
+
+#[derive(Clone, Serialize)]
+pub struct BackupStatus {
+    mode: Option<BackupMode>,
+    port: Option<u16>,
+    // El backup en el principal, el principal en el backup
+    peer: Option<String>,
+    // Milisegundos desde el último heartbeat recibido
+    last_heartbeat_ms: Option<u64>,
+    output_held: bool,
+}
+
+// Lo que viaja por UDP del principal al backup
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum BackupPacket {
+    Heartbeat { cue: Option<f64> },
+    // Niveles del buffer del universo (sin start code); el backup calcula la salida con su
+    // propio patch, master y curvas
+    Levels { universe: u16, levels: Vec<u8> },
+}
+
+struct BackupLink {
+    port: u16,
+    peer: Option<SocketAddr>,
+    last_heartbeat: Option<Instant>,
+    stop: Sender<()>,
+    handle: thread::JoinHandle<()>,
+}
+
+#[derive(Default)]
+pub struct BackupState {
+    link: Mutex<Option<BackupLink>>,
+    mode: Mutex<Option<BackupMode>>,
+    // Lo consulta el loop DMX en cada frame
+    held: AtomicBool,
+}
+
+impl BackupState {
+    fn lock_link(&self) -> Result<std::sync::MutexGuard<'_, Option<BackupLink>>, String> {
+        self.link
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el enlace con el backup: {e}"))
+    }
+
+    fn lock_mode(&self) -> Result<std::sync::MutexGuard<'_, Option<BackupMode>>, String> {
+        self.mode
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el modo de backup: {e}"))
+    }
+
+    // `true` mientras un backup sigue al principal: los frames se calculan pero no salen
+    pub(crate) fn holds_output(&self) -> bool {
+        self.held.load(Ordering::Relaxed)
+    }
+
+    fn mode(&self) -> Option<BackupMode> {
+        self.mode.lock().ok().and_then(|mode| *mode)
+    }
+
+    fn store_mode(&self, mode: Option<BackupMode>) {
+        self.held
+            .store(mode == Some(BackupMode::Tracking), Ordering::Relaxed);
+        match self.lock_mode() {
+            Ok(mut current) => *current = mode,
+            Err(err) => error!("{err}"),
+        }
+    }
+
+    fn set_mode(&self, app_handle: &AppHandle, mode: Option<BackupMode>) {
+        self.store_mode(mode);
+        self.emit_status(app_handle);
+    }
+
+    fn emit_status(&self, app_handle: &AppHandle) {
+        match self.status() {
+            Ok(status) => {
+                if let Err(err) = app_handle.emit(BACKUP_EVENT, status) {
+                    error!("No se pudo emitir el estado del backup: {err}");
+                }
+            }
+            Err(err) => error!("{err}"),
+        }
+    }
+
+    fn heard_from(&self, from: SocketAddr) {
+        if let Ok(mut link) = self.link.lock() {
+            if let Some(link) = link.as_mut() {
+                link.peer = Some(from);
+                link.last_heartbeat = Some(Instant::now());
+            }
+        }
+    }
+
+    fn last_heartbeat(&self) -> Option<Instant> {
+        self.link.lock().ok()?.as_ref()?.last_heartbeat
+    }
+
+    fn status(&self) -> Result<BackupStatus, String> {
+        let link = self.lock_link()?;
+        let link = link.as_ref();
+        Ok(BackupStatus {
+            mode: self.mode(),
+            port: link.map(|link| link.port),
+            peer: link.and_then(|link| link.peer).map(|peer| peer.to_string()),
+            last_heartbeat_ms: link
+                .and_then(|link| link.last_heartbeat)
+                .map(|last| last.elapsed().as_millis() as u64),
+            output_held: self.holds_output(),
+        })
+    }
+}
+
+fn timed_out(err: &std::io::Error) -> bool {
+    matches!(
+        err.kind(),
+        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+    )
+}
+
+fn send_packet(socket: &UdpSocket, target: SocketAddr, packet: &BackupPacket) {
+    let bytes = match serde_json::to_vec(packet) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            error!("No se pudo serializar el estado para el backup: {err}");
+            return;
+        }
+    };
+    if let Err(err) = socket.send_to(&bytes, target) {
+        warn!("No se pudo enviar el estado al backup ({target}): {err}");
+    }
+}
+
+fn run_primary(app_handle: AppHandle, socket: UdpSocket, target: SocketAddr, rx: Receiver<()>) {
+    loop {
+        let cue = app_handle.state::<CueState>().active_number();
+        send_packet(&socket, target, &BackupPacket::Heartbeat { cue });
+        app_handle
+            .state::<DmxState>()
+            .for_each_universe(|universe, shared| {
+                let levels = shared.channel_levels();
+                send_packet(&socket, target, &BackupPacket::Levels { universe, levels });
+            });
+
+        match rx.recv_timeout(SEND_INTERVAL) {
+            Err(RecvTimeoutError::Timeout) => {}
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    info!("Envío al backup detenido");
+}
+
+// Lleva la lista de cues a la misma cue del principal, sin fade: la salida está retenida
+fn mirror_cue(app_handle: &AppHandle, cue: Option<f64>) -> Result<(), String> {
+    let cues = app_handle.state::<CueState>();
+    let dmx = app_handle.state::<DmxState>();
+    match cue {
+        Some(number) => cues.go(app_handle, &dmx, Some(number), Some(0)).map(|_| ()),
+        None => cue_release(app_handle.clone(), cues, dmx).map(|_| ()),
+    }
+}
+
+fn take_over(app_handle: &AppHandle, reason: &str) {
+    warn!("El backup toma la salida DMX: {reason}");
+    app_handle
+        .state::<BackupState>()
+        .set_mode(app_handle, Some(BackupMode::Active));
+}
+
+fn run_tracking(app_handle: AppHandle, socket: UdpSocket, timeout: Duration, rx: Receiver<()>) {
+    let state = app_handle.state::<BackupState>();
+    let mut buffer = [0u8; MAX_PACKET];
+    // Lo último que se copió del principal, para no reescribir lo que no cambió
+    let mut cue: Option<Option<f64>> = None;
+    let mut levels: HashMap<u16, Vec<u8>> = HashMap::new();
+    let mut returned_warned = false;
+
+    while rx.try_recv().is_err() {
+        let tracking = state.mode() == Some(BackupMode::Tracking);
+        // Sin ningún heartbeat todavía no hay principal que perder
+        if tracking {
+            if let Some(last) = state.last_heartbeat() {
+                if last.elapsed() > timeout {
+                    take_over(
+                        &app_handle,
+                        &format!(
+                            "sin heartbeat del principal hace {} ms",
+                            last.elapsed().as_millis()
+                        ),
+                    );
+                    continue;
+                }
+            }
+        }
+
+        let (len, from) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(err) if timed_out(&err) => continue,
+            Err(err) => {
+                error!("Error al recibir del principal: {err}");
+                thread::sleep(SEND_INTERVAL);
+                continue;
+            }
+        };
+        let packet = match serde_json::from_slice::<BackupPacket>(&buffer[..len]) {
+            Ok(packet) => packet,
+            Err(err) => {
+                warn!("Paquete de backup inválido de {from}: {err}");
+                continue;
+            }
+        };
+        state.heard_from(from);
+
+        // Ya con la salida tomada no se vuelve sola al principal: dos equipos sacando DMX a
+        // la vez es peor que uno solo
+        if !tracking {
+            if !returned_warned {
+                warn!("El principal ({from}) volvió a responder; el backup sigue con la salida");
+                returned_warned = true;
+            }
+            continue;
+        }
+        match packet {
+            BackupPacket::Heartbeat { cue: active } => {
+                if cue != Some(active) {
+                    if let Err(err) = mirror_cue(&app_handle, active) {
+                        warn!("No se pudo seguir la cue del principal: {err}");
+                    }
+                    cue = Some(active);
+                }
+            }
+            BackupPacket::Levels {
+                universe,
+                levels: received,
+            } => {
+                if levels.get(&universe) == Some(&received) {
+                    continue;
+                }
+                let result = app_handle.state::<DmxState>().with_universe(
+                    app_handle.clone(),
+                    universe,
+                    |shared| shared.update_levels(&received),
+                );
+                match result {
+                    Ok(()) => {
+                        levels.insert(universe, received);
+                    }
+                    Err(err) => warn!("No se pudo copiar el universo {universe}: {err}"),
+                }
+            }
+        }
+    }
+    info!("Seguimiento del principal detenido");
+}
+
+fn stop_link(link: BackupLink) {
+    let _ = link.stop.send(());
+    if link.handle.join().is_err() {
+        error!("El hilo del enlace de backup terminó con pánico");
+    }
+}
+
+// Arranca un enlace nuevo en lugar del que hubiera
+fn start_link(
+    app_handle: &AppHandle,
+    state: &BackupState,
+    mode: BackupMode,
+    port: u16,
+    peer: Option<SocketAddr>,
+    spawn: impl FnOnce(Receiver<()>) -> thread::JoinHandle<()>,
+) -> Result<BackupStatus, String> {
+    // Sin el lock: el hilo que se detiene también lo toma
+    let previous = state.lock_link()?.take();
+    if let Some(previous) = previous {
+        stop_link(previous);
+    }
+    // El modo va antes que el hilo, que lo consulta desde el primer paquete
+    state.store_mode(Some(mode));
+    let (tx, rx) = mpsc::channel();
+    let handle = spawn(rx);
+    *state.lock_link()? = Some(BackupLink {
+        port,
+        peer,
+        last_heartbeat: None,
+        stop: tx,
+        handle,
+    });
+    state.emit_status(app_handle);
+    state.status()
+}
+
+// En el equipo principal: manda al backup en `backup_ip` la cue activa y los niveles de cada
+// universo. Lo demás que suena (chases, efectos, matrices, Art-Net de entrada, sonido) no viaja.
+#[tauri::command]
+pub fn backup_start_primary(
+    app_handle: AppHandle,
+    state: State<'_, BackupState>,
+    backup_ip: String,
+    port: Option<u16>,
+) -> Result<BackupStatus, String> {
+    let port = port.unwrap_or(DEFAULT_BACKUP_PORT);
+    let target = (backup_ip.as_str(), port)
+        .to_socket_addrs()
+        .map_err(|e| format!("Dirección del backup inválida ({backup_ip}): {e}"))?
+        .find(SocketAddr::is_ipv4)
+        .ok_or_else(|| format!("El backup {backup_ip} no tiene dirección IPv4"))?;
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .map_err(|e| format!("No se pudo abrir el socket del backup: {e}"))?;
+
+    info!("Enviando el estado al backup en {target}");
+    let thread_handle = app_handle.clone();
+    start_link(
+        &app_handle,
+        &state,
+        BackupMode::Primary,
+        port,
+        Some(target),
+        move |rx| thread::spawn(move || run_primary(thread_handle, socket, target, rx)),
+    )
+}
+
+// En el equipo de backup: sigue al principal con la salida DMX retenida y la toma si pasan
+// `timeout_ms` (1000 por defecto) sin heartbeat. Al tomarla sigue con los chases, efectos y
+// fuentes de merge que tenga activos él, no con los del principal.
+#[tauri::command]
+pub fn backup_start_tracking(
+    app_handle: AppHandle,
+    state: State<'_, BackupState>,
+    bind_ip: Option<String>,
+    port: Option<u16>,
+    timeout_ms: Option<u64>,
+) -> Result<BackupStatus, String> {
+    let bind_ip = match bind_ip {
+        Some(ip) => ip
+            .parse::<Ipv4Addr>()
+            .map_err(|e| format!("IP de escucha del backup inválida ({ip}): {e}"))?,
+        None => Ipv4Addr::UNSPECIFIED,
+    };
+    let port = port.unwrap_or(DEFAULT_BACKUP_PORT);
+    let timeout_ms = timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS);
+    if timeout_ms < MIN_TIMEOUT_MS {
+        return Err(format!(
+            "El timeout del heartbeat tiene que ser de {MIN_TIMEOUT_MS} ms o más"
+        ));
+    }
+
+    // Se suelta el puerto del enlace anterior antes de abrir el nuevo
+    // Sin el lock: el hilo que se detiene también lo toma
+    let previous = state.lock_link()?.take();
+    if let Some(previous) = previous {
+        stop_link(previous);
+    }
+    let socket = UdpSocket::bind((bind_ip, port))
+        .map_err(|e| format!("No se pudo escuchar al principal en {bind_ip}:{port}: {e}"))?;
+    socket
+        .set_read_timeout(Some(SEND_INTERVAL))
+        .map_err(|e| format!("No se pudo configurar el socket del backup: {e}"))?;
+
+    info!("Siguiendo al principal en {bind_ip}:{port} (timeout {timeout_ms} ms)");
+    let thread_handle = app_handle.clone();
+    let timeout = Duration::from_millis(timeout_ms);
+    start_link(
+        &app_handle,
+        &state,
+        BackupMode::Tracking,
+        port,
+        None,
+        move |rx| thread::spawn(move || run_tracking(thread_handle, socket, timeout, rx)),
+    )
+}
+
+// Toma la salida sin esperar al timeout, p. ej. para apagar el principal a propósito
+#[tauri::command]
+pub fn backup_take_over(
+    app_handle: AppHandle,
+    state: State<'_, BackupState>,
+) -> Result<BackupStatus, String> {
+    if state.mode() != Some(BackupMode::Tracking) {
+        return Err("El equipo no está siguiendo a un principal".to_string());
+    }
+    take_over(&app_handle, "pedido a mano");
+    state.status()
+}
+
+// Corta el enlace; en un backup la salida DMX vuelve a salir
+#[tauri::command]
+pub fn backup_stop(app_handle: AppHandle, state: State<'_, BackupState>) -> Result<(), String> {
+    let link = state.lock_link()?.take();
+    if let Some(link) = link {
+        stop_link(link);
+        info!("Enlace de backup detenido");
+    }
+    state.set_mode(&app_handle, None);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn backup_status(state: State<'_, BackupState>) -> Result<BackupStatus, String> {
+    state.status()
+}
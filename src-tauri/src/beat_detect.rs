@@ -0,0 +1,244 @@
+use crate::audio::{AudioBlock, AudioState};
+use crate::chase::{set_running_bpm, sync_to_beat};
+use crate::dmx::DmxState;
+use log::{error, info};
+use serde::Serialize;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub const AUDIO_BEAT_EVENT: &str = "audio://beat";
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+// Ventanas de energía de 10 ms
+const FRAMES_PER_SECOND: u32 = 100;
+// La energía de cada ventana se compara con el promedio del último segundo
+const HISTORY: usize = FRAMES_PER_SECOND as usize;
+// Tempos que se aceptan; un intervalo fuera del rango se dobla o se parte a la mitad
+const MIN_BPM: f64 = 60.0;
+const MAX_BPM: f64 = 200.0;
+// Intervalos entre golpes que entran en la estimación del tempo
+const ONSET_WINDOW: usize = 8;
+// Con esta pausa sin golpes se empieza a contar de nuevo (s)
+const ONSET_TIMEOUT_SECS: u64 = 2;
+// Por debajo de esta energía (mean square) se considera silencio
+const MIN_ENERGY: f32 = 1e-5;
+const DEFAULT_SENSITIVITY: f32 = 1.5;
+
+#[derive(Clone, Serialize)]
+pub struct BeatStatus {
+    enabled: bool,
+    sensitivity: f32,
+    drive_chases: bool,
+    bpm: Option<f64>,
+    // Golpes detectados desde que se activó
+    beats: u64,
+}
+
+// Detecta golpes en la entrada con un umbral sobre la energía promedio y estima el tempo con la
+// mediana de los intervalos. No reserva memoria: corre en el callback de audio.
+pub(crate) struct BeatDetector {
+    enabled: bool,
+    // Cuántas veces por encima del promedio tiene que estar la energía para contar un golpe
+    sensitivity: f32,
+    drive_chases: bool,
+    history: [f32; HISTORY],
+    history_pos: usize,
+    frame_energy: f32,
+    frame_samples: u32,
+    // Muestras procesadas desde que se activó
+    clock: u64,
+    onsets: [u64; ONSET_WINDOW + 1],
+    onset_count: usize,
+    beats: u64,
+    bpm: Option<f64>,
+}
+
+impl Default for BeatDetector {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sensitivity: DEFAULT_SENSITIVITY,
+            drive_chases: false,
+            history: [0.0; HISTORY],
+            history_pos: 0,
+            frame_energy: 0.0,
+            frame_samples: 0,
+            clock: 0,
+            onsets: [0; ONSET_WINDOW + 1],
+            onset_count: 0,
+            beats: 0,
+            bpm: None,
+        }
+    }
+}
+
+impl BeatDetector {
+    pub(crate) fn status(&self) -> BeatStatus {
+        BeatStatus {
+            enabled: self.enabled,
+            sensitivity: self.sensitivity,
+            drive_chases: self.drive_chases,
+            bpm: self.bpm,
+            beats: self.beats,
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Self {
+            enabled: self.enabled,
+            sensitivity: self.sensitivity,
+            drive_chases: self.drive_chases,
+            ..Self::default()
+        };
+    }
+
+    // Lo llama el callback de salida en cada bloque
+    pub(crate) fn process(&mut self, block: &AudioBlock) {
+        if !self.enabled || block.sample_rate == 0 {
+            return;
+        }
+        let frame_len = (block.sample_rate / FRAMES_PER_SECOND).max(1);
+        for sample in block.input {
+            self.frame_energy += sample * sample;
+            self.frame_samples += 1;
+            self.clock += 1;
+            if self.frame_samples == frame_len {
+                let energy = self.frame_energy / frame_len as f32;
+                self.frame_energy = 0.0;
+                self.frame_samples = 0;
+                self.frame(energy, block.sample_rate);
+            }
+        }
+    }
+
+    fn frame(&mut self, energy: f32, sample_rate: u32) {
+        let average = self.history.iter().sum::<f32>() / HISTORY as f32;
+        self.history[self.history_pos] = energy;
+        self.history_pos = (self.history_pos + 1) % HISTORY;
+
+        if energy < MIN_ENERGY || energy < average * self.sensitivity {
+            return;
+        }
+        // Más cerca que el tempo máximo es el mismo golpe
+        let min_gap = (60.0 / MAX_BPM * sample_rate as f64) as u64;
+        let timeout = ONSET_TIMEOUT_SECS * sample_rate as u64;
+        if let Some(last) = self.onset_count.checked_sub(1).map(|idx| self.onsets[idx]) {
+            let gap = self.clock - last;
+            if gap < min_gap {
+                return;
+            }
+            if gap > timeout {
+                self.onset_count = 0;
+            }
+        }
+
+        if self.onset_count == self.onsets.len() {
+            self.onsets.copy_within(1.., 0);
+            self.onset_count -= 1;
+        }
+        self.onsets[self.onset_count] = self.clock;
+        self.onset_count += 1;
+        self.beats += 1;
+        if let Some(bpm) = self.estimate(sample_rate) {
+            self.bpm = Some(bpm);
+        }
+    }
+
+    // Mediana de los tempos de cada intervalo, llevados al rango aceptado
+    fn estimate(&self, sample_rate: u32) -> Option<f64> {
+        let count = self
+            .onset_count
+            .checked_sub(1)
+            .filter(|count| *count >= 2)?;
+        let mut tempos = [0.0; ONSET_WINDOW];
+        for (idx, tempo) in tempos.iter_mut().take(count).enumerate() {
+            let gap = (self.onsets[idx + 1] - self.onsets[idx]) as f64 / sample_rate as f64;
+            let mut bpm = 60.0 / gap;
+            while bpm < MIN_BPM {
+                bpm *= 2.0;
+            }
+            while bpm > MAX_BPM {
+                bpm /= 2.0;
+            }
+            *tempo = bpm;
+        }
+        let tempos = &mut tempos[..count];
+        tempos.sort_by(|a, b| a.total_cmp(b));
+        Some((tempos[count / 2] * 10.0).round() / 10.0)
+    }
+}
+
+// Avisa cada golpe detectado y, si se pidió, lleva el tempo a los chases en marcha y los pone
+// en fase con el golpe
+pub fn start_beat_follow(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    thread::spawn(move || {
+        let mut last_beats = 0;
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let audio = app_handle.state::<AudioState>();
+            let status = match audio.shared().lock_beats() {
+                Ok(detector) => detector.status(),
+                Err(err) => {
+                    error!("{err}");
+                    continue;
+                }
+            };
+            if status.beats == last_beats {
+                continue;
+            }
+            last_beats = status.beats;
+            if status.beats == 0 {
+                continue;
+            }
+
+            if status.drive_chases {
+                let dmx = app_handle.state::<DmxState>();
+                // En cada golpe, así también lo toman los chases que arrancaron después
+                if let Some(bpm) = status.bpm {
+                    set_running_bpm(&dmx, bpm);
+                }
+                sync_to_beat(&dmx);
+            }
+            if let Err(err) = app_handle.emit(AUDIO_BEAT_EVENT, status) {
+                error!("No se pudo emitir el golpe detectado: {err}");
+            }
+        }
+    });
+}
+
+// Los argumentos que no llegan quedan como estaban
+#[tauri::command]
+pub fn beat_detect_configure(
+    audio: State<'_, AudioState>,
+    enabled: bool,
+    sensitivity: Option<f32>,
+    drive_chases: Option<bool>,
+) -> Result<BeatStatus, String> {
+    if sensitivity.is_some_and(|sensitivity| !(1.0..=5.0).contains(&sensitivity)) {
+        return Err("La sensibilidad de la detección de golpes va de 1 a 5".to_string());
+    }
+    let mut detector = audio.shared().lock_beats()?;
+    if enabled && !detector.enabled {
+        detector.reset();
+    }
+    detector.enabled = enabled;
+    if let Some(sensitivity) = sensitivity {
+        detector.sensitivity = sensitivity;
+    }
+    if let Some(drive_chases) = drive_chases {
+        detector.drive_chases = drive_chases;
+    }
+    info!(
+        "Detección de golpes {} (sensibilidad {}, chases {})",
+        if enabled { "activada" } else { "apagada" },
+        detector.sensitivity,
+        if detector.drive_chases { "sí" } else { "no" }
+    );
+    Ok(detector.status())
+}
+
+#[tauri::command]
+pub fn beat_detect_status(audio: State<'_, AudioState>) -> Result<BeatStatus, String> {
+    Ok(audio.shared().lock_beats()?.status())
+}
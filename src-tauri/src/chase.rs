@@ -0,0 +1,485 @@
+use crate::dmx::DmxState;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, State};
+
+const MIN_BPM: f64 = 1.0;
+const MAX_BPM: f64 = 600.0;
+// Un golpe después de esta pausa empieza a contar de nuevo
+const TAP_TIMEOUT: Duration = Duration::from_secs(2);
+// Intervalos que se promedian
+const TAP_WINDOW: usize = 4;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChaseDirection {
+    #[default]
+    Forward,
+    Backward,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChaseLoopMode {
+    /// Vuelve a empezar al terminar
+    #[default]
+    Loop,
+    /// Recorre los pasos una vez y se detiene
+    Once,
+    /// Ida y vuelta (ping-pong)
+    Bounce,
+}
+
+// Transporte de un reloj externo (clock MIDI)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Transport {
+    /// Vuelve al primer paso y al primer beat
+    Start,
+    /// Pausa donde esté
+    Stop,
+    /// Sigue desde donde se pausó
+    Continue,
+}
+
+// Canal DMX (1-512) -> valor; los canales que no aparecen no los toca el chase
+pub type ChaseStep = BTreeMap<u16, u8>;
+
+#[derive(Clone, Serialize)]
+pub struct ChaseInfo {
+    name: String,
+    steps: Vec<ChaseStep>,
+    bpm: f64,
+    direction: ChaseDirection,
+    loop_mode: ChaseLoopMode,
+    running: bool,
+    current_step: usize,
+}
+
+pub(crate) struct Chase {
+    steps: Vec<ChaseStep>,
+    bpm: f64,
+    direction: ChaseDirection,
+    loop_mode: ChaseLoopMode,
+    running: bool,
+    current: usize,
+    // En modo bounce indica si se está volviendo
+    reversed: bool,
+    step_started: Instant,
+    // Pausado por el transporte del reloj externo; `continue` lo retoma
+    paused_at: Option<Instant>,
+}
+
+impl Chase {
+    fn step_period(&self) -> Duration {
+        Duration::from_secs_f64(60.0 / self.bpm)
+    }
+
+    fn start(&mut self) {
+        self.running = !self.steps.is_empty();
+        self.reversed = false;
+        self.current = match self.direction {
+            ChaseDirection::Forward => 0,
+            ChaseDirection::Backward => self.steps.len().saturating_sub(1),
+        };
+        self.step_started = Instant::now();
+        self.paused_at = None;
+    }
+
+    fn transport(&mut self, transport: Transport, now: Instant) {
+        match transport {
+            Transport::Start if self.running || self.paused_at.is_some() => self.start(),
+            Transport::Stop if self.running => {
+                self.running = false;
+                self.paused_at = Some(now);
+            }
+            Transport::Continue => {
+                if let Some(paused_at) = self.paused_at.take() {
+                    self.step_started += now.saturating_duration_since(paused_at);
+                    self.running = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Con un golpe detectado cerca del final del paso se adelanta el cambio; cerca del
+    // principio se corre el paso en curso, así el chase queda en fase sin saltear pasos
+    fn beat(&mut self, now: Instant) {
+        if !self.running || self.steps.is_empty() {
+            return;
+        }
+        if now.saturating_duration_since(self.step_started) >= self.step_period() / 2 {
+            self.next_step();
+        }
+        self.step_started = now;
+    }
+
+    fn next_step(&mut self) {
+        let last = self.steps.len() - 1;
+        let forward = (self.direction == ChaseDirection::Forward) != self.reversed;
+
+        let at_end = if forward {
+            self.current == last
+        } else {
+            self.current == 0
+        };
+
+        if !at_end {
+            self.current = if forward {
+                self.current + 1
+            } else {
+                self.current - 1
+            };
+            return;
+        }
+
+        match self.loop_mode {
+            ChaseLoopMode::Loop => {
+                self.current = if forward { 0 } else { last };
+            }
+            ChaseLoopMode::Once => self.running = false,
+            ChaseLoopMode::Bounce => {
+                self.reversed = !self.reversed;
+                if last > 0 {
+                    self.current = if forward {
+                        self.current - 1
+                    } else {
+                        self.current + 1
+                    };
+                }
+            }
+        }
+    }
+
+    // Lo llama el hilo de escritura en cada frame: avanza según el tempo y deja en `levels`
+    // los canales del paso actual para el merge. Devuelve `false` si el chase no aporta nada.
+    pub(crate) fn step_levels(&mut self, now: Instant, levels: &mut Vec<Option<u8>>) -> bool {
+        if !self.running || self.steps.is_empty() {
+            return false;
+        }
+
+        let period = self.step_period();
+        while self.running && now.saturating_duration_since(self.step_started) >= period {
+            self.step_started += period;
+            self.next_step();
+        }
+        if !self.running {
+            return false;
+        }
+
+        levels.clear();
+        levels.resize(512, None);
+        for (channel, value) in &self.steps[self.current] {
+            levels[*channel as usize - 1] = Some(*value);
+        }
+        true
+    }
+
+    fn info(&self, name: &str) -> ChaseInfo {
+        ChaseInfo {
+            name: name.to_string(),
+            steps: self.steps.clone(),
+            bpm: self.bpm,
+            direction: self.direction,
+            loop_mode: self.loop_mode,
+            running: self.running,
+            current_step: self.current,
+        }
+    }
+}
+
+fn validate_bpm(bpm: f64) -> Result<(), String> {
+    if !(MIN_BPM..=MAX_BPM).contains(&bpm) {
+        return Err(format!(
+            "El tempo del chase debe estar entre {MIN_BPM} y {MAX_BPM} BPM"
+        ));
+    }
+    Ok(())
+}
+
+// Tap tempo compartido por el front y los controladores
+#[derive(Default)]
+pub struct TempoState {
+    taps: Mutex<Vec<Instant>>,
+}
+
+impl TempoState {
+    // Devuelve el tempo a partir del segundo golpe
+    pub(crate) fn tap(&self, now: Instant) -> Result<Option<f64>, String> {
+        let mut taps = self
+            .taps
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el tap tempo: {e}"))?;
+        if taps
+            .last()
+            .is_some_and(|last| now.saturating_duration_since(*last) > TAP_TIMEOUT)
+        {
+            taps.clear();
+        }
+        taps.push(now);
+        if taps.len() > TAP_WINDOW + 1 {
+            taps.remove(0);
+        }
+        if taps.len() < 2 {
+            return Ok(None);
+        }
+
+        let span = now.saturating_duration_since(taps[0]).as_secs_f64();
+        let bpm = 60.0 * (taps.len() - 1) as f64 / span;
+        Ok(Some(clamp_bpm(bpm)))
+    }
+}
+
+// Pasa el tempo a todos los chases en marcha y a los efectos que siguen el beat, en todos los
+// universos
+pub(crate) fn set_running_bpm(state: &DmxState, bpm: f64) {
+    state.for_each_universe(|_, shared| {
+        if let Ok(mut chases) = shared.chases.lock() {
+            chases
+                .values_mut()
+                .filter(|chase| chase.running)
+                .for_each(|chase| chase.bpm = bpm);
+        }
+        if let Ok(mut effects) = shared.effects.lock() {
+            effects.set_bpm(bpm);
+        }
+    });
+}
+
+pub(crate) fn apply_transport(state: &DmxState, transport: Transport) {
+    let now = Instant::now();
+    state.for_each_universe(|_, shared| {
+        if let Ok(mut chases) = shared.chases.lock() {
+            chases
+                .values_mut()
+                .for_each(|chase| chase.transport(transport, now));
+        }
+        if let Ok(mut effects) = shared.effects.lock() {
+            effects.transport(transport);
+        }
+    });
+}
+
+// Pone en fase con un golpe de la detección de audio a todos los chases en marcha
+pub(crate) fn sync_to_beat(state: &DmxState) {
+    let now = Instant::now();
+    state.for_each_universe(|_, shared| {
+        if let Ok(mut chases) = shared.chases.lock() {
+            chases.values_mut().for_each(|chase| chase.beat(now));
+        }
+    });
+}
+
+pub(crate) fn clamp_bpm(bpm: f64) -> f64 {
+    bpm.clamp(MIN_BPM, MAX_BPM)
+}
+
+fn validate_step(step: &ChaseStep) -> Result<(), String> {
+    match step.keys().find(|channel| !(1..=512).contains(*channel)) {
+        Some(channel) => Err(format!("Canal DMX inválido en el chase: {channel}")),
+        None => Ok(()),
+    }
+}
+
+fn with_chase<T>(
+    state: &DmxState,
+    universe: u16,
+    name: &str,
+    f: impl FnOnce(&mut Chase) -> Result<T, String>,
+) -> Result<T, String> {
+    state.read_universe(universe, |shared| {
+        let mut chases = shared
+            .chases
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear los chases: {e}"))?;
+        let chase = chases
+            .get_mut(name)
+            .ok_or_else(|| format!("El chase \"{name}\" no existe en el universo {universe}"))?;
+        f(chase)
+    })
+}
+
+// Crea el chase o reemplaza sus pasos si ya existía (queda detenido, en loop hacia adelante)
+#[tauri::command]
+pub fn chase_create(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    name: String,
+    steps: Vec<ChaseStep>,
+    bpm: f64,
+) -> Result<(), String> {
+    validate_bpm(bpm)?;
+    steps.iter().try_for_each(validate_step)?;
+
+    state.with_universe(app_handle, universe, |shared| {
+        let mut chases = shared
+            .chases
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear los chases: {e}"))?;
+        chases.insert(
+            name,
+            Chase {
+                steps,
+                bpm,
+                direction: ChaseDirection::default(),
+                loop_mode: ChaseLoopMode::default(),
+                running: false,
+                current: 0,
+                reversed: false,
+                step_started: Instant::now(),
+                paused_at: None,
+            },
+        );
+        Ok(())
+    })
+}
+
+// Reemplaza el paso `index`; con `index` igual a la cantidad de pasos lo agrega al final
+#[tauri::command]
+pub fn chase_set_step(
+    state: State<'_, DmxState>,
+    universe: u16,
+    name: String,
+    index: usize,
+    step: ChaseStep,
+) -> Result<(), String> {
+    validate_step(&step)?;
+    with_chase(&state, universe, &name, |chase| {
+        match index.cmp(&chase.steps.len()) {
+            std::cmp::Ordering::Less => chase.steps[index] = step,
+            std::cmp::Ordering::Equal => chase.steps.push(step),
+            std::cmp::Ordering::Greater => {
+                return Err(format!("El chase \"{name}\" no tiene el paso {index}"));
+            }
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn chase_remove_step(
+    state: State<'_, DmxState>,
+    universe: u16,
+    name: String,
+    index: usize,
+) -> Result<(), String> {
+    with_chase(&state, universe, &name, |chase| {
+        if index >= chase.steps.len() {
+            return Err(format!("El chase \"{name}\" no tiene el paso {index}"));
+        }
+        chase.steps.remove(index);
+        if chase.current >= chase.steps.len() {
+            chase.current = 0;
+        }
+        if chase.steps.is_empty() {
+            chase.running = false;
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn chase_start(state: State<'_, DmxState>, universe: u16, name: String) -> Result<(), String> {
+    with_chase(&state, universe, &name, |chase| {
+        if chase.steps.is_empty() {
+            return Err(format!("El chase \"{name}\" no tiene pasos"));
+        }
+        chase.start();
+        info!(
+            "Chase \"{name}\" iniciado en el universo {universe} a {} BPM",
+            chase.bpm
+        );
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn chase_stop(state: State<'_, DmxState>, universe: u16, name: String) -> Result<(), String> {
+    with_chase(&state, universe, &name, |chase| {
+        chase.running = false;
+        chase.paused_at = None;
+        Ok(())
+    })
+}
+
+// Cambia el tempo sin reiniciar el paso en curso
+#[tauri::command]
+pub fn chase_set_bpm(
+    state: State<'_, DmxState>,
+    universe: u16,
+    name: String,
+    bpm: f64,
+) -> Result<(), String> {
+    validate_bpm(bpm)?;
+    with_chase(&state, universe, &name, |chase| {
+        chase.bpm = bpm;
+        Ok(())
+    })
+}
+
+// Cada golpe ajusta el tempo de los chases en marcha; devuelve el tempo calculado, que no
+// existe hasta el segundo golpe
+#[tauri::command]
+pub fn chase_tap_tempo(
+    state: State<'_, DmxState>,
+    tempo: State<'_, TempoState>,
+) -> Result<Option<f64>, String> {
+    let bpm = tempo.tap(Instant::now())?;
+    if let Some(bpm) = bpm {
+        set_running_bpm(&state, bpm);
+    }
+    Ok(bpm)
+}
+
+#[tauri::command]
+pub fn chase_set_mode(
+    state: State<'_, DmxState>,
+    universe: u16,
+    name: String,
+    direction: Option<ChaseDirection>,
+    loop_mode: Option<ChaseLoopMode>,
+) -> Result<(), String> {
+    with_chase(&state, universe, &name, |chase| {
+        if let Some(direction) = direction {
+            chase.direction = direction;
+            chase.reversed = false;
+        }
+        if let Some(loop_mode) = loop_mode {
+            chase.loop_mode = loop_mode;
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn chase_delete(state: State<'_, DmxState>, universe: u16, name: String) -> Result<(), String> {
+    state.read_universe(universe, |shared| {
+        shared
+            .chases
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear los chases: {e}"))?
+            .remove(&name)
+            .map(|_| ())
+            .ok_or_else(|| format!("El chase \"{name}\" no existe en el universo {universe}"))
+    })
+}
+
+#[tauri::command]
+pub fn chase_list(state: State<'_, DmxState>, universe: u16) -> Result<Vec<ChaseInfo>, String> {
+    state.read_universe(universe, |shared| {
+        let chases = shared
+            .chases
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear los chases: {e}"))?;
+        let mut infos = chases
+            .iter()
+            .map(|(name, chase)| chase.info(name))
+            .collect::<Vec<_>>();
+        infos.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(infos)
+    })
+}
@@ -0,0 +1,491 @@
+use crate::cues::{cue_goto, cue_release, CueState};
+use crate::dmx::{dmx_blackout, DmxState};
+use crate::fixture::FixtureState;
+use crate::scenes::{scene_recall, SceneState};
+use crate::storage;
+use crate::timeline::{timeline_load, timeline_play, TimelineState};
+use chrono::{DateTime, Datelike, Local, NaiveDate};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub const SCHEDULE_EVENT: &str = "schedule://fired";
+const SCHEDULE_FILE: &str = "schedule.json";
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+// Con un salto mayor del reloj (equipo suspendido, hora cambiada a mano) no se disparan los
+// eventos que quedaron en el medio
+const MAX_CATCH_UP_SECS: i64 = 5 * 60;
+// Distancia cenital de la salida y la puesta: el borde superior del sol con la refracción
+const SUN_ZENITH: f64 = 90.833;
+const MAX_OFFSET_MIN: i64 = 12 * 60;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Location {
+    latitude: f64,
+    // Positiva al este de Greenwich
+    longitude: f64,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "at", rename_all = "snake_case")]
+pub enum ScheduleTime {
+    /// Hora del reloj local
+    Clock { hour: u32, minute: u32 },
+    /// Salida del sol, corrida `offset_min` minutos (negativo = antes)
+    Sunrise {
+        #[serde(default)]
+        offset_min: i64,
+    },
+    /// Puesta del sol, corrida `offset_min` minutos
+    Sunset {
+        #[serde(default)]
+        offset_min: i64,
+    },
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ScheduleAction {
+    Scene {
+        scene: String,
+        #[serde(default)]
+        fade_ms: Option<u64>,
+    },
+    Cue {
+        cue: f64,
+        #[serde(default)]
+        fade_ms: Option<u64>,
+    },
+    /// Suelta la lista de cues
+    Release,
+    Blackout {
+        #[serde(default)]
+        fade_ms: Option<u64>,
+    },
+    /// Carga la línea de tiempo y la reproduce desde 0
+    Timeline { timeline: String },
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScheduleEvent {
+    id: u32,
+    name: String,
+    #[serde(flatten)]
+    time: ScheduleTime,
+    #[serde(flatten)]
+    action: ScheduleAction,
+    // Días de la semana, de 1 (lunes) a 7; vacío es todos los días
+    #[serde(default)]
+    days: Vec<u32>,
+    enabled: bool,
+}
+
+impl ScheduleEvent {
+    fn validate(&self) -> Result<(), String> {
+        match self.time {
+            ScheduleTime::Clock { hour, minute } if hour > 23 || minute > 59 => {
+                return Err(format!("Hora inválida: {hour:02}:{minute:02}"));
+            }
+            ScheduleTime::Sunrise { offset_min } | ScheduleTime::Sunset { offset_min }
+                if offset_min.abs() > MAX_OFFSET_MIN =>
+            {
+                return Err(format!(
+                    "El corrimiento tiene que estar entre -{MAX_OFFSET_MIN} y {MAX_OFFSET_MIN} minutos"
+                ));
+            }
+            _ => {}
+        }
+        if let Some(day) = self.days.iter().find(|day| !(1..=7).contains(*day)) {
+            return Err(format!("Día de la semana inválido: {day} (de 1 a 7)"));
+        }
+        Ok(())
+    }
+
+    // Cuándo ocurre el evento en la fecha local dada; `None` si ese día no toca, si el sol no
+    // sale o no se pone (cerca de los polos) o si la hora no existe por el cambio de horario
+    fn occurrence(&self, date: NaiveDate, location: Option<Location>) -> Option<DateTime<Local>> {
+        let weekday = date.weekday().number_from_monday();
+        if !self.days.is_empty() && !self.days.contains(&weekday) {
+            return None;
+        }
+        match self.time {
+            ScheduleTime::Clock { hour, minute } => date
+                .and_hms_opt(hour, minute, 0)?
+                .and_local_timezone(Local)
+                .earliest(),
+            ScheduleTime::Sunrise { offset_min } => {
+                Some(sun_event(date, location?, true)? + chrono::Duration::minutes(offset_min))
+            }
+            ScheduleTime::Sunset { offset_min } => {
+                Some(sun_event(date, location?, false)? + chrono::Duration::minutes(offset_min))
+            }
+        }
+    }
+}
+
+// Hora UTC (en horas) de la salida o la puesta del sol en el día del año dado, con el algoritmo
+// del Almanaque Náutico; precisión de un par de minutos
+fn solar_hours(day_of_year: u32, location: Location, rising: bool) -> Option<f64> {
+    let lng_hour = location.longitude / 15.0;
+    let base = if rising { 6.0 } else { 18.0 };
+    let t = day_of_year as f64 + (base - lng_hour) / 24.0;
+
+    let mean_anomaly = 0.9856 * t - 3.289;
+    let m = mean_anomaly.to_radians();
+    let true_longitude =
+        (mean_anomaly + 1.916 * m.sin() + 0.020 * (2.0 * m).sin() + 282.634).rem_euclid(360.0);
+    let l = true_longitude.to_radians();
+
+    // La ascensión recta queda en el mismo cuadrante que la longitud
+    let mut right_ascension = (0.91764 * l.tan()).atan().to_degrees().rem_euclid(360.0);
+    right_ascension +=
+        (true_longitude / 90.0).floor() * 90.0 - (right_ascension / 90.0).floor() * 90.0;
+    let right_ascension = right_ascension / 15.0;
+
+    let sin_dec = 0.39782 * l.sin();
+    let cos_dec = sin_dec.asin().cos();
+    let latitude = location.latitude.to_radians();
+    let cos_h =
+        (SUN_ZENITH.to_radians().cos() - sin_dec * latitude.sin()) / (cos_dec * latitude.cos());
+    if !(-1.0..=1.0).contains(&cos_h) {
+        return None;
+    }
+    let hour_angle = match rising {
+        true => 360.0 - cos_h.acos().to_degrees(),
+        false => cos_h.acos().to_degrees(),
+    } / 15.0;
+
+    let local_mean_time = hour_angle + right_ascension - 0.06571 * t - 6.622;
+    Some((local_mean_time - lng_hour).rem_euclid(24.0))
+}
+
+// Días cuyos eventos pueden caer entre `from` y `to`: con el corrimiento de hasta 12 h un
+// evento de un día puede pasar al anterior o al siguiente
+fn candidate_dates(from: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+    let last = to.succ_opt().unwrap_or(to);
+    let mut dates = Vec::new();
+    let mut date = Some(from.pred_opt().unwrap_or(from));
+    while let Some(current) = date.filter(|current| *current <= last) {
+        dates.push(current);
+        date = current.succ_opt();
+    }
+    dates
+}
+
+// La hora UTC puede caer en el día UTC anterior o siguiente al día local: se toma la que
+// convertida a hora local es de la fecha pedida
+fn sun_event(date: NaiveDate, location: Location, rising: bool) -> Option<DateTime<Local>> {
+    let hours = solar_hours(date.ordinal(), location, rising)?;
+    let offset = chrono::Duration::seconds((hours * 3600.0).round() as i64);
+    [date.pred_opt(), Some(date), date.succ_opt()]
+        .into_iter()
+        .flatten()
+        .filter_map(|day| day.and_hms_opt(0, 0, 0))
+        .map(|midnight| (midnight.and_utc() + offset).with_timezone(&Local))
+        .find(|at| at.date_naive() == date)
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct ScheduleConfig {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default)]
+    location: Option<Location>,
+    #[serde(default)]
+    events: Vec<ScheduleEvent>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct UpcomingEvent {
+    id: u32,
+    name: String,
+    at: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ScheduleStatus {
+    enabled: bool,
+    location: Option<Location>,
+    // Salida y puesta del sol de hoy, en hora local
+    sunrise: Option<String>,
+    sunset: Option<String>,
+    next: Option<UpcomingEvent>,
+}
+
+#[derive(Clone, Serialize)]
+struct FiredEvent {
+    id: u32,
+    name: String,
+    at: String,
+    error: Option<String>,
+}
+
+fn format_time(at: DateTime<Local>) -> String {
+    at.format("%Y-%m-%d %H:%M").to_string()
+}
+
+#[derive(Default)]
+pub struct ScheduleState {
+    config: Mutex<ScheduleConfig>,
+    path: Mutex<Option<PathBuf>>,
+}
+
+impl ScheduleState {
+    pub fn load(&self, app_handle: &AppHandle) {
+        let Some(path) = storage::config_file(app_handle, SCHEDULE_FILE) else {
+            return;
+        };
+
+        if let Some(config) = storage::load_json::<ScheduleConfig>(&path) {
+            if let Ok(mut current) = self.config.lock() {
+                *current = config;
+            }
+        }
+
+        if let Ok(mut current) = self.path.lock() {
+            *current = Some(path);
+        }
+    }
+
+    fn lock_config(&self) -> Result<std::sync::MutexGuard<'_, ScheduleConfig>, String> {
+        self.config
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la programación horaria: {e}"))
+    }
+
+    fn save(&self, config: &ScheduleConfig) -> Result<(), String> {
+        let path = self
+            .path
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la programación horaria: {e}"))?
+            .clone();
+
+        match path {
+            Some(path) => storage::save_json(&path, config),
+            None => Ok(()),
+        }
+    }
+
+    fn status(&self) -> Result<ScheduleStatus, String> {
+        let config = self.lock_config()?;
+        let now = Local::now();
+        let today = now.date_naive();
+        let sun = |rising| {
+            config
+                .location
+                .and_then(|location| sun_event(today, location, rising))
+                .map(format_time)
+        };
+
+        // El próximo evento de hoy o de mañana
+        let location = config.location;
+        let next = candidate_dates(today, today.succ_opt().unwrap_or(today))
+            .into_iter()
+            .flat_map(|date| {
+                config
+                    .events
+                    .iter()
+                    .filter(|event| event.enabled)
+                    .filter_map(move |event| Some((event.occurrence(date, location)?, event)))
+            })
+            .filter(|(at, _)| *at > now)
+            .min_by_key(|(at, _)| *at)
+            .map(|(at, event)| UpcomingEvent {
+                id: event.id,
+                name: event.name.clone(),
+                at: format_time(at),
+            });
+
+        Ok(ScheduleStatus {
+            enabled: config.enabled,
+            location: config.location,
+            sunrise: sun(true),
+            sunset: sun(false),
+            next,
+        })
+    }
+
+    // Eventos que ocurren en (from, to], en orden
+    fn due(
+        &self,
+        from: DateTime<Local>,
+        to: DateTime<Local>,
+    ) -> Vec<(DateTime<Local>, ScheduleEvent)> {
+        let Ok(config) = self.lock_config() else {
+            return Vec::new();
+        };
+        if !config.enabled {
+            return Vec::new();
+        }
+        let location = config.location;
+        let mut due: Vec<_> = candidate_dates(from.date_naive(), to.date_naive())
+            .into_iter()
+            .flat_map(|date| {
+                config
+                    .events
+                    .iter()
+                    .filter(|event| event.enabled)
+                    .filter_map(move |event| Some((event.occurrence(date, location)?, event)))
+            })
+            .filter(|(at, _)| *at > from && *at <= to)
+            .map(|(at, event)| (at, event.clone()))
+            .collect();
+        due.sort_by_key(|(at, _)| *at);
+        due
+    }
+}
+
+pub(crate) fn run_action(app_handle: &AppHandle, action: &ScheduleAction) -> Result<(), String> {
+    let app = || app_handle.clone();
+    let dmx = || app_handle.state::<DmxState>();
+    let cues = || app_handle.state::<CueState>();
+
+    match action.clone() {
+        ScheduleAction::Scene { scene, fade_ms } => scene_recall(
+            app(),
+            app_handle.state::<SceneState>(),
+            dmx(),
+            scene,
+            fade_ms,
+        ),
+        ScheduleAction::Cue { cue, fade_ms } => {
+            cue_goto(app(), cues(), dmx(), cue, fade_ms).map(|_| ())
+        }
+        ScheduleAction::Release => cue_release(app(), cues(), dmx()).map(|_| ()),
+        ScheduleAction::Blackout { fade_ms } => dmx_blackout(dmx(), None, fade_ms),
+        ScheduleAction::Timeline { timeline } => {
+            let timelines = || app_handle.state::<TimelineState>();
+            timeline_load(
+                app(),
+                timelines(),
+                app_handle.state::<FixtureState>(),
+                timeline,
+            )?;
+            timeline_play(app(), timelines()).map(|_| ())
+        }
+    }
+}
+
+pub fn start_schedule(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    thread::spawn(move || {
+        // Lo que ya pasó al arrancar no se dispara
+        let mut last = Local::now();
+        loop {
+            thread::sleep(CHECK_INTERVAL);
+            let now = Local::now();
+            if now < last || now.timestamp() - last.timestamp() > MAX_CATCH_UP_SECS {
+                warn!("El reloj saltó: no se disparan los eventos programados del medio");
+                last = now;
+                continue;
+            }
+
+            let due = app_handle.state::<ScheduleState>().due(last, now);
+            last = now;
+            for (at, event) in due {
+                let result = run_action(&app_handle, &event.action);
+                match &result {
+                    Ok(()) => info!("Evento programado \"{}\" disparado", event.name),
+                    Err(err) => {
+                        error!(
+                            "No se pudo disparar el evento programado \"{}\": {err}",
+                            event.name
+                        )
+                    }
+                }
+                let fired = FiredEvent {
+                    id: event.id,
+                    name: event.name,
+                    at: format_time(at),
+                    error: result.err(),
+                };
+                if let Err(err) = app_handle.emit(SCHEDULE_EVENT, fired) {
+                    error!("No se pudo emitir el evento programado: {err}");
+                }
+            }
+        }
+    });
+}
+
+// Crea el evento o lo reemplaza si ya existía con ese id
+#[tauri::command]
+pub fn schedule_set(
+    state: State<'_, ScheduleState>,
+    event: ScheduleEvent,
+) -> Result<ScheduleEvent, String> {
+    event.validate()?;
+    let mut config = state.lock_config()?;
+    match config
+        .events
+        .iter_mut()
+        .find(|stored| stored.id == event.id)
+    {
+        Some(stored) => *stored = event.clone(),
+        None => config.events.push(event.clone()),
+    }
+    config.events.sort_by_key(|stored| stored.id);
+    state.save(&config)?;
+    Ok(event)
+}
+
+#[tauri::command]
+pub fn schedule_delete(state: State<'_, ScheduleState>, id: u32) -> Result<(), String> {
+    let mut config = state.lock_config()?;
+    let before = config.events.len();
+    config.events.retain(|event| event.id != id);
+    if config.events.len() == before {
+        return Err(format!("El evento programado {id} no existe"));
+    }
+    state.save(&config)
+}
+
+#[tauri::command]
+pub fn schedule_list(state: State<'_, ScheduleState>) -> Result<Vec<ScheduleEvent>, String> {
+    Ok(state.lock_config()?.events.clone())
+}
+
+// Hace falta para los eventos de salida y puesta del sol
+#[tauri::command]
+pub fn schedule_set_location(
+    state: State<'_, ScheduleState>,
+    latitude: f64,
+    longitude: f64,
+) -> Result<ScheduleStatus, String> {
+    if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+        return Err(format!("Coordenadas inválidas: {latitude}, {longitude}"));
+    }
+    {
+        let mut config = state.lock_config()?;
+        config.location = Some(Location {
+            latitude,
+            longitude,
+        });
+        state.save(&config)?;
+    }
+    state.status()
+}
+
+#[tauri::command]
+pub fn schedule_set_enabled(
+    state: State<'_, ScheduleState>,
+    enabled: bool,
+) -> Result<ScheduleStatus, String> {
+    {
+        let mut config = state.lock_config()?;
+        config.enabled = enabled;
+        state.save(&config)?;
+    }
+    if enabled {
+        info!("Programación horaria activada");
+    }
+    state.status()
+}
+
+#[tauri::command]
+pub fn schedule_status(state: State<'_, ScheduleState>) -> Result<ScheduleStatus, String> {
+    state.status()
+}
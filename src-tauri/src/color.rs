@@ -0,0 +1,278 @@
+use crate::dmx::DmxState;
+use crate::fixture::{Attribute, FixtureState};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::{AppHandle, State};
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorValue {
+    /// "#rrggbb" (el `#` es opcional)
+    Hex(String),
+    /// Tono en grados; saturación y valor entre 0 y 1
+    Hsv { h: f64, s: f64, v: f64 },
+}
+
+// Corrección para que el mismo "blanco" se vea igual en fixtures LED distintos
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WhiteBalance {
+    /// Multiplicadores por emisor entre 0 y 1
+    Gains {
+        red: f64,
+        green: f64,
+        blue: f64,
+        #[serde(default)]
+        white: Option<f64>,
+    },
+    /// Temperatura de color objetivo del blanco, en kelvin
+    Temperature { kelvin: f64 },
+}
+
+const MIN_KELVIN: f64 = 1000.0;
+const MAX_KELVIN: f64 = 40000.0;
+
+impl WhiteBalance {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            WhiteBalance::Gains {
+                red,
+                green,
+                blue,
+                white,
+            } => {
+                let gains = [*red, *green, *blue, white.unwrap_or(1.0)];
+                if gains.iter().any(|gain| !(0.0..=1.0).contains(gain)) {
+                    return Err("Las ganancias de balance de blanco van entre 0 y 1".to_string());
+                }
+            }
+            WhiteBalance::Temperature { kelvin } => {
+                if !(MIN_KELVIN..=MAX_KELVIN).contains(kelvin) {
+                    return Err(format!(
+                        "La temperatura de color debe estar entre {MIN_KELVIN} y {MAX_KELVIN} K"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Ganancia (0-1) para un canal con este atributo
+    pub(crate) fn gain(&self, attribute: Attribute) -> f64 {
+        let (red, green, blue, white) = match self {
+            WhiteBalance::Gains {
+                red,
+                green,
+                blue,
+                white,
+            } => (*red, *green, *blue, white.unwrap_or(1.0)),
+            WhiteBalance::Temperature { kelvin } => {
+                let Rgb { r, g, b } = kelvin_to_rgb(*kelvin);
+                (r, g, b, 1.0)
+            }
+        };
+
+        match attribute {
+            Attribute::Red => red,
+            Attribute::Green => green,
+            Attribute::Blue => blue,
+            Attribute::White => white,
+            _ => 1.0,
+        }
+    }
+}
+
+// Color de un cuerpo negro a esa temperatura (aproximación de Tanner Helland), normalizado
+// para que el primario más fuerte quede en 1
+fn kelvin_to_rgb(kelvin: f64) -> Rgb {
+    let t = kelvin / 100.0;
+    let r = if t <= 66.0 {
+        255.0
+    } else {
+        329.698727446 * (t - 60.0).powf(-0.1332047592)
+    };
+    let g = if t <= 66.0 {
+        99.4708025861 * t.ln() - 161.1195681661
+    } else {
+        288.1221695283 * (t - 60.0).powf(-0.0755148492)
+    };
+    let b = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.5177312231 * (t - 10.0).ln() - 305.0447927307
+    };
+
+    let [r, g, b] = [r, g, b].map(|value| value.clamp(0.0, 255.0));
+    let max = r.max(g).max(b);
+    Rgb {
+        r: r / max,
+        g: g / max,
+        b: b / max,
+    }
+}
+
+// Color en RGB lineal, cada componente entre 0 y 1
+#[derive(Clone, Copy)]
+pub(crate) struct Rgb {
+    r: f64,
+    g: f64,
+    b: f64,
+}
+
+impl Rgb {
+    pub(crate) const BLACK: Rgb = Rgb {
+        r: 0.0,
+        g: 0.0,
+        b: 0.0,
+    };
+
+    pub(crate) fn scaled(self, amount: f64) -> Rgb {
+        Rgb {
+            r: self.r * amount,
+            g: self.g * amount,
+            b: self.b * amount,
+        }
+    }
+}
+
+impl ColorValue {
+    pub(crate) fn to_rgb(&self) -> Result<Rgb, String> {
+        match self {
+            ColorValue::Hex(hex) => parse_hex(hex),
+            ColorValue::Hsv { h, s, v } => hsv_to_rgb(*h, *s, *v),
+        }
+    }
+}
+
+fn parse_hex(hex: &str) -> Result<Rgb, String> {
+    let digits = hex.trim().trim_start_matches('#');
+    let component = |idx: usize| {
+        digits
+            .get(idx..idx + 2)
+            .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+            .map(|value| value as f64 / 255.0)
+    };
+
+    match (digits.len(), component(0), component(2), component(4)) {
+        (6, Some(r), Some(g), Some(b)) => Ok(Rgb { r, g, b }),
+        _ => Err(format!("Color hexadecimal inválido: {hex}")),
+    }
+}
+
+pub(crate) fn hsv_to_rgb(h: f64, s: f64, v: f64) -> Result<Rgb, String> {
+    if !h.is_finite() || !(0.0..=1.0).contains(&s) || !(0.0..=1.0).contains(&v) {
+        return Err(format!("Color HSV inválido: {h}, {s}, {v}"));
+    }
+
+    let sector = h.rem_euclid(360.0) / 60.0;
+    let f = sector.fract();
+    let p = v * (1.0 - s);
+    let q = v * (1.0 - s * f);
+    let t = v * (1.0 - s * (1.0 - f));
+
+    let (r, g, b) = match sector as u32 {
+        0 => (v, t, p),
+        1 => (q, v, p),
+        2 => (p, v, t),
+        3 => (p, q, v),
+        4 => (t, p, v),
+        _ => (v, p, q),
+    };
+    Ok(Rgb { r, g, b })
+}
+
+fn to_dmx(value: f64) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+// Reparte el color entre los emisores que tiene el fixture. En RGBW/RGBA la parte común
+// de los tres primarios se pasa al blanco (y al ámbar) para aprovechar esos LEDs.
+pub(crate) fn emitter_levels(
+    color: Rgb,
+    emitters: &[Attribute],
+) -> Result<HashMap<Attribute, u8>, String> {
+    let has = |attribute| emitters.contains(&attribute);
+    let mut values = HashMap::new();
+
+    if has(Attribute::Red) || has(Attribute::Green) || has(Attribute::Blue) {
+        let Rgb {
+            mut r,
+            mut g,
+            mut b,
+        } = color;
+
+        if has(Attribute::White) {
+            let white = r.min(g).min(b);
+            r -= white;
+            g -= white;
+            b -= white;
+            values.insert(Attribute::White, to_dmx(white));
+        }
+        if has(Attribute::Amber) {
+            // El ámbar se aproxima como rojo pleno con medio verde
+            let amber = r.min(g * 2.0);
+            r -= amber;
+            g -= amber / 2.0;
+            values.insert(Attribute::Amber, to_dmx(amber));
+        }
+
+        for (attribute, value) in [
+            (Attribute::Red, r),
+            (Attribute::Green, g),
+            (Attribute::Blue, b),
+        ] {
+            if has(attribute) {
+                values.insert(attribute, to_dmx(value));
+            }
+        }
+        return Ok(values);
+    }
+
+    if has(Attribute::Cyan) || has(Attribute::Magenta) || has(Attribute::Yellow) {
+        // Mezcla sustractiva: cada filtro quita su complementario
+        for (attribute, value) in [
+            (Attribute::Cyan, 1.0 - color.r),
+            (Attribute::Magenta, 1.0 - color.g),
+            (Attribute::Yellow, 1.0 - color.b),
+        ] {
+            if has(attribute) {
+                values.insert(attribute, to_dmx(value));
+            }
+        }
+        return Ok(values);
+    }
+
+    Err("El fixture no tiene emisores de color".to_string())
+}
+
+// Pone el fixture en un color HSV o hexadecimal, convertido a sus emisores reales
+#[tauri::command]
+pub fn fixture_set_color(
+    app_handle: AppHandle,
+    fixtures: State<'_, FixtureState>,
+    dmx: State<'_, DmxState>,
+    id: u32,
+    color: ColorValue,
+    fade_ms: Option<u64>,
+) -> Result<(), String> {
+    let rgb = color.to_rgb()?;
+    let values = emitter_levels(rgb, &fixtures.attributes(id)?)?;
+    fixtures.write_attributes(&app_handle, &dmx, id, &values, fade_ms)
+}
+
+// Guarda la calibración del fixture (o la borra con `null`); la corrección se aplica al
+// frame de salida, después del merge y los efectos
+#[tauri::command]
+pub fn fixture_set_white_balance(
+    app_handle: AppHandle,
+    fixtures: State<'_, FixtureState>,
+    id: u32,
+    balance: Option<WhiteBalance>,
+) -> Result<(), String> {
+    if let Some(balance) = &balance {
+        balance.validate()?;
+    }
+    fixtures.set_white_balance(&app_handle, id, balance)
+}
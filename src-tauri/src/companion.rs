@@ -0,0 +1,391 @@
+use crate::cues::CueState;
+use crate::remote::{self, RemoteCommand};
+use crate::scenes::SceneState;
+use log::{debug, error, info, warn};
+use serde::Serialize;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+// El mismo puerto en TCP y en UDP
+pub const DEFAULT_COMPANION_PORT: u16 = 51234;
+const MAX_CLIENTS: usize = 16;
+// Cada cuánto se revisa la señal de parada y si cambió lo que se informa
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_LINE: usize = 1024;
+
+#[derive(Clone, Serialize)]
+pub struct CompanionStatus {
+    listening: bool,
+    port: Option<u16>,
+    clients: usize,
+}
+
+struct CompanionServer {
+    port: u16,
+    stopped: Arc<AtomicBool>,
+    clients: Arc<AtomicUsize>,
+    handles: Vec<thread::JoinHandle<()>>,
+}
+
+#[derive(Default)]
+pub struct CompanionState {
+    server: Mutex<Option<CompanionServer>>,
+}
+
+impl CompanionState {
+    fn lock_server(&self) -> Result<std::sync::MutexGuard<'_, Option<CompanionServer>>, String> {
+        self.server
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el servidor de Companion: {e}"))
+    }
+}
+
+// Lo que se le informa a Companion para pintar los botones
+#[derive(Clone, PartialEq)]
+struct Feedback {
+    cue: Option<f64>,
+    scene: Option<String>,
+}
+
+impl Feedback {
+    fn current(app_handle: &AppHandle) -> Self {
+        Self {
+            cue: app_handle.state::<CueState>().active_number(),
+            scene: app_handle.state::<SceneState>().active(),
+        }
+    }
+
+    // Solo lo que cambió desde `sent`; sin `sent`, todo
+    fn lines(&self, sent: Option<&Feedback>) -> String {
+        let mut lines = String::new();
+        if sent.map(|sent| sent.cue) != Some(self.cue) {
+            match self.cue {
+                Some(number) => lines.push_str(&format!("CUE {number}\n")),
+                None => lines.push_str("CUE -\n"),
+            }
+        }
+        if sent.map(|sent| &sent.scene) != Some(&self.scene) {
+            lines.push_str(&format!("SCENE {}\n", self.scene.as_deref().unwrap_or("-")));
+        }
+        lines
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(text: Option<&str>, what: &str) -> Result<T, String> {
+    let text = text.ok_or_else(|| format!("Falta {what}"))?;
+    text.parse().map_err(|_| format!("{what} inválido: {text}"))
+}
+
+fn loop_command(words: &[&str]) -> Result<RemoteCommand, String> {
+    let track = match words.get(1) {
+        Some(track) => Some(parse_number(Some(track), "la pista")?),
+        None => None,
+    };
+    let action = words.first().map(|action| action.to_ascii_lowercase());
+    Ok(match action.as_deref() {
+        Some("record") => RemoteCommand::LoopRecord { track },
+        Some("overdub") => RemoteCommand::LoopOverdub { track },
+        Some("play") => RemoteCommand::LoopPlay { track },
+        Some("stop") => RemoteCommand::LoopStop { track },
+        Some("clear") => RemoteCommand::LoopClear { track },
+        Some("undo") => RemoteCommand::LoopUndo { track },
+        _ => return Err("LOOP necesita RECORD, OVERDUB, PLAY, STOP, CLEAR o UNDO".to_string()),
+    })
+}
+
+// Una orden por línea: la palabra clave (sin importar mayúsculas) y sus argumentos
+fn command(line: &str) -> Result<Option<RemoteCommand>, String> {
+    let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let rest = rest.trim();
+    let words = rest.split_whitespace().collect::<Vec<_>>();
+    let command = match keyword.to_ascii_uppercase().as_str() {
+        "GO" => RemoteCommand::CueGo,
+        "BACK" => RemoteCommand::CueBack,
+        "RELEASE" => RemoteCommand::CueRelease,
+        "GOTO" => RemoteCommand::CueGoto {
+            number: parse_number(words.first().copied(), "el número de cue")?,
+            fade_ms: None,
+        },
+        // El nombre es el resto de la línea, con espacios
+        "SCENE" if !rest.is_empty() => RemoteCommand::SceneRecall {
+            name: rest.to_string(),
+            fade_ms: None,
+        },
+        "SCENE" => return Err("SCENE necesita el nombre de la escena".to_string()),
+        "SUB" => RemoteCommand::SubmasterSetLevel {
+            id: parse_number(words.first().copied(), "el grupo")?,
+            value: parse_number(words.get(1).copied(), "el nivel")?,
+        },
+        "CHANNEL" => RemoteCommand::DmxSetChannel {
+            universe: parse_number(words.first().copied(), "el universo")?,
+            channel: parse_number(words.get(1).copied(), "el canal")?,
+            value: parse_number(words.get(2).copied(), "el nivel")?,
+        },
+        "BLACKOUT" => RemoteCommand::DmxBlackout {
+            universe: None,
+            fade_ms: None,
+        },
+        "LOOP" => loop_command(&words)?,
+        "TRANSPORT" => match words
+            .first()
+            .map(|word| word.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("start") => RemoteCommand::TransportStart,
+            Some("stop") => RemoteCommand::TransportStop,
+            _ => return Err("TRANSPORT necesita START o STOP".to_string()),
+        },
+        // Lo contesta cada conexión con el estado completo
+        "STATE" => return Ok(None),
+        _ => return Err(format!("Orden desconocida: {keyword}")),
+    };
+    Ok(Some(command))
+}
+
+// Devuelve la respuesta a la orden: `OK` o `ERR <motivo>`
+fn run_line(app_handle: &AppHandle, line: &str) -> String {
+    let result = command(line).and_then(|command| match command {
+        Some(command) => remote::run(app_handle, command).map(|_| ()),
+        None => Ok(()),
+    });
+    match result {
+        Ok(()) => "OK\n".to_string(),
+        Err(err) => {
+            warn!("Orden de Companion fallida ({line}): {err}");
+            format!("ERR {err}\n")
+        }
+    }
+}
+
+fn timed_out(err: &std::io::Error) -> bool {
+    matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+// Por TCP además de las respuestas llega el feedback: el estado al conectarse y después cada
+// cambio de cue o escena
+fn run_client(app_handle: AppHandle, stream: TcpStream, from: SocketAddr, stopped: &AtomicBool) {
+    let setup = stream
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .and_then(|_| stream.try_clone());
+    let mut writer = match setup {
+        Ok(writer) => writer,
+        Err(err) => {
+            error!("No se pudo configurar la conexión de Companion de {from}: {err}");
+            return;
+        }
+    };
+    info!("Companion conectado: {from}");
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    let mut sent: Option<Feedback> = None;
+
+    while !stopped.load(Ordering::Relaxed) {
+        let mut reply = String::new();
+        // Lo leído antes de un timeout queda en `line` hasta que llega el fin de línea
+        match reader.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) if line.ends_with('\n') => {
+                let received = line.trim();
+                if !received.is_empty() {
+                    debug!("Companion de {from}: {received}");
+                    reply = run_line(&app_handle, received);
+                    if received.eq_ignore_ascii_case("STATE") {
+                        sent = None;
+                    }
+                }
+                line.clear();
+            }
+            Ok(_) => {}
+            Err(err) if timed_out(&err) => {}
+            Err(err) => {
+                warn!("Error en la conexión de Companion de {from}: {err}");
+                break;
+            }
+        }
+        if line.len() > MAX_LINE {
+            reply.push_str("ERR Línea demasiado larga\n");
+            line.clear();
+        }
+
+        let feedback = Feedback::current(&app_handle);
+        reply.push_str(&feedback.lines(sent.as_ref()));
+        sent = Some(feedback);
+        if !reply.is_empty() {
+            if let Err(err) = writer.write_all(reply.as_bytes()) {
+                warn!("No se pudo escribir a Companion ({from}): {err}");
+                break;
+            }
+        }
+    }
+    info!("Companion desconectado: {from}");
+}
+
+fn run_tcp(
+    app_handle: AppHandle,
+    listener: TcpListener,
+    stopped: Arc<AtomicBool>,
+    clients: Arc<AtomicUsize>,
+) {
+    let mut connections = Vec::new();
+
+    while !stopped.load(Ordering::Relaxed) {
+        let (stream, from) = match listener.accept() {
+            Ok(accepted) => accepted,
+            Err(err) if timed_out(&err) => {
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+            Err(err) => {
+                error!("Error al aceptar una conexión de Companion: {err}");
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+        };
+        connections.retain(|connection: &thread::JoinHandle<()>| !connection.is_finished());
+        if connections.len() >= MAX_CLIENTS {
+            warn!("Conexión de Companion de {from} rechazada: ya hay {MAX_CLIENTS}");
+            continue;
+        }
+        // El listener es no bloqueante; la conexión no
+        if let Err(err) = stream.set_nonblocking(false) {
+            error!("No se pudo configurar la conexión de Companion de {from}: {err}");
+            continue;
+        }
+        let app_handle = app_handle.clone();
+        let stopped = stopped.clone();
+        let clients = clients.clone();
+        connections.push(thread::spawn(move || {
+            clients.fetch_add(1, Ordering::Relaxed);
+            run_client(app_handle, stream, from, &stopped);
+            clients.fetch_sub(1, Ordering::Relaxed);
+        }));
+    }
+
+    for connection in connections {
+        if connection.join().is_err() {
+            error!("Un hilo de conexión de Companion terminó con pánico");
+        }
+    }
+}
+
+// Por UDP cada datagrama puede traer varias líneas; la respuesta vuelve al que las mandó
+fn run_udp(app_handle: AppHandle, socket: UdpSocket, stopped: Arc<AtomicBool>) {
+    let mut buffer = [0u8; MAX_LINE];
+    while !stopped.load(Ordering::Relaxed) {
+        let (len, from) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(err) if timed_out(&err) => continue,
+            Err(err) => {
+                error!("Error al recibir de Companion por UDP: {err}");
+                thread::sleep(Duration::from_millis(200));
+                continue;
+            }
+        };
+        let text = String::from_utf8_lossy(&buffer[..len]);
+        let reply = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| match line.eq_ignore_ascii_case("STATE") {
+                true => Feedback::current(&app_handle).lines(None),
+                false => run_line(&app_handle, line),
+            })
+            .collect::<String>();
+        if let Err(err) = socket.send_to(reply.as_bytes(), from) {
+            warn!("No se pudo responder a Companion ({from}): {err}");
+        }
+    }
+}
+
+fn stop_server(server: CompanionServer) {
+    server.stopped.store(true, Ordering::Relaxed);
+    for handle in server.handles {
+        if handle.join().is_err() {
+            error!("El hilo del servidor de Companion terminó con pánico");
+        }
+    }
+    info!("Servidor de Companion detenido");
+}
+
+// Para el módulo "Generic TCP/UDP" de Bitfocus Companion: órdenes de texto, una por línea
+#[tauri::command]
+pub fn companion_listen_start(
+    app_handle: AppHandle,
+    state: State<'_, CompanionState>,
+    bind_ip: Option<String>,
+    port: Option<u16>,
+) -> Result<CompanionStatus, String> {
+    let bind_ip = match bind_ip {
+        Some(ip) => ip
+            .parse::<Ipv4Addr>()
+            .map_err(|e| format!("IP de escucha de Companion inválida ({ip}): {e}"))?,
+        None => Ipv4Addr::UNSPECIFIED,
+    };
+    let port = port.unwrap_or(DEFAULT_COMPANION_PORT);
+
+    let mut server = state.lock_server()?;
+    // Se reinicia con la configuración nueva
+    if let Some(previous) = server.take() {
+        stop_server(previous);
+    }
+
+    let listener = TcpListener::bind((bind_ip, port))
+        .map_err(|e| format!("No se pudo escuchar TCP en {bind_ip}:{port}: {e}"))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| format!("No se pudo configurar el socket TCP de Companion: {e}"))?;
+    let socket = UdpSocket::bind((bind_ip, port))
+        .map_err(|e| format!("No se pudo escuchar UDP en {bind_ip}:{port}: {e}"))?;
+    socket
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .map_err(|e| format!("No se pudo configurar el socket UDP de Companion: {e}"))?;
+
+    info!("Escuchando a Companion en {bind_ip}:{port} (TCP y UDP)");
+    let stopped = Arc::new(AtomicBool::new(false));
+    let clients = Arc::new(AtomicUsize::new(0));
+    let tcp = {
+        let (app_handle, stopped, clients) = (app_handle.clone(), stopped.clone(), clients.clone());
+        thread::spawn(move || run_tcp(app_handle, listener, stopped, clients))
+    };
+    let udp = {
+        let stopped = stopped.clone();
+        thread::spawn(move || run_udp(app_handle, socket, stopped))
+    };
+    *server = Some(CompanionServer {
+        port,
+        stopped,
+        clients,
+        handles: vec![tcp, udp],
+    });
+    Ok(CompanionStatus {
+        listening: true,
+        port: Some(port),
+        clients: 0,
+    })
+}
+
+#[tauri::command]
+pub fn companion_listen_stop(state: State<'_, CompanionState>) -> Result<(), String> {
+    let server = state.lock_server()?.take();
+    if let Some(server) = server {
+        stop_server(server);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn companion_status(state: State<'_, CompanionState>) -> Result<CompanionStatus, String> {
+    let server = state.lock_server()?;
+    Ok(CompanionStatus {
+        listening: server.is_some(),
+        port: server.as_ref().map(|server| server.port),
+        clients: server
+            .as_ref()
+            .map_or(0, |server| server.clients.load(Ordering::Relaxed)),
+    })
+}
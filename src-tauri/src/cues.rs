@@ -0,0 +1,396 @@
+use crate::dmx::DmxState;
+use crate::msc::{self, MscCommand};
+use crate::mtc::Timecode;
+use crate::storage;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+
+pub const CUE_EVENT: &str = "cues://state";
+const CUES_FILE: &str = "cues.json";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Cue {
+    number: f64,
+    name: String,
+    fade_ms: u64,
+    // Niveles (512 canales, sin start code) por universo
+    universes: BTreeMap<u16, Vec<u8>>,
+    // Se dispara sola cuando el timecode MIDI entrante llega a este cuadro
+    #[serde(default)]
+    timecode: Option<Timecode>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct CueStatus {
+    active: Option<f64>,
+    pending: Option<f64>,
+}
+
+#[derive(Default)]
+struct CueStack {
+    // Siempre ordenadas por número
+    cues: Vec<Cue>,
+    active: Option<usize>,
+}
+
+impl CueStack {
+    fn status(&self) -> CueStatus {
+        let pending = match self.active {
+            Some(idx) => idx + 1,
+            None => 0,
+        };
+        CueStatus {
+            active: self.active.map(|idx| self.cues[idx].number),
+            pending: self.cues.get(pending).map(|cue| cue.number),
+        }
+    }
+
+    fn position(&self, number: f64) -> Option<usize> {
+        self.cues.iter().position(|cue| cue.number == number)
+    }
+}
+
+#[derive(Default)]
+pub struct CueState {
+    stack: Mutex<CueStack>,
+    path: Mutex<Option<PathBuf>>,
+}
+
+impl CueState {
+    pub fn load(&self, app_handle: &AppHandle) {
+        let Some(path) = storage::config_file(app_handle, CUES_FILE) else {
+            return;
+        };
+
+        if let Some(mut cues) = storage::load_json::<Vec<Cue>>(&path) {
+            cues.sort_by(|a, b| a.number.total_cmp(&b.number));
+            if let Ok(mut stack) = self.stack.lock() {
+                stack.cues = cues;
+                stack.active = None;
+            }
+        }
+
+        if let Ok(mut current) = self.path.lock() {
+            *current = Some(path);
+        }
+    }
+
+    pub(crate) fn export(&self) -> Result<Vec<Cue>, String> {
+        Ok(self.lock_stack()?.cues.clone())
+    }
+
+    pub(crate) fn replace(&self, mut cues: Vec<Cue>) -> Result<(), String> {
+        cues.sort_by(|a, b| a.number.total_cmp(&b.number));
+        let mut stack = self.lock_stack()?;
+        stack.cues = cues;
+        stack.active = None;
+        self.save(&stack)
+    }
+
+    // Número de la cue en escena, si hay alguna
+    pub(crate) fn active_number(&self) -> Option<f64> {
+        let stack = self.stack.lock().ok()?;
+        stack.active.map(|idx| stack.cues[idx].number)
+    }
+
+    fn lock_stack(&self) -> Result<std::sync::MutexGuard<'_, CueStack>, String> {
+        self.stack
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la lista de cues: {e}"))
+    }
+
+    fn save(&self, stack: &CueStack) -> Result<(), String> {
+        let path = self
+            .path
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la lista de cues: {e}"))?
+            .clone();
+
+        match path {
+            Some(path) => storage::save_json(&path, &stack.cues),
+            None => Ok(()),
+        }
+    }
+
+    // GO: la cue siguiente o, con número, esa cue; sin `fade_ms` usa el fade de la cue
+    pub(crate) fn go(
+        &self,
+        app_handle: &AppHandle,
+        dmx: &DmxState,
+        number: Option<f64>,
+        fade_ms: Option<u64>,
+    ) -> Result<CueStatus, String> {
+        let mut stack = self.lock_stack()?;
+        let idx = match number {
+            Some(number) => stack
+                .position(number)
+                .ok_or_else(|| format!("La cue {number} no existe"))?,
+            None => {
+                let next = stack.active.map_or(0, |idx| idx + 1);
+                if next >= stack.cues.len() {
+                    return Err("No hay más cues en la lista".to_string());
+                }
+                next
+            }
+        };
+        self.fire(app_handle, dmx, &mut stack, idx, fade_ms)
+    }
+
+    // Cues que se disparan por timecode, con su número
+    pub(crate) fn timecode_cues(&self) -> Vec<(Timecode, f64)> {
+        let Ok(stack) = self.stack.lock() else {
+            return Vec::new();
+        };
+        stack
+            .cues
+            .iter()
+            .filter_map(|cue| Some((cue.timecode?, cue.number)))
+            .collect()
+    }
+
+    // Congela o retoma el fade de la cue en curso en todos los universos
+    pub(crate) fn pause(&self, dmx: &DmxState, paused: bool) -> Result<(), String> {
+        let mut result = Ok(());
+        dmx.for_each_universe(|_, shared| {
+            if let Err(err) = shared.pause_playback(paused) {
+                result = Err(err);
+            }
+        });
+        result
+    }
+
+    // Activa la cue `idx`: el crossfade lo hace el loop DMX de cada universo
+    fn fire(
+        &self,
+        app_handle: &AppHandle,
+        dmx: &DmxState,
+        stack: &mut CueStack,
+        idx: usize,
+        fade_ms: Option<u64>,
+    ) -> Result<CueStatus, String> {
+        let cue = &stack.cues[idx];
+        let duration = Duration::from_millis(fade_ms.unwrap_or(cue.fade_ms));
+
+        for (universe, levels) in &cue.universes {
+            dmx.with_universe(app_handle.clone(), *universe, |shared| {
+                shared.start_playback(levels, duration)
+            })?;
+        }
+
+        info!(
+            "Cue {} \"{}\" en {} ms",
+            cue.number,
+            cue.name,
+            duration.as_millis()
+        );
+        stack.active = Some(idx);
+
+        let status = stack.status();
+        emit_status(app_handle, &status);
+        Ok(status)
+    }
+}
+
+fn emit_status(app_handle: &AppHandle, status: &CueStatus) {
+    if let Err(err) = app_handle.emit(CUE_EVENT, status.clone()) {
+        error!("No se pudo emitir el estado de la lista de cues: {err}");
+    }
+}
+
+// Graba los niveles actuales como cue `number` (si ya existe la reemplaza).
+// Sin universo se capturan todos los universos configurados.
+#[tauri::command]
+pub fn cue_record(
+    app_handle: AppHandle,
+    cues: State<'_, CueState>,
+    dmx: State<'_, DmxState>,
+    number: f64,
+    name: Option<String>,
+    fade_ms: Option<u64>,
+    universe: Option<u16>,
+) -> Result<Cue, String> {
+    if !number.is_finite() || number <= 0.0 {
+        return Err(format!("Número de cue inválido: {number}"));
+    }
+
+    let mut universes = BTreeMap::new();
+    dmx.for_each_universe(|id, shared| {
+        if universe.is_some_and(|universe| universe != id) {
+            return;
+        }
+        universes.insert(id, shared.channel_levels());
+    });
+    if universes.is_empty() {
+        return Err("No hay universos DMX para grabar en la cue".to_string());
+    }
+
+    let mut stack = cues.lock_stack()?;
+    // Al regrabar una cue se conserva su timecode
+    let timecode = stack
+        .position(number)
+        .and_then(|idx| stack.cues[idx].timecode);
+    let cue = Cue {
+        number,
+        name: name.unwrap_or_default(),
+        fade_ms: fade_ms.unwrap_or(0),
+        universes,
+        timecode,
+    };
+
+    let active = stack.active.map(|idx| stack.cues[idx].number);
+    match stack.position(number) {
+        Some(idx) => stack.cues[idx] = cue.clone(),
+        None => {
+            stack.cues.push(cue.clone());
+            stack.cues.sort_by(|a, b| a.number.total_cmp(&b.number));
+        }
+    }
+    stack.active = active.and_then(|number| stack.position(number));
+    cues.save(&stack)?;
+
+    emit_status(&app_handle, &stack.status());
+    Ok(cue)
+}
+
+#[tauri::command]
+pub fn cue_delete(
+    app_handle: AppHandle,
+    cues: State<'_, CueState>,
+    number: f64,
+) -> Result<(), String> {
+    let mut stack = cues.lock_stack()?;
+    let idx = stack
+        .position(number)
+        .ok_or_else(|| format!("La cue {number} no existe"))?;
+
+    let active = stack.active.map(|idx| stack.cues[idx].number);
+    stack.cues.remove(idx);
+    stack.active = active.and_then(|number| stack.position(number));
+    cues.save(&stack)?;
+
+    emit_status(&app_handle, &stack.status());
+    Ok(())
+}
+
+// Con `null` la cue deja de dispararse por timecode
+#[tauri::command]
+pub fn cue_set_timecode(
+    cues: State<'_, CueState>,
+    number: f64,
+    timecode: Option<Timecode>,
+) -> Result<(), String> {
+    if let Some(timecode) = &timecode {
+        timecode.validate()?;
+    }
+
+    let mut stack = cues.lock_stack()?;
+    let idx = stack
+        .position(number)
+        .ok_or_else(|| format!("La cue {number} no existe"))?;
+    stack.cues[idx].timecode = timecode;
+    cues.save(&stack)
+}
+
+#[tauri::command]
+pub fn cue_list(cues: State<'_, CueState>) -> Result<Vec<Cue>, String> {
+    Ok(cues.lock_stack()?.cues.clone())
+}
+
+#[tauri::command]
+pub fn cue_status(cues: State<'_, CueState>) -> Result<CueStatus, String> {
+    Ok(cues.lock_stack()?.status())
+}
+
+#[tauri::command]
+pub fn cue_go(
+    app_handle: AppHandle,
+    cues: State<'_, CueState>,
+    dmx: State<'_, DmxState>,
+) -> Result<CueStatus, String> {
+    let status = cues.go(&app_handle, &dmx, None, None)?;
+    msc::announce(&app_handle, MscCommand::Go, status.active);
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn cue_back(
+    app_handle: AppHandle,
+    cues: State<'_, CueState>,
+    dmx: State<'_, DmxState>,
+) -> Result<CueStatus, String> {
+    let mut stack = cues.lock_stack()?;
+    let previous = match stack.active {
+        Some(idx) if idx > 0 => idx - 1,
+        _ => return Err("No hay una cue anterior".to_string()),
+    };
+    let status = cues.fire(&app_handle, &dmx, &mut stack, previous, None)?;
+    msc::announce(&app_handle, MscCommand::Go, status.active);
+    Ok(status)
+}
+
+// Apaga la capa de reproducción y deja la lista sin cue activa
+#[tauri::command]
+pub fn cue_release(
+    app_handle: AppHandle,
+    cues: State<'_, CueState>,
+    dmx: State<'_, DmxState>,
+) -> Result<CueStatus, String> {
+    let mut stack = cues.lock_stack()?;
+    let mut result = Ok(());
+    dmx.for_each_universe(|_, shared| {
+        if let Err(err) = shared.release_playback() {
+            result = Err(err);
+        }
+    });
+    result?;
+
+    stack.active = None;
+    let status = stack.status();
+    emit_status(&app_handle, &status);
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn cue_goto(
+    app_handle: AppHandle,
+    cues: State<'_, CueState>,
+    dmx: State<'_, DmxState>,
+    number: f64,
+    fade_ms: Option<u64>,
+) -> Result<CueStatus, String> {
+    let mut stack = cues.lock_stack()?;
+    let idx = stack
+        .position(number)
+        .ok_or_else(|| format!("La cue {number} no existe"))?;
+    let status = cues.fire(&app_handle, &dmx, &mut stack, idx, fade_ms)?;
+    msc::announce(&app_handle, MscCommand::Go, status.active);
+    Ok(status)
+}
+
+// Congela el fade de la cue en curso (STOP de MSC)
+#[tauri::command]
+pub fn cue_pause(
+    app_handle: AppHandle,
+    cues: State<'_, CueState>,
+    dmx: State<'_, DmxState>,
+) -> Result<(), String> {
+    cues.pause(&dmx, true)?;
+    msc::announce(&app_handle, MscCommand::Stop, cues.active_number());
+    Ok(())
+}
+
+#[tauri::command]
+pub fn cue_resume(
+    app_handle: AppHandle,
+    cues: State<'_, CueState>,
+    dmx: State<'_, DmxState>,
+) -> Result<(), String> {
+    cues.pause(&dmx, false)?;
+    msc::announce(&app_handle, MscCommand::Resume, cues.active_number());
+    Ok(())
+}
@@ -0,0 +1,181 @@
+use crate::dmx::{DmxState, DMX_CHANNELS};
+use crate::fixture::FixtureState;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tauri::{AppHandle, State};
+
+const MIN_CUSTOM_POINTS: usize = 2;
+const MAX_CUSTOM_POINTS: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CurveKind {
+    /// Sin corrección; sirve para que un canal ignore la curva de su fixture
+    Linear,
+    /// Salida proporcional al cuadrado del nivel, para que las incandescentes no se coman la
+    /// parte baja
+    Square,
+    /// Suave en los extremos y más rápida en el medio
+    SCurve,
+    /// Tabla propia en `points`
+    Custom,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DimmerCurve {
+    kind: CurveKind,
+    // Solo para `custom`: de 2 a 256 valores repartidos de forma pareja entre 0 y 255, con
+    // interpolación lineal entre ellos
+    #[serde(default)]
+    points: Vec<u8>,
+}
+
+impl DimmerCurve {
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        match self.kind {
+            CurveKind::Custom
+                if !(MIN_CUSTOM_POINTS..=MAX_CUSTOM_POINTS).contains(&self.points.len()) =>
+            {
+                Err(format!(
+                    "La curva propia necesita entre {MIN_CUSTOM_POINTS} y {MAX_CUSTOM_POINTS} puntos"
+                ))
+            }
+            CurveKind::Custom => Ok(()),
+            _ if !self.points.is_empty() => {
+                Err("Solo la curva `custom` lleva puntos".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn table(&self) -> [u8; 256] {
+        std::array::from_fn(|level| {
+            let t = level as f64 / 255.0;
+            let out = match self.kind {
+                CurveKind::Linear => t,
+                CurveKind::Square => t * t,
+                CurveKind::SCurve => t * t * (3.0 - 2.0 * t),
+                CurveKind::Custom => {
+                    let position = t * (self.points.len() - 1) as f64;
+                    let idx = (position.floor() as usize).min(self.points.len() - 2);
+                    let from = self.points[idx] as f64;
+                    let to = self.points[idx + 1] as f64;
+                    (from + (to - from) * (position - idx as f64)) / 255.0
+                }
+            };
+            (out * 255.0).round().clamp(0.0, 255.0) as u8
+        })
+    }
+}
+
+// Curvas de dimmer de un universo, aplicadas al frame de salida después del master
+#[derive(Default)]
+pub(crate) struct ChannelCurves {
+    // Asignadas a mano con `dmx_set_curve`; pisan a las de los fixtures
+    channels: BTreeMap<u16, DimmerCurve>,
+    // Las de los fixtures patcheados en el universo, por canal
+    fixtures: BTreeMap<u16, DimmerCurve>,
+    // Tabla efectiva de cada canal (índice 0 = canal 1); vacío si ningún canal tiene curva
+    tables: Vec<Option<Arc<[u8; 256]>>>,
+}
+
+impl ChannelCurves {
+    pub(crate) fn set_channels(&mut self, channels: &[u16], curve: Option<DimmerCurve>) {
+        for channel in channels {
+            match &curve {
+                Some(curve) => self.channels.insert(*channel, curve.clone()),
+                None => self.channels.remove(channel),
+            };
+        }
+        self.rebuild();
+    }
+
+    pub(crate) fn set_fixtures(&mut self, curves: Option<BTreeMap<u16, DimmerCurve>>) {
+        self.fixtures = curves.unwrap_or_default();
+        self.rebuild();
+    }
+
+    // Solo las asignadas por canal; las de los fixtures se guardan con cada fixture
+    pub(crate) fn channel_curves(&self) -> Vec<(u16, DimmerCurve)> {
+        self.channels
+            .iter()
+            .map(|(channel, curve)| (*channel, curve.clone()))
+            .collect()
+    }
+
+    pub(crate) fn apply(&self, levels: &mut [u8]) {
+        for (level, table) in levels.iter_mut().zip(&self.tables) {
+            if let Some(table) = table {
+                *level = table[*level as usize];
+            }
+        }
+    }
+
+    fn rebuild(&mut self) {
+        if self.channels.is_empty() && self.fixtures.is_empty() {
+            self.tables.clear();
+            return;
+        }
+
+        // Muchos canales comparten curva: cada una se calcula una sola vez
+        let mut cache: Vec<(&DimmerCurve, Arc<[u8; 256]>)> = Vec::new();
+        self.tables = (1..=DMX_CHANNELS as u16)
+            .map(|channel| {
+                let curve = self
+                    .channels
+                    .get(&channel)
+                    .or_else(|| self.fixtures.get(&channel))?;
+                if let Some((_, table)) = cache.iter().find(|(cached, _)| *cached == curve) {
+                    return Some(table.clone());
+                }
+                let table = Arc::new(curve.table());
+                cache.push((curve, table.clone()));
+                Some(table)
+            })
+            .collect();
+    }
+}
+
+// Con `null` los canales vuelven a la curva de su fixture, o a lineal si no tienen
+#[tauri::command]
+pub fn dmx_set_curve(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    channels: Vec<u16>,
+    curve: Option<DimmerCurve>,
+) -> Result<(), String> {
+    if let Some(channel) = channels
+        .iter()
+        .find(|channel| !(1..=DMX_CHANNELS as u16).contains(*channel))
+    {
+        return Err(format!("Canal DMX inválido: {channel}"));
+    }
+    if let Some(curve) = &curve {
+        curve.validate()?;
+    }
+
+    state.with_universe(app_handle, universe, |shared| {
+        shared
+            .curves
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear las curvas DMX: {e}"))?
+            .set_channels(&channels, curve);
+        Ok(())
+    })
+}
+
+// La curva va a los canales de intensidad del fixture y lo sigue si se mueve en el patch
+#[tauri::command]
+pub fn fixture_set_curve(
+    app_handle: AppHandle,
+    fixtures: State<'_, FixtureState>,
+    id: u32,
+    curve: Option<DimmerCurve>,
+) -> Result<(), String> {
+    if let Some(curve) = &curve {
+        curve.validate()?;
+    }
+    fixtures.set_curve(&app_handle, id, curve)
+}
@@ -1,19 +1,264 @@
+use crate::artnet::{self, ArtNetConfig, ArtNetInput, ArtNetSender};
+use crate::backup::BackupState;
+use crate::chase::Chase;
+use crate::curves::{ChannelCurves, DimmerCurve};
+use crate::effects::Effects;
+use crate::enttec;
+use crate::errors::{self, DmxErrorCode};
+use crate::fade::Crossfade;
+use crate::levels::LevelBuffer;
+use crate::matrix::MatrixRender;
+use crate::merge::{Contributions, MergeSource, MergeSourceKind, Merger};
+use crate::metrics::FrameMetrics;
+use crate::monitor::OutputMonitor;
+use crate::reconnect::{self, OutputHealth, Reconnect};
+use crate::recording::RecordingState;
+use crate::sacn::{self, SacnConfig, SacnSender};
+use crate::scheduler::FrameScheduler;
+use crate::serial_direct::DirectSerial;
+use crate::settings::SettingsState;
+use crate::stop::{StopPolicy, StopRamp, STOP_POLICY_FILE};
+use crate::storage;
+use crate::udmx::{self, UdmxOutput};
 use log::{debug, error, info};
-use serde::Serialize;
-use std::sync::mpsc::{self, Sender};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Manager, State, Wry};
 use tauri_plugin_serialplugin::state::{DataBits, FlowControl, Parity, StopBits, UNKNOWN};
 use tauri_plugin_serialplugin::SerialPort;
 
-#[derive(Clone, Default)]
-struct DmxSharedState {
+pub(crate) const DMX_CHANNELS: usize = 512;
+pub(crate) const DMX_FRAME_SIZE: usize = DMX_CHANNELS + 1; // Start code + 512 channels
+pub(crate) const DMX_BAUD_RATE: u32 = 250000;
+const PI_UART_PATHS: &[&str] = &["/dev/serial0", "/dev/ttyAMA0"];
+const DEFAULT_REFRESH_HZ: u32 = 40;
+// Con 512 canales un frame completo ocupa ~22.7 ms, así que 44 Hz es el techo real
+pub(crate) const MAX_REFRESH_HZ: u32 = 44;
+pub(crate) const MIN_REFRESH_HZ: u32 = 1;
+const PORT_SETTINGS_FILE: &str = "dmx_ports.json";
+// Mínimos del estándar DMX512-A para el transmisor
+const MIN_BREAK_US: u32 = 92;
+const MIN_MAB_US: u32 = 12;
+const MAX_TIMING_US: u32 = 1000;
+const NULL_START_CODE: u8 = 0x00;
+// Reservado para RDM, que tiene su propio camino en `rdm.rs`
+const RDM_START_CODE: u8 = 0xCC;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DmxDriver {
+    /// Interfaces "tontas" (OpenDMX, FTDI/CH340) donde el break se genera por software
+    #[default]
+    OpenDmx,
+    /// Widgets Enttec DMX USB Pro / Pro Mk2, que generan el break por hardware
+    EnttecPro,
+    /// UART nativa (p. ej. Raspberry Pi + MAX485), con el break generado bajando el baudrate
+    PiUart,
+}
+
+// Cómo llega el hilo de escritura al puerto serie
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SerialBackend {
+    /// A través de `tauri-plugin-serialplugin` (necesario para RDM y la entrada DMX)
+    #[default]
+    Plugin,
+    /// Con un handle de `serialport` propio del hilo, menos overhead por frame
+    Direct,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DmxTiming {
+    break_us: u32,
+    mab_us: u32,
+}
+
+impl Default for DmxTiming {
+    fn default() -> Self {
+        Self {
+            break_us: 110,
+            mab_us: 12,
+        }
+    }
+}
+
+impl DmxTiming {
+    fn new(break_us: u32, mab_us: u32) -> Result<Self, String> {
+        if !(MIN_BREAK_US..=MAX_TIMING_US).contains(&break_us) {
+            return Err(format!(
+                "El break DMX debe estar entre {MIN_BREAK_US} y {MAX_TIMING_US} µs"
+            ));
+        }
+        if !(MIN_MAB_US..=MAX_TIMING_US).contains(&mab_us) {
+            return Err(format!(
+                "El MAB DMX debe estar entre {MIN_MAB_US} y {MAX_TIMING_US} µs"
+            ));
+        }
+        Ok(Self { break_us, mab_us })
+    }
+}
+
+// Grandmaster del universo: escala los canales de intensidad al enviar, sin tocar el buffer
+struct DmxMaster {
+    value: u8,
+    // Sin lista se consideran de intensidad todos los canales
+    intensity_channels: Option<Vec<bool>>,
+}
+
+impl Default for DmxMaster {
+    fn default() -> Self {
+        Self {
+            value: 255,
+            intensity_channels: None,
+        }
+    }
+}
+
+impl DmxMaster {
+    fn is_intensity(&self, idx: usize) -> bool {
+        match &self.intensity_channels {
+            Some(channels) => channels.get(idx).copied().unwrap_or(false),
+            None => true,
+        }
+    }
+
+    fn apply(&self, levels: &mut [u8]) {
+        if self.value == 255 {
+            return;
+        }
+
+        for (idx, level) in levels.iter_mut().enumerate() {
+            if self.is_intensity(idx) {
+                *level = ((*level as u16 * self.value as u16 + 127) / 255) as u8;
+            }
+        }
+    }
+}
+
+// Capa de reproducción de cues, con buffer y fades propios, separada del programmer
+struct PlaybackLayer {
+    levels: Vec<u8>,
+    fade: Crossfade,
+    active: bool,
+}
+
+impl Default for PlaybackLayer {
+    fn default() -> Self {
+        Self {
+            levels: vec![0; DMX_CHANNELS],
+            fade: Crossfade::new(DMX_CHANNELS),
+            active: false,
+        }
+    }
+}
+
+// Quién puso el frame que sale tal cual: cada uno solo saca el suyo
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ReplaySource {
+    /// Reproducción de una grabación DMX
+    Recording,
+    /// Lo que llega por red en modo nodo
+    Node,
+}
+
+// (mínimo, máximo) de cada canal
+type ChannelLimits = Vec<(u8, u8)>;
+// Valor forzado por canal; `None` deja el que venga
+type ChannelOverrides = Vec<Option<u8>>;
+// Frame con start code y la fuente que lo puso
+type ReplayFrame = Option<(ReplaySource, Vec<u8>)>;
+
+#[derive(Clone)]
+pub(crate) struct DmxSharedState {
     port_path: Arc<Mutex<Option<String>>>,
+    driver: Arc<Mutex<DmxDriver>>,
+    serial_backend: Arc<Mutex<SerialBackend>>,
+    // Primer byte de cada frame; con algo distinto de 0 el buffer sale tal cual (texto, SIP...)
+    start_code: Arc<Mutex<u8>>,
     open_port: Arc<Mutex<Option<String>>>,
-    levels: Arc<Mutex<[u8; 513]>>, // Start code + 512 channels
+    levels: Arc<LevelBuffer>,
+    // Último frame que salió (merge, efectos y master ya aplicados)
+    output: Arc<Mutex<[u8; DMX_FRAME_SIZE]>>,
+    fade: Arc<Mutex<Crossfade>>,
+    master: Arc<Mutex<DmxMaster>>,
+    merger: Arc<Mutex<Merger>>,
+    replay: Arc<Mutex<ReplayFrame>>,
+    playback: Arc<Mutex<PlaybackLayer>>,
+    // Atenuación por canal de los submasters (None si ninguno atenúa este universo)
+    submasters: Arc<Mutex<Option<Vec<u8>>>>,
+    // Balance de blanco de los fixtures, por canal, aplicado al frame de salida
+    calibration: Arc<Mutex<Option<Vec<u8>>>>,
+    // Cambio máximo por frame de cada canal (0 = sin límite), para dimmers que "saltan"
+    slew: Arc<Mutex<Option<Vec<u8>>>>,
+    pub(crate) curves: Arc<Mutex<ChannelCurves>>,
+    // Piso y techo por canal; el rango 0-255 del canal se escala proporcionalmente adentro
+    limits: Arc<Mutex<Option<ChannelLimits>>>,
+    // Fixtures en locate: pisan la salida después del master
+    locate: Arc<Mutex<Option<ChannelOverrides>>>,
+    // Canales que maneja el sound-to-light; entran al merge como una fuente
+    sound: Arc<Mutex<Option<ChannelOverrides>>>,
+    // Canales que salen invertidos (255 - x), p. ej. hazers o packs de relés
+    inverted: Arc<Mutex<Option<Vec<bool>>>>,
     write_lock: Arc<Mutex<()>>,
+    refresh_hz: Arc<Mutex<u32>>,
+    timing: Arc<Mutex<DmxTiming>>,
+    // El Enttec Pro guarda break/MAB/refresco en el widget: hay que reenviarlos al cambiar
+    widget_params_dirty: Arc<AtomicBool>,
+    pub(crate) artnet: Arc<Mutex<Option<ArtNetSender>>>,
+    pub(crate) sacn: Arc<Mutex<Option<SacnSender>>>,
+    pub(crate) artnet_input: Arc<Mutex<Option<ArtNetInput>>>,
+    pub(crate) chases: Arc<Mutex<HashMap<String, Chase>>>,
+    pub(crate) matrices: Arc<Mutex<HashMap<String, MatrixRender>>>,
+    pub(crate) effects: Arc<Mutex<Effects>>,
+    pub(crate) monitor: Arc<Mutex<OutputMonitor>>,
+    reconnect: Arc<Mutex<Reconnect>>,
+    pub(crate) metrics: Arc<Mutex<FrameMetrics>>,
+}
+
+impl Default for DmxSharedState {
+    fn default() -> Self {
+        Self {
+            port_path: Arc::new(Mutex::new(None)),
+            driver: Arc::new(Mutex::new(DmxDriver::default())),
+            serial_backend: Arc::new(Mutex::new(SerialBackend::default())),
+            start_code: Arc::new(Mutex::new(NULL_START_CODE)),
+            open_port: Arc::new(Mutex::new(None)),
+            levels: Arc::new(LevelBuffer::default()),
+            output: Arc::new(Mutex::new([0; DMX_FRAME_SIZE])),
+            fade: Arc::new(Mutex::new(Crossfade::new(DMX_CHANNELS))),
+            master: Arc::new(Mutex::new(DmxMaster::default())),
+            merger: Arc::new(Mutex::new(Merger::new(DMX_CHANNELS))),
+            replay: Arc::new(Mutex::new(None)),
+            playback: Arc::new(Mutex::new(PlaybackLayer::default())),
+            submasters: Arc::new(Mutex::new(None)),
+            calibration: Arc::new(Mutex::new(None)),
+            slew: Arc::new(Mutex::new(None)),
+            curves: Arc::new(Mutex::new(ChannelCurves::default())),
+            limits: Arc::new(Mutex::new(None)),
+            locate: Arc::new(Mutex::new(None)),
+            sound: Arc::new(Mutex::new(None)),
+            inverted: Arc::new(Mutex::new(None)),
+            write_lock: Arc::new(Mutex::new(())),
+            refresh_hz: Arc::new(Mutex::new(DEFAULT_REFRESH_HZ)),
+            timing: Arc::new(Mutex::new(DmxTiming::default())),
+            widget_params_dirty: Arc::new(AtomicBool::new(true)),
+            artnet: Arc::new(Mutex::new(None)),
+            sacn: Arc::new(Mutex::new(None)),
+            artnet_input: Arc::new(Mutex::new(None)),
+            chases: Arc::new(Mutex::new(HashMap::new())),
+            matrices: Arc::new(Mutex::new(HashMap::new())),
+            effects: Arc::new(Mutex::new(Effects::default())),
+            monitor: Arc::new(Mutex::new(OutputMonitor::default())),
+            reconnect: Arc::new(Mutex::new(Reconnect::default())),
+            metrics: Arc::new(Mutex::new(FrameMetrics::default())),
+        }
+    }
 }
 
 impl DmxSharedState {
@@ -26,188 +271,2215 @@ impl DmxSharedState {
         Ok(())
     }
 
-    fn update_levels(&self, levels: &[u8]) -> Result<(), String> {
-        if levels.len() > 512 {
-            return Err("El buffer DMX debe tener 512 canales como máximo".to_string());
+    fn clear_port(&self) -> Result<(), String> {
+        let mut path_guard = self
+            .port_path
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el puerto seleccionado: {e}"))?;
+        *path_guard = None;
+        Ok(())
+    }
+
+    fn set_refresh_rate(&self, hz: u32) -> Result<(), String> {
+        let mut rate = self
+            .refresh_hz
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la frecuencia DMX: {e}"))?;
+        *rate = hz;
+        self.widget_params_dirty.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn set_timing(&self, timing: DmxTiming) -> Result<(), String> {
+        let mut current = self
+            .timing
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear los tiempos DMX: {e}"))?;
+        *current = timing;
+        self.widget_params_dirty.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn timing(&self) -> DmxTiming {
+        self.timing.lock().map(|timing| *timing).unwrap_or_default()
+    }
+
+    fn refresh_rate(&self) -> u32 {
+        self.refresh_hz
+            .lock()
+            .map(|rate| *rate)
+            .unwrap_or(DEFAULT_REFRESH_HZ)
+    }
+
+    fn frame_period(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.refresh_rate() as f64)
+    }
+
+    pub(crate) fn port(&self) -> Option<String> {
+        self.port_path.lock().ok().and_then(|guard| guard.clone())
+    }
+
+    fn set_driver(&self, driver: DmxDriver) -> Result<(), String> {
+        let mut driver_guard = self
+            .driver
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el driver DMX: {e}"))?;
+
+        if *driver_guard != driver {
+            *driver_guard = driver;
+            // Fuerza a reabrir el puerto con los parámetros del nuevo driver
+            self.clear_open_port();
         }
+        Ok(())
+    }
+
+    pub(crate) fn driver(&self) -> DmxDriver {
+        self.driver.lock().map(|guard| *guard).unwrap_or_default()
+    }
 
-        let mut buffer = self
-            .levels
+    fn set_serial_backend(&self, backend: SerialBackend) -> Result<(), String> {
+        let mut guard = self
+            .serial_backend
             .lock()
-            .map_err(|e| format!("No se pudo bloquear el buffer DMX: {e}"))?;
+            .map_err(|e| format!("No se pudo bloquear el backend serie: {e}"))?;
 
-        buffer.fill(0);
-        for (idx, value) in levels.iter().take(512).enumerate() {
-            buffer[idx + 1] = *value;
+        if *guard != backend {
+            *guard = backend;
+            self.clear_open_port();
         }
+        Ok(())
+    }
+
+    pub(crate) fn serial_backend(&self) -> SerialBackend {
+        self.serial_backend
+            .lock()
+            .map(|guard| *guard)
+            .unwrap_or_default()
+    }
 
+    fn set_start_code(&self, start_code: u8) -> Result<(), String> {
+        if start_code == RDM_START_CODE {
+            return Err("El start code 0xCC está reservado para RDM".to_string());
+        }
+        *self
+            .start_code
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el start code: {e}"))? = start_code;
         Ok(())
     }
 
-    fn snapshot_levels(&self) -> Vec<u8> {
-        self.levels
+    pub(crate) fn start_code(&self) -> u8 {
+        self.start_code
             .lock()
-            .map(|levels| levels.to_vec())
-            .unwrap_or_else(|_| vec![0; 513])
+            .map(|guard| *guard)
+            .unwrap_or(NULL_START_CODE)
     }
 
-    fn clear_open_port(&self) {
-        if let Ok(mut open) = self.open_port.lock() {
-            *open = None;
+    pub(crate) fn update_levels(&self, levels: &[u8]) -> Result<(), String> {
+        if levels.len() > DMX_CHANNELS {
+            return Err("El buffer DMX debe tener 512 canales como máximo".to_string());
         }
+
+        // Un valor nuevo del front pisa cualquier fade en curso
+        self.lock_fade()?.clear();
+
+        self.levels.write(|buffer| {
+            buffer.fill(0);
+            for (idx, value) in levels.iter().take(DMX_CHANNELS).enumerate() {
+                buffer[idx + 1] = *value;
+            }
+        })
     }
-}
 
-#[derive(Default)]
-pub struct DmxState {
-    shared: DmxSharedState,
-    stop_tx: Mutex<Option<Sender<()>>>,
-    writer_handle: Mutex<Option<thread::JoinHandle<()>>>,
-}
+    // Cambia solo los canales desde `start` (1-512); el resto del buffer queda como estaba
+    pub(crate) fn patch_levels(&self, start: u16, values: &[u8]) -> Result<(), String> {
+        let first = start as usize;
+        if first == 0 || first + values.len() > DMX_CHANNELS + 1 {
+            return Err(format!(
+                "Rango DMX inválido: {} canales desde el {start}",
+                values.len()
+            ));
+        }
+        let range = first..first + values.len();
 
-#[derive(Serialize)]
-pub struct DmxPortInfo {
-    path: String,
-    kind: Option<String>,
-    manufacturer: Option<String>,
-    product: Option<String>,
-    serial_number: Option<String>,
-}
+        // Los canales que cambian dejan su fade; los demás lo siguen
+        self.lock_fade()?.cancel(first - 1..range.end - 1);
 
-#[tauri::command]
-pub fn dmx_list_ports(serial: State<'_, SerialPort<Wry>>) -> Result<Vec<DmxPortInfo>, String> {
-    let mut ports = serial
-        .available_ports()
-        .map_err(|e| format!("No se pudieron listar los puertos: {e}"))?
-        .into_iter()
-        .map(|(path, meta)| DmxPortInfo {
-            path,
-            kind: meta.get("type").cloned().filter(|t| t != UNKNOWN),
-            manufacturer: meta.get("manufacturer").cloned().filter(|m| m != UNKNOWN),
-            product: meta.get("product").cloned().filter(|p| p != UNKNOWN),
-            serial_number: meta.get("serial_number").cloned().filter(|s| s != UNKNOWN),
+        self.levels
+            .write(|buffer| buffer[range].copy_from_slice(values))
+    }
+
+    // Suma `delta` a cada canal (1-512) con el valor recortado a 0-255. La lectura y la
+    // escritura van dentro del mismo lock del buffer, así dos ajustes seguidos no se pisan.
+    pub(crate) fn adjust_levels(&self, deltas: &[(u16, i16)]) -> Result<Vec<(u16, u8)>, String> {
+        if let Some((channel, _)) = deltas
+            .iter()
+            .find(|(channel, _)| !(1..=DMX_CHANNELS as u16).contains(channel))
+        {
+            return Err(format!("Canal DMX inválido: {channel}"));
+        }
+
+        {
+            let mut fade = self.lock_fade()?;
+            for (channel, _) in deltas {
+                let idx = *channel as usize - 1;
+                fade.cancel(idx..idx + 1);
+            }
+        }
+
+        self.levels.write(|buffer| {
+            deltas
+                .iter()
+                .map(|(channel, delta)| {
+                    let slot = &mut buffer[*channel as usize];
+                    *slot = (*slot as i16 + delta).clamp(0, u8::MAX as i16) as u8;
+                    (*channel, *slot)
+                })
+                .collect()
         })
-        .collect::<Vec<_>>();
+    }
 
-    ports.sort_by(|a, b| a.path.cmp(&b.path));
-    Ok(ports)
-}
+    // Pares [grueso, fino] (canales 1-512) de los atributos de 16 bits patcheados, para que
+    // los fades y los efectos los muevan como un solo valor
+    pub(crate) fn set_fine_pairs(&self, pairs: Option<Vec<(u16, u16)>>) {
+        let pairs = pairs.unwrap_or_default();
+        if let Ok(mut fade) = self.fade.lock() {
+            let indices = pairs
+                .iter()
+                .map(|(coarse, fine)| (*coarse as usize - 1, *fine as usize - 1))
+                .collect::<Vec<_>>();
+            fade.set_pairs(&indices);
+        }
+        if let Ok(mut effects) = self.effects.lock() {
+            effects.set_fine_channels(&pairs);
+        }
+    }
 
-#[tauri::command]
-pub fn dmx_set_levels(
-    app_handle: AppHandle,
-    state: State<'_, DmxState>,
-    port_path: String,
-    levels: Vec<u8>,
-) -> Result<(), String> {
-    state.shared.set_port(port_path)?;
-    state.shared.update_levels(&levels)?;
-    state.ensure_writer(app_handle)?;
-    Ok(())
-}
+    fn lock_fade(&self) -> Result<std::sync::MutexGuard<'_, Crossfade>, String> {
+        self.fade
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el fade DMX: {e}"))
+    }
 
-impl DmxState {
-    fn ensure_writer(&self, app_handle: AppHandle) -> Result<(), String> {
-        let mut writer_guard = self
-            .writer_handle
+    // Siempre se bloquea el fade antes que el buffer para no cruzarse con el hilo de escritura
+    pub(crate) fn start_fade(&self, levels: &[u8], duration: Duration) -> Result<(), String> {
+        if levels.len() > DMX_CHANNELS {
+            return Err("El buffer DMX debe tener 512 canales como máximo".to_string());
+        }
+
+        let mut target = [0u8; DMX_CHANNELS];
+        target[..levels.len()].copy_from_slice(levels);
+
+        let mut fade = self.lock_fade()?;
+        self.levels.write(|buffer| {
+            if duration.is_zero() {
+                buffer[1..].copy_from_slice(&target);
+                fade.clear();
+            } else {
+                fade.start(&buffer[1..], &target, duration);
+            }
+        })
+    }
+
+    // El hilo de escritura no espera a los comandos: si el fade o el buffer están ocupados se
+    // saltea este frame, y como el fade depende del tiempo el siguiente lo alcanza
+    fn advance_fade(&self) {
+        let Ok(mut fade) = self.fade.try_lock() else {
+            return;
+        };
+        if !fade.is_active() {
+            return;
+        }
+
+        self.levels.try_write(|buffer| fade.step(&mut buffer[1..]));
+    }
+
+    pub(crate) fn start_playback(&self, levels: &[u8], duration: Duration) -> Result<(), String> {
+        if levels.len() > DMX_CHANNELS {
+            return Err("El buffer DMX debe tener 512 canales como máximo".to_string());
+        }
+
+        let mut target = [0u8; DMX_CHANNELS];
+        target[..levels.len()].copy_from_slice(levels);
+
+        let mut playback = self
+            .playback
             .lock()
-            .map_err(|e| format!("No se pudo preparar el hilo DMX: {e}"))?;
+            .map_err(|e| format!("No se pudo bloquear la reproducción DMX: {e}"))?;
+        let playback = &mut *playback;
 
-        if writer_guard.is_some() {
-            return Ok(());
+        // Al activarse la capa arranca desde cero, así las intensidades entran con el fade
+        if !playback.active {
+            playback.levels.fill(0);
+            playback.active = true;
         }
 
-        let (tx, rx) = mpsc::channel();
+        if duration.is_zero() {
+            playback.levels.copy_from_slice(&target);
+            playback.fade.clear();
+        } else {
+            playback.fade.start(&playback.levels, &target, duration);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn release_playback(&self) -> Result<(), String> {
+        let mut playback = self
+            .playback
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la reproducción DMX: {e}"))?;
+        playback.active = false;
+        playback.fade.clear();
+        Ok(())
+    }
+
+    // Congela o retoma el fade de la capa de reproducción
+    pub(crate) fn pause_playback(&self, paused: bool) -> Result<(), String> {
+        let mut playback = self
+            .playback
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la reproducción DMX: {e}"))?;
+        let now = Instant::now();
+        if paused {
+            playback.fade.pause(now);
+        } else {
+            playback.fade.resume(now);
+        }
+        Ok(())
+    }
+
+    // `frame` incluye el start code
+    pub(crate) fn set_replay_frame(
+        &self,
+        source: ReplaySource,
+        frame: &[u8],
+    ) -> Result<(), String> {
+        let mut replay = self
+            .replay
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la reproducción de la grabación: {e}"))?;
+        *replay = Some((source, frame.iter().take(DMX_FRAME_SIZE).copied().collect()));
+        Ok(())
+    }
+
+    // Vuelve a la salida normal del universo, salvo que el frame ya sea de otra fuente
+    pub(crate) fn clear_replay_frame(&self, source: ReplaySource) -> Result<(), String> {
+        let mut replay = self
+            .replay
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la reproducción de la grabación: {e}"))?;
+        if replay.as_ref().is_some_and(|(owner, _)| *owner == source) {
+            *replay = None;
+        }
+        Ok(())
+    }
+
+    // Copia el frame de la grabación en `frame` (lo que falte queda en 0); `false` si no hay
+    fn copy_replay_frame(&self, frame: &mut [u8; DMX_FRAME_SIZE]) -> bool {
+        let Ok(replay) = self.replay.lock() else {
+            return false;
+        };
+        let Some((_, replay)) = replay.as_ref() else {
+            return false;
+        };
+        let len = replay.len().min(DMX_FRAME_SIZE);
+        frame[..len].copy_from_slice(&replay[..len]);
+        frame[len..].fill(0);
+        true
+    }
+
+    fn lock_merger(&self) -> Result<std::sync::MutexGuard<'_, Merger>, String> {
+        self.merger
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el merge DMX: {e}"))
+    }
+
+    fn lock_master(&self) -> Result<std::sync::MutexGuard<'_, DmxMaster>, String> {
+        self.master
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el master DMX: {e}"))
+    }
+
+    fn set_intensity_channels(&self, channels: &[u16]) -> Result<(), String> {
+        if let Some(channel) = channels
+            .iter()
+            .find(|channel| !(1..=DMX_CHANNELS as u16).contains(*channel))
         {
-            let mut stop_guard = self
-                .stop_tx
-                .lock()
-                .map_err(|e| format!("No se pudo instalar el canal de parada: {e}"))?;
-            *stop_guard = Some(tx);
+            return Err(format!("Canal DMX inválido: {channel}"));
         }
 
-        let shared = self.shared.clone();
+        let mut master = self.lock_master()?;
+        master.intensity_channels = if channels.is_empty() {
+            None
+        } else {
+            let mut mask = vec![false; DMX_CHANNELS];
+            for channel in channels {
+                mask[*channel as usize - 1] = true;
+            }
+            Some(mask)
+        };
+        Ok(())
+    }
 
-        let handle = thread::spawn(move || loop {
-            if rx.try_recv().is_ok() {
-                info!("Cerrando loop DMX por señal de parada");
-                break;
+    fn intensity_channels(&self) -> Vec<u16> {
+        let Ok(master) = self.lock_master() else {
+            return Vec::new();
+        };
+        match &master.intensity_channels {
+            Some(mask) => (1..=DMX_CHANNELS as u16)
+                .filter(|channel| mask[*channel as usize - 1])
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn master(&self) -> u8 {
+        self.lock_master().map(|master| master.value).unwrap_or(255)
+    }
+
+    // `levels` llega con el buffer del universo y sale con el merge de todas las fuentes.
+    // `contributions` es del hilo de escritura y se reutiliza entre frames.
+    fn merge_sources(&self, levels: &mut [u8], now: Instant, contributions: &mut Contributions) {
+        contributions.clear();
+        contributions
+            .next(MergeSource::Programmer)
+            .extend(levels.iter().map(|value| Some(*value)));
+
+        if let Ok(mut playback) = self.playback.lock() {
+            let playback = &mut *playback;
+            if playback.active {
+                playback.fade.step(&mut playback.levels);
+                contributions
+                    .next(MergeSource::Playback)
+                    .extend(playback.levels.iter().map(|value| Some(*value)));
             }
+        }
 
-            let target_port = match shared.port_path.lock() {
-                Ok(guard) => guard.clone(),
-                Err(err) => {
-                    error!("No se pudo leer el puerto DMX: {err}");
-                    thread::sleep(Duration::from_millis(200));
-                    continue;
+        if let Ok(mut chases) = self.chases.lock() {
+            for (name, chase) in chases.iter_mut() {
+                let step = contributions.next_named(name, MergeSource::Chase);
+                if !chase.step_levels(now, step) {
+                    contributions.discard_last();
                 }
-            };
+            }
+        }
 
-            if let Some(port_path) = target_port {
-                let serial = app_handle.state::<SerialPort<Wry>>();
+        if let Ok(matrices) = self.matrices.lock() {
+            for (name, matrix) in matrices.iter() {
+                matrix.render(now, contributions.next_named(name, MergeSource::Matrix));
+            }
+        }
 
-                let needs_open = match shared.open_port.lock() {
-                    Ok(opened) => opened.as_deref() != Some(port_path.as_str()),
-                    Err(err) => {
-                        error!("No se pudo comprobar el estado del puerto DMX: {err}");
-                        true
-                    }
-                };
+        if let Ok(mut input) = self.artnet_input.lock() {
+            if let Some(input) = input.as_mut() {
+                input.contribute(contributions);
+            }
+        }
 
-                if needs_open {
-                    match serial.open(
-                        port_path.clone(),
-                        250000,
-                        Some(DataBits::Eight),
-                        Some(FlowControl::None),
-                        Some(Parity::None),
-                        Some(StopBits::Two),
-                        Some(100),
-                    ) {
-                        Ok(_) => {
-                            info!("Puerto DMX abierto: {}", port_path);
-                            if let Ok(mut open) = shared.open_port.lock() {
-                                *open = Some(port_path.clone());
-                            }
-                        }
-                        Err(err) => {
-                            error!("No se pudo abrir el puerto DMX {}: {err}", port_path);
-                            shared.clear_open_port();
-                            thread::sleep(Duration::from_millis(500));
-                            continue;
+        if let Ok(sound) = self.sound.lock() {
+            if let Some(sound) = sound.as_ref() {
+                contributions.next(MergeSource::Sound).extend(sound);
+            }
+        }
+
+        // Los submasters escalan lo que aporta cada fuente antes de combinarlas
+        if let Ok(submasters) = self.submasters.lock() {
+            if let Some(scale) = submasters.as_ref() {
+                for values in contributions.values_mut() {
+                    for (value, scale) in values.iter_mut().zip(scale) {
+                        if let Some(value) = value {
+                            *value = ((*value as u16 * *scale as u16 + 127) / 255) as u8;
                         }
                     }
                 }
+            }
+        }
 
-                let frame = shared.snapshot_levels();
-
-                if let Ok(_guard) = shared.write_lock.lock() {
-                    if let Err(err) = serial.set_break(port_path.clone()) {
-                        error!("No se pudo iniciar el break DMX en {}: {err}", port_path);
-                        shared.clear_open_port();
-                    } else {
-                        thread::sleep(Duration::from_micros(110));
-                        if let Err(err) = serial.clear_break(port_path.clone()) {
-                            error!("No se pudo limpiar el break DMX en {}: {err}", port_path);
-                            shared.clear_open_port();
-                        }
+        let (Ok(master), Ok(mut merger)) = (self.master.lock(), self.merger.lock()) else {
+            return;
+        };
+        merger.merge(
+            contributions.as_slice(),
+            |idx| master.is_intensity(idx),
+            levels,
+        );
+    }
 
-                        thread::sleep(Duration::from_micros(12));
+    pub(crate) fn set_submasters(&self, scale: Option<Vec<u8>>) {
+        if let Ok(mut submasters) = self.submasters.lock() {
+            *submasters = scale;
+        }
+    }
 
-                        if let Err(err) = serial.write_binary(port_path.clone(), frame.clone()) {
-                            error!("Error al escribir frame DMX en {}: {err}", port_path);
-                            shared.clear_open_port();
-                        } else {
-                            debug!("Frame DMX enviado a {} ({} bytes)", port_path, frame.len());
-                        }
-                    }
-                }
+    pub(crate) fn set_calibration(&self, gains: Option<Vec<u8>>) {
+        if let Ok(mut calibration) = self.calibration.lock() {
+            *calibration = gains;
+        }
+    }
+
+    fn apply_calibration(&self, levels: &mut [u8]) {
+        let Ok(calibration) = self.calibration.lock() else {
+            return;
+        };
+        if let Some(gains) = calibration.as_ref() {
+            for (level, gain) in levels.iter_mut().zip(gains) {
+                *level = ((*level as u16 * *gain as u16 + 127) / 255) as u8;
             }
+        }
+    }
 
-            thread::sleep(Duration::from_millis(25));
-        });
+    // `max_step` 0 quita el límite de esos canales
+    fn set_slew_limit(&self, channels: &[u16], max_step: u8) -> Result<(), String> {
+        if let Some(channel) = channels
+            .iter()
+            .find(|channel| !(1..=DMX_CHANNELS as u16).contains(*channel))
+        {
+            return Err(format!("Canal DMX inválido: {channel}"));
+        }
 
-        *writer_guard = Some(handle);
+        let mut slew = self
+            .slew
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el límite de cambio DMX: {e}"))?;
+        let limits = slew.get_or_insert_with(|| vec![0; DMX_CHANNELS]);
+        for channel in channels {
+            limits[*channel as usize - 1] = max_step;
+        }
+        if limits.iter().all(|limit| *limit == 0) {
+            *slew = None;
+        }
+        Ok(())
+    }
+
+    fn clear_slew_limits(&self) -> Result<(), String> {
+        *self
+            .slew
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el límite de cambio DMX: {e}"))? = None;
         Ok(())
     }
+
+    // Pares [canal, cambio máximo] de los canales con límite
+    fn slew_limits(&self) -> Vec<(u16, u8)> {
+        let Ok(slew) = self.slew.lock() else {
+            return Vec::new();
+        };
+        slew.iter()
+            .flatten()
+            .enumerate()
+            .filter(|(_, limit)| **limit > 0)
+            .map(|(idx, limit)| (idx as u16 + 1, *limit))
+            .collect()
+    }
+
+    // `min` 0 y `max` 255 quitan el límite de esos canales
+    fn set_limits(&self, channels: &[u16], min: u8, max: u8) -> Result<(), String> {
+        if let Some(channel) = channels
+            .iter()
+            .find(|channel| !(1..=DMX_CHANNELS as u16).contains(*channel))
+        {
+            return Err(format!("Canal DMX inválido: {channel}"));
+        }
+        if min > max {
+            return Err(format!(
+                "El mínimo ({min}) no puede ser mayor que el máximo ({max})"
+            ));
+        }
+
+        let mut current = self
+            .limits
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear los límites DMX: {e}"))?;
+        let limits = current.get_or_insert_with(|| vec![(0, 255); DMX_CHANNELS]);
+        for channel in channels {
+            limits[*channel as usize - 1] = (min, max);
+        }
+        if limits.iter().all(|limit| *limit == (0, 255)) {
+            *current = None;
+        }
+        Ok(())
+    }
+
+    // Ternas [canal, mínimo, máximo] de los canales con límite
+    fn channel_limits(&self) -> Vec<(u16, u8, u8)> {
+        let Ok(limits) = self.limits.lock() else {
+            return Vec::new();
+        };
+        limits
+            .iter()
+            .flatten()
+            .enumerate()
+            .filter(|(_, limit)| **limit != (0, 255))
+            .map(|(idx, (min, max))| (idx as u16 + 1, *min, *max))
+            .collect()
+    }
+
+    fn apply_limits(&self, levels: &mut [u8]) {
+        let Ok(limits) = self.limits.lock() else {
+            return;
+        };
+        if let Some(limits) = limits.as_ref() {
+            for (level, (min, max)) in levels.iter_mut().zip(limits) {
+                let span = (*max - *min) as u16;
+                *level = *min + ((*level as u16 * span + 127) / 255) as u8;
+            }
+        }
+    }
+
+    fn set_inverted(&self, channels: &[u16], inverted: bool) -> Result<(), String> {
+        if let Some(channel) = channels
+            .iter()
+            .find(|channel| !(1..=DMX_CHANNELS as u16).contains(*channel))
+        {
+            return Err(format!("Canal DMX inválido: {channel}"));
+        }
+
+        let mut current = self
+            .inverted
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear los canales invertidos: {e}"))?;
+        let mask = current.get_or_insert_with(|| vec![false; DMX_CHANNELS]);
+        for channel in channels {
+            mask[*channel as usize - 1] = inverted;
+        }
+        if !mask.iter().any(|inverted| *inverted) {
+            *current = None;
+        }
+        Ok(())
+    }
+
+    fn inverted_channels(&self) -> Vec<u16> {
+        let Ok(inverted) = self.inverted.lock() else {
+            return Vec::new();
+        };
+        inverted
+            .iter()
+            .flatten()
+            .enumerate()
+            .filter(|(_, inverted)| **inverted)
+            .map(|(idx, _)| idx as u16 + 1)
+            .collect()
+    }
+
+    fn apply_invert(&self, levels: &mut [u8]) {
+        let Ok(inverted) = self.inverted.lock() else {
+            return;
+        };
+        if let Some(mask) = inverted.as_ref() {
+            for (level, inverted) in levels.iter_mut().zip(mask) {
+                if *inverted {
+                    *level = u8::MAX - *level;
+                }
+            }
+        }
+    }
+
+    // Acerca cada canal con límite al frame anterior como mucho `limit` por frame
+    fn apply_slew(&self, levels: &mut [u8]) {
+        let Ok(slew) = self.slew.lock() else {
+            return;
+        };
+        let Some(limits) = slew.as_ref() else {
+            return;
+        };
+        let Ok(output) = self.output.lock() else {
+            return;
+        };
+
+        for ((level, previous), limit) in levels.iter_mut().zip(&output[1..]).zip(limits) {
+            if *limit > 0 {
+                *level = (*level).clamp(
+                    previous.saturating_sub(*limit),
+                    previous.saturating_add(*limit),
+                );
+            }
+        }
+    }
+
+    pub(crate) fn set_sound(&self, levels: Option<Vec<Option<u8>>>) {
+        if let Ok(mut sound) = self.sound.lock() {
+            *sound = levels;
+        }
+    }
+
+    pub(crate) fn set_locate(&self, levels: Option<Vec<Option<u8>>>) {
+        if let Ok(mut locate) = self.locate.lock() {
+            *locate = levels;
+        }
+    }
+
+    fn apply_locate(&self, levels: &mut [u8]) {
+        let Ok(locate) = self.locate.lock() else {
+            return;
+        };
+        if let Some(overrides) = locate.as_ref() {
+            for (level, value) in levels.iter_mut().zip(overrides) {
+                if let Some(value) = value {
+                    *level = *value;
+                }
+            }
+        }
+    }
+
+    fn apply_master(&self, levels: &mut [u8]) {
+        if let Ok(master) = self.master.lock() {
+            master.apply(levels);
+        }
+    }
+
+    fn store_output(&self, frame: &[u8]) {
+        if let Ok(mut output) = self.output.lock() {
+            let len = frame.len().min(DMX_FRAME_SIZE);
+            output[..len].copy_from_slice(&frame[..len]);
+        }
+    }
+
+    // Último frame de salida sin el start code
+    pub(crate) fn output_levels(&self) -> Vec<u8> {
+        self.output
+            .lock()
+            .map(|output| output[1..].to_vec())
+            .unwrap_or_else(|_| vec![0; DMX_CHANNELS])
+    }
+
+    // Niveles del buffer sin el start code
+    pub(crate) fn channel_levels(&self) -> Vec<u8> {
+        let mut frame = [0u8; DMX_FRAME_SIZE];
+        self.levels.read(&mut frame);
+        frame[1..].to_vec()
+    }
+
+    fn copy_levels(&self, frame: &mut [u8; DMX_FRAME_SIZE]) {
+        self.levels.read(frame);
+    }
+
+    pub(crate) fn is_port_open(&self, port_path: &str) -> bool {
+        self.open_port
+            .lock()
+            .map(|open| open.as_deref() == Some(port_path))
+            .unwrap_or(false)
+    }
+
+    pub(crate) fn lock_output(&self) -> Result<std::sync::MutexGuard<'_, ()>, String> {
+        self.write_lock
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la salida DMX: {e}"))
+    }
+
+    fn clear_open_port(&self) {
+        if let Ok(mut open) = self.open_port.lock() {
+            *open = None;
+        }
+    }
+
+    fn may_attempt_output(&self, port_path: &str) -> bool {
+        self.reconnect
+            .lock()
+            .map(|mut reconnect| reconnect.may_attempt(port_path, Instant::now()))
+            .unwrap_or(true)
+    }
+}
+
+#[derive(Default)]
+struct DmxUniverse {
+    shared: DmxSharedState,
+    stop_tx: Option<Sender<StopPolicy>>,
+    writer_handle: Option<thread::JoinHandle<()>>,
+    // El hilo anterior, si todavía hace su hold o su fade
+    stopping: Option<StoppingWriter>,
+}
+
+impl DmxUniverse {
+    fn ensure_writer(&mut self, app_handle: AppHandle, universe: u16) {
+        if self.writer_handle.is_some() {
+            return;
+        }
+        // El hilo que se está deteniendo sigue usando el puerto y lo cierra al terminar: se
+        // corta antes de arrancar el nuevo
+        self.cut_stopping(universe);
+
+        let (tx, rx) = mpsc::channel();
+        self.stop_tx = Some(tx);
+
+        let shared = self.shared.clone();
+        self.writer_handle = Some(thread::spawn(move || {
+            run_writer(app_handle, shared, rx, universe)
+        }));
+    }
+
+    fn stop_writer(&mut self, universe: u16) {
+        self.cut_stopping(universe);
+        if let Some(handle) = self.finish_writer(StopPolicy::default()) {
+            join_writer(universe, handle);
+        }
+    }
+
+    // Manda la señal de parada sin esperar: con hold o fade el hilo sigue transmitiendo un
+    // rato y el que llama decide si espera el handle
+    fn finish_writer(&mut self, policy: StopPolicy) -> Option<thread::JoinHandle<()>> {
+        if let Some(tx) = self.stop_tx.take() {
+            let _ = tx.send(policy);
+        }
+        self.writer_handle.take()
+    }
+
+    // Como `finish_writer`, pero el hilo con hold o fade queda en `stopping` para poder cortarlo
+    fn begin_stop(&mut self, universe: u16, policy: StopPolicy) {
+        let (Some(stop_tx), Some(handle)) = (self.stop_tx.take(), self.writer_handle.take()) else {
+            return;
+        };
+        let _ = stop_tx.send(policy);
+        if policy.duration().is_zero() {
+            join_writer(universe, handle);
+        } else {
+            self.stopping = Some(StoppingWriter { stop_tx, handle });
+        }
+    }
+
+    // Una segunda señal termina el hold o el fade en el acto
+    fn cut_stopping(&mut self, universe: u16) {
+        if let Some(stopping) = self.stopping.take() {
+            let _ = stopping.stop_tx.send(StopPolicy::default());
+            join_writer(universe, stopping.handle);
+        }
+    }
+}
+
+// Hilo que sigue transmitiendo después de la señal de parada
+struct StoppingWriter {
+    stop_tx: Sender<StopPolicy>,
+    handle: thread::JoinHandle<()>,
+}
+
+fn join_writer(universe: u16, handle: thread::JoinHandle<()>) {
+    if handle.join().is_err() {
+        error!("El hilo DMX del universo {universe} terminó con pánico");
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct DmxPortSettings {
+    refresh_hz: Option<u32>,
+    timing: Option<DmxTiming>,
+}
+
+#[derive(Default)]
+pub struct DmxState {
+    universes: Mutex<HashMap<u16, DmxUniverse>>,
+    port_settings: Mutex<HashMap<String, DmxPortSettings>>,
+    port_settings_path: Mutex<Option<PathBuf>>,
+    stop_policy: Mutex<StopPolicy>,
+    stop_policy_path: Mutex<Option<PathBuf>>,
+}
+
+// Configuración persistible de un universo (para el archivo de proyecto)
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct UniverseSettings {
+    universe: u16,
+    port_path: Option<String>,
+    driver: DmxDriver,
+    #[serde(default)]
+    serial_backend: SerialBackend,
+    #[serde(default)]
+    start_code: u8,
+    refresh_hz: u32,
+    timing: DmxTiming,
+    master: u8,
+    intensity_channels: Vec<u16>,
+    #[serde(default)]
+    slew_limits: Vec<(u16, u8)>,
+    #[serde(default)]
+    curves: Vec<(u16, DimmerCurve)>,
+    #[serde(default)]
+    channel_limits: Vec<(u16, u8, u8)>,
+    #[serde(default)]
+    inverted_channels: Vec<u16>,
+    artnet: Option<ArtNetConfig>,
+    sacn: Option<SacnConfig>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct DmxPortInfo {
+    path: String,
+    kind: Option<String>,
+    manufacturer: Option<String>,
+    product: Option<String>,
+    serial_number: Option<String>,
+}
+
+impl DmxPortInfo {
+    pub(crate) fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+#[derive(Serialize)]
+pub struct DmxStatus {
+    universe: u16,
+    port_path: Option<String>,
+    // Puerto (serie o uDMX) que el hilo de escritura tiene abierto ahora
+    open_port: Option<String>,
+    running: bool,
+    #[serde(flatten)]
+    health: OutputHealth,
+}
+
+#[derive(Serialize)]
+pub struct DmxUniverseInfo {
+    universe: u16,
+    port_path: Option<String>,
+    driver: DmxDriver,
+    serial_backend: SerialBackend,
+    start_code: u8,
+    refresh_hz: u32,
+    timing: DmxTiming,
+    master: u8,
+    running: bool,
+}
+
+#[tauri::command]
+pub fn dmx_list_ports(serial: State<'_, SerialPort<Wry>>) -> Result<Vec<DmxPortInfo>, String> {
+    list_ports(&serial)
+}
+
+// Puertos serie, uDMX y UARTs de la Raspberry Pi, ordenados por ruta
+pub(crate) fn list_ports(serial: &SerialPort<Wry>) -> Result<Vec<DmxPortInfo>, String> {
+    let mut ports = serial
+        .available_ports()
+        .map_err(|e| format!("No se pudieron listar los puertos: {e}"))?
+        .into_iter()
+        .map(|(path, meta)| DmxPortInfo {
+            path,
+            kind: meta.get("type").cloned().filter(|t| t != UNKNOWN),
+            manufacturer: meta.get("manufacturer").cloned().filter(|m| m != UNKNOWN),
+            product: meta.get("product").cloned().filter(|p| p != UNKNOWN),
+            serial_number: meta.get("serial_number").cloned().filter(|s| s != UNKNOWN),
+        })
+        .collect::<Vec<_>>();
+
+    match udmx::list_devices() {
+        Ok(devices) => ports.extend(devices.into_iter().map(|device| DmxPortInfo {
+            path: device.path,
+            kind: Some("uDMX".to_string()),
+            manufacturer: device.manufacturer,
+            product: device.product,
+            serial_number: device.serial_number,
+        })),
+        Err(err) => error!("{err}"),
+    }
+
+    for uart in PI_UART_PATHS {
+        if std::path::Path::new(uart).exists() && !ports.iter().any(|p| p.path == *uart) {
+            ports.push(DmxPortInfo {
+                path: uart.to_string(),
+                kind: Some("UART".to_string()),
+                manufacturer: None,
+                product: None,
+                serial_number: None,
+            });
+        }
+    }
+
+    ports.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(ports)
+}
+
+#[tauri::command]
+pub fn dmx_set_port(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    port_path: String,
+) -> Result<(), String> {
+    let mut universes = state.lock_universes()?;
+
+    if let Some((other, _)) = universes
+        .iter()
+        .find(|(id, u)| **id != universe && u.shared.port().as_deref() == Some(port_path.as_str()))
+    {
+        return Err(format!(
+            "El puerto {port_path} ya está asignado al universo {other}"
+        ));
+    }
+
+    let entry = universes.entry(universe).or_default();
+    if PI_UART_PATHS.contains(&port_path.as_str()) {
+        entry.shared.set_driver(DmxDriver::PiUart)?;
+    }
+    let settings = state.port_settings(&port_path).unwrap_or_default();
+    // Sin frecuencia guardada para el puerto vale la de la configuración
+    let refresh_hz = settings
+        .refresh_hz
+        .or_else(|| app_handle.state::<SettingsState>().refresh_hz())
+        .unwrap_or(DEFAULT_REFRESH_HZ);
+    entry.shared.set_refresh_rate(refresh_hz)?;
+    entry
+        .shared
+        .set_timing(settings.timing.unwrap_or_default())?;
+    entry.shared.set_port(port_path)?;
+    entry.ensure_writer(app_handle, universe);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn dmx_set_levels(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    levels: Vec<u8>,
+    fade_ms: Option<u64>,
+) -> Result<(), String> {
+    let mut universes = state.lock_universes()?;
+    let entry = universes.entry(universe).or_default();
+    match fade_ms {
+        Some(fade_ms) => entry
+            .shared
+            .start_fade(&levels, Duration::from_millis(fade_ms))?,
+        None => entry.shared.update_levels(&levels)?,
+    }
+    entry.ensure_writer(app_handle, universe);
+    Ok(())
+}
+
+// Para un fader: no pisa el resto del universo como `dmx_set_levels`
+#[tauri::command]
+pub fn dmx_set_channel(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    channel: u16,
+    value: u8,
+) -> Result<(), String> {
+    state.with_universe(app_handle, universe, |shared| {
+        shared.patch_levels(channel, &[value])
+    })
+}
+
+// `values` va a los canales `start`, `start + 1`...
+#[tauri::command]
+pub fn dmx_set_range(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    start: u16,
+    values: Vec<u8>,
+) -> Result<(), String> {
+    state.with_universe(app_handle, universe, |shared| {
+        shared.patch_levels(start, &values)
+    })
+}
+
+// Pares [canal, delta] para encoders y CCs relativos; devuelve los valores que quedaron
+#[tauri::command]
+pub fn dmx_adjust_channels(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    deltas: Vec<(u16, i16)>,
+) -> Result<Vec<(u16, u8)>, String> {
+    state.with_universe(app_handle, universe, |shared| shared.adjust_levels(&deltas))
+}
+
+// Vuelve a intentar la salida física de inmediato, también después de haberse rendido
+#[tauri::command]
+pub fn dmx_reconnect(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+) -> Result<(), String> {
+    state.with_universe(app_handle, universe, |shared| {
+        let port = shared
+            .port()
+            .ok_or_else(|| format!("El universo {universe} no tiene puerto asignado"))?;
+        shared
+            .reconnect
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la reconexión DMX: {e}"))?
+            .reset();
+        shared.clear_open_port();
+        info!("Reconectando {port} (universo {universe})");
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn dmx_set_driver(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    driver: DmxDriver,
+) -> Result<(), String> {
+    state.with_universe(app_handle, universe, |shared| {
+        info!("Universo {universe} usando driver {driver:?}");
+        shared.set_driver(driver)
+    })
+}
+
+// El backend directo saca el frame con un handle propio del hilo; RDM y `dmx_listen` siguen
+// necesitando el del plugin
+#[tauri::command]
+pub fn dmx_set_serial_backend(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    backend: SerialBackend,
+) -> Result<(), String> {
+    state.with_universe(app_handle, universe, |shared| {
+        info!("Universo {universe} usando backend serie {backend:?}");
+        shared.set_serial_backend(backend)
+    })
+}
+
+// Para mandar paquetes de texto o SIP por el universo en lugar de niveles
+#[tauri::command]
+pub fn dmx_set_start_code(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    start_code: u8,
+) -> Result<(), String> {
+    state.with_universe(app_handle, universe, |shared| {
+        info!("Universo {universe} usando start code {start_code:#04x}");
+        shared.set_start_code(start_code)
+    })
+}
+
+#[tauri::command]
+pub fn dmx_set_refresh_rate(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    hz: u32,
+) -> Result<(), String> {
+    if !(MIN_REFRESH_HZ..=MAX_REFRESH_HZ).contains(&hz) {
+        return Err(format!(
+            "La frecuencia DMX debe estar entre {MIN_REFRESH_HZ} y {MAX_REFRESH_HZ} Hz"
+        ));
+    }
+
+    let port = state.with_universe(app_handle, universe, |shared| {
+        shared.set_refresh_rate(hz)?;
+        Ok(shared.port())
+    })?;
+
+    info!("Universo {universe} refrescando a {hz} Hz");
+    if let Some(port) = port {
+        state.update_port_settings(&port, |settings| settings.refresh_hz = Some(hz))?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn dmx_set_timing(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    break_us: u32,
+    mab_us: u32,
+) -> Result<(), String> {
+    let timing = DmxTiming::new(break_us, mab_us)?;
+
+    let port = state.with_universe(app_handle, universe, |shared| {
+        shared.set_timing(timing)?;
+        Ok(shared.port())
+    })?;
+
+    info!("Universo {universe} con break de {break_us} µs y MAB de {mab_us} µs");
+    if let Some(port) = port {
+        state.update_port_settings(&port, |settings| settings.timing = Some(timing))?;
+    }
+    Ok(())
+}
+
+// Sin universo apaga todos los universos
+#[tauri::command]
+pub fn dmx_blackout(
+    state: State<'_, DmxState>,
+    universe: Option<u16>,
+    fade_ms: Option<u64>,
+) -> Result<(), String> {
+    let duration = Duration::from_millis(fade_ms.unwrap_or(0));
+    let mut result = Ok(());
+
+    state.for_each_universe(|id, shared| {
+        if universe.is_some_and(|universe| universe != id) {
+            return;
+        }
+        if let Err(err) = shared.start_fade(&[], duration) {
+            result = Err(err);
+        }
+    });
+
+    info!(
+        "Blackout DMX en {} ({} ms)",
+        universe.map_or("todos los universos".to_string(), |u| format!(
+            "universo {u}"
+        )),
+        duration.as_millis()
+    );
+    result
+}
+
+// Sin universo ajusta el master de todos los universos
+#[tauri::command]
+pub fn dmx_set_master(
+    state: State<'_, DmxState>,
+    value: u8,
+    universe: Option<u16>,
+) -> Result<(), String> {
+    let mut result = Ok(());
+
+    state.for_each_universe(|id, shared| {
+        if universe.is_some_and(|universe| universe != id) {
+            return;
+        }
+        match shared.lock_master() {
+            Ok(mut master) => master.value = value,
+            Err(err) => result = Err(err),
+        }
+    });
+    result
+}
+
+// Aplicado al final, después del master; un valor que salta llega en varios frames
+#[tauri::command]
+pub fn dmx_set_slew_limit(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    channels: Vec<u16>,
+    max_step: u8,
+) -> Result<(), String> {
+    state.with_universe(app_handle, universe, |shared| {
+        shared.set_slew_limit(&channels, max_step)
+    })
+}
+
+// Patch proporcional: 0 sale como `min` y 255 como `max`, p. ej. para que la lámpara de un
+// mover no se apague o para topear un strobe
+#[tauri::command]
+pub fn dmx_set_limits(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    channels: Vec<u16>,
+    min: u8,
+    max: u8,
+) -> Result<(), String> {
+    state.with_universe(app_handle, universe, |shared| {
+        shared.set_limits(&channels, min, max)
+    })
+}
+
+#[tauri::command]
+pub fn dmx_set_inverted(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    channels: Vec<u16>,
+    inverted: bool,
+) -> Result<(), String> {
+    state.with_universe(app_handle, universe, |shared| {
+        shared.set_inverted(&channels, inverted)
+    })
+}
+
+// Canales 1-512 a los que afecta el master; una lista vacía vuelve a escalar todo el universo
+#[tauri::command]
+pub fn dmx_set_intensity_channels(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    channels: Vec<u16>,
+) -> Result<(), String> {
+    state.with_universe(app_handle, universe, |shared| {
+        shared.set_intensity_channels(&channels)
+    })
+}
+
+#[tauri::command]
+pub fn dmx_set_source_priority(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    source: MergeSourceKind,
+    priority: u8,
+) -> Result<(), String> {
+    state.with_universe(app_handle, universe, |shared| {
+        shared.lock_merger()?.set_priority(source, priority);
+        info!("Universo {universe}: prioridad {priority} para {source:?}");
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn dmx_get_source_priorities(
+    state: State<'_, DmxState>,
+    universe: u16,
+) -> Result<HashMap<MergeSourceKind, u8>, String> {
+    state.read_universe(universe, |shared| {
+        let merger = shared.lock_merger()?;
+        Ok(MergeSourceKind::ALL
+            .into_iter()
+            .map(|kind| (kind, merger.priority(kind)))
+            .collect())
+    })
+}
+
+// Fuente que controla cada canal (índice 0 = canal 1) en el último frame enviado
+#[tauri::command]
+pub fn dmx_channel_owners(
+    state: State<'_, DmxState>,
+    universe: u16,
+) -> Result<Vec<Option<MergeSource>>, String> {
+    state.read_universe(universe, |shared| {
+        Ok(shared.lock_merger()?.owners().to_vec())
+    })
+}
+
+// Sin universo detiene todas las salidas
+#[tauri::command]
+pub fn dmx_stop(state: State<'_, DmxState>, universe: Option<u16>) -> Result<(), String> {
+    let policy = state.stop_policy();
+    let mut universes = state.lock_universes()?;
+
+    for (id, entry) in universes.iter_mut() {
+        if universe.is_some_and(|universe| universe != *id) {
+            continue;
+        }
+        entry.begin_stop(*id, policy);
+        entry.shared.clear_port()?;
+        info!("Salida DMX del universo {id} detenida");
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn dmx_remove_universe(state: State<'_, DmxState>, universe: u16) -> Result<(), String> {
+    let mut universes = state.lock_universes()?;
+    let mut removed = universes
+        .remove(&universe)
+        .ok_or_else(|| format!("El universo {universe} no existe"))?;
+    removed.stop_writer(universe);
+    Ok(())
+}
+
+// Lo que está saliendo por el universo, para que el front lo lea después de recargar
+#[tauri::command]
+pub fn dmx_get_levels(state: State<'_, DmxState>, universe: u16) -> Result<Vec<u8>, String> {
+    state.read_universe(universe, |shared| Ok(shared.output_levels()))
+}
+
+#[tauri::command]
+pub fn dmx_get_channel(
+    state: State<'_, DmxState>,
+    universe: u16,
+    channel: u16,
+) -> Result<u8, String> {
+    if !(1..=DMX_CHANNELS as u16).contains(&channel) {
+        return Err(format!("Canal DMX inválido: {channel}"));
+    }
+    state.read_universe(universe, |shared| {
+        Ok(shared.output_levels()[channel as usize - 1])
+    })
+}
+
+#[tauri::command]
+pub fn dmx_list_universes(state: State<'_, DmxState>) -> Result<Vec<DmxUniverseInfo>, String> {
+    let universes = state.lock_universes()?;
+    let mut infos = universes
+        .iter()
+        .map(|(id, u)| DmxUniverseInfo {
+            universe: *id,
+            port_path: u.shared.port(),
+            driver: u.shared.driver(),
+            serial_backend: u.shared.serial_backend(),
+            start_code: u.shared.start_code(),
+            refresh_hz: u.shared.refresh_rate(),
+            timing: u.shared.timing(),
+            master: u.shared.master(),
+            running: u.writer_handle.is_some(),
+        })
+        .collect::<Vec<_>>();
+
+    infos.sort_by_key(|info| info.universe);
+    Ok(infos)
+}
+
+// Estado de conexión de cada universo para el indicador del front
+#[tauri::command]
+pub fn dmx_status(state: State<'_, DmxState>) -> Result<Vec<DmxStatus>, String> {
+    let universes = state.lock_universes()?;
+    let mut statuses = universes
+        .iter()
+        .map(|(id, u)| DmxStatus {
+            universe: *id,
+            port_path: u.shared.port(),
+            open_port: u.shared.open_port.lock().ok().and_then(|open| open.clone()),
+            running: u.writer_handle.is_some(),
+            health: u
+                .shared
+                .reconnect
+                .lock()
+                .map(|reconnect| reconnect.health())
+                .unwrap_or_default(),
+        })
+        .collect::<Vec<_>>();
+
+    statuses.sort_by_key(|status| status.universe);
+    Ok(statuses)
+}
+
+impl DmxState {
+    // Al cerrar la app: detiene los hilos de escritura con la política de parada y espera a
+    // que terminen (y cierren su puerto), incluidos los que ya estaban en su hold o su fade
+    pub fn shutdown(&self) {
+        let policy = self.stop_policy().on_exit();
+        // Los hilos se esperan sin el lock de los universos
+        let removed = match self.lock_universes() {
+            Ok(mut universes) => universes.drain().collect::<Vec<_>>(),
+            Err(_) => return,
+        };
+
+        let mut handles = Vec::new();
+        for (id, mut entry) in removed {
+            if let Some(handle) = entry.finish_writer(policy) {
+                handles.push((id, handle));
+            }
+            // Un hold o un fade de un `dmx_stop` anterior se corta
+            if let Some(stopping) = entry.stopping.take() {
+                let _ = stopping.stop_tx.send(StopPolicy::default());
+                handles.push((id, stopping.handle));
+            }
+        }
+        for (id, handle) in handles {
+            join_writer(id, handle);
+        }
+        info!("Salida DMX detenida al cerrar la app");
+    }
+
+    pub fn load_stop_policy(&self, app_handle: &AppHandle) {
+        let Some(path) = storage::config_file(app_handle, STOP_POLICY_FILE) else {
+            return;
+        };
+
+        if let Some(policy) = storage::load_json(&path) {
+            if let Ok(mut current) = self.stop_policy.lock() {
+                *current = policy;
+            }
+        }
+
+        if let Ok(mut current) = self.stop_policy_path.lock() {
+            *current = Some(path);
+        }
+    }
+
+    pub(crate) fn stop_policy(&self) -> StopPolicy {
+        self.stop_policy
+            .lock()
+            .map(|policy| *policy)
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn set_stop_policy(&self, policy: StopPolicy) -> Result<(), String> {
+        let mut current = self
+            .stop_policy
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la política de parada: {e}"))?;
+        *current = policy;
+
+        let path = self
+            .stop_policy_path
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la política de parada: {e}"))?
+            .clone();
+        match path {
+            Some(path) => storage::save_json(&path, &*current),
+            None => Ok(()),
+        }
+    }
+
+    fn lock_universes(
+        &self,
+    ) -> Result<std::sync::MutexGuard<'_, HashMap<u16, DmxUniverse>>, String> {
+        self.universes
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la tabla de universos DMX: {e}"))
+    }
+
+    pub fn load_port_settings(&self, app_handle: &AppHandle) {
+        let Some(path) = storage::config_file(app_handle, PORT_SETTINGS_FILE) else {
+            return;
+        };
+
+        if let Some(settings) = storage::load_json(&path) {
+            if let Ok(mut current) = self.port_settings.lock() {
+                *current = settings;
+            }
+        }
+
+        if let Ok(mut current) = self.port_settings_path.lock() {
+            *current = Some(path);
+        }
+    }
+
+    fn port_settings(&self, port_path: &str) -> Option<DmxPortSettings> {
+        self.port_settings
+            .lock()
+            .ok()
+            .and_then(|settings| settings.get(port_path).cloned())
+    }
+
+    fn update_port_settings(
+        &self,
+        port_path: &str,
+        f: impl FnOnce(&mut DmxPortSettings),
+    ) -> Result<(), String> {
+        let mut settings = self
+            .port_settings
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la configuración de puertos: {e}"))?;
+        f(settings.entry(port_path.to_string()).or_default());
+
+        let path = self
+            .port_settings_path
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la configuración de puertos: {e}"))?
+            .clone();
+        match path {
+            Some(path) => storage::save_json(&path, &*settings),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn port_in_use(&self, port_path: &str) -> Result<bool, String> {
+        let universes = self.lock_universes()?;
+        Ok(universes
+            .values()
+            .any(|u| u.shared.port().as_deref() == Some(port_path)))
+    }
+
+    pub(crate) fn for_each_universe(&self, mut f: impl FnMut(u16, &DmxSharedState)) {
+        match self.lock_universes() {
+            Ok(universes) => universes.iter().for_each(|(id, u)| f(*id, &u.shared)),
+            Err(err) => error!("{err}"),
+        }
+    }
+
+    // Reparte un valor por universo (p. ej. la atenuación de los submasters). Los universos
+    // que faltan se crean y los que no aparecen en `values` reciben `None`.
+    pub(crate) fn distribute<T>(
+        &self,
+        app_handle: &AppHandle,
+        values: BTreeMap<u16, T>,
+        set: impl Fn(&DmxSharedState, Option<T>),
+    ) -> Result<(), String> {
+        self.for_each_universe(|universe, shared| {
+            if !values.contains_key(&universe) {
+                set(shared, None);
+            }
+        });
+        for (universe, value) in values {
+            self.with_universe(app_handle.clone(), universe, |shared| {
+                set(shared, Some(value));
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn with_universe<T>(
+        &self,
+        app_handle: AppHandle,
+        universe: u16,
+        f: impl FnOnce(&DmxSharedState) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut universes = self.lock_universes()?;
+        let entry = universes.entry(universe).or_default();
+        let result = f(&entry.shared)?;
+        entry.ensure_writer(app_handle, universe);
+        Ok(result)
+    }
+
+    pub(crate) fn read_universe<T>(
+        &self,
+        universe: u16,
+        f: impl FnOnce(&DmxSharedState) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let universes = self.lock_universes()?;
+        let entry = universes
+            .get(&universe)
+            .ok_or_else(|| format!("El universo {universe} no existe"))?;
+        f(&entry.shared)
+    }
+
+    pub(crate) fn export_settings(&self) -> Result<Vec<UniverseSettings>, String> {
+        let universes = self.lock_universes()?;
+        let mut settings = universes
+            .iter()
+            .map(|(id, u)| UniverseSettings {
+                universe: *id,
+                port_path: u.shared.port(),
+                driver: u.shared.driver(),
+                serial_backend: u.shared.serial_backend(),
+                start_code: u.shared.start_code(),
+                refresh_hz: u.shared.refresh_rate(),
+                timing: u.shared.timing(),
+                master: u.shared.master(),
+                intensity_channels: u.shared.intensity_channels(),
+                slew_limits: u.shared.slew_limits(),
+                curves: u
+                    .shared
+                    .curves
+                    .lock()
+                    .map(|curves| curves.channel_curves())
+                    .unwrap_or_default(),
+                channel_limits: u.shared.channel_limits(),
+                inverted_channels: u.shared.inverted_channels(),
+                artnet: artnet::export_config(&u.shared),
+                sacn: sacn::export_config(&u.shared),
+            })
+            .collect::<Vec<_>>();
+
+        settings.sort_by_key(|settings| settings.universe);
+        Ok(settings)
+    }
+
+    // Reemplaza todos los universos por los de `settings` (p. ej. al abrir un proyecto)
+    pub(crate) fn restore_settings(
+        &self,
+        app_handle: &AppHandle,
+        settings: Vec<UniverseSettings>,
+    ) -> Result<(), String> {
+        // Se arman aparte: con un universo inválido los que están saliendo siguen igual
+        let mut restored: HashMap<u16, DmxUniverse> = HashMap::new();
+        for settings in settings {
+            if settings.port_path.as_deref().is_some_and(|port| {
+                restored
+                    .values()
+                    .any(|u| u.shared.port().as_deref() == Some(port))
+            }) {
+                return Err(format!(
+                    "El puerto del universo {} está repetido en el proyecto",
+                    settings.universe
+                ));
+            }
+            if !(MIN_REFRESH_HZ..=MAX_REFRESH_HZ).contains(&settings.refresh_hz) {
+                return Err(format!(
+                    "Frecuencia DMX inválida en el universo {}",
+                    settings.universe
+                ));
+            }
+            let timing = DmxTiming::new(settings.timing.break_us, settings.timing.mab_us)?;
+
+            let entry = restored.entry(settings.universe).or_default();
+            entry.shared.set_driver(settings.driver)?;
+            entry.shared.set_serial_backend(settings.serial_backend)?;
+            entry.shared.set_start_code(settings.start_code)?;
+            entry.shared.set_refresh_rate(settings.refresh_hz)?;
+            entry.shared.set_timing(timing)?;
+            entry
+                .shared
+                .set_intensity_channels(&settings.intensity_channels)?;
+            entry.shared.clear_slew_limits()?;
+            for (channel, min, max) in &settings.channel_limits {
+                entry.shared.set_limits(&[*channel], *min, *max)?;
+            }
+            entry
+                .shared
+                .set_inverted(&settings.inverted_channels, true)?;
+            for (channel, max_step) in &settings.slew_limits {
+                entry.shared.set_slew_limit(&[*channel], *max_step)?;
+            }
+            {
+                let mut curves = entry
+                    .shared
+                    .curves
+                    .lock()
+                    .map_err(|e| format!("No se pudo bloquear las curvas DMX: {e}"))?;
+                for (channel, curve) in settings.curves {
+                    curve.validate()?;
+                    curves.set_channels(&[channel], Some(curve));
+                }
+            }
+            entry.shared.lock_master()?.value = settings.master;
+            if let Some(config) = settings.artnet {
+                artnet::restore_config(&entry.shared, config)?;
+            }
+            if let Some(config) = settings.sacn {
+                sacn::restore_config(&entry.shared, config)?;
+            }
+            if let Some(port) = settings.port_path {
+                entry.shared.set_port(port)?;
+            }
+        }
+
+        let mut universes = self.lock_universes()?;
+        for (id, mut removed) in universes.drain() {
+            removed.stop_writer(id);
+        }
+        for (id, mut universe) in restored {
+            universe.ensure_writer(app_handle.clone(), id);
+            universes.insert(id, universe);
+        }
+        Ok(())
+    }
+}
+
+fn run_writer(
+    app_handle: AppHandle,
+    shared: DmxSharedState,
+    rx: Receiver<StopPolicy>,
+    universe: u16,
+) {
+    // El handle USB del uDMX vive en el hilo de escritura, no en el plugin serie
+    let mut udmx_output: Option<UdmxOutput> = None;
+    // Lo mismo con el puerto serie cuando el universo usa el backend directo
+    let mut direct_output: Option<DirectSerial> = None;
+    if let Ok(mut metrics) = shared.metrics.lock() {
+        metrics.reset();
+    }
+    let mut scheduler = FrameScheduler::new();
+    // Buffers del hilo, reutilizados en cada frame para no pedir memoria en el loop
+    let mut frame = [0u8; DMX_FRAME_SIZE];
+    let mut contributions = Contributions::default();
+    let mut target_port: Option<String> = None;
+    // Desde la señal de parada hasta el último frame del hold, el negro o el fade
+    let mut stopping: Option<StopRamp> = None;
+
+    loop {
+        if stopping.is_some() {
+            // Un hilo nuevo va a usar el mismo puerto: termina sin esperar el hold o el fade
+            if rx.try_recv().is_ok() {
+                break;
+            }
+        } else if let Ok(policy) = rx.try_recv() {
+            info!("Cerrando loop DMX del universo {universe} por señal de parada");
+            if policy.is_immediate() {
+                break;
+            }
+            // `frame` todavía tiene lo último que salió
+            let intensity = shared.intensity_channels();
+            stopping = Some(StopRamp::new(policy, &frame, &intensity, Instant::now()));
+        }
+
+        let now = Instant::now();
+        let last_frame = match &stopping {
+            // El puerto ya quedó liberado en el estado; se sigue usando el que tenía el hilo
+            Some(ramp) => ramp.render(&mut frame, now),
+            None => {
+                match shared.port_path.lock() {
+                    Ok(guard) => {
+                        if guard.as_deref() != target_port.as_deref() {
+                            target_port = guard.clone();
+                        }
+                    }
+                    Err(err) => {
+                        error!("No se pudo leer el puerto DMX del universo {universe}: {err}");
+                        thread::sleep(Duration::from_millis(200));
+                        continue;
+                    }
+                };
+
+                shared.advance_fade();
+                shared.copy_levels(&mut frame);
+
+                // Un paquete alternativo no son niveles: sin merge, efectos ni master
+                frame[0] = shared.start_code();
+                if frame[0] == NULL_START_CODE {
+                    shared.merge_sources(&mut frame[1..], now, &mut contributions);
+                    if let Ok(mut effects) = shared.effects.lock() {
+                        effects.apply(&mut frame[1..], now);
+                    }
+
+                    shared.apply_calibration(&mut frame[1..]);
+                    shared.apply_master(&mut frame[1..]);
+                    shared.apply_locate(&mut frame[1..]);
+                    if let Ok(curves) = shared.curves.lock() {
+                        curves.apply(&mut frame[1..]);
+                    }
+                    shared.apply_limits(&mut frame[1..]);
+                    // Se invierte antes del límite de cambio, que compara con lo que ya salió
+                    shared.apply_invert(&mut frame[1..]);
+                    shared.apply_slew(&mut frame[1..]);
+                }
+
+                // Mientras se reproduce una grabación sale tal cual, sin merge ni master
+                shared.copy_replay_frame(&mut frame);
+                false
+            }
+        };
+
+        shared.store_output(&frame);
+        if let Ok(mut monitor) = shared.monitor.lock() {
+            monitor.observe(&app_handle, universe, &frame[1..], now);
+        }
+        app_handle
+            .state::<RecordingState>()
+            .capture(universe, &frame);
+
+        // Un backup que sigue al principal calcula los frames pero no los saca
+        let held = app_handle.state::<BackupState>().holds_output();
+        match target_port.as_deref() {
+            _ if held => {}
+            Some(port_path) if udmx::is_udmx_path(port_path) => {
+                direct_output = None;
+                write_udmx_frame(
+                    &app_handle,
+                    &shared,
+                    &mut udmx_output,
+                    universe,
+                    port_path,
+                    &frame,
+                );
+            }
+            Some(port_path) if shared.serial_backend() == SerialBackend::Direct => {
+                udmx_output = None;
+                write_direct_frame(
+                    &app_handle,
+                    &shared,
+                    &mut direct_output,
+                    universe,
+                    port_path,
+                    &frame,
+                );
+            }
+            Some(port_path) => {
+                udmx_output = None;
+                direct_output = None;
+                write_serial_frame(&app_handle, &shared, universe, port_path, &frame);
+            }
+            None => {
+                // El uDMX y el handle directo se cierran al soltarlos; el estado tiene que
+                // reflejarlo
+                let udmx_closed = udmx_output.take().is_some();
+                let direct_closed = direct_output.take().is_some();
+                if udmx_closed || direct_closed {
+                    shared.clear_open_port();
+                }
+            }
+        }
+
+        if !held {
+            write_network_frames(&shared, universe, &frame);
+        }
+        if let Ok(mut metrics) = shared.metrics.lock() {
+            metrics.record(Instant::now(), shared.refresh_rate());
+        }
+
+        if last_frame {
+            break;
+        }
+        scheduler.wait(shared.frame_period());
+    }
+
+    let open_port = shared
+        .open_port
+        .lock()
+        .ok()
+        .and_then(|mut open| open.take());
+    if direct_output.take().is_some() {
+        info!("Puerto DMX directo cerrado (universo {universe})");
+    } else if let Some(port_path) = open_port {
+        let serial = app_handle.state::<SerialPort<Wry>>();
+        if let Err(err) = serial.close(port_path.clone()) {
+            error!("No se pudo cerrar el puerto DMX {}: {err}", port_path);
+        } else {
+            info!("Puerto DMX cerrado: {} (universo {universe})", port_path);
+        }
+    }
+}
+
+fn write_serial_frame(
+    app_handle: &AppHandle,
+    shared: &DmxSharedState,
+    universe: u16,
+    port_path: &str,
+    frame: &[u8],
+) {
+    let serial = app_handle.state::<SerialPort<Wry>>();
+    let driver = shared.driver();
+
+    let needs_open = match shared.open_port.lock() {
+        Ok(opened) => opened.as_deref() != Some(port_path),
+        Err(err) => {
+            error!("No se pudo comprobar el estado del puerto DMX: {err}");
+            true
+        }
+    };
+
+    if needs_open {
+        if !shared.may_attempt_output(port_path) {
+            return;
+        }
+        // Descarta un handle viejo (cambio de driver o error previo) antes de reabrir
+        let _ = serial.close(port_path.to_string());
+
+        match open_serial_port(&serial, port_path, driver) {
+            Ok(_) => {
+                info!(
+                    "Puerto DMX abierto: {} (universo {universe}, driver {driver:?})",
+                    port_path
+                );
+                if let Ok(mut open) = shared.open_port.lock() {
+                    *open = Some(port_path.to_string());
+                }
+                shared.widget_params_dirty.store(true, Ordering::Relaxed);
+            }
+            Err(err) => {
+                let code = DmxErrorCode::classify(&err, DmxErrorCode::OpenFailed);
+                let message = format!("No se pudo abrir el puerto DMX {}: {err}", port_path);
+                output_failed(app_handle, shared, universe, port_path, code, message);
+                return;
+            }
+        }
+    }
+
+    let Ok(_guard) = shared.write_lock.lock() else {
+        return;
+    };
+    let mut link = PluginLink {
+        serial: &serial,
+        port_path,
+    };
+
+    match write_driver_frame(&mut link, shared, driver, port_path, frame) {
+        Ok(()) => reconnect::report_success(&shared.reconnect, universe, port_path),
+        Err(message) => {
+            let code = DmxErrorCode::classify(&message, DmxErrorCode::WriteFailed);
+            output_failed(app_handle, shared, universe, port_path, code, message);
+        }
+    }
+}
+
+// El mismo puerto pero con un handle propio del hilo de escritura, sin pasar por el plugin
+fn write_direct_frame(
+    app_handle: &AppHandle,
+    shared: &DmxSharedState,
+    output: &mut Option<DirectSerial>,
+    universe: u16,
+    port_path: &str,
+    frame: &[u8],
+) {
+    let driver = shared.driver();
+
+    if !output
+        .as_ref()
+        .is_some_and(|direct| direct.matches(port_path, driver))
+    {
+        *output = None;
+        if !shared.may_attempt_output(port_path) {
+            return;
+        }
+        // El plugin no puede quedarse con el puerto abierto al mismo tiempo
+        let _ = app_handle
+            .state::<SerialPort<Wry>>()
+            .close(port_path.to_string());
+
+        match DirectSerial::open(port_path, driver) {
+            Ok(opened) => {
+                info!(
+                    "Puerto DMX abierto en modo directo: {} (universo {universe}, driver {driver:?})",
+                    port_path
+                );
+                *output = Some(opened);
+                if let Ok(mut open) = shared.open_port.lock() {
+                    *open = Some(port_path.to_string());
+                }
+                shared.widget_params_dirty.store(true, Ordering::Relaxed);
+            }
+            Err(err) => {
+                let code = DmxErrorCode::classify(&err, DmxErrorCode::OpenFailed);
+                output_failed(app_handle, shared, universe, port_path, code, err);
+                return;
+            }
+        }
+    }
+
+    let Some(direct) = output.as_mut() else {
+        return;
+    };
+    match write_driver_frame(direct, shared, driver, port_path, frame) {
+        Ok(()) => reconnect::report_success(&shared.reconnect, universe, port_path),
+        Err(message) => {
+            *output = None;
+            let code = DmxErrorCode::classify(&message, DmxErrorCode::WriteFailed);
+            output_failed(app_handle, shared, universe, port_path, code, message);
+        }
+    }
+}
+
+// Operaciones de bajo nivel que usan los drivers; las implementan el plugin serie y el handle
+// directo
+pub(crate) trait SerialLink {
+    fn set_break(&mut self) -> Result<(), String>;
+    fn clear_break(&mut self) -> Result<(), String>;
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), String>;
+    fn write(&mut self, data: &[u8]) -> Result<(), String>;
+    // Paquete Enttec: cada implementación decide dónde arma el buffer
+    fn write_enttec_packet(&mut self, label: u8, payload: &[u8]) -> Result<(), String>;
+}
+
+struct PluginLink<'a> {
+    serial: &'a SerialPort<Wry>,
+    port_path: &'a str,
+}
+
+impl SerialLink for PluginLink<'_> {
+    fn set_break(&mut self) -> Result<(), String> {
+        self.serial
+            .set_break(self.port_path.to_string())
+            .map_err(|e| e.to_string())
+    }
+
+    fn clear_break(&mut self) -> Result<(), String> {
+        self.serial
+            .clear_break(self.port_path.to_string())
+            .map_err(|e| e.to_string())
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), String> {
+        self.serial
+            .set_baud_rate(self.port_path.to_string(), baud_rate)
+            .map_err(|e| e.to_string())
+    }
+
+    // El plugin recibe un `Vec` propio, así que acá la copia no se puede evitar
+    fn write(&mut self, data: &[u8]) -> Result<(), String> {
+        self.serial
+            .write_binary(self.port_path.to_string(), data.to_vec())
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    fn write_enttec_packet(&mut self, label: u8, payload: &[u8]) -> Result<(), String> {
+        self.serial
+            .write_binary(
+                self.port_path.to_string(),
+                enttec::build_packet(label, payload),
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+fn write_driver_frame(
+    link: &mut impl SerialLink,
+    shared: &DmxSharedState,
+    driver: DmxDriver,
+    port_path: &str,
+    frame: &[u8],
+) -> Result<(), String> {
+    match driver {
+        DmxDriver::OpenDmx => write_break_frame(link, shared, port_path, frame),
+        DmxDriver::EnttecPro => write_enttec_pro_frame(link, shared, port_path, frame),
+        DmxDriver::PiUart => write_uart_frame(link, shared, port_path, frame),
+    }
+}
+
+// Cierra la salida física, avisa al front y agenda el reintento
+fn output_failed(
+    app_handle: &AppHandle,
+    shared: &DmxSharedState,
+    universe: u16,
+    port_path: &str,
+    code: DmxErrorCode,
+    message: String,
+) {
+    shared.clear_open_port();
+    let error = errors::report(app_handle, universe, port_path, code, message);
+    reconnect::report_failure(app_handle, &shared.reconnect, universe, port_path, error);
+}
+
+pub(crate) fn open_serial_port(
+    serial: &SerialPort<Wry>,
+    port_path: &str,
+    driver: DmxDriver,
+) -> Result<(), String> {
+    let result = match driver {
+        DmxDriver::OpenDmx | DmxDriver::PiUart => serial.open(
+            port_path.to_string(),
+            DMX_BAUD_RATE,
+            Some(DataBits::Eight),
+            Some(FlowControl::None),
+            Some(Parity::None),
+            Some(StopBits::Two),
+            Some(100),
+        ),
+        // El Pro es un puerto virtual: el baudrate no afecta la salida DMX
+        DmxDriver::EnttecPro => serial.open(
+            port_path.to_string(),
+            57600,
+            Some(DataBits::Eight),
+            Some(FlowControl::None),
+            Some(Parity::None),
+            Some(StopBits::One),
+            Some(100),
+        ),
+    };
+
+    result.map_err(|e| e.to_string())
+}
+
+fn write_break_frame(
+    link: &mut impl SerialLink,
+    shared: &DmxSharedState,
+    port_path: &str,
+    frame: &[u8],
+) -> Result<(), String> {
+    link.set_break()
+        .map_err(|e| format!("No se pudo iniciar el break DMX en {}: {e}", port_path))?;
+
+    let timing = shared.timing();
+    thread::sleep(Duration::from_micros(timing.break_us as u64));
+    link.clear_break()
+        .map_err(|e| format!("No se pudo limpiar el break DMX en {}: {e}", port_path))?;
+
+    thread::sleep(Duration::from_micros(timing.mab_us as u64));
+
+    link.write(frame)
+        .map_err(|e| format!("Error al escribir frame DMX en {}: {e}", port_path))?;
+    debug!("Frame DMX enviado a {} ({} bytes)", port_path, frame.len());
+    Ok(())
+}
+
+fn write_uart_frame(
+    link: &mut impl SerialLink,
+    shared: &DmxSharedState,
+    port_path: &str,
+    frame: &[u8],
+) -> Result<(), String> {
+    // Un 0x00 deja la línea en bajo durante el start bit + 8 bits de datos, así que el
+    // baudrate se elige para que esos 9 bits duren el break pedido
+    let timing = shared.timing();
+    let break_baud_rate = 9_000_000 / timing.break_us;
+
+    link.set_baud_rate(break_baud_rate).map_err(|e| {
+        format!(
+            "No se pudo bajar el baudrate para el break en {}: {e}",
+            port_path
+        )
+    })?;
+
+    link.write(&[0])
+        .map_err(|e| format!("No se pudo enviar el break DMX en {}: {e}", port_path))?;
+
+    // Espera a que el byte de break (y sus 2 stop bits) salga de la FIFO antes de
+    // cambiar el baudrate, con margen para la latencia del driver
+    let byte_us = timing.break_us as u64 * 11 / 9;
+    thread::sleep(Duration::from_micros(byte_us + 40));
+
+    link.set_baud_rate(DMX_BAUD_RATE)
+        .map_err(|e| format!("No se pudo restaurar el baudrate DMX en {}: {e}", port_path))?;
+
+    // Los stop bits y el cambio de baudrate ya aportan parte del MAB; alargarlo es válido
+    thread::sleep(Duration::from_micros(timing.mab_us as u64));
+
+    link.write(frame)
+        .map_err(|e| format!("Error al escribir frame DMX en {}: {e}", port_path))?;
+    debug!(
+        "Frame DMX (UART) enviado a {} ({} bytes)",
+        port_path,
+        frame.len()
+    );
+    Ok(())
+}
+
+fn write_enttec_pro_frame(
+    link: &mut impl SerialLink,
+    shared: &DmxSharedState,
+    port_path: &str,
+    frame: &[u8],
+) -> Result<(), String> {
+    if shared.widget_params_dirty.swap(false, Ordering::Relaxed) {
+        let timing = shared.timing();
+        let params =
+            enttec::build_widget_parameters(timing.break_us, timing.mab_us, shared.refresh_rate());
+        if let Err(err) = link.write(&params) {
+            error!(
+                "No se pudieron configurar los tiempos del Enttec Pro en {}: {err}",
+                port_path
+            );
+            shared.widget_params_dirty.store(true, Ordering::Relaxed);
+        }
+    }
+
+    link.write_enttec_packet(enttec::LABEL_OUTPUT_ONLY_SEND_DMX, frame)
+        .map_err(|e| format!("Error al escribir frame Enttec Pro en {}: {e}", port_path))?;
+    debug!(
+        "Frame Enttec Pro enviado a {} ({} bytes)",
+        port_path,
+        frame.len()
+    );
+    Ok(())
+}
+
+fn write_udmx_frame(
+    app_handle: &AppHandle,
+    shared: &DmxSharedState,
+    output: &mut Option<UdmxOutput>,
+    universe: u16,
+    port_path: &str,
+    frame: &[u8],
+) {
+    if output.as_ref().map(UdmxOutput::path) != Some(port_path) {
+        if !shared.may_attempt_output(port_path) {
+            return;
+        }
+        match UdmxOutput::open(port_path) {
+            Ok(opened) => {
+                info!("uDMX abierto: {} (universo {universe})", port_path);
+                *output = Some(opened);
+                if let Ok(mut open) = shared.open_port.lock() {
+                    *open = Some(port_path.to_string());
+                }
+            }
+            Err(err) => {
+                *output = None;
+                let code = DmxErrorCode::classify(&err, DmxErrorCode::OpenFailed);
+                output_failed(app_handle, shared, universe, port_path, code, err);
+                return;
+            }
+        }
+    }
+
+    if let Some(device) = output.as_ref() {
+        if let Err(err) = device.write(&frame[1..]) {
+            *output = None;
+            let code = DmxErrorCode::classify(&err, DmxErrorCode::WriteFailed);
+            output_failed(app_handle, shared, universe, port_path, code, err);
+        } else {
+            reconnect::report_success(&shared.reconnect, universe, port_path);
+            debug!(
+                "Frame uDMX enviado a {} ({} canales)",
+                port_path,
+                frame.len() - 1
+            );
+        }
+    }
+}
+
+fn write_network_frames(shared: &DmxSharedState, universe: u16, frame: &[u8]) {
+    match shared.artnet.lock() {
+        Ok(mut artnet) => {
+            // ArtDmx solo lleva frames con start code 0; sACN manda el start code en el paquete
+            if let Some(sender) = artnet.as_mut().filter(|_| frame[0] == NULL_START_CODE) {
+                if let Err(err) = sender.send(&frame[1..]) {
+                    error!("Universo {universe}: {err}");
+                }
+            }
+        }
+        Err(err) => error!("No se pudo bloquear la salida Art-Net: {err}"),
+    }
+
+    match shared.sacn.lock() {
+        Ok(mut sacn) => {
+            if let Some(sender) = sacn.as_mut() {
+                if let Err(err) = sender.send(frame) {
+                    error!("Universo {universe}: {err}");
+                }
+            }
+        }
+        Err(err) => error!("No se pudo bloquear la salida sACN: {err}"),
+    }
 }
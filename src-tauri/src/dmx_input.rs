@@ -0,0 +1,294 @@
+use crate::dmx::{open_serial_port, DmxDriver, DmxState};
+use crate::enttec;
+use log::{debug, error, info};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+use tauri::{AppHandle, Emitter, Manager, State, Wry};
+use tauri_plugin_serialplugin::SerialPort;
+
+pub const DMX_INPUT_EVENT: &str = "dmx://input";
+const READ_TIMEOUT_MS: u64 = 5;
+const READ_CHUNK: usize = 1024;
+// Frames seguidos que tienen que coincidir para ubicar el inicio sin pausas en la línea
+const SYNC_FRAMES: usize = 3;
+
+struct DmxListener {
+    stop_tx: Sender<()>,
+    handle: thread::JoinHandle<()>,
+}
+
+#[derive(Default)]
+pub struct DmxInputState {
+    listeners: Mutex<HashMap<String, DmxListener>>,
+}
+
+#[derive(Clone, Serialize)]
+struct DmxInputFrame {
+    port_path: String,
+    start_code: u8,
+    levels: Vec<u8>,
+}
+
+struct ListenOptions {
+    port_path: String,
+    driver: DmxDriver,
+    universe: Option<u16>,
+    channels: u16,
+}
+
+// Separa los frames de una interfaz sin recepción por hardware. El break llega como un 0x00 con
+// error de trama, indistinguible de un nivel en 0: el corte se ubica por una pausa en la línea o,
+// con una consola que transmite sin pausas, contando bytes con el largo de frame conocido.
+struct RawFramer {
+    // Break, start code y canales
+    frame_len: usize,
+    buffer: Vec<u8>,
+    // El buffer empieza en un break
+    locked: bool,
+}
+
+impl RawFramer {
+    fn new(channels: u16) -> Self {
+        Self {
+            frame_len: channels as usize + 2,
+            buffer: Vec::new(),
+            locked: false,
+        }
+    }
+
+    // Lectura vacía: lo recibido hasta acá cierra un frame y lo próximo empieza con un break
+    fn gap(&mut self) -> Option<Vec<u8>> {
+        // Sin ubicar, lo que había puede ser el final de un frame recortado
+        let frame = if self.locked {
+            take_raw_frame(&mut self.buffer)
+        } else {
+            self.buffer.clear();
+            None
+        };
+        self.locked = true;
+        frame
+    }
+
+    fn push(&mut self, chunk: &[u8]) -> Vec<Vec<u8>> {
+        self.buffer.extend_from_slice(chunk);
+        let mut frames = Vec::new();
+        if !self.locked {
+            match self.sync() {
+                Some(offset) => {
+                    self.buffer.drain(..offset);
+                    self.locked = true;
+                }
+                None => {
+                    // Se guardan solo los frames necesarios para volver a probar
+                    let keep = self.frame_len * SYNC_FRAMES + 1;
+                    if self.buffer.len() > keep {
+                        self.buffer.drain(..self.buffer.len() - keep);
+                    }
+                    return frames;
+                }
+            }
+        }
+
+        while self.buffer.len() >= self.frame_len {
+            // Un frame de otro largo (p. ej. RDM) corre el conteo: hay que volver a ubicarlo
+            if self.buffer[0] != 0 {
+                self.locked = false;
+                return frames;
+            }
+            frames.push(self.buffer.drain(..self.frame_len).skip(1).collect());
+        }
+        frames
+    }
+
+    // Posición del break en el buffer: la única donde cada `frame_len` bytes hay un break y un
+    // start code 0. Con varias no se sabe cuál es, salvo que den el mismo frame (p. ej. todo en 0).
+    fn sync(&self) -> Option<usize> {
+        let span = self.frame_len * (SYNC_FRAMES - 1) + 2;
+        if self.buffer.len() < span + self.frame_len {
+            return None;
+        }
+        let candidates = (0..self.frame_len)
+            .filter(|offset| {
+                (0..SYNC_FRAMES).all(|frame| {
+                    let at = offset + frame * self.frame_len;
+                    self.buffer[at] == 0 && self.buffer[at + 1] == 0
+                })
+            })
+            .collect::<Vec<_>>();
+        let first = *candidates.first()?;
+        let frame = |offset: usize| &self.buffer[offset..offset + self.frame_len];
+        candidates
+            .iter()
+            .all(|offset| frame(*offset) == frame(first))
+            .then_some(first)
+    }
+}
+
+#[tauri::command]
+pub fn dmx_listen(
+    app_handle: AppHandle,
+    input: State<'_, DmxInputState>,
+    dmx: State<'_, DmxState>,
+    port_path: String,
+    driver: Option<DmxDriver>,
+    universe: Option<u16>,
+    channels: Option<u16>,
+) -> Result<(), String> {
+    let channels = channels.unwrap_or(512);
+    if !(1..=512).contains(&channels) {
+        return Err(format!("Cantidad de canales inválida: {channels}"));
+    }
+    let mut listeners = input
+        .listeners
+        .lock()
+        .map_err(|e| format!("No se pudo bloquear la tabla de entradas DMX: {e}"))?;
+
+    if listeners.contains_key(&port_path) {
+        return Err(format!("Ya se está escuchando DMX en {port_path}"));
+    }
+    if dmx.port_in_use(&port_path)? {
+        return Err(format!(
+            "El puerto {port_path} está asignado a un universo de salida"
+        ));
+    }
+
+    let options = ListenOptions {
+        port_path: port_path.clone(),
+        driver: driver.unwrap_or_default(),
+        universe,
+        channels,
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let handle = thread::spawn(move || run_listener(app_handle, options, rx));
+    listeners.insert(
+        port_path,
+        DmxListener {
+            stop_tx: tx,
+            handle,
+        },
+    );
+    Ok(())
+}
+
+#[tauri::command]
+pub fn dmx_stop_listening(
+    input: State<'_, DmxInputState>,
+    port_path: String,
+) -> Result<(), String> {
+    let listener = input
+        .listeners
+        .lock()
+        .map_err(|e| format!("No se pudo bloquear la tabla de entradas DMX: {e}"))?
+        .remove(&port_path)
+        .ok_or_else(|| format!("No hay una entrada DMX activa en {port_path}"))?;
+
+    let _ = listener.stop_tx.send(());
+    if listener.handle.join().is_err() {
+        error!("El hilo de entrada DMX de {port_path} terminó con pánico");
+    }
+    Ok(())
+}
+
+fn run_listener(app_handle: AppHandle, options: ListenOptions, rx: Receiver<()>) {
+    let serial = app_handle.state::<SerialPort<Wry>>();
+    let port_path = options.port_path.clone();
+
+    if let Err(err) = open_serial_port(&serial, &port_path, options.driver) {
+        error!("No se pudo abrir la entrada DMX {}: {err}", port_path);
+        return;
+    }
+    info!("Escuchando DMX en {} ({:?})", port_path, options.driver);
+
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut framer = RawFramer::new(options.channels);
+    let mut last_frame: Vec<u8> = Vec::new();
+
+    while rx.try_recv().is_err() {
+        let chunk =
+            match serial.read_binary(port_path.clone(), Some(READ_TIMEOUT_MS), Some(READ_CHUNK)) {
+                Ok(chunk) => chunk,
+                Err(err) => {
+                    // El plugin informa los timeouts como error: en modo raw marcan el fin del frame
+                    debug!("Lectura DMX sin datos en {}: {err}", port_path);
+                    Vec::new()
+                }
+            };
+
+        let frames = match options.driver {
+            DmxDriver::EnttecPro => {
+                buffer.extend_from_slice(&chunk);
+                enttec::parse_packets(&mut buffer)
+                    .into_iter()
+                    .filter(|(label, _)| *label == enttec::LABEL_RECEIVED_DMX_PACKET)
+                    // El primer byte es el estado del widget (overrun/overflow)
+                    .filter_map(|(_, payload)| match payload.split_first() {
+                        Some((status, data)) if *status == 0 && !data.is_empty() => {
+                            Some(data.to_vec())
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+            }
+            DmxDriver::OpenDmx | DmxDriver::PiUart => {
+                if chunk.is_empty() {
+                    framer.gap().into_iter().collect()
+                } else {
+                    framer.push(&chunk)
+                }
+            }
+        };
+
+        for frame in frames {
+            if frame == last_frame {
+                continue;
+            }
+            handle_frame(&app_handle, &options, &frame);
+            last_frame = frame;
+        }
+    }
+
+    let _ = serial.close(port_path.clone());
+    info!("Entrada DMX cerrada: {}", port_path);
+}
+
+// El frame sin el 0x00 del break del principio
+fn take_raw_frame(buffer: &mut Vec<u8>) -> Option<Vec<u8>> {
+    if buffer.len() < 2 {
+        buffer.clear();
+        return None;
+    }
+
+    let frame = buffer[1..].iter().take(513).copied().collect::<Vec<_>>();
+    buffer.clear();
+    Some(frame)
+}
+
+fn handle_frame(app_handle: &AppHandle, options: &ListenOptions, frame: &[u8]) {
+    let start_code = frame[0];
+    let levels = frame[1..].to_vec();
+
+    // Solo los frames con start code 0 llevan niveles DMX
+    if start_code == 0 {
+        if let Some(universe) = options.universe {
+            let dmx = app_handle.state::<DmxState>();
+            if let Err(err) = dmx.with_universe(app_handle.clone(), universe, |shared| {
+                shared.update_levels(&levels)
+            }) {
+                error!("No se pudo reenviar la entrada DMX al universo {universe}: {err}");
+            }
+        }
+    }
+
+    let payload = DmxInputFrame {
+        port_path: options.port_path.clone(),
+        start_code,
+        levels,
+    };
+    if let Err(err) = app_handle.emit(DMX_INPUT_EVENT, payload) {
+        error!("No se pudo emitir el frame DMX recibido: {err}");
+    }
+}
@@ -0,0 +1,282 @@
+use crate::chase::Transport;
+use crate::dmx::{DmxSharedState, DmxState};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::f64::consts::TAU;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, State};
+
+const MAX_SPEED_HZ: f64 = 25.0;
+const MAX_BEATS: f64 = 64.0;
+// Tempo de los efectos con `beats` hasta que llegue un tap tempo o un clock MIDI
+const DEFAULT_BPM: f64 = 120.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Waveform {
+    Sine,
+    Saw,
+    Square,
+    /// Un valor aleatorio nuevo por ciclo (sample & hold)
+    Random,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct EffectParams {
+    waveform: Waveform,
+    #[serde(default)]
+    speed_hz: f64,
+    // Largo del ciclo en beats; si está, el efecto sigue el tempo en lugar de `speed_hz`
+    #[serde(default)]
+    beats: Option<f64>,
+    size: u8,
+    // Desfase en grados, para escalonar el mismo efecto entre canales
+    #[serde(default)]
+    offset: f64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ChannelEffectInfo {
+    channel: u16,
+    effect: EffectParams,
+}
+
+struct ChannelEffect {
+    params: EffectParams,
+    // Estado del sample & hold de `Random`
+    cycle: u64,
+    random_value: f64,
+}
+
+impl ChannelEffect {
+    // Valor del oscilador entre 0 y 1 para el instante `elapsed` (en segundos) o, si sigue el
+    // tempo, para la posición `beat`
+    fn sample(&mut self, elapsed: f64, beat: f64) -> f64 {
+        let cycles = match self.params.beats {
+            Some(beats) => beat / beats,
+            None => elapsed * self.params.speed_hz,
+        };
+        let phase = cycles + self.params.offset / 360.0;
+        let position = phase.rem_euclid(1.0);
+
+        match self.params.waveform {
+            Waveform::Sine => 0.5 - 0.5 * (position * TAU).cos(),
+            Waveform::Saw => position,
+            Waveform::Square => {
+                if position < 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Waveform::Random => {
+                let cycle = phase.floor() as u64;
+                if cycle != self.cycle {
+                    self.cycle = cycle;
+                    self.random_value = random_unit();
+                }
+                self.random_value
+            }
+        }
+    }
+}
+
+fn random_unit() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(nanos);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+pub(crate) struct Effects {
+    channels: BTreeMap<u16, ChannelEffect>,
+    started: Instant,
+    // Canal fino de cada canal grueso de 16 bits (p. ej. pan -> pan fino)
+    fine_channels: BTreeMap<u16, u16>,
+    // Escala del tamaño (0-1) que pone el sound-to-light en sus canales
+    intensities: BTreeMap<u16, f64>,
+    // Posición en beats de los efectos que siguen el tempo; se frena con el transporte
+    bpm: f64,
+    beat: f64,
+    beat_running: bool,
+    last_frame: Instant,
+}
+
+impl Default for Effects {
+    fn default() -> Self {
+        Self {
+            channels: BTreeMap::new(),
+            started: Instant::now(),
+            fine_channels: BTreeMap::new(),
+            intensities: BTreeMap::new(),
+            bpm: DEFAULT_BPM,
+            beat: 0.0,
+            beat_running: true,
+            last_frame: Instant::now(),
+        }
+    }
+}
+
+impl Effects {
+    // Lo llama el hilo de escritura en cada frame: suma cada oscilador (0..size) al nivel
+    // base del canal, sin modificar el buffer
+    pub(crate) fn apply(&mut self, levels: &mut [u8], now: Instant) {
+        if self.beat_running {
+            let frame = now.saturating_duration_since(self.last_frame).as_secs_f64();
+            self.beat += frame * self.bpm / 60.0;
+        }
+        self.last_frame = now;
+        if self.channels.is_empty() {
+            return;
+        }
+
+        let elapsed = now.saturating_duration_since(self.started).as_secs_f64();
+        let beat = self.beat;
+        for (channel, effect) in self.channels.iter_mut() {
+            let coarse = *channel as usize - 1;
+            if coarse >= levels.len() {
+                continue;
+            }
+            let intensity = self.intensities.get(channel).copied().unwrap_or(1.0);
+            let amount = effect.sample(elapsed, beat) * effect.params.size as f64 * intensity;
+
+            // En un canal de 16 bits el oscilador recorre también el fino y el movimiento no
+            // da saltos de un paso grueso
+            match self
+                .fine_channels
+                .get(channel)
+                .map(|fine| *fine as usize - 1)
+                .filter(|fine| *fine < levels.len())
+            {
+                Some(fine) => {
+                    let base = u16::from_be_bytes([levels[coarse], levels[fine]]) as f64;
+                    let value = (base + amount * 257.0).round().min(u16::MAX as f64) as u16;
+                    [levels[coarse], levels[fine]] = value.to_be_bytes();
+                }
+                None => {
+                    levels[coarse] = (levels[coarse] as f64 + amount).round().min(255.0) as u8;
+                }
+            }
+        }
+    }
+
+    // Pares [grueso, fino] (canales 1-512) de los fixtures patcheados en el universo
+    pub(crate) fn set_fine_channels(&mut self, pairs: &[(u16, u16)]) {
+        self.fine_channels = pairs.iter().copied().collect();
+    }
+
+    pub(crate) fn set_intensities(&mut self, intensities: BTreeMap<u16, f64>) {
+        self.intensities = intensities;
+    }
+
+    pub(crate) fn set_bpm(&mut self, bpm: f64) {
+        self.bpm = bpm;
+    }
+
+    pub(crate) fn bpm(&self) -> f64 {
+        self.bpm
+    }
+
+    pub(crate) fn transport(&mut self, transport: Transport) {
+        match transport {
+            Transport::Start => {
+                self.beat = 0.0;
+                self.beat_running = true;
+            }
+            Transport::Stop => self.beat_running = false,
+            Transport::Continue => self.beat_running = true,
+        }
+    }
+}
+
+fn lock_effects(shared: &DmxSharedState) -> Result<std::sync::MutexGuard<'_, Effects>, String> {
+    shared
+        .effects
+        .lock()
+        .map_err(|e| format!("No se pudo bloquear los efectos DMX: {e}"))
+}
+
+// Asigna (o reemplaza) el oscilador de un canal
+#[tauri::command]
+pub fn effect_set(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    channel: u16,
+    effect: EffectParams,
+) -> Result<(), String> {
+    if !(1..=512).contains(&channel) {
+        return Err(format!("Canal DMX inválido: {channel}"));
+    }
+    match effect.beats {
+        Some(beats) if !(beats > 0.0 && beats <= MAX_BEATS) => {
+            return Err(format!(
+                "El ciclo del efecto debe durar entre 0 y {MAX_BEATS} beats"
+            ));
+        }
+        Some(_) => {}
+        None if !(effect.speed_hz > 0.0 && effect.speed_hz <= MAX_SPEED_HZ) => {
+            return Err(format!(
+                "La velocidad del efecto debe estar entre 0 y {MAX_SPEED_HZ} Hz"
+            ));
+        }
+        None => {}
+    }
+    if !effect.offset.is_finite() {
+        return Err("Desfase de efecto inválido".to_string());
+    }
+
+    state.with_universe(app_handle, universe, |shared| {
+        lock_effects(shared)?.channels.insert(
+            channel,
+            ChannelEffect {
+                params: effect,
+                cycle: u64::MAX,
+                random_value: 0.0,
+            },
+        );
+        Ok(())
+    })
+}
+
+// Sin canal quita todos los efectos del universo
+#[tauri::command]
+pub fn effect_clear(
+    state: State<'_, DmxState>,
+    universe: u16,
+    channel: Option<u16>,
+) -> Result<(), String> {
+    state.read_universe(universe, |shared| {
+        let mut effects = lock_effects(shared)?;
+        match channel {
+            Some(channel) => {
+                effects.channels.remove(&channel);
+            }
+            None => effects.channels.clear(),
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn effect_list(
+    state: State<'_, DmxState>,
+    universe: u16,
+) -> Result<Vec<ChannelEffectInfo>, String> {
+    state.read_universe(universe, |shared| {
+        Ok(lock_effects(shared)?
+            .channels
+            .iter()
+            .map(|(channel, effect)| ChannelEffectInfo {
+                channel: *channel,
+                effect: effect.params,
+            })
+            .collect())
+    })
+}
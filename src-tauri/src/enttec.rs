@@ -0,0 +1,76 @@
+// Formato de mensaje del widget Enttec DMX USB Pro / Pro Mk2:
+// 0x7E, label, largo LSB, largo MSB, datos..., 0xE7
+const START_OF_MESSAGE: u8 = 0x7E;
+const END_OF_MESSAGE: u8 = 0xE7;
+
+pub(crate) const LABEL_SET_WIDGET_PARAMETERS: u8 = 4;
+pub(crate) const LABEL_OUTPUT_ONLY_SEND_DMX: u8 = 6;
+// El widget mide break y MAB en unidades de 10.67 µs
+const TIME_UNIT_NS: u32 = 10_670;
+const MAX_OUTPUT_RATE: u32 = 40;
+
+pub(crate) fn build_packet(label: u8, payload: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(payload.len() + 5);
+    build_packet_into(&mut packet, label, payload);
+    packet
+}
+
+// Como `build_packet` pero sobre un buffer que se reutiliza entre frames
+pub(crate) fn build_packet_into(packet: &mut Vec<u8>, label: u8, payload: &[u8]) {
+    let length = payload.len() as u16;
+
+    packet.clear();
+    packet.push(START_OF_MESSAGE);
+    packet.push(label);
+    packet.extend_from_slice(&length.to_le_bytes());
+    packet.extend_from_slice(payload);
+    packet.push(END_OF_MESSAGE);
+}
+
+// Label 4 sin bloque de usuario: largo 0, break (9-127), MAB (1-127) y frames por segundo
+pub(crate) fn build_widget_parameters(break_us: u32, mab_us: u32, refresh_hz: u32) -> Vec<u8> {
+    let to_units = |us: u32| (us * 1000).div_ceil(TIME_UNIT_NS);
+    let payload = [
+        0,
+        0,
+        to_units(break_us).clamp(9, 127) as u8,
+        to_units(mab_us).clamp(1, 127) as u8,
+        refresh_hz.min(MAX_OUTPUT_RATE) as u8,
+    ];
+    build_packet(LABEL_SET_WIDGET_PARAMETERS, &payload)
+}
+
+pub(crate) const LABEL_RECEIVED_DMX_PACKET: u8 = 5;
+
+// Extrae los mensajes completos del buffer y deja en él los bytes de un mensaje a medias
+pub(crate) fn parse_packets(buffer: &mut Vec<u8>) -> Vec<(u8, Vec<u8>)> {
+    let mut packets = Vec::new();
+
+    loop {
+        let Some(start) = buffer.iter().position(|b| *b == START_OF_MESSAGE) else {
+            buffer.clear();
+            break;
+        };
+        buffer.drain(..start);
+
+        if buffer.len() < 4 {
+            break;
+        }
+
+        let label = buffer[1];
+        let length = u16::from_le_bytes([buffer[2], buffer[3]]) as usize;
+        if buffer.len() < length + 5 {
+            break;
+        }
+
+        if buffer[length + 4] == END_OF_MESSAGE {
+            packets.push((label, buffer[4..length + 4].to_vec()));
+            buffer.drain(..length + 5);
+        } else {
+            // Falso inicio de mensaje: se descarta el 0x7E y se resincroniza
+            buffer.drain(..1);
+        }
+    }
+
+    packets
+}
@@ -0,0 +1,103 @@
+use log::error;
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter};
+
+pub const ERROR_EVENT: &str = "dmx://error";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DmxErrorCode {
+    /// La interfaz ya no está (desenchufada o apagada)
+    PortLost,
+    /// El sistema no dio permiso para abrir el puerto
+    OpenDenied,
+    /// El puerto existe pero no se pudo abrir
+    OpenFailed,
+    /// Falló el envío de un frame con el puerto abierto
+    WriteFailed,
+}
+
+impl DmxErrorCode {
+    // Afina el código a partir del texto que devuelve el sistema; si no reconoce nada
+    // se queda con `fallback`
+    pub(crate) fn classify(message: &str, fallback: DmxErrorCode) -> DmxErrorCode {
+        let message = message.to_lowercase();
+        let matches = |patterns: &[&str]| patterns.iter().any(|p| message.contains(p));
+
+        if matches(&[
+            "permission denied",
+            "access denied",
+            "access is denied",
+            "acceso denegado",
+            "insufficient permissions",
+        ]) {
+            DmxErrorCode::OpenDenied
+        } else if matches(&[
+            "no such file",
+            "no such device",
+            "not found",
+            "no se encontró",
+            "disconnected",
+            "broken pipe",
+            "device not configured",
+        ]) {
+            DmxErrorCode::PortLost
+        } else {
+            fallback
+        }
+    }
+
+    fn hint(self) -> &'static str {
+        match self {
+            DmxErrorCode::PortLost => "Comprobar que la interfaz esté conectada; se reintenta sola",
+            DmxErrorCode::OpenDenied => {
+                "En Linux, agregar el usuario al grupo dialout (o una regla udev) y cerrar otros programas que usen el puerto"
+            }
+            DmxErrorCode::OpenFailed => "Probar con otro driver o volver a conectar la interfaz",
+            DmxErrorCode::WriteFailed => "Revisar el cable USB y el driver elegido para el universo",
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct DmxErrorEvent {
+    code: DmxErrorCode,
+    universe: u16,
+    port: String,
+    message: String,
+    // Sugerencia para mostrar al usuario
+    hint: &'static str,
+    // Milisegundos desde epoch
+    at: u64,
+}
+
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+// Registra el error en el log y lo emite al front
+pub(crate) fn report(
+    app_handle: &AppHandle,
+    universe: u16,
+    port: &str,
+    code: DmxErrorCode,
+    message: String,
+) -> DmxErrorEvent {
+    error!("{message}");
+    let event = DmxErrorEvent {
+        code,
+        universe,
+        port: port.to_string(),
+        message,
+        hint: code.hint(),
+        at: now_ms(),
+    };
+    if let Err(err) = app_handle.emit(ERROR_EVENT, event.clone()) {
+        error!("No se pudo emitir el error DMX: {err}");
+    }
+    event
+}
@@ -0,0 +1,213 @@
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+// Los canales de 8 bits usan 0-255; un par grueso/fino, el valor de 16 bits completo
+struct ChannelFade {
+    from: u16,
+    to: u16,
+    started: Instant,
+    duration: Duration,
+}
+
+impl ChannelFade {
+    // Devuelve el valor para este instante y si el fade ya llegó al destino
+    fn value(&self, now: Instant) -> (u16, bool) {
+        let elapsed = now.saturating_duration_since(self.started);
+        if elapsed >= self.duration {
+            return (self.to, true);
+        }
+
+        let progress = elapsed.as_secs_f64() / self.duration.as_secs_f64();
+        let from = self.from as f64;
+        let to = self.to as f64;
+        ((from + (to - from) * progress).round() as u16, false)
+    }
+}
+
+// Par de canales (índices desde 0) que forman un atributo de 16 bits, p. ej. pan + pan fino
+struct WidePair {
+    coarse: usize,
+    fine: usize,
+    fade: Option<ChannelFade>,
+}
+
+impl WidePair {
+    fn value(&self, levels: &[u8]) -> u16 {
+        u16::from_be_bytes([levels[self.coarse], levels[self.fine]])
+    }
+
+    fn touches(&self, range: &Range<usize>) -> bool {
+        range.contains(&self.coarse) || range.contains(&self.fine)
+    }
+}
+
+// Fades independientes por canal: un fade nuevo solo reemplaza los canales que cambia.
+// Los avanza el hilo de escritura en cada frame, así el tiempo no depende del front.
+// Los pares de 16 bits se funden como un único valor para que el canal fino no dé saltos.
+pub(crate) struct Crossfade {
+    channels: Vec<Option<ChannelFade>>,
+    active: usize,
+    pairs: Vec<WidePair>,
+    // Fades congelados (STOP de MSC) desde este instante
+    paused_at: Option<Instant>,
+}
+
+impl Crossfade {
+    pub(crate) fn new(channels: usize) -> Self {
+        Self {
+            channels: (0..channels).map(|_| None).collect(),
+            active: 0,
+            pairs: Vec::new(),
+            paused_at: None,
+        }
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.active > 0 || self.pairs.iter().any(|pair| pair.fade.is_some())
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.channels.iter_mut().for_each(|fade| *fade = None);
+        self.active = 0;
+        self.pairs.iter_mut().for_each(|pair| pair.fade = None);
+        self.paused_at = None;
+    }
+
+    // Congela los fades en curso donde estén
+    pub(crate) fn pause(&mut self, now: Instant) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(now);
+        }
+    }
+
+    // Retoma los fades congelados por el tiempo que les faltaba
+    pub(crate) fn resume(&mut self, now: Instant) {
+        let Some(paused_at) = self.paused_at.take() else {
+            return;
+        };
+        let paused = now.saturating_duration_since(paused_at);
+        let fades = self.channels.iter_mut().flatten();
+        let pairs = self.pairs.iter_mut().filter_map(|pair| pair.fade.as_mut());
+        for fade in fades.chain(pairs) {
+            fade.started += paused;
+        }
+    }
+
+    // Reemplaza los pares grueso/fino (índices desde 0); los que se salen del buffer se ignoran
+    pub(crate) fn set_pairs(&mut self, pairs: &[(usize, usize)]) {
+        self.pairs = pairs
+            .iter()
+            .filter(|(coarse, fine)| *coarse < self.channels.len() && *fine < self.channels.len())
+            .map(|(coarse, fine)| WidePair {
+                coarse: *coarse,
+                fine: *fine,
+                fade: None,
+            })
+            .collect();
+    }
+
+    // Corta el fade de los canales de `range` (índices desde 0), p. ej. al mover un fader
+    pub(crate) fn cancel(&mut self, range: Range<usize>) {
+        for pair in self.pairs.iter_mut().filter(|pair| pair.touches(&range)) {
+            pair.fade = None;
+        }
+        let Some(slots) = self.channels.get_mut(range) else {
+            return;
+        };
+        for slot in slots {
+            if slot.take().is_some() {
+                self.active -= 1;
+            }
+        }
+    }
+
+    // `current` son los niveles actuales (punto de partida); `target` el destino por canal
+    pub(crate) fn start(&mut self, current: &[u8], target: &[u8], duration: Duration) {
+        let started = Instant::now();
+        // Un fade nuevo descongela los que quedaban, así siguen todos juntos
+        self.resume(started);
+
+        for (idx, (from, to)) in current.iter().zip(target).enumerate() {
+            let Some(slot) = self.channels.get_mut(idx) else {
+                break;
+            };
+
+            let was_active = slot.is_some();
+            *slot = (from != to).then_some(ChannelFade {
+                from: *from as u16,
+                to: *to as u16,
+                started,
+                duration,
+            });
+
+            match (was_active, slot.is_some()) {
+                (false, true) => self.active += 1,
+                (true, false) => self.active -= 1,
+                _ => {}
+            }
+        }
+
+        for pair in &mut self.pairs {
+            if pair.coarse >= target.len() || pair.fine >= target.len() {
+                continue;
+            }
+            let (from, to) = (pair.value(current), pair.value(target));
+            if from == to {
+                continue;
+            }
+
+            // El par deja los fades de 8 bits de sus dos canales
+            for idx in [pair.coarse, pair.fine] {
+                if self.channels[idx].take().is_some() {
+                    self.active -= 1;
+                }
+            }
+            pair.fade = Some(ChannelFade {
+                from,
+                to,
+                started,
+                duration,
+            });
+        }
+    }
+
+    // Escribe en `levels` el valor de cada canal en fade y descarta los que terminaron
+    pub(crate) fn step(&mut self, levels: &mut [u8]) {
+        if !self.is_active() || self.paused_at.is_some() {
+            return;
+        }
+
+        let now = Instant::now();
+        if self.active > 0 {
+            for (slot, level) in self.channels.iter_mut().zip(levels.iter_mut()) {
+                let Some(fade) = slot else {
+                    continue;
+                };
+
+                let (value, finished) = fade.value(now);
+                *level = value as u8;
+                if finished {
+                    *slot = None;
+                    self.active -= 1;
+                }
+            }
+        }
+
+        for pair in &mut self.pairs {
+            let Some(fade) = &pair.fade else {
+                continue;
+            };
+            if pair.coarse >= levels.len() || pair.fine >= levels.len() {
+                continue;
+            }
+
+            let (value, finished) = fade.value(now);
+            let [coarse, fine] = value.to_be_bytes();
+            levels[pair.coarse] = coarse;
+            levels[pair.fine] = fine;
+            if finished {
+                pair.fade = None;
+            }
+        }
+    }
+}
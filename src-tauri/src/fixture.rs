@@ -0,0 +1,1091 @@
+use crate::color::WhiteBalance;
+use crate::curves::DimmerCurve;
+use crate::dmx::DmxState;
+use crate::groups::GroupState;
+use crate::storage;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+const FIXTURES_FILE: &str = "fixtures.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Attribute {
+    Dimmer,
+    Pan,
+    PanFine,
+    Tilt,
+    TiltFine,
+    Red,
+    Green,
+    Blue,
+    White,
+    Amber,
+    Uv,
+    Cyan,
+    Magenta,
+    Yellow,
+    ColorWheel,
+    Gobo,
+    Strobe,
+    Shutter,
+    Zoom,
+    Focus,
+    Iris,
+    Prism,
+    Speed,
+    Control,
+    /// Canal sin función conocida
+    Generic,
+}
+
+// Rango de valores con un significado propio dentro del canal (p. ej. "Strobe" de 16 a 131)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChannelFunction {
+    name: String,
+    from: u8,
+    to: u8,
+}
+
+impl ChannelFunction {
+    pub(crate) fn new(name: String, from: u8, to: u8) -> Self {
+        Self { name, from, to }
+    }
+}
+
+impl Attribute {
+    // Valor en locate: blanco abierto a full y pan/tilt al centro; lo que no tiene un valor
+    // obvio (shutter, zoom...) queda en el valor por defecto del perfil
+    fn locate_value(self, default: u8) -> u8 {
+        match self {
+            Attribute::Dimmer
+            | Attribute::Red
+            | Attribute::Green
+            | Attribute::Blue
+            | Attribute::White => 255,
+            Attribute::Pan | Attribute::Tilt => 128,
+            Attribute::PanFine
+            | Attribute::TiltFine
+            | Attribute::Amber
+            | Attribute::Uv
+            | Attribute::Cyan
+            | Attribute::Magenta
+            | Attribute::Yellow
+            | Attribute::ColorWheel
+            | Attribute::Gobo
+            | Attribute::Prism => 0,
+            _ => default,
+        }
+    }
+
+    // Canal fino que completa a este en 16 bits
+    pub(crate) fn fine(self) -> Option<Attribute> {
+        match self {
+            Attribute::Pan => Some(Attribute::PanFine),
+            Attribute::Tilt => Some(Attribute::TiltFine),
+            _ => None,
+        }
+    }
+
+    // Emisores que maneja el motor de color
+    pub(crate) fn is_color(self) -> bool {
+        matches!(
+            self,
+            Attribute::Red
+                | Attribute::Green
+                | Attribute::Blue
+                | Attribute::White
+                | Attribute::Amber
+                | Attribute::Cyan
+                | Attribute::Magenta
+                | Attribute::Yellow
+        )
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChannelDef {
+    attribute: Attribute,
+    // Valor al patchear el fixture (p. ej. shutter abierto)
+    #[serde(default)]
+    default: u8,
+    #[serde(default)]
+    functions: Vec<ChannelFunction>,
+}
+
+impl ChannelDef {
+    pub(crate) fn new(attribute: Attribute, default: u8, functions: Vec<ChannelFunction>) -> Self {
+        Self {
+            attribute,
+            default,
+            functions,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FixtureProfile {
+    name: String,
+    #[serde(default)]
+    manufacturer: String,
+    // Modo DMX del aparato al que corresponde este layout
+    #[serde(default)]
+    mode: String,
+    // En orden desde la dirección de inicio
+    channels: Vec<ChannelDef>,
+}
+
+impl FixtureProfile {
+    pub(crate) fn new(
+        name: String,
+        manufacturer: String,
+        mode: String,
+        channels: Vec<ChannelDef>,
+    ) -> Self {
+        Self {
+            name,
+            manufacturer,
+            mode,
+            channels,
+        }
+    }
+
+    pub(crate) fn footprint(&self) -> u16 {
+        self.channels.len() as u16
+    }
+
+    // Pares [grueso, fino] (desde 0) de los atributos de 16 bits que tiene el perfil
+    fn fine_pairs(&self) -> Vec<(usize, usize)> {
+        self.channels
+            .iter()
+            .enumerate()
+            .filter_map(|(coarse, channel)| {
+                let fine = channel.attribute.fine()?;
+                let fine = self.channels.iter().position(|c| c.attribute == fine)?;
+                Some((coarse, fine))
+            })
+            .collect()
+    }
+
+    // Canales (desde 0) que controlan la intensidad: los dimmers o, si no tiene, los emisores
+    // de color
+    fn intensity_offsets(&self) -> Vec<usize> {
+        let offsets_with = |matches: fn(Attribute) -> bool| {
+            self.channels
+                .iter()
+                .enumerate()
+                .filter(|(_, channel)| matches(channel.attribute))
+                .map(|(offset, _)| offset)
+                .collect::<Vec<_>>()
+        };
+        let offsets = offsets_with(|attribute| attribute == Attribute::Dimmer);
+        if !offsets.is_empty() {
+            return offsets;
+        }
+        offsets_with(|attribute| {
+            matches!(
+                attribute,
+                Attribute::Red
+                    | Attribute::Green
+                    | Attribute::Blue
+                    | Attribute::White
+                    | Attribute::Amber
+                    | Attribute::Uv
+            )
+        })
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Fixture {
+    id: u32,
+    name: String,
+    profile: String,
+    universe: u16,
+    // Dirección de inicio (1-512)
+    address: u16,
+    #[serde(default)]
+    white_balance: Option<WhiteBalance>,
+    // Curva de dimmer de sus canales de intensidad
+    #[serde(default)]
+    curve: Option<DimmerCurve>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct FixtureChannelValue {
+    attribute: Attribute,
+    channel: u16,
+    value: u8,
+}
+
+// Fixture ya patcheado que ocupa parte de los canales pedidos
+#[derive(Clone, Serialize)]
+pub struct PatchConflict {
+    fixture: u32,
+    name: String,
+    universe: u16,
+    first_channel: u16,
+    last_channel: u16,
+}
+
+// Error de los comandos de patch: además del mensaje lleva los conflictos para la UI
+#[derive(Serialize)]
+pub struct PatchError {
+    message: String,
+    conflicts: Vec<PatchConflict>,
+}
+
+impl From<String> for PatchError {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            conflicts: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub(crate) struct FixtureLibrary {
+    profiles: Vec<FixtureProfile>,
+    fixtures: Vec<Fixture>,
+}
+
+impl FixtureLibrary {
+    // Para rechazar un proyecto antes de aplicar nada
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        match Fixtures::from_library(self.clone())
+            .invalid_fixtures()
+            .into_iter()
+            .next()
+        {
+            Some((_, err)) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct Fixtures {
+    profiles: BTreeMap<String, FixtureProfile>,
+    fixtures: BTreeMap<u32, Fixture>,
+    // Fixtures en locate; no se guarda
+    located: BTreeSet<u32>,
+}
+
+impl Fixtures {
+    fn from_library(library: FixtureLibrary) -> Self {
+        Self {
+            profiles: library
+                .profiles
+                .into_iter()
+                .map(|profile| (profile.name.clone(), profile))
+                .collect(),
+            fixtures: library
+                .fixtures
+                .into_iter()
+                .map(|fixture| (fixture.id, fixture))
+                .collect(),
+            located: BTreeSet::new(),
+        }
+    }
+
+    // Los que vienen de archivo no pasaron por los comandos de patch: uno fuera del universo
+    // indexaría canales que no existen
+    fn invalid_fixtures(&self) -> Vec<(u32, String)> {
+        self.fixtures
+            .values()
+            .filter_map(|fixture| {
+                let profile = self.profiles.get(&fixture.profile)?;
+                validate_address(fixture.address, profile.footprint())
+                    .err()
+                    .map(|err| (fixture.id, format!("Fixture \"{}\": {err}", fixture.name)))
+            })
+            .collect()
+    }
+
+    fn library(&self) -> FixtureLibrary {
+        FixtureLibrary {
+            profiles: self.profiles.values().cloned().collect(),
+            fixtures: self.fixtures.values().cloned().collect(),
+        }
+    }
+
+    fn next_id(&self) -> u32 {
+        self.fixtures.keys().next_back().map_or(1, |id| id + 1)
+    }
+
+    // Crea el perfil o lo reemplaza si ya existía
+    fn store_profile(&mut self, profile: FixtureProfile) -> Result<(), String> {
+        if profile.name.trim().is_empty() {
+            return Err("El perfil necesita un nombre".to_string());
+        }
+        if profile.channels.is_empty() || profile.channels.len() > 512 {
+            return Err(format!(
+                "El perfil \"{}\" debe tener entre 1 y 512 canales",
+                profile.name
+            ));
+        }
+
+        // Un perfil más largo no puede dejar fixtures patcheados fuera del universo
+        if let Some(fixture) = self
+            .fixtures
+            .values()
+            .filter(|fixture| fixture.profile == profile.name)
+            .find(|fixture| validate_address(fixture.address, profile.footprint()).is_err())
+        {
+            return Err(format!(
+                "El fixture \"{}\" quedaría fuera del universo con este perfil",
+                fixture.name
+            ));
+        }
+        if let Some((fixture, conflict)) = self
+            .fixtures
+            .values()
+            .filter(|fixture| fixture.profile == profile.name)
+            .find_map(|fixture| {
+                let conflicts = self.conflicts(
+                    fixture.universe,
+                    fixture.address,
+                    profile.footprint(),
+                    Some(fixture.id),
+                );
+                Some((fixture, conflicts.into_iter().next()?))
+            })
+        {
+            return Err(format!(
+                "Con este perfil el fixture \"{}\" se superpondría con \"{}\"",
+                fixture.name, conflict.name
+            ));
+        }
+
+        self.profiles.insert(profile.name.clone(), profile);
+        Ok(())
+    }
+
+    // Fixtures del universo que se superponen con `address..address+footprint`
+    fn conflicts(
+        &self,
+        universe: u16,
+        address: u16,
+        footprint: u16,
+        ignore: Option<u32>,
+    ) -> Vec<PatchConflict> {
+        let last = address + footprint - 1;
+        self.fixtures
+            .values()
+            .filter(|fixture| fixture.universe == universe && Some(fixture.id) != ignore)
+            .filter_map(|fixture| {
+                let footprint = self.profiles.get(&fixture.profile)?.footprint();
+                let fixture_last = fixture.address + footprint - 1;
+                (fixture.address <= last && address <= fixture_last).then(|| PatchConflict {
+                    fixture: fixture.id,
+                    name: fixture.name.clone(),
+                    universe,
+                    first_channel: fixture.address,
+                    last_channel: fixture_last,
+                })
+            })
+            .collect()
+    }
+
+    fn check_patch(
+        &self,
+        universe: u16,
+        address: u16,
+        footprint: u16,
+        ignore: Option<u32>,
+    ) -> Result<(), PatchError> {
+        validate_address(address, footprint)?;
+
+        let conflicts = self.conflicts(universe, address, footprint, ignore);
+        if conflicts.is_empty() {
+            return Ok(());
+        }
+        let names = conflicts
+            .iter()
+            .map(|conflict| format!("\"{}\"", conflict.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        Err(PatchError {
+            message: format!(
+                "Los canales {universe}.{address}-{} se superponen con {names}",
+                address + footprint - 1
+            ),
+            conflicts,
+        })
+    }
+
+    fn fixture(&self, id: u32) -> Result<(&Fixture, &FixtureProfile), String> {
+        let fixture = self
+            .fixtures
+            .get(&id)
+            .ok_or_else(|| format!("El fixture {id} no existe"))?;
+        let profile = self
+            .profiles
+            .get(&fixture.profile)
+            .ok_or_else(|| format!("El perfil \"{}\" no existe", fixture.profile))?;
+        Ok((fixture, profile))
+    }
+}
+
+#[derive(Default)]
+pub struct FixtureState {
+    fixtures: Mutex<Fixtures>,
+    path: Mutex<Option<PathBuf>>,
+}
+
+impl FixtureState {
+    pub fn load(&self, app_handle: &AppHandle) {
+        let Some(path) = storage::config_file(app_handle, FIXTURES_FILE) else {
+            return;
+        };
+
+        if let Some(library) = storage::load_json::<FixtureLibrary>(&path) {
+            if let Ok(mut fixtures) = self.fixtures.lock() {
+                *fixtures = Fixtures::from_library(library);
+                for (id, err) in fixtures.invalid_fixtures() {
+                    warn!("{err}; se quita del patch");
+                    fixtures.fixtures.remove(&id);
+                }
+            }
+        }
+
+        if let Ok(mut current) = self.path.lock() {
+            *current = Some(path);
+        }
+        if let Err(err) = self
+            .lock_fixtures()
+            .and_then(|fixtures| refresh_calibration(app_handle, &fixtures))
+        {
+            error!("{err}");
+        }
+    }
+
+    pub(crate) fn set_white_balance(
+        &self,
+        app_handle: &AppHandle,
+        id: u32,
+        balance: Option<WhiteBalance>,
+    ) -> Result<(), String> {
+        let mut stored = self.lock_fixtures()?;
+        stored
+            .fixtures
+            .get_mut(&id)
+            .ok_or_else(|| format!("El fixture {id} no existe"))?
+            .white_balance = balance;
+        self.save(&stored)?;
+        refresh_calibration(app_handle, &stored)
+    }
+
+    pub(crate) fn set_curve(
+        &self,
+        app_handle: &AppHandle,
+        id: u32,
+        curve: Option<DimmerCurve>,
+    ) -> Result<(), String> {
+        let mut stored = self.lock_fixtures()?;
+        stored
+            .fixtures
+            .get_mut(&id)
+            .ok_or_else(|| format!("El fixture {id} no existe"))?
+            .curve = curve;
+        self.save(&stored)?;
+        refresh_calibration(app_handle, &stored)
+    }
+
+    // Universo y canales que controlan la intensidad del fixture
+    pub(crate) fn intensity_channels(&self, id: u32) -> Result<(u16, Vec<u16>), String> {
+        let stored = self.lock_fixtures()?;
+        let (fixture, profile) = stored.fixture(id)?;
+        let channels = profile
+            .intensity_offsets()
+            .into_iter()
+            .map(|offset| fixture.address + offset as u16)
+            .collect();
+        Ok((fixture.universe, channels))
+    }
+
+    // Atributos que tiene el perfil del fixture, en orden de canal
+    pub(crate) fn attributes(&self, id: u32) -> Result<Vec<Attribute>, String> {
+        let stored = self.lock_fixtures()?;
+        let (_, profile) = stored.fixture(id)?;
+        Ok(profile
+            .channels
+            .iter()
+            .map(|channel| channel.attribute)
+            .collect())
+    }
+
+    pub(crate) fn write_attributes(
+        &self,
+        app_handle: &AppHandle,
+        dmx: &DmxState,
+        id: u32,
+        values: &HashMap<Attribute, u8>,
+        fade_ms: Option<u64>,
+    ) -> Result<(), String> {
+        let wide = values
+            .iter()
+            .map(|(attribute, value)| (*attribute, (*value as u16) << 8))
+            .collect::<HashMap<_, _>>();
+        self.write_wide_attributes(app_handle, dmx, id, &wide, false, fade_ms)
+    }
+
+    // Valores de 16 bits: el byte alto va al canal del atributo y el bajo a su canal fino, si
+    // el perfil lo tiene. Con `with_fine` en `false` el canal fino no se toca.
+    pub(crate) fn write_wide_attributes(
+        &self,
+        app_handle: &AppHandle,
+        dmx: &DmxState,
+        id: u32,
+        values: &HashMap<Attribute, u16>,
+        with_fine: bool,
+        fade_ms: Option<u64>,
+    ) -> Result<(), String> {
+        let stored = self.lock_fixtures()?;
+        let (fixture, profile) = stored.fixture(id)?;
+
+        if let Some(attribute) = values
+            .keys()
+            .find(|attribute| !profile.channels.iter().any(|c| c.attribute == **attribute))
+        {
+            return Err(format!(
+                "El fixture \"{}\" no tiene el atributo {attribute:?}",
+                fixture.name
+            ));
+        }
+
+        dmx.with_universe(app_handle.clone(), fixture.universe, |shared| {
+            let mut levels = shared.channel_levels();
+            // Un atributo repetido (varias celdas, varios motores) tiene un canal por repetición
+            let channel_indices = |attribute: Attribute| -> Vec<usize> {
+                profile
+                    .channels
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, c)| c.attribute == attribute)
+                    .map(|(offset, _)| fixture.address as usize - 1 + offset)
+                    .collect()
+            };
+            for (attribute, value) in values {
+                let [coarse, fine] = value.to_be_bytes();
+                for idx in channel_indices(*attribute) {
+                    levels[idx] = coarse;
+                }
+                if let Some(fine_attribute) = attribute.fine().filter(|_| with_fine) {
+                    for idx in channel_indices(fine_attribute) {
+                        levels[idx] = fine;
+                    }
+                }
+            }
+            match fade_ms {
+                Some(fade_ms) => shared.start_fade(&levels, Duration::from_millis(fade_ms)),
+                None => shared.update_levels(&levels),
+            }
+        })
+    }
+
+    // Id, nombre y si tiene emisores de color de cada fixture patcheado
+    pub(crate) fn overview(&self) -> Result<Vec<(u32, String, bool)>, String> {
+        let stored = self.lock_fixtures()?;
+        stored
+            .fixtures
+            .keys()
+            .map(|id| {
+                let (fixture, profile) = stored.fixture(*id)?;
+                let has_color = profile
+                    .channels
+                    .iter()
+                    .any(|channel| channel.attribute.is_color());
+                Ok((fixture.id, fixture.name.clone(), has_color))
+            })
+            .collect()
+    }
+
+    // Universo y canal (1-512) de un atributo del fixture
+    pub(crate) fn attribute_channel(
+        &self,
+        id: u32,
+        attribute: Attribute,
+    ) -> Result<(u16, u16), String> {
+        let stored = self.lock_fixtures()?;
+        let (fixture, profile) = stored.fixture(id)?;
+        let offset = profile
+            .channels
+            .iter()
+            .position(|channel| channel.attribute == attribute)
+            .ok_or_else(|| {
+                format!(
+                    "El fixture \"{}\" no tiene el atributo {attribute:?}",
+                    fixture.name
+                )
+            })?;
+        Ok((fixture.universe, fixture.address + offset as u16))
+    }
+
+    // Universo y canales de los emisores de color del fixture, para usarlo como pixel
+    pub(crate) fn color_channels(&self, id: u32) -> Result<(u16, Vec<(Attribute, u16)>), String> {
+        let stored = self.lock_fixtures()?;
+        let (fixture, profile) = stored.fixture(id)?;
+
+        let channels = profile
+            .channels
+            .iter()
+            .enumerate()
+            .filter(|(_, channel)| channel.attribute.is_color())
+            .map(|(offset, channel)| (channel.attribute, fixture.address + offset as u16))
+            .collect::<Vec<_>>();
+        if channels.is_empty() {
+            return Err(format!(
+                "El fixture \"{}\" no tiene emisores de color",
+                fixture.name
+            ));
+        }
+        Ok((fixture.universe, channels))
+    }
+
+    pub(crate) fn export(&self) -> Result<FixtureLibrary, String> {
+        Ok(self.lock_fixtures()?.library())
+    }
+
+    // Guarda varios perfiles de una vez (p. ej. los modos de un GDTF): si alguno es inválido
+    // no se guarda ninguno
+    pub(crate) fn import_profiles(
+        &self,
+        app_handle: &AppHandle,
+        profiles: Vec<FixtureProfile>,
+    ) -> Result<(), String> {
+        let mut stored = self.lock_fixtures()?;
+        let mut updated = stored.clone();
+        for profile in profiles {
+            updated.store_profile(profile)?;
+        }
+        *stored = updated;
+        self.save(&stored)?;
+        refresh_calibration(app_handle, &stored)
+    }
+
+    pub(crate) fn replace(
+        &self,
+        app_handle: &AppHandle,
+        library: FixtureLibrary,
+    ) -> Result<(), String> {
+        library.validate()?;
+        let mut fixtures = self.lock_fixtures()?;
+        *fixtures = Fixtures::from_library(library);
+        self.save(&fixtures)?;
+        refresh_calibration(app_handle, &fixtures)
+    }
+
+    fn lock_fixtures(&self) -> Result<std::sync::MutexGuard<'_, Fixtures>, String> {
+        self.fixtures
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la tabla de fixtures: {e}"))
+    }
+
+    fn save(&self, fixtures: &Fixtures) -> Result<(), String> {
+        let path = self
+            .path
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la tabla de fixtures: {e}"))?
+            .clone();
+
+        match path {
+            Some(path) => storage::save_json(&path, &fixtures.library()),
+            None => Ok(()),
+        }
+    }
+}
+
+// Ganancias de balance de blanco por canal para cada universo con fixtures calibrados, y las
+// curvas de dimmer de los fixtures
+fn refresh_calibration(app_handle: &AppHandle, fixtures: &Fixtures) -> Result<(), String> {
+    let mut gains: BTreeMap<u16, Vec<u8>> = BTreeMap::new();
+
+    for fixture in fixtures.fixtures.values() {
+        let (Some(balance), Some(profile)) = (
+            fixture.white_balance,
+            fixtures.profiles.get(&fixture.profile),
+        ) else {
+            continue;
+        };
+        for (offset, channel) in profile.channels.iter().enumerate() {
+            let gain = balance.gain(channel.attribute);
+            if gain >= 1.0 {
+                continue;
+            }
+            let universe = gains
+                .entry(fixture.universe)
+                .or_insert_with(|| vec![255; 512]);
+            universe[fixture.address as usize - 1 + offset] = (gain * 255.0).round() as u8;
+        }
+    }
+
+    let dmx = app_handle.state::<DmxState>();
+    dmx.distribute(app_handle, gains, |shared, gains| {
+        shared.set_calibration(gains)
+    })?;
+    dmx.distribute(app_handle, fixture_curves(fixtures), |shared, curves| {
+        if let Ok(mut current) = shared.curves.lock() {
+            current.set_fixtures(curves);
+        }
+    })?;
+    dmx.distribute(app_handle, fixture_fine_pairs(fixtures), |shared, pairs| {
+        shared.set_fine_pairs(pairs)
+    })?;
+    dmx.distribute(app_handle, locate_levels(fixtures), |shared, levels| {
+        shared.set_locate(levels)
+    })
+}
+
+// Niveles de los fixtures en locate por universo; `None` en los canales que no tocan
+fn locate_levels(fixtures: &Fixtures) -> BTreeMap<u16, Vec<Option<u8>>> {
+    let mut levels: BTreeMap<u16, Vec<Option<u8>>> = BTreeMap::new();
+
+    for id in &fixtures.located {
+        let Ok((fixture, profile)) = fixtures.fixture(*id) else {
+            continue;
+        };
+        let universe = levels
+            .entry(fixture.universe)
+            .or_insert_with(|| vec![None; 512]);
+        for (offset, channel) in profile.channels.iter().enumerate() {
+            universe[fixture.address as usize - 1 + offset] =
+                Some(channel.attribute.locate_value(channel.default));
+        }
+    }
+    levels
+}
+
+// Pares [grueso, fino] de cada universo con fixtures que tienen atributos de 16 bits
+fn fixture_fine_pairs(fixtures: &Fixtures) -> BTreeMap<u16, Vec<(u16, u16)>> {
+    let mut pairs: BTreeMap<u16, Vec<(u16, u16)>> = BTreeMap::new();
+
+    for fixture in fixtures.fixtures.values() {
+        let Some(profile) = fixtures.profiles.get(&fixture.profile) else {
+            continue;
+        };
+        let fixture_pairs = profile.fine_pairs();
+        if fixture_pairs.is_empty() {
+            continue;
+        }
+        pairs
+            .entry(fixture.universe)
+            .or_default()
+            .extend(fixture_pairs.into_iter().map(|(coarse, fine)| {
+                (
+                    fixture.address + coarse as u16,
+                    fixture.address + fine as u16,
+                )
+            }));
+    }
+    pairs
+}
+
+// Curvas de dimmer por canal para cada universo con fixtures que tienen curva
+fn fixture_curves(fixtures: &Fixtures) -> BTreeMap<u16, BTreeMap<u16, DimmerCurve>> {
+    let mut curves: BTreeMap<u16, BTreeMap<u16, DimmerCurve>> = BTreeMap::new();
+
+    for fixture in fixtures.fixtures.values() {
+        let (Some(curve), Some(profile)) =
+            (&fixture.curve, fixtures.profiles.get(&fixture.profile))
+        else {
+            continue;
+        };
+        let universe = curves.entry(fixture.universe).or_default();
+        for offset in profile.intensity_offsets() {
+            universe.insert(fixture.address + offset as u16, curve.clone());
+        }
+    }
+    curves
+}
+
+// Escribe `values` en el buffer del universo a partir de `address`
+fn write_channels(
+    dmx: &DmxState,
+    app_handle: &AppHandle,
+    universe: u16,
+    address: u16,
+    values: &[u8],
+) -> Result<(), String> {
+    dmx.with_universe(app_handle.clone(), universe, |shared| {
+        let mut levels = shared.channel_levels();
+        let start = address as usize - 1;
+        levels[start..start + values.len()].copy_from_slice(values);
+        shared.update_levels(&levels)
+    })
+}
+
+fn validate_address(address: u16, footprint: u16) -> Result<(), String> {
+    if address == 0 || address as usize + footprint as usize - 1 > 512 {
+        return Err(format!(
+            "Dirección inválida: {address} (el fixture ocupa {footprint} canales)"
+        ));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn fixture_profile_save(
+    app_handle: AppHandle,
+    fixtures: State<'_, FixtureState>,
+    profile: FixtureProfile,
+) -> Result<(), String> {
+    let mut stored = fixtures.lock_fixtures()?;
+    stored.store_profile(profile)?;
+    fixtures.save(&stored)?;
+    refresh_calibration(&app_handle, &stored)
+}
+
+#[tauri::command]
+pub fn fixture_profile_delete(
+    fixtures: State<'_, FixtureState>,
+    name: String,
+) -> Result<(), String> {
+    let mut stored = fixtures.lock_fixtures()?;
+    if let Some(fixture) = stored
+        .fixtures
+        .values()
+        .find(|fixture| fixture.profile == name)
+    {
+        return Err(format!(
+            "El perfil \"{name}\" lo usa el fixture \"{}\"",
+            fixture.name
+        ));
+    }
+    stored
+        .profiles
+        .remove(&name)
+        .ok_or_else(|| format!("El perfil \"{name}\" no existe"))?;
+    fixtures.save(&stored)
+}
+
+#[tauri::command]
+pub fn fixture_profile_list(
+    fixtures: State<'_, FixtureState>,
+) -> Result<Vec<FixtureProfile>, String> {
+    Ok(fixtures
+        .lock_fixtures()?
+        .profiles
+        .values()
+        .cloned()
+        .collect())
+}
+
+// Agrega una instancia del perfil `fixture` en `universe`/`address` y escribe los valores por
+// defecto de sus canales
+#[tauri::command]
+pub fn patch_add(
+    app_handle: AppHandle,
+    fixtures: State<'_, FixtureState>,
+    dmx: State<'_, DmxState>,
+    fixture: String,
+    universe: u16,
+    address: u16,
+    name: Option<String>,
+) -> Result<Fixture, PatchError> {
+    let mut stored = fixtures.lock_fixtures()?;
+    let profile = stored
+        .profiles
+        .get(&fixture)
+        .ok_or_else(|| format!("El perfil \"{fixture}\" no existe"))?;
+    stored.check_patch(universe, address, profile.footprint(), None)?;
+
+    let defaults = profile
+        .channels
+        .iter()
+        .map(|channel| channel.default)
+        .collect::<Vec<_>>();
+    write_channels(&dmx, &app_handle, universe, address, &defaults)?;
+
+    let id = stored.next_id();
+    let patched = Fixture {
+        id,
+        name: name.unwrap_or_else(|| format!("{fixture} {id}")),
+        profile: fixture,
+        universe,
+        address,
+        white_balance: None,
+        curve: None,
+    };
+    stored.fixtures.insert(id, patched.clone());
+    fixtures.save(&stored)?;
+    refresh_calibration(&app_handle, &stored)?;
+
+    info!(
+        "Fixture \"{}\" ({}) patcheado en {}.{}",
+        patched.name, patched.profile, patched.universe, patched.address
+    );
+    Ok(patched)
+}
+
+// Cambia la dirección del fixture llevándose sus valores actuales
+#[tauri::command]
+pub fn patch_move(
+    app_handle: AppHandle,
+    fixtures: State<'_, FixtureState>,
+    dmx: State<'_, DmxState>,
+    id: u32,
+    universe: u16,
+    address: u16,
+) -> Result<Fixture, PatchError> {
+    let mut stored = fixtures.lock_fixtures()?;
+    let (fixture, profile) = stored.fixture(id)?;
+    let footprint = profile.footprint();
+    stored.check_patch(universe, address, footprint, Some(id))?;
+
+    let (old_universe, old_address) = (fixture.universe, fixture.address);
+    let start = old_address as usize - 1;
+    let values = dmx
+        .read_universe(old_universe, |shared| {
+            Ok(shared.channel_levels()[start..start + footprint as usize].to_vec())
+        })
+        .unwrap_or_else(|_| vec![0; footprint as usize]);
+    write_channels(
+        &dmx,
+        &app_handle,
+        old_universe,
+        old_address,
+        &vec![0; footprint as usize],
+    )?;
+    write_channels(&dmx, &app_handle, universe, address, &values)?;
+
+    let moved = stored
+        .fixtures
+        .get_mut(&id)
+        .ok_or_else(|| format!("El fixture {id} no existe"))?;
+    moved.universe = universe;
+    moved.address = address;
+    let moved = moved.clone();
+    fixtures.save(&stored)?;
+    refresh_calibration(&app_handle, &stored)?;
+    drop(stored);
+    app_handle.state::<GroupState>().refresh(&app_handle)?;
+
+    info!(
+        "Fixture \"{}\" movido de {old_universe}.{old_address} a {universe}.{address}",
+        moved.name
+    );
+    Ok(moved)
+}
+
+// Lleva el fixture a blanco abierto a full con pan/tilt al centro, por encima de escenas,
+// cues y efectos, sin tocar el buffer: al soltarlo vuelve a lo que estuviera saliendo
+#[tauri::command]
+pub fn fixture_locate(
+    app_handle: AppHandle,
+    fixtures: State<'_, FixtureState>,
+    id: u32,
+) -> Result<(), String> {
+    let mut stored = fixtures.lock_fixtures()?;
+    stored.fixture(id)?;
+    stored.located.insert(id);
+    refresh_calibration(&app_handle, &stored)
+}
+
+// Sin `id` suelta todos los fixtures en locate
+#[tauri::command]
+pub fn fixture_release_locate(
+    app_handle: AppHandle,
+    fixtures: State<'_, FixtureState>,
+    id: Option<u32>,
+) -> Result<(), String> {
+    let mut stored = fixtures.lock_fixtures()?;
+    match id {
+        Some(id) => {
+            stored.located.remove(&id);
+        }
+        None => stored.located.clear(),
+    }
+    refresh_calibration(&app_handle, &stored)
+}
+
+#[tauri::command]
+pub fn patch_remove(
+    app_handle: AppHandle,
+    fixtures: State<'_, FixtureState>,
+    id: u32,
+) -> Result<(), String> {
+    {
+        let mut stored = fixtures.lock_fixtures()?;
+        stored
+            .fixtures
+            .remove(&id)
+            .ok_or_else(|| format!("El fixture {id} no existe"))?;
+        stored.located.remove(&id);
+        fixtures.save(&stored)?;
+        refresh_calibration(&app_handle, &stored)?;
+    }
+    app_handle.state::<GroupState>().refresh(&app_handle)
+}
+
+#[tauri::command]
+pub fn fixture_list(fixtures: State<'_, FixtureState>) -> Result<Vec<Fixture>, String> {
+    Ok(fixtures
+        .lock_fixtures()?
+        .fixtures
+        .values()
+        .cloned()
+        .collect())
+}
+
+// Escribe atributos por nombre en el buffer del universo del fixture. Si el perfil repite
+// un atributo se escriben todos sus canales.
+#[tauri::command]
+pub fn fixture_set_attributes(
+    app_handle: AppHandle,
+    fixtures: State<'_, FixtureState>,
+    dmx: State<'_, DmxState>,
+    id: u32,
+    values: HashMap<Attribute, u8>,
+    fade_ms: Option<u64>,
+) -> Result<(), String> {
+    fixtures.write_attributes(&app_handle, &dmx, id, &values, fade_ms)
+}
+
+// Como `fixture_set_attributes` pero con valores de 0 a 65535 para los atributos con canal fino
+// (pan, tilt); en los demás cuenta solo el byte alto
+#[tauri::command]
+pub fn fixture_set_wide_attributes(
+    app_handle: AppHandle,
+    fixtures: State<'_, FixtureState>,
+    dmx: State<'_, DmxState>,
+    id: u32,
+    values: HashMap<Attribute, u16>,
+    fade_ms: Option<u64>,
+) -> Result<(), String> {
+    fixtures.write_wide_attributes(&app_handle, &dmx, id, &values, true, fade_ms)
+}
+
+// Valores actuales del buffer para cada canal del fixture
+#[tauri::command]
+pub fn fixture_get_attributes(
+    fixtures: State<'_, FixtureState>,
+    dmx: State<'_, DmxState>,
+    id: u32,
+) -> Result<Vec<FixtureChannelValue>, String> {
+    let stored = fixtures.lock_fixtures()?;
+    let (fixture, profile) = stored.fixture(id)?;
+
+    dmx.read_universe(fixture.universe, |shared| {
+        let levels = shared.channel_levels();
+        Ok(profile
+            .channels
+            .iter()
+            .enumerate()
+            .map(|(offset, channel)| {
+                let channel_number = fixture.address + offset as u16;
+                FixtureChannelValue {
+                    attribute: channel.attribute,
+                    channel: channel_number,
+                    value: levels[channel_number as usize - 1],
+                }
+            })
+            .collect())
+    })
+}
@@ -0,0 +1,252 @@
+use crate::fixture::{Attribute, ChannelDef, ChannelFunction, FixtureProfile, FixtureState};
+use log::info;
+use roxmltree::{Document, Node};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use tauri::{AppHandle, State};
+
+// Dentro del .gdtf (un zip) la definición del aparato está en este XML
+const DESCRIPTION_FILE: &str = "description.xml";
+// GDTF permite canales de hasta 32 bits
+const MAX_CHANNEL_BYTES: usize = 4;
+
+fn read_description(path: &Path) -> Result<String, String> {
+    let file = File::open(path).map_err(|e| format!("No se pudo abrir {}: {e}", path.display()))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| format!("Archivo GDTF inválido {}: {e}", path.display()))?;
+    let mut entry = archive
+        .by_name(DESCRIPTION_FILE)
+        .map_err(|e| format!("El GDTF no tiene {DESCRIPTION_FILE}: {e}"))?;
+
+    let mut description = String::new();
+    entry
+        .read_to_string(&mut description)
+        .map_err(|e| format!("No se pudo leer {DESCRIPTION_FILE}: {e}"))?;
+    Ok(description)
+}
+
+// Un perfil por cada modo DMX del aparato
+fn parse_description(xml: &str) -> Result<Vec<FixtureProfile>, String> {
+    let document = Document::parse(xml).map_err(|e| format!("XML GDTF inválido: {e}"))?;
+    let fixture_type = document
+        .descendants()
+        .find(|node| node.has_tag_name("FixtureType"))
+        .ok_or_else(|| "El GDTF no define un FixtureType".to_string())?;
+
+    let name = fixture_type.attribute("Name").unwrap_or("GDTF");
+    let manufacturer = fixture_type.attribute("Manufacturer").unwrap_or_default();
+
+    let profiles = fixture_type
+        .descendants()
+        .filter(|node| node.has_tag_name("DMXMode"))
+        .map(|mode| parse_mode(name, manufacturer, mode))
+        .collect::<Result<Vec<_>, _>>()?;
+    if profiles.is_empty() {
+        return Err("El GDTF no tiene modos DMX".to_string());
+    }
+    Ok(profiles)
+}
+
+fn parse_mode(fixture: &str, manufacturer: &str, mode: Node) -> Result<FixtureProfile, String> {
+    let mode_name = mode.attribute("Name").unwrap_or_default();
+    let mut channels: Vec<Option<ChannelDef>> = Vec::new();
+
+    for channel in mode
+        .descendants()
+        .filter(|node| node.has_tag_name("DMXChannel"))
+    {
+        // Solo el primer DMX break: los canales en otro universo no entran en el perfil
+        if channel.attribute("DMXBreak").unwrap_or("1") != "1" {
+            continue;
+        }
+        // Los canales virtuales no tienen offset
+        let Some(offsets) = parse_offsets(channel.attribute("Offset"))? else {
+            continue;
+        };
+
+        let logical = channel
+            .children()
+            .find(|node| node.has_tag_name("LogicalChannel"));
+        let attribute_name = logical
+            .and_then(|node| node.attribute("Attribute"))
+            .unwrap_or_default();
+        let functions = logical.map(parse_functions).unwrap_or_default();
+
+        // GDTF 1.0 lo pone en el canal; 1.1 en la primera función
+        let default = channel
+            .attribute("Default")
+            .or_else(|| {
+                logical
+                    .into_iter()
+                    .flat_map(|node| node.descendants())
+                    .filter(|node| node.has_tag_name("ChannelFunction"))
+                    .find_map(|node| node.attribute("Default"))
+            })
+            .and_then(|value| parse_dmx_value(value, offsets.len()))
+            .unwrap_or(0);
+
+        for (byte, offset) in offsets.iter().enumerate() {
+            let (attribute, functions) = if byte == 0 {
+                (map_attribute(attribute_name), functions.clone())
+            } else {
+                (fine_attribute(attribute_name), Vec::new())
+            };
+            let value = (default >> (8 * (offsets.len() - 1 - byte))) as u8;
+
+            let idx = offset - 1;
+            if channels.len() <= idx {
+                channels.resize(idx + 1, None);
+            }
+            channels[idx] = Some(ChannelDef::new(attribute, value, functions));
+        }
+    }
+
+    // Los huecos del layout quedan como canales genéricos
+    let channels = channels
+        .into_iter()
+        .map(|channel| {
+            channel.unwrap_or_else(|| ChannelDef::new(Attribute::Generic, 0, Vec::new()))
+        })
+        .collect();
+
+    Ok(FixtureProfile::new(
+        format!("{manufacturer} {fixture} ({mode_name})")
+            .trim()
+            .to_string(),
+        manufacturer.to_string(),
+        mode_name.to_string(),
+        channels,
+    ))
+}
+
+// "1,2" -> canal grueso en 1 y fino en 2; "None" en los canales virtuales
+fn parse_offsets(offset: Option<&str>) -> Result<Option<Vec<usize>>, String> {
+    let Some(offset) = offset.filter(|offset| !offset.is_empty() && *offset != "None") else {
+        return Ok(None);
+    };
+
+    let offsets = offset
+        .split(',')
+        .map(|value| value.trim().parse::<usize>())
+        .collect::<Result<Vec<_>, _>>()
+        .ok()
+        .filter(|offsets| offsets.len() <= MAX_CHANNEL_BYTES)
+        .filter(|offsets| offsets.iter().all(|value| (1..=512).contains(value)))
+        .ok_or_else(|| format!("Offset DMX inválido en el GDTF: {offset}"))?;
+    Ok(Some(offsets))
+}
+
+// Valores GDTF "valor/bytes" (p. ej. "32768/2"), llevados a la resolución de `bytes`
+fn parse_dmx_value(value: &str, bytes: usize) -> Option<u64> {
+    let (value, resolution) = match value.split_once('/') {
+        Some((value, resolution)) => (value, resolution.parse::<usize>().ok()?),
+        None => (value, 1),
+    };
+    let value = value.parse::<u64>().ok()?;
+    if resolution == 0 || resolution > MAX_CHANNEL_BYTES {
+        return None;
+    }
+
+    if resolution < bytes {
+        Some(value << (8 * (bytes - resolution)))
+    } else {
+        Some(value >> (8 * (resolution - bytes)))
+    }
+}
+
+fn parse_functions(logical: Node) -> Vec<ChannelFunction> {
+    let mut starts = logical
+        .children()
+        .filter(|node| node.has_tag_name("ChannelFunction"))
+        .map(|node| {
+            let name = node
+                .attribute("Name")
+                .or_else(|| node.attribute("Attribute"))
+                .unwrap_or_default()
+                .to_string();
+            let from = node
+                .attribute("DMXFrom")
+                .and_then(|value| parse_dmx_value(value, 1))
+                .unwrap_or(0)
+                .min(255) as u8;
+            (name, from)
+        })
+        .collect::<Vec<_>>();
+    starts.sort_by_key(|(_, from)| *from);
+
+    // Cada función llega hasta donde empieza la siguiente
+    let mut functions = Vec::with_capacity(starts.len());
+    for (idx, (name, from)) in starts.iter().enumerate() {
+        let to = match starts.get(idx + 1) {
+            Some((_, next)) if next > from => next - 1,
+            Some(_) => *from,
+            None => 255,
+        };
+        functions.push(ChannelFunction::new(name.clone(), *from, to));
+    }
+    functions
+}
+
+fn map_attribute(name: &str) -> Attribute {
+    match name {
+        "Dimmer" => Attribute::Dimmer,
+        "Pan" => Attribute::Pan,
+        "Tilt" => Attribute::Tilt,
+        "ColorAdd_R" | "ColorRGB_Red" => Attribute::Red,
+        "ColorAdd_G" | "ColorRGB_Green" => Attribute::Green,
+        "ColorAdd_B" | "ColorRGB_Blue" => Attribute::Blue,
+        "ColorAdd_W" | "ColorAdd_WW" | "ColorAdd_CW" => Attribute::White,
+        "ColorAdd_A" => Attribute::Amber,
+        "ColorAdd_UV" => Attribute::Uv,
+        "ColorSub_C" | "ColorAdd_C" => Attribute::Cyan,
+        "ColorSub_M" | "ColorAdd_M" => Attribute::Magenta,
+        "ColorSub_Y" | "ColorAdd_Y" => Attribute::Yellow,
+        "Zoom" => Attribute::Zoom,
+        "Iris" => Attribute::Iris,
+        _ if name.starts_with("Shutter") && name.ends_with("Strobe") => Attribute::Strobe,
+        _ if name.starts_with("Shutter") => Attribute::Shutter,
+        _ if name.starts_with("Strobe") => Attribute::Strobe,
+        // Color1, Color2WheelIndex... son ruedas de color
+        _ if name
+            .strip_prefix("Color")
+            .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit())) =>
+        {
+            Attribute::ColorWheel
+        }
+        _ if name.starts_with("Gobo") => Attribute::Gobo,
+        _ if name.starts_with("Focus") => Attribute::Focus,
+        _ if name.starts_with("Prism") => Attribute::Prism,
+        _ if name.contains("Speed") => Attribute::Speed,
+        _ if name.starts_with("Control") => Attribute::Control,
+        _ => Attribute::Generic,
+    }
+}
+
+// Bytes finos de un canal de 16 bits
+fn fine_attribute(name: &str) -> Attribute {
+    match name {
+        "Pan" => Attribute::PanFine,
+        "Tilt" => Attribute::TiltFine,
+        _ => Attribute::Generic,
+    }
+}
+
+// Importa un .gdtf como un perfil por modo DMX y los guarda en la biblioteca de fixtures
+#[tauri::command]
+pub fn fixture_import_gdtf(
+    app_handle: AppHandle,
+    fixtures: State<'_, FixtureState>,
+    path: String,
+) -> Result<Vec<FixtureProfile>, String> {
+    let path = Path::new(&path);
+    let profiles = parse_description(&read_description(path)?)?;
+    fixtures.import_profiles(&app_handle, profiles.clone())?;
+
+    info!(
+        "GDTF importado: {} ({} modos)",
+        path.display(),
+        profiles.len()
+    );
+    Ok(profiles)
+}
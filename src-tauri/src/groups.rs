@@ -0,0 +1,244 @@
+use crate::dmx::DmxState;
+use crate::fixture::FixtureState;
+use crate::storage;
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+const GROUPS_FILE: &str = "groups.json";
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct GroupChannel {
+    universe: u16,
+    channel: u16,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Group {
+    id: u32,
+    name: String,
+    #[serde(default)]
+    fixtures: Vec<u32>,
+    // Canales sueltos, además de los de intensidad de los fixtures
+    #[serde(default)]
+    channels: Vec<GroupChannel>,
+    // Nivel del submaster del grupo (255 = no atenúa)
+    level: u8,
+}
+
+impl Group {
+    fn invalid_channel(&self) -> Option<u16> {
+        self.channels
+            .iter()
+            .map(|channel| channel.channel)
+            .find(|channel| !(1..=512).contains(channel))
+    }
+}
+
+// Los grupos que vienen de archivo no pasaron por `group_set`; también sirve para rechazar un
+// proyecto antes de aplicar nada
+pub(crate) fn validate(groups: &[Group]) -> Result<(), String> {
+    match groups
+        .iter()
+        .find_map(|group| Some((group, group.invalid_channel()?)))
+    {
+        Some((group, channel)) => Err(format!(
+            "Canal DMX inválido en el grupo \"{}\": {channel}",
+            group.name
+        )),
+        None => Ok(()),
+    }
+}
+
+#[derive(Default)]
+pub struct GroupState {
+    groups: Mutex<BTreeMap<u32, Group>>,
+    path: Mutex<Option<PathBuf>>,
+}
+
+impl GroupState {
+    pub fn load(&self, app_handle: &AppHandle) {
+        let Some(path) = storage::config_file(app_handle, GROUPS_FILE) else {
+            return;
+        };
+
+        if let Some(mut groups) = storage::load_json::<Vec<Group>>(&path) {
+            for group in &mut groups {
+                if let Some(channel) = group.invalid_channel() {
+                    warn!(
+                        "Canal DMX inválido en el grupo \"{}\": {channel}; se quitan los canales fuera de 1-512",
+                        group.name
+                    );
+                    group
+                        .channels
+                        .retain(|channel| (1..=512).contains(&channel.channel));
+                }
+            }
+            if let Ok(mut current) = self.groups.lock() {
+                *current = groups.into_iter().map(|group| (group.id, group)).collect();
+            }
+        }
+
+        if let Ok(mut current) = self.path.lock() {
+            *current = Some(path);
+        }
+        if let Err(err) = self.refresh(app_handle) {
+            error!("{err}");
+        }
+    }
+
+    pub(crate) fn export(&self) -> Result<Vec<Group>, String> {
+        Ok(self.lock_groups()?.values().cloned().collect())
+    }
+
+    pub(crate) fn replace(&self, app_handle: &AppHandle, groups: Vec<Group>) -> Result<(), String> {
+        validate(&groups)?;
+        {
+            let mut stored = self.lock_groups()?;
+            *stored = groups.into_iter().map(|group| (group.id, group)).collect();
+            self.save(&stored)?;
+        }
+        self.refresh(app_handle)
+    }
+
+    // Recalcula la atenuación por canal de cada universo a partir de los submasters. Hay que
+    // llamarlo cuando cambian los grupos, sus niveles o el patch de sus fixtures.
+    pub(crate) fn refresh(&self, app_handle: &AppHandle) -> Result<(), String> {
+        let fixtures = app_handle.state::<FixtureState>();
+        let mut scales: BTreeMap<u16, Vec<u8>> = BTreeMap::new();
+
+        for group in self
+            .lock_groups()?
+            .values()
+            .filter(|group| group.level < 255)
+        {
+            let mut members = group.channels.clone();
+            for id in &group.fixtures {
+                // Un fixture borrado del patch simplemente deja de contar
+                if let Ok((universe, channels)) = fixtures.intensity_channels(*id) {
+                    members.extend(
+                        channels
+                            .into_iter()
+                            .map(|channel| GroupChannel { universe, channel }),
+                    );
+                }
+            }
+            // Cada grupo atenúa un canal una sola vez; entre grupos los niveles se multiplican
+            members.sort();
+            members.dedup();
+
+            for member in members {
+                let scale = scales
+                    .entry(member.universe)
+                    .or_insert_with(|| vec![255; 512]);
+                let value = &mut scale[member.channel as usize - 1];
+                *value = ((*value as u16 * group.level as u16 + 127) / 255) as u8;
+            }
+        }
+
+        app_handle
+            .state::<DmxState>()
+            .distribute(app_handle, scales, |shared, scale| {
+                shared.set_submasters(scale)
+            })
+    }
+
+    fn lock_groups(&self) -> Result<std::sync::MutexGuard<'_, BTreeMap<u32, Group>>, String> {
+        self.groups
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la tabla de grupos: {e}"))
+    }
+
+    fn save(&self, groups: &BTreeMap<u32, Group>) -> Result<(), String> {
+        let path = self
+            .path
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la tabla de grupos: {e}"))?
+            .clone();
+
+        match path {
+            Some(path) => storage::save_json(&path, &groups.values().collect::<Vec<_>>()),
+            None => Ok(()),
+        }
+    }
+}
+
+// Crea el grupo o reemplaza sus miembros si ya existía (conserva el nivel del submaster)
+#[tauri::command]
+pub fn group_set(
+    app_handle: AppHandle,
+    groups: State<'_, GroupState>,
+    id: u32,
+    name: String,
+    fixtures: Vec<u32>,
+    channels: Vec<GroupChannel>,
+) -> Result<Group, String> {
+    let mut group = Group {
+        id,
+        name,
+        fixtures,
+        channels,
+        level: 255,
+    };
+    validate(std::slice::from_ref(&group))?;
+    let patched = app_handle.state::<FixtureState>();
+    for fixture in &group.fixtures {
+        patched.intensity_channels(*fixture)?;
+    }
+
+    let group = {
+        let mut stored = groups.lock_groups()?;
+        // El submaster conserva su nivel
+        group.level = stored.get(&id).map_or(255, |group| group.level);
+        stored.insert(id, group.clone());
+        groups.save(&stored)?;
+        group
+    };
+
+    groups.refresh(&app_handle)?;
+    Ok(group)
+}
+
+#[tauri::command]
+pub fn group_delete(
+    app_handle: AppHandle,
+    groups: State<'_, GroupState>,
+    id: u32,
+) -> Result<(), String> {
+    {
+        let mut stored = groups.lock_groups()?;
+        stored
+            .remove(&id)
+            .ok_or_else(|| format!("El grupo {id} no existe"))?;
+        groups.save(&stored)?;
+    }
+    groups.refresh(&app_handle)
+}
+
+#[tauri::command]
+pub fn group_list(groups: State<'_, GroupState>) -> Result<Vec<Group>, String> {
+    groups.export()
+}
+
+// Atenúa las intensidades del grupo antes del merge (255 = sin atenuar)
+#[tauri::command]
+pub fn submaster_set_level(
+    app_handle: AppHandle,
+    groups: State<'_, GroupState>,
+    id: u32,
+    value: u8,
+) -> Result<(), String> {
+    {
+        let mut stored = groups.lock_groups()?;
+        stored
+            .get_mut(&id)
+            .ok_or_else(|| format!("El grupo {id} no existe"))?
+            .level = value;
+        // No se escribe el archivo en cada movimiento del fader: queda en el autoguardado y
+        // en el proyecto
+    }
+    groups.refresh(&app_handle)
+}
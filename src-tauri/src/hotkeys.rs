@@ -0,0 +1,273 @@
+use crate::audio::AudioState;
+use crate::chase::{set_running_bpm, TempoState};
+use crate::cues::{cue_back, cue_go, cue_release, CueState};
+use crate::dmx::{dmx_blackout, DmxState};
+use crate::looper::{self, PedalAction};
+use crate::scenes::{scene_recall, SceneState};
+use crate::storage;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::plugin::TauriPlugin;
+use tauri::{AppHandle, Manager, State, Wry};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+const HOTKEYS_FILE: &str = "hotkeys.json";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HotkeyAction {
+    /// Todos los universos a 0
+    Blackout {
+        #[serde(default)]
+        fade_ms: Option<u64>,
+    },
+    CueGo,
+    CueBack,
+    CueRelease,
+    SceneRecall {
+        scene: String,
+        #[serde(default)]
+        fade_ms: Option<u64>,
+    },
+    TapTempo,
+    /// Sin `track` va a la pista elegida del looper
+    Looper {
+        action: PedalAction,
+        #[serde(default)]
+        track: Option<usize>,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Hotkey {
+    // Como lo entiende el sistema: "CmdOrCtrl+Shift+B", "F5", "Alt+Space"
+    shortcut: String,
+    action: HotkeyAction,
+    // Falso si al arrancar no se pudo registrar; se sigue guardando igual
+    #[serde(default, skip_deserializing)]
+    active: bool,
+}
+
+fn parse(shortcut: &str) -> Result<Shortcut, String> {
+    shortcut
+        .parse::<Shortcut>()
+        .map_err(|e| format!("Atajo inválido \"{shortcut}\": {e}"))
+}
+
+// Sin `Shortcut` si el archivo trae uno que no se entiende
+type RegisteredHotkey = (Option<Shortcut>, Hotkey);
+
+// Atajos globales del sistema: responden aunque la ventana no tenga el foco
+#[derive(Default)]
+pub struct HotkeyState {
+    hotkeys: Mutex<Vec<RegisteredHotkey>>,
+    path: Mutex<Option<PathBuf>>,
+}
+
+impl HotkeyState {
+    // Después de instalar el plugin: registra los atajos guardados
+    pub fn load(&self, app_handle: &AppHandle) {
+        let Some(path) = storage::config_file(app_handle, HOTKEYS_FILE) else {
+            return;
+        };
+
+        if let Some(hotkeys) = storage::load_json::<Vec<Hotkey>>(&path) {
+            let mut registered = Vec::new();
+            for mut hotkey in hotkeys {
+                // Uno que ya tomó otra aplicación no impide registrar el resto
+                let shortcut = match parse(&hotkey.shortcut) {
+                    Ok(shortcut) => Some(shortcut),
+                    Err(err) => {
+                        warn!("{err}");
+                        None
+                    }
+                };
+                if let Some(shortcut) = shortcut {
+                    match register(app_handle, shortcut) {
+                        Ok(()) => hotkey.active = true,
+                        Err(err) => warn!("{err}"),
+                    }
+                }
+                registered.push((shortcut, hotkey));
+            }
+            if let Ok(mut current) = self.hotkeys.lock() {
+                *current = registered;
+            }
+        }
+
+        if let Ok(mut current) = self.path.lock() {
+            *current = Some(path);
+        }
+    }
+
+    fn lock_hotkeys(&self) -> Result<std::sync::MutexGuard<'_, Vec<RegisteredHotkey>>, String> {
+        self.hotkeys
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear los atajos de teclado: {e}"))
+    }
+
+    fn save(&self, hotkeys: &[RegisteredHotkey]) -> Result<(), String> {
+        let path = self
+            .path
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear los atajos de teclado: {e}"))?
+            .clone();
+
+        match path {
+            Some(path) => storage::save_json(
+                &path,
+                &hotkeys.iter().map(|(_, hotkey)| hotkey).collect::<Vec<_>>(),
+            ),
+            None => Ok(()),
+        }
+    }
+
+    fn action(&self, shortcut: &Shortcut) -> Option<HotkeyAction> {
+        self.lock_hotkeys()
+            .ok()?
+            .iter()
+            .find(|(registered, hotkey)| hotkey.active && *registered == Some(*shortcut))
+            .map(|(_, hotkey)| hotkey.action.clone())
+    }
+}
+
+fn register(app_handle: &AppHandle, shortcut: Shortcut) -> Result<(), String> {
+    app_handle
+        .global_shortcut()
+        .register(shortcut)
+        .map_err(|e| {
+            format!(
+                "No se pudo registrar el atajo {}: {e}",
+                shortcut.into_string()
+            )
+        })
+}
+
+fn run(app_handle: &AppHandle, action: HotkeyAction) -> Result<(), String> {
+    let app = || app_handle.clone();
+    let dmx = || app_handle.state::<DmxState>();
+    let cues = || app_handle.state::<CueState>();
+
+    match action {
+        HotkeyAction::Blackout { fade_ms } => dmx_blackout(dmx(), None, fade_ms),
+        HotkeyAction::CueGo => cue_go(app(), cues(), dmx()).map(|_| ()),
+        HotkeyAction::CueBack => cue_back(app(), cues(), dmx()).map(|_| ()),
+        HotkeyAction::CueRelease => cue_release(app(), cues(), dmx()).map(|_| ()),
+        HotkeyAction::SceneRecall { scene, fade_ms } => scene_recall(
+            app(),
+            app_handle.state::<SceneState>(),
+            dmx(),
+            scene,
+            fade_ms,
+        ),
+        HotkeyAction::TapTempo => {
+            if let Some(bpm) = app_handle.state::<TempoState>().tap(Instant::now())? {
+                set_running_bpm(&dmx(), bpm);
+            }
+            Ok(())
+        }
+        HotkeyAction::Looper { action, track } => {
+            looper::pedal(&app_handle.state::<AudioState>(), action, track)
+        }
+    }
+}
+
+// Las acciones se disparan al apretar, no al soltar
+pub fn plugin() -> TauriPlugin<Wry> {
+    tauri_plugin_global_shortcut::Builder::new()
+        .with_handler(|app_handle, shortcut, event| {
+            if event.state() != ShortcutState::Pressed {
+                return;
+            }
+            let Some(action) = app_handle.state::<HotkeyState>().action(shortcut) else {
+                return;
+            };
+            if let Err(err) = run(app_handle, action) {
+                error!(
+                    "No se pudo ejecutar el atajo {}: {err}",
+                    shortcut.into_string()
+                );
+            }
+        })
+        .build()
+}
+
+// Con un atajo que ya existía reemplaza su acción. Falla si otra aplicación ya lo tiene.
+#[tauri::command]
+pub fn hotkey_set(
+    app_handle: AppHandle,
+    state: State<'_, HotkeyState>,
+    shortcut: String,
+    action: HotkeyAction,
+) -> Result<Hotkey, String> {
+    if let HotkeyAction::SceneRecall { scene, .. } = &action {
+        if scene.trim().is_empty() {
+            return Err("Falta el nombre de la escena".to_string());
+        }
+    }
+    let parsed = parse(&shortcut)?;
+    let hotkey = Hotkey {
+        shortcut: parsed.into_string(),
+        action,
+        active: true,
+    };
+
+    let mut hotkeys = state.lock_hotkeys()?;
+    match hotkeys
+        .iter_mut()
+        .find(|(registered, _)| *registered == Some(parsed))
+    {
+        Some((_, current)) => {
+            // Uno que no se pudo registrar al arrancar se vuelve a intentar
+            if !current.active {
+                register(&app_handle, parsed)?;
+            }
+            *current = hotkey.clone();
+        }
+        None => {
+            register(&app_handle, parsed)?;
+            hotkeys.push((Some(parsed), hotkey.clone()));
+        }
+    }
+    state.save(&hotkeys)?;
+    info!("Atajo {} asignado", hotkey.shortcut);
+    Ok(hotkey)
+}
+
+#[tauri::command]
+pub fn hotkey_remove(
+    app_handle: AppHandle,
+    state: State<'_, HotkeyState>,
+    shortcut: String,
+) -> Result<(), String> {
+    // Uno que no se entiende se busca por el texto guardado
+    let parsed = parse(&shortcut).ok();
+    let mut hotkeys = state.lock_hotkeys()?;
+    let index = hotkeys
+        .iter()
+        .position(|(registered, hotkey)| match parsed {
+            Some(parsed) => *registered == Some(parsed),
+            None => hotkey.shortcut == shortcut,
+        })
+        .ok_or_else(|| format!("El atajo \"{shortcut}\" no está asignado"))?;
+    if let (Some(parsed), true) = (parsed, hotkeys[index].1.active) {
+        app_handle
+            .global_shortcut()
+            .unregister(parsed)
+            .map_err(|e| format!("No se pudo liberar el atajo \"{shortcut}\": {e}"))?;
+    }
+    hotkeys.remove(index);
+    state.save(&hotkeys)
+}
+
+#[tauri::command]
+pub fn hotkey_list(state: State<'_, HotkeyState>) -> Result<Vec<Hotkey>, String> {
+    Ok(state
+        .lock_hotkeys()?
+        .iter()
+        .map(|(_, hotkey)| hotkey.clone())
+        .collect())
+}
@@ -0,0 +1,142 @@
+use crate::dmx::{self, DmxPortInfo};
+use crate::midi::{self, MidiDirection, MidiState};
+use log::{debug, error, info};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Wry};
+use tauri_plugin_serialplugin::SerialPort;
+
+pub const PORT_ADDED_EVENT: &str = "dmx://port-added";
+pub const PORT_REMOVED_EVENT: &str = "dmx://port-removed";
+pub const MIDI_ADDED_EVENT: &str = "midi://device-added";
+pub const MIDI_REMOVED_EVENT: &str = "midi://device-removed";
+pub const MIDI_REOPENED_EVENT: &str = "midi://device-reopened";
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// Revisa la lista de puertos cada segundo y avisa al front de los que aparecen o desaparecen.
+// Los puertos presentes al arrancar no generan eventos: el front ya los pide con `dmx_list_ports`.
+pub fn watch_ports(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    thread::spawn(move || {
+        let mut known = current_ports(&app_handle).unwrap_or_default();
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let Some(ports) = current_ports(&app_handle) else {
+                continue;
+            };
+            notify_changes(&app_handle, &known, &ports);
+            known = ports;
+        }
+    });
+}
+
+fn current_ports(app_handle: &AppHandle) -> Option<BTreeMap<String, DmxPortInfo>> {
+    let serial = app_handle.try_state::<SerialPort<Wry>>()?;
+    match dmx::list_ports(&serial) {
+        Ok(ports) => Some(
+            ports
+                .into_iter()
+                .map(|port| (port.path().to_string(), port))
+                .collect(),
+        ),
+        Err(err) => {
+            // Se reintenta en la próxima vuelta; no hace falta llenar el log
+            debug!("{err}");
+            None
+        }
+    }
+}
+
+fn notify_changes(
+    app_handle: &AppHandle,
+    known: &BTreeMap<String, DmxPortInfo>,
+    ports: &BTreeMap<String, DmxPortInfo>,
+) {
+    for (path, port) in ports {
+        if !known.contains_key(path) {
+            info!("Puerto DMX conectado: {path}");
+            if let Err(err) = app_handle.emit(PORT_ADDED_EVENT, port) {
+                error!("No se pudo avisar del puerto {path}: {err}");
+            }
+        }
+    }
+    for (path, port) in known {
+        if !ports.contains_key(path) {
+            info!("Puerto DMX desconectado: {path}");
+            if let Err(err) = app_handle.emit(PORT_REMOVED_EVENT, port) {
+                error!("No se pudo avisar del puerto {path}: {err}");
+            }
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct MidiDeviceEvent {
+    name: String,
+    direction: MidiDirection,
+}
+
+// Lo mismo con los dispositivos MIDI. Además, los que se abrieron con los comandos y se
+// desconectaron se reabren solos cuando vuelven, con sus mapeos y su feedback intactos.
+pub fn watch_midi_devices(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    thread::spawn(move || {
+        let directions = [MidiDirection::Input, MidiDirection::Output];
+        let mut known: BTreeMap<MidiDirection, BTreeSet<String>> = directions
+            .iter()
+            .map(|direction| (*direction, midi::port_names(*direction).unwrap_or_default()))
+            .collect();
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            for direction in directions {
+                let names = match midi::port_names(direction) {
+                    Ok(names) => names,
+                    Err(err) => {
+                        debug!("{err}");
+                        continue;
+                    }
+                };
+                let previous = known.entry(direction).or_default();
+                notify_midi_changes(&app_handle, direction, previous, &names);
+
+                let state = app_handle.state::<MidiState>();
+                for name in state.reopen(&app_handle, direction, &names) {
+                    info!("Dispositivo MIDI reabierto: {name}");
+                    emit_midi(&app_handle, MIDI_REOPENED_EVENT, direction, &name);
+                }
+                *previous = names;
+            }
+        }
+    });
+}
+
+fn notify_midi_changes(
+    app_handle: &AppHandle,
+    direction: MidiDirection,
+    known: &BTreeSet<String>,
+    names: &BTreeSet<String>,
+) {
+    for name in names.difference(known) {
+        info!("Dispositivo MIDI conectado: {name}");
+        emit_midi(app_handle, MIDI_ADDED_EVENT, direction, name);
+    }
+    for name in known.difference(names) {
+        info!("Dispositivo MIDI desconectado: {name}");
+        app_handle
+            .state::<MidiState>()
+            .disconnected(direction, name);
+        emit_midi(app_handle, MIDI_REMOVED_EVENT, direction, name);
+    }
+}
+
+fn emit_midi(app_handle: &AppHandle, event: &str, direction: MidiDirection, name: &str) {
+    let payload = MidiDeviceEvent {
+        name: name.to_string(),
+        direction,
+    };
+    if let Err(err) = app_handle.emit(event, payload) {
+        error!("No se pudo avisar del dispositivo MIDI {name}: {err}");
+    }
+}
@@ -0,0 +1,339 @@
+use crate::remote::{self, RemoteCommand};
+use log::{debug, error, info, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Read;
+use std::net::Ipv4Addr;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, State};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+pub const DEFAULT_HTTP_PORT: u16 = 8080;
+// Cuerpo más largo que se lee; alcanza para los 512 canales de un universo
+const MAX_BODY: u64 = 64 * 1024;
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const LOOP_ACTIONS: [&str; 6] = ["record", "overdub", "play", "stop", "clear", "undo"];
+
+#[derive(Clone, Serialize)]
+pub struct HttpApiStatus {
+    listening: bool,
+    port: Option<u16>,
+}
+
+#[derive(Serialize)]
+struct ApiStatus {
+    dmx: Value,
+    cues: Value,
+    loops: Value,
+    transport: Value,
+}
+
+#[derive(Deserialize)]
+struct ChannelBody {
+    value: u8,
+}
+
+#[derive(Deserialize)]
+struct LevelsBody {
+    levels: Vec<u8>,
+    #[serde(default)]
+    fade_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct GotoBody {
+    number: f64,
+    #[serde(default)]
+    fade_ms: Option<u64>,
+}
+
+#[derive(Default, Deserialize)]
+struct FadeBody {
+    #[serde(default)]
+    fade_ms: Option<u64>,
+}
+
+#[derive(Default, Deserialize)]
+struct TrackBody {
+    #[serde(default)]
+    track: Option<usize>,
+}
+
+struct HttpServer {
+    port: u16,
+    stop: Sender<()>,
+    handle: thread::JoinHandle<()>,
+}
+
+#[derive(Default)]
+pub struct HttpApiState {
+    server: Mutex<Option<HttpServer>>,
+}
+
+impl HttpApiState {
+    fn lock_server(&self) -> Result<std::sync::MutexGuard<'_, Option<HttpServer>>, String> {
+        self.server
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el servidor HTTP: {e}"))
+    }
+
+    pub(crate) fn port(&self) -> Option<u16> {
+        self.lock_server().ok()?.as_ref().map(|server| server.port)
+    }
+}
+
+// Respuesta en JSON con su código
+enum Reply {
+    Ok(Value),
+    Raw(String),
+    Error(u16, String),
+}
+
+fn parse_body<T: DeserializeOwned + Default>(body: &str) -> Result<T, String> {
+    if body.trim().is_empty() {
+        return Ok(T::default());
+    }
+    parse_required(body)
+}
+
+fn parse_required<T: DeserializeOwned>(body: &str) -> Result<T, String> {
+    serde_json::from_str(body).map_err(|e| format!("Cuerpo inválido: {e}"))
+}
+
+fn parse_number<T: std::str::FromStr>(text: &str, what: &str) -> Result<T, String> {
+    text.parse().map_err(|_| format!("{what} inválido: {text}"))
+}
+
+// Los nombres de escena pueden venir con `%20` y compañía
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let escaped = text
+            .get(idx + 1..idx + 3)
+            .filter(|_| bytes[idx] == b'%')
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                idx += 3;
+            }
+            None => {
+                decoded.push(bytes[idx]);
+                idx += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn command(
+    method: &Method,
+    segments: &[&str],
+    body: &str,
+) -> Result<Option<RemoteCommand>, String> {
+    let command = match (method, segments) {
+        (Method::Get, ["cue"]) => RemoteCommand::CueStatus,
+        (Method::Post, ["cue", "go"]) => RemoteCommand::CueGo,
+        (Method::Post, ["cue", "back"]) => RemoteCommand::CueBack,
+        (Method::Post, ["cue", "release"]) => RemoteCommand::CueRelease,
+        (Method::Post, ["cue", "goto"]) => {
+            let body = parse_required::<GotoBody>(body)?;
+            RemoteCommand::CueGoto {
+                number: body.number,
+                fade_ms: body.fade_ms,
+            }
+        }
+        (Method::Get, ["scenes"]) => RemoteCommand::SceneList,
+        (Method::Post, ["scene", name, "recall"]) => RemoteCommand::SceneRecall {
+            name: percent_decode(name),
+            fade_ms: parse_body::<FadeBody>(body)?.fade_ms,
+        },
+        (Method::Get, ["dmx", universe, "levels"]) => RemoteCommand::DmxGetLevels {
+            universe: parse_number(universe, "Universo")?,
+        },
+        (Method::Put, ["dmx", universe, "levels"]) => {
+            let body = parse_required::<LevelsBody>(body)?;
+            RemoteCommand::DmxSetLevels {
+                universe: parse_number(universe, "Universo")?,
+                levels: body.levels,
+                fade_ms: body.fade_ms,
+            }
+        }
+        (Method::Put, ["dmx", universe, "channel", channel]) => RemoteCommand::DmxSetChannel {
+            universe: parse_number(universe, "Universo")?,
+            channel: parse_number(channel, "Canal DMX")?,
+            value: parse_required::<ChannelBody>(body)?.value,
+        },
+        (Method::Post, ["dmx", "blackout"]) => RemoteCommand::DmxBlackout {
+            universe: None,
+            fade_ms: parse_body::<FadeBody>(body)?.fade_ms,
+        },
+        (Method::Get, ["loop"]) => RemoteCommand::LoopTracks,
+        (Method::Post, ["loop", action]) if LOOP_ACTIONS.contains(action) => {
+            let track = parse_body::<TrackBody>(body)?.track;
+            match *action {
+                "record" => RemoteCommand::LoopRecord { track },
+                "overdub" => RemoteCommand::LoopOverdub { track },
+                "play" => RemoteCommand::LoopPlay { track },
+                "stop" => RemoteCommand::LoopStop { track },
+                "clear" => RemoteCommand::LoopClear { track },
+                _ => RemoteCommand::LoopUndo { track },
+            }
+        }
+        (Method::Post, ["transport", "start"]) => RemoteCommand::TransportStart,
+        (Method::Post, ["transport", "stop"]) => RemoteCommand::TransportStop,
+        _ => return Ok(None),
+    };
+    Ok(Some(command))
+}
+
+fn status(app_handle: &AppHandle) -> Result<Value, String> {
+    let status = ApiStatus {
+        dmx: remote::run(app_handle, RemoteCommand::DmxStatus)?,
+        cues: remote::run(app_handle, RemoteCommand::CueStatus)?,
+        loops: remote::run(app_handle, RemoteCommand::LoopTracks)?,
+        transport: remote::run(app_handle, RemoteCommand::TransportStatus)?,
+    };
+    serde_json::to_value(status).map_err(|e| format!("No se pudo serializar la respuesta: {e}"))
+}
+
+fn route(app_handle: &AppHandle, method: &Method, url: &str, body: &str) -> Reply {
+    let path = url.split('?').next().unwrap_or_default();
+    let segments = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    let ["api", segments @ ..] = segments.as_slice() else {
+        return Reply::Error(404, format!("Ruta desconocida: {path}"));
+    };
+
+    let result = match (method, segments) {
+        (Method::Get, ["status"]) => status(app_handle),
+        // Los mismos pedidos que el WebSocket, para lo que no tiene ruta propia
+        (Method::Post, ["command"]) => return Reply::Raw(remote::handle_text(app_handle, body)),
+        _ => match command(method, segments, body) {
+            Ok(Some(command)) => remote::run(app_handle, command),
+            Ok(None) => return Reply::Error(404, format!("Ruta desconocida: {method} {path}")),
+            Err(err) => return Reply::Error(400, err),
+        },
+    };
+    match result {
+        Ok(value) => Reply::Ok(value),
+        Err(err) => Reply::Error(400, err),
+    }
+}
+
+fn respond(app_handle: &AppHandle, mut request: Request) {
+    let mut body = String::new();
+    let read = request.as_reader().take(MAX_BODY).read_to_string(&mut body);
+    let reply = match read {
+        Ok(_) => route(app_handle, request.method(), request.url(), &body),
+        Err(err) => Reply::Error(400, format!("No se pudo leer el cuerpo: {err}")),
+    };
+    debug!("HTTP {} {}", request.method(), request.url());
+
+    let (code, text) = match reply {
+        Reply::Ok(value) => (200, serde_json::to_string(&value).unwrap_or_default()),
+        Reply::Raw(text) => (200, text),
+        Reply::Error(code, err) => {
+            warn!(
+                "Pedido HTTP fallido ({} {}): {err}",
+                request.method(),
+                request.url()
+            );
+            let error = serde_json::to_string(&err).unwrap_or_default();
+            (code, format!("{{\"error\":{error}}}"))
+        }
+    };
+    let mut response = Response::from_string(text).with_status_code(code);
+    if let Ok(header) = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]) {
+        response = response.with_header(header);
+    }
+    if let Err(err) = request.respond(response) {
+        error!("No se pudo responder el pedido HTTP: {err}");
+    }
+}
+
+fn run_server(app_handle: AppHandle, server: Server, rx: Receiver<()>) {
+    while rx.try_recv().is_err() {
+        match server.recv_timeout(POLL_INTERVAL) {
+            Ok(Some(request)) => respond(&app_handle, request),
+            // El timeout permite revisar la señal de parada
+            Ok(None) => {}
+            Err(err) => {
+                error!("Error al recibir un pedido HTTP: {err}");
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+    info!("API HTTP detenida");
+}
+
+fn stop_server(server: HttpServer) {
+    let _ = server.stop.send(());
+    if server.handle.join().is_err() {
+        error!("El hilo de la API HTTP terminó con pánico");
+    }
+}
+
+// Para sistemas de show control y automatizaciones con curl; apagada hasta que se llama
+#[tauri::command]
+pub fn http_api_start(
+    app_handle: AppHandle,
+    state: State<'_, HttpApiState>,
+    bind_ip: Option<String>,
+    port: Option<u16>,
+) -> Result<HttpApiStatus, String> {
+    let bind_ip = match bind_ip {
+        Some(ip) => ip
+            .parse::<Ipv4Addr>()
+            .map_err(|e| format!("IP de escucha HTTP inválida ({ip}): {e}"))?,
+        None => Ipv4Addr::UNSPECIFIED,
+    };
+    let port = port.unwrap_or(DEFAULT_HTTP_PORT);
+
+    let mut server = state.lock_server()?;
+    // Se reinicia con la configuración nueva
+    if let Some(previous) = server.take() {
+        stop_server(previous);
+    }
+
+    let http = Server::http((bind_ip, port))
+        .map_err(|e| format!("No se pudo escuchar HTTP en {bind_ip}:{port}: {e}"))?;
+    info!("API HTTP en {bind_ip}:{port}");
+    let (tx, rx) = mpsc::channel();
+    let handle = thread::spawn(move || run_server(app_handle, http, rx));
+    *server = Some(HttpServer {
+        port,
+        stop: tx,
+        handle,
+    });
+    Ok(HttpApiStatus {
+        listening: true,
+        port: Some(port),
+    })
+}
+
+#[tauri::command]
+pub fn http_api_stop(state: State<'_, HttpApiState>) -> Result<(), String> {
+    let server = state.lock_server()?.take();
+    if let Some(server) = server {
+        stop_server(server);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn http_api_status(state: State<'_, HttpApiState>) -> Result<HttpApiStatus, String> {
+    let server = state.lock_server()?;
+    Ok(HttpApiStatus {
+        listening: server.is_some(),
+        port: server.as_ref().map(|server| server.port),
+    })
+}
@@ -0,0 +1,195 @@
+use crate::audio::{AudioBlock, AudioState, AUDIO_ERROR_EVENT};
+use log::{error, info};
+use serde::Serialize;
+use std::f32::consts::TAU;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub const AUDIO_LATENCY_EVENT: &str = "audio://latency";
+// Silencio antes del primer ping, para medir el ruido de la entrada (ms)
+const LEAD_MS: u32 = 200;
+// Entre pings; también es la latencia más larga que se puede medir (ms)
+const PING_INTERVAL_MS: u32 = 400;
+const PINGS: usize = 5;
+// Pings que tienen que volver para dar la medición por buena
+const MIN_PINGS: usize = 3;
+const PING_MS: u32 = 3;
+const PING_HZ: f32 = 2000.0;
+const PING_LEVEL: f32 = 0.5;
+// Umbral mínimo de detección, y cuántas veces por encima del ruido tiene que estar
+const MIN_THRESHOLD: f32 = 0.02;
+const NOISE_MARGIN: f32 = 4.0;
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Clone, Serialize)]
+pub struct LatencyResult {
+    latency_samples: u32,
+    latency_ms: f64,
+    // Cada ping medido; si varían mucho la conexión tiene ruido o realimentación
+    measurements: Vec<u32>,
+}
+
+// Manda pings por la salida principal y mide cuántas muestras tardan en volver por la entrada
+#[derive(Default)]
+pub(crate) struct Calibration {
+    active: bool,
+    finished: bool,
+    sample_rate: u32,
+    // Muestras desde que empezó
+    clock: u32,
+    noise: f32,
+    // Muestra donde arrancó el ping que todavía no volvió
+    pending: Option<u32>,
+    // Con capacidad para todos los pings, reservada al empezar
+    measurements: Vec<u32>,
+}
+
+impl Calibration {
+    fn start(&mut self) {
+        *self = Self {
+            active: true,
+            measurements: Vec::with_capacity(PINGS),
+            ..Self::default()
+        };
+    }
+
+    // Lo llama el callback de salida en cada bloque, después de la mezcla
+    pub(crate) fn process(&mut self, block: &mut AudioBlock) {
+        if !self.active {
+            return;
+        }
+        self.sample_rate = block.sample_rate;
+        let per_ms = block.sample_rate / 1000;
+        let (lead, interval, ping_len) = (
+            LEAD_MS * per_ms,
+            PING_INTERVAL_MS * per_ms,
+            PING_MS * per_ms,
+        );
+        let threshold = (self.noise * NOISE_MARGIN).max(MIN_THRESHOLD);
+
+        for (input, out) in block.input.iter().zip(block.main.iter_mut()) {
+            let clock = self.clock;
+            self.clock += 1;
+            // Mientras mide, en la salida solo suena el ping
+            *out = [0.0; 2];
+            if clock < lead {
+                self.noise = self.noise.max(input.abs());
+                continue;
+            }
+
+            let ping = ((clock - lead) / interval) as usize;
+            let offset = (clock - lead) % interval;
+            if ping >= PINGS {
+                self.active = false;
+                self.finished = true;
+                return;
+            }
+            if offset == 0 {
+                self.pending = Some(clock);
+            }
+            if offset < ping_len {
+                let envelope = 1.0 - offset as f32 / ping_len as f32;
+                let sample = (TAU * PING_HZ * offset as f32 / block.sample_rate as f32).sin()
+                    * PING_LEVEL
+                    * envelope;
+                *out = [sample; 2];
+            }
+            if let Some(start) = self.pending {
+                if input.abs() > threshold {
+                    self.measurements.push(clock - start);
+                    self.pending = None;
+                }
+            }
+        }
+    }
+
+    fn result(&self) -> Result<LatencyResult, String> {
+        if self.measurements.len() < MIN_PINGS {
+            return Err(format!(
+                "Volvieron {} de {PINGS} pings: conectar la salida a la entrada y subir el nivel",
+                self.measurements.len()
+            ));
+        }
+        let mut sorted = self.measurements.clone();
+        sorted.sort_unstable();
+        let latency_samples = sorted[sorted.len() / 2];
+        Ok(LatencyResult {
+            latency_samples,
+            latency_ms: latency_samples as f64 * 1000.0 / self.sample_rate.max(1) as f64,
+            measurements: self.measurements.clone(),
+        })
+    }
+}
+
+// Espera el final de la medición y guarda la compensación
+fn wait_result(app_handle: AppHandle) {
+    let timeout = Duration::from_millis((LEAD_MS + PING_INTERVAL_MS * PINGS as u32) as u64 + 2000);
+    let started = Instant::now();
+    let result = loop {
+        thread::sleep(POLL_INTERVAL);
+        let audio = app_handle.state::<AudioState>();
+        let mut calibration = match audio.shared().lock_calibration() {
+            Ok(calibration) => calibration,
+            Err(err) => break Err(err),
+        };
+        if calibration.finished {
+            break calibration.result();
+        }
+        if started.elapsed() > timeout {
+            // El motor se detuvo en el medio
+            calibration.active = false;
+            break Err("La medición de latencia no terminó".to_string());
+        }
+    };
+
+    let audio = app_handle.state::<AudioState>();
+    let result = result.and_then(|result| {
+        audio.set_latency(Some(result.latency_samples))?;
+        Ok(result)
+    });
+    match result {
+        Ok(result) => {
+            info!(
+                "Latencia de ida y vuelta: {} muestras ({:.1} ms)",
+                result.latency_samples, result.latency_ms
+            );
+            if let Err(err) = app_handle.emit(AUDIO_LATENCY_EVENT, result) {
+                error!("No se pudo emitir la latencia medida: {err}");
+            }
+        }
+        Err(err) => {
+            error!("Calibración de latencia: {err}");
+            if let Err(err) = app_handle.emit(AUDIO_ERROR_EVENT, err) {
+                error!("No se pudo emitir el error de audio: {err}");
+            }
+        }
+    }
+}
+
+// Hace falta la salida conectada a la entrada (un cable o un micrófono frente al parlante).
+// Tarda unos 2 s; el resultado llega en `audio://latency` y queda guardado.
+#[tauri::command]
+pub fn audio_calibrate_latency(
+    app_handle: AppHandle,
+    audio: State<'_, AudioState>,
+) -> Result<(), String> {
+    if !audio.has_input()? {
+        return Err("Para medir la latencia hace falta el motor en marcha con entrada".to_string());
+    }
+    let mut calibration = audio.shared().lock_calibration()?;
+    if calibration.active {
+        return Err("Ya se está midiendo la latencia".to_string());
+    }
+    calibration.start();
+    drop(calibration);
+
+    thread::spawn(move || wait_result(app_handle));
+    Ok(())
+}
+
+// Compensación a mano, en muestras; sin valor no se compensa
+#[tauri::command]
+pub fn audio_set_latency(audio: State<'_, AudioState>, samples: Option<u32>) -> Result<(), String> {
+    audio.set_latency(samples)
+}
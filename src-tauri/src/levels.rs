@@ -0,0 +1,82 @@
+use crate::dmx::DMX_FRAME_SIZE;
+use std::hint;
+use std::sync::atomic::{fence, AtomicU64, AtomicU8, Ordering};
+use std::sync::Mutex;
+
+// Buffer del universo (start code + 512 canales) compartido entre los comandos y el hilo de
+// escritura sin que el hilo tenga que esperar un lock. Es un seqlock: quien escribe arma el
+// frame aparte y lo publica canal por canal entre dos incrementos de `sequence`; el hilo copia
+// los canales y reintenta si la secuencia cambió en el medio, así nunca ve un frame a medias.
+pub(crate) struct LevelBuffer {
+    sequence: AtomicU64,
+    values: [AtomicU8; DMX_FRAME_SIZE],
+    // Solo ordena a los que escriben entre sí; el hilo de escritura nunca lo espera
+    writers: Mutex<()>,
+}
+
+impl Default for LevelBuffer {
+    fn default() -> Self {
+        Self {
+            sequence: AtomicU64::new(0),
+            values: std::array::from_fn(|_| AtomicU8::new(0)),
+            writers: Mutex::new(()),
+        }
+    }
+}
+
+impl LevelBuffer {
+    pub(crate) fn read(&self, frame: &mut [u8; DMX_FRAME_SIZE]) {
+        loop {
+            let before = self.sequence.load(Ordering::Acquire);
+            if before % 2 == 1 {
+                hint::spin_loop();
+                continue;
+            }
+
+            for (value, slot) in frame.iter_mut().zip(&self.values) {
+                *value = slot.load(Ordering::Relaxed);
+            }
+
+            fence(Ordering::Acquire);
+            if self.sequence.load(Ordering::Relaxed) == before {
+                return;
+            }
+        }
+    }
+
+    // Modifica el buffer; `f` recibe una copia del frame actual
+    pub(crate) fn write<T>(
+        &self,
+        f: impl FnOnce(&mut [u8; DMX_FRAME_SIZE]) -> T,
+    ) -> Result<T, String> {
+        let _guard = self
+            .writers
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el buffer DMX: {e}"))?;
+        Ok(self.modify(f))
+    }
+
+    // Para el hilo de escritura: si un comando está escribiendo no espera y devuelve `None`
+    pub(crate) fn try_write<T>(&self, f: impl FnOnce(&mut [u8; DMX_FRAME_SIZE]) -> T) -> Option<T> {
+        let _guard = self.writers.try_lock().ok()?;
+        Some(self.modify(f))
+    }
+
+    // Solo con `writers` tomado
+    fn modify<T>(&self, f: impl FnOnce(&mut [u8; DMX_FRAME_SIZE]) -> T) -> T {
+        let mut frame = [0u8; DMX_FRAME_SIZE];
+        for (value, slot) in frame.iter_mut().zip(&self.values) {
+            *value = slot.load(Ordering::Relaxed);
+        }
+        let result = f(&mut frame);
+
+        let sequence = self.sequence.load(Ordering::Relaxed);
+        self.sequence.store(sequence + 1, Ordering::Relaxed);
+        fence(Ordering::Release);
+        for (value, slot) in frame.iter().zip(&self.values) {
+            slot.store(*value, Ordering::Relaxed);
+        }
+        self.sequence.store(sequence + 2, Ordering::Release);
+        result
+    }
+}
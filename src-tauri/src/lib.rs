@@ -1,15 +1,484 @@
+mod artnet;
+mod audio;
+mod autosave;
+mod backup;
+mod beat_detect;
+mod chase;
+mod clock_schedule;
+mod color;
+mod companion;
+mod cues;
+mod curves;
 mod dmx;
+mod dmx_input;
+mod effects;
+mod enttec;
+mod errors;
+mod fade;
+mod fixture;
+mod gdtf;
+mod groups;
+mod hotkeys;
+mod hotplug;
+mod http_api;
+mod latency;
+mod levels;
+mod link;
+mod looper;
+mod ltc;
+mod matrix;
+mod mdns;
+mod merge;
+mod meters;
+mod metrics;
+mod midi;
+mod midi_clock;
+mod midi_feedback;
+mod midi_gesture;
+mod midi_map;
+mod monitor;
+mod msc;
+mod mtc;
+mod node;
+mod osc;
+mod osc_output;
+mod plugin_host;
+mod project;
+mod rdm;
+mod reconnect;
+mod recording;
+mod remote;
+mod sacn;
+mod sampler;
+mod scenes;
+mod scheduler;
+mod serial_direct;
+mod settings;
+mod sound_light;
+mod spectrum;
+mod stop;
+mod storage;
+mod timeline;
+mod timers;
+mod touchosc;
+mod transport;
+mod udmx;
+mod websocket;
 
-use dmx::{dmx_list_ports, dmx_set_levels, DmxState};
-use tauri::Manager;
+use artnet::{
+    artnet_configure, artnet_get_config, artnet_input_map, artnet_input_sources,
+    artnet_input_unmap, artnet_listen_start, artnet_listen_stop, artnet_set_enabled,
+    ArtNetInputState,
+};
+use audio::{
+    audio_get_config, audio_get_monitor, audio_get_routing, audio_list_devices, audio_list_hosts,
+    audio_set_monitor, audio_set_routing, audio_start, audio_status, audio_stop, AudioState,
+};
+use autosave::{
+    autosave_set_interval, discard_last_session, last_session_info, recover_last_session,
+    AutosaveState,
+};
+use backup::{
+    backup_start_primary, backup_start_tracking, backup_status, backup_stop, backup_take_over,
+    BackupState,
+};
+use beat_detect::{beat_detect_configure, beat_detect_status};
+use chase::{
+    chase_create, chase_delete, chase_list, chase_remove_step, chase_set_bpm, chase_set_mode,
+    chase_set_step, chase_start, chase_stop, chase_tap_tempo, TempoState,
+};
+use clock_schedule::{
+    schedule_delete, schedule_list, schedule_set, schedule_set_enabled, schedule_set_location,
+    schedule_status, ScheduleState,
+};
+use color::{fixture_set_color, fixture_set_white_balance};
+use companion::{companion_listen_start, companion_listen_stop, companion_status, CompanionState};
+use cues::{
+    cue_back, cue_delete, cue_go, cue_goto, cue_list, cue_pause, cue_record, cue_release,
+    cue_resume, cue_set_timecode, cue_status, CueState,
+};
+use curves::{dmx_set_curve, fixture_set_curve};
+use dmx::{
+    dmx_adjust_channels, dmx_blackout, dmx_channel_owners, dmx_get_channel, dmx_get_levels,
+    dmx_get_source_priorities, dmx_list_ports, dmx_list_universes, dmx_reconnect,
+    dmx_remove_universe, dmx_set_channel, dmx_set_driver, dmx_set_intensity_channels,
+    dmx_set_inverted, dmx_set_levels, dmx_set_limits, dmx_set_master, dmx_set_port, dmx_set_range,
+    dmx_set_refresh_rate, dmx_set_serial_backend, dmx_set_slew_limit, dmx_set_source_priority,
+    dmx_set_start_code, dmx_set_timing, dmx_status, dmx_stop, DmxState,
+};
+use dmx_input::{dmx_listen, dmx_stop_listening, DmxInputState};
+use effects::{effect_clear, effect_list, effect_set};
+use fixture::{
+    fixture_get_attributes, fixture_list, fixture_locate, fixture_profile_delete,
+    fixture_profile_list, fixture_profile_save, fixture_release_locate, fixture_set_attributes,
+    fixture_set_wide_attributes, patch_add, patch_move, patch_remove, FixtureState,
+};
+use gdtf::fixture_import_gdtf;
+use groups::{group_delete, group_list, group_set, submaster_set_level, GroupState};
+use hotkeys::{hotkey_list, hotkey_remove, hotkey_set, HotkeyState};
+use http_api::{http_api_start, http_api_status, http_api_stop, HttpApiState};
+use latency::{audio_calibrate_latency, audio_set_latency};
+use link::{link_enable, link_status};
+use looper::{
+    loop_add_track, loop_clear, loop_export, loop_overdub, loop_play, loop_record,
+    loop_remove_track, loop_select_track, loop_set_quantize, loop_set_stretch, loop_status,
+    loop_stop, loop_tracks, loop_undo, session_export_all, track_mute, track_set_pan,
+    track_set_volume,
+};
+use ltc::{ltc_configure, ltc_status};
+use matrix::{
+    matrix_create, matrix_delete, matrix_effect_start, matrix_effect_stop, matrix_effect_update,
+    matrix_list, MatrixState,
+};
+use mdns::{mdns_advertise_start, mdns_advertise_stop, mdns_discover, mdns_status, MdnsState};
+use metrics::dmx_get_metrics;
+use midi::{
+    midi_close_input, midi_close_output, midi_list_inputs, midi_list_outputs, midi_open_input,
+    midi_open_output, MidiState,
+};
+use midi_clock::{midi_clock_status, midi_clock_sync, MidiClockState};
+use midi_feedback::{
+    midi_feedback_add, midi_feedback_list, midi_feedback_remove, MidiFeedbackState,
+};
+use midi_map::{
+    midi_learn_cancel, midi_learn_start, midi_map_add, midi_map_list, midi_map_remove, MidiMapState,
+};
+use monitor::dmx_set_monitor;
+use msc::{msc_configure, msc_get_config, MscState};
+use mtc::{mtc_configure, mtc_status, MtcState};
+use node::{node_get_config, node_start, node_status, node_stop, NodeState};
+use osc::{osc_listen_start, osc_listen_stop, osc_status, OscState};
+use osc_output::{osc_output_configure, osc_output_get_config, OscOutputState};
+use plugin_host::{
+    plugin_get_param, plugin_list, plugin_load, plugin_params, plugin_set_param, plugin_unload,
+};
+use project::{project_current_path, project_open, project_save, ProjectState};
+use rdm::{
+    rdm_discover, rdm_get_identify, rdm_get_personality, rdm_get_start_address, rdm_list_devices,
+    rdm_set_identify, rdm_set_personality, rdm_set_start_address, RdmState,
+};
+use recording::{
+    dmx_play_recording, dmx_playback_pause, dmx_playback_seek, dmx_playback_status,
+    dmx_playback_stop, dmx_record_start, dmx_record_stop, RecordingState,
+};
+use sacn::{sacn_configure, sacn_get_config, sacn_set_enabled};
+use sampler::{sample_list, sample_load, sample_set, sample_stop, sample_trigger, sample_unload};
+use scenes::{scene_delete, scene_list, scene_recall, scene_store, SceneState};
+use settings::{settings_get, settings_set, SettingsState};
+use sound_light::{
+    sound_light_bands, sound_light_list, sound_light_remove, sound_light_set, SoundLightState,
+};
+use stop::{dmx_get_stop_policy, dmx_set_stop_policy};
+use tauri::{Manager, RunEvent};
+use timeline::{
+    timeline_chase, timeline_delete, timeline_list, timeline_load, timeline_locate, timeline_pause,
+    timeline_play, timeline_save, timeline_status, timeline_stop, TimelineState,
+};
+use timers::{timer_cancel, timer_list, timer_pause, timer_resume, timer_start, TimerState};
+use touchosc::touchosc_export;
+use transport::{
+    metronome_set, transport_set_signature, transport_set_tempo, transport_start, transport_status,
+    transport_stop,
+};
+use websocket::{ws_listen_start, ws_listen_stop, ws_status, WsState};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_serialplugin::init())
+        .plugin(hotkeys::plugin())
         .manage(DmxState::default())
-        .invoke_handler(tauri::generate_handler![dmx_list_ports, dmx_set_levels])
+        .manage(DmxInputState::default())
+        .manage(ArtNetInputState::default())
+        .manage(RdmState::default())
+        .manage(SceneState::default())
+        .manage(CueState::default())
+        .manage(RecordingState::default())
+        .manage(ProjectState::default())
+        .manage(FixtureState::default())
+        .manage(GroupState::default())
+        .manage(MatrixState::default())
+        .manage(AutosaveState::default())
+        .manage(MidiState::default())
+        .manage(TempoState::default())
+        .manage(MidiMapState::default())
+        .manage(MidiFeedbackState::default())
+        .manage(MidiClockState::default())
+        .manage(MscState::default())
+        .manage(MtcState::default())
+        .manage(OscState::default())
+        .manage(OscOutputState::default())
+        .manage(WsState::default())
+        .manage(HttpApiState::default())
+        .manage(MdnsState::default())
+        .manage(CompanionState::default())
+        .manage(BackupState::default())
+        .manage(NodeState::default())
+        .manage(TimelineState::default())
+        .manage(ScheduleState::default())
+        .manage(TimerState::default())
+        .manage(HotkeyState::default())
+        .manage(SettingsState::default())
+        .manage(AudioState::default())
+        .manage(SoundLightState::default())
+        .invoke_handler(tauri::generate_handler![
+            dmx_list_ports,
+            dmx_set_port,
+            dmx_set_levels,
+            dmx_set_channel,
+            dmx_set_range,
+            dmx_adjust_channels,
+            dmx_set_driver,
+            dmx_set_serial_backend,
+            dmx_set_start_code,
+            dmx_set_refresh_rate,
+            dmx_set_timing,
+            dmx_stop,
+            dmx_set_stop_policy,
+            dmx_get_stop_policy,
+            dmx_blackout,
+            dmx_set_master,
+            dmx_set_intensity_channels,
+            dmx_set_slew_limit,
+            dmx_set_curve,
+            dmx_set_limits,
+            dmx_set_inverted,
+            dmx_set_source_priority,
+            dmx_get_source_priorities,
+            dmx_channel_owners,
+            dmx_get_levels,
+            dmx_get_channel,
+            dmx_set_monitor,
+            dmx_reconnect,
+            dmx_status,
+            dmx_get_metrics,
+            dmx_record_start,
+            dmx_record_stop,
+            dmx_play_recording,
+            dmx_playback_pause,
+            dmx_playback_seek,
+            dmx_playback_stop,
+            dmx_playback_status,
+            project_save,
+            project_open,
+            project_current_path,
+            autosave_set_interval,
+            last_session_info,
+            recover_last_session,
+            discard_last_session,
+            dmx_remove_universe,
+            dmx_list_universes,
+            dmx_listen,
+            dmx_stop_listening,
+            artnet_configure,
+            artnet_set_enabled,
+            artnet_get_config,
+            artnet_listen_start,
+            artnet_listen_stop,
+            artnet_input_map,
+            artnet_input_unmap,
+            artnet_input_sources,
+            rdm_discover,
+            rdm_list_devices,
+            rdm_get_start_address,
+            rdm_set_start_address,
+            rdm_get_personality,
+            rdm_set_personality,
+            rdm_get_identify,
+            rdm_set_identify,
+            sacn_configure,
+            sacn_set_enabled,
+            sacn_get_config,
+            scene_store,
+            scene_recall,
+            scene_delete,
+            scene_list,
+            cue_record,
+            cue_delete,
+            cue_list,
+            cue_status,
+            cue_go,
+            cue_back,
+            cue_goto,
+            cue_release,
+            cue_pause,
+            cue_resume,
+            cue_set_timecode,
+            chase_create,
+            chase_set_step,
+            chase_remove_step,
+            chase_start,
+            chase_stop,
+            chase_set_bpm,
+            chase_tap_tempo,
+            chase_set_mode,
+            chase_delete,
+            chase_list,
+            effect_set,
+            effect_clear,
+            effect_list,
+            fixture_profile_save,
+            fixture_profile_delete,
+            fixture_profile_list,
+            patch_add,
+            patch_move,
+            patch_remove,
+            fixture_list,
+            fixture_set_attributes,
+            fixture_set_wide_attributes,
+            fixture_get_attributes,
+            fixture_locate,
+            fixture_release_locate,
+            fixture_import_gdtf,
+            fixture_set_color,
+            fixture_set_white_balance,
+            fixture_set_curve,
+            group_set,
+            group_delete,
+            group_list,
+            submaster_set_level,
+            matrix_create,
+            matrix_delete,
+            matrix_list,
+            matrix_effect_start,
+            matrix_effect_update,
+            matrix_effect_stop,
+            midi_list_inputs,
+            midi_open_input,
+            midi_close_input,
+            midi_learn_start,
+            midi_learn_cancel,
+            midi_map_add,
+            midi_map_remove,
+            midi_map_list,
+            midi_list_outputs,
+            midi_open_output,
+            midi_close_output,
+            midi_feedback_add,
+            midi_feedback_remove,
+            midi_feedback_list,
+            midi_clock_sync,
+            midi_clock_status,
+            msc_configure,
+            msc_get_config,
+            mtc_configure,
+            mtc_status,
+            ltc_configure,
+            ltc_status,
+            osc_listen_start,
+            osc_listen_stop,
+            osc_status,
+            osc_output_configure,
+            osc_output_get_config,
+            touchosc_export,
+            audio_start,
+            audio_stop,
+            audio_status,
+            audio_get_config,
+            audio_list_devices,
+            audio_list_hosts,
+            loop_record,
+            loop_overdub,
+            loop_play,
+            loop_stop,
+            loop_clear,
+            loop_status,
+            transport_set_tempo,
+            transport_set_signature,
+            transport_start,
+            transport_stop,
+            transport_status,
+            metronome_set,
+            beat_detect_configure,
+            beat_detect_status,
+            sound_light_set,
+            sound_light_remove,
+            sound_light_list,
+            sound_light_bands,
+            link_enable,
+            link_status,
+            sample_load,
+            sample_trigger,
+            sample_stop,
+            sample_set,
+            sample_unload,
+            sample_list,
+            audio_set_routing,
+            audio_get_routing,
+            audio_set_monitor,
+            audio_get_monitor,
+            audio_calibrate_latency,
+            audio_set_latency,
+            loop_set_quantize,
+            loop_set_stretch,
+            loop_add_track,
+            loop_remove_track,
+            loop_tracks,
+            loop_export,
+            session_export_all,
+            loop_undo,
+            loop_select_track,
+            track_set_volume,
+            track_set_pan,
+            track_mute,
+            plugin_load,
+            plugin_unload,
+            plugin_list,
+            plugin_params,
+            plugin_set_param,
+            plugin_get_param,
+            ws_listen_start,
+            ws_listen_stop,
+            ws_status,
+            http_api_start,
+            http_api_stop,
+            http_api_status,
+            mdns_advertise_start,
+            mdns_advertise_stop,
+            mdns_status,
+            mdns_discover,
+            companion_listen_start,
+            companion_listen_stop,
+            companion_status,
+            backup_start_primary,
+            backup_start_tracking,
+            backup_take_over,
+            backup_stop,
+            backup_status,
+            node_start,
+            node_stop,
+            node_status,
+            node_get_config,
+            timeline_save,
+            timeline_delete,
+            timeline_list,
+            timeline_load,
+            timeline_play,
+            timeline_pause,
+            timeline_stop,
+            timeline_locate,
+            timeline_chase,
+            timeline_status,
+            schedule_set,
+            schedule_delete,
+            schedule_list,
+            schedule_set_location,
+            schedule_set_enabled,
+            schedule_status,
+            timer_start,
+            timer_pause,
+            timer_resume,
+            timer_cancel,
+            timer_list,
+            hotkey_set,
+            hotkey_remove,
+            hotkey_list,
+            settings_get,
+            settings_set
+        ])
         .setup(|app| {
+            plugin_host::mark_main_thread();
+            // Primero el log, para no perder lo que avisan las cargas y restauraciones
             if cfg!(debug_assertions) {
                 app.handle().plugin(
                     tauri_plugin_log::Builder::default()
@@ -19,8 +488,46 @@ pub fn run() {
             }
             app.handle().plugin(tauri_plugin_dialog::init())?;
             app.handle().plugin(tauri_plugin_fs::init())?;
+            app.state::<DmxState>().load_port_settings(app.handle());
+            app.state::<DmxState>().load_stop_policy(app.handle());
+            app.state::<SettingsState>().load(app.handle());
+            app.state::<SceneState>().load(app.handle());
+            app.state::<CueState>().load(app.handle());
+            app.state::<FixtureState>().load(app.handle());
+            app.state::<GroupState>().load(app.handle());
+            app.state::<MidiMapState>().load(app.handle());
+            app.state::<MidiFeedbackState>().load(app.handle());
+            app.state::<AudioState>().load(app.handle());
+            app.state::<SoundLightState>().load(app.handle());
+            app.state::<NodeState>().load(app.handle());
+            app.state::<TimelineState>().load(app.handle());
+            app.state::<ScheduleState>().load(app.handle());
+            app.state::<HotkeyState>().load(app.handle());
+            app.state::<SettingsState>().restore(app.handle());
+            app.state::<AutosaveState>().start(app.handle());
+            hotplug::watch_ports(app.handle());
+            hotplug::watch_midi_devices(app.handle());
+            midi_feedback::start_feedback(app.handle());
+            midi_map::start_gestures(app.handle());
+            osc_output::start_output(app.handle());
+            transport::start_beat_events(app.handle());
+            beat_detect::start_beat_follow(app.handle());
+            ltc::start_ltc_follow(app.handle());
+            timeline::start_playback(app.handle());
+            clock_schedule::start_schedule(app.handle());
+            timers::start_timers(app.handle());
+            meters::start_meter_events(app.handle());
+            sound_light::start_sound_light(app.handle());
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app_handle, event| {
+            if let RunEvent::Exit = event {
+                app_handle.state::<SettingsState>().shutdown(app_handle);
+                app_handle.state::<DmxState>().shutdown();
+                app_handle.state::<AutosaveState>().shutdown();
+                app_handle.state::<AudioState>().shutdown();
+            }
+        });
 }
@@ -0,0 +1,124 @@
+use crate::audio::AudioState;
+use log::info;
+use rusty_link::{AblLink, SessionState};
+use serde::Serialize;
+use tauri::State;
+
+#[derive(Clone, Serialize)]
+pub struct LinkStatus {
+    enabled: bool,
+    // Otras apps de la red en la misma sesión
+    peers: u64,
+    bpm: f64,
+}
+
+// El estado de sesión de Link no es Send porque envuelve un puntero de C++
+struct Session(SessionState);
+
+// SAFETY: solo se usa con el transporte bloqueado, desde un hilo por vez; Link no lo ata al
+// hilo que lo creó
+unsafe impl Send for Session {}
+
+// Sesión de Ableton Link: el transporte toma de acá el tempo y la fase en cada bloque de
+// audio, y le pasa los cambios de tempo locales al resto de la red
+pub(crate) struct LinkClock {
+    link: AblLink,
+    // Se reserva al activar Link, para no pedir memoria en el callback
+    audio: Session,
+}
+
+impl LinkClock {
+    pub(crate) fn new(bpm: f64) -> Self {
+        let link = AblLink::new(bpm);
+        link.enable(true);
+        Self {
+            link,
+            audio: Session(SessionState::new()),
+        }
+    }
+
+    pub(crate) fn peers(&self) -> u64 {
+        self.link.num_peers()
+    }
+
+    // Tempo, tiempo al empezar el bloque y avance por muestra. `quantum` es el largo del
+    // compás: los tiempos 1 de todas las apps coinciden.
+    pub(crate) fn block(
+        &mut self,
+        frames: usize,
+        sample_rate: u32,
+        quantum: f64,
+    ) -> (f64, f64, f64) {
+        let state = &mut self.audio.0;
+        self.link.capture_audio_session_state(state);
+        let start = self.link.clock_micros();
+        let end = start + (frames as f64 * 1_000_000.0 / sample_rate as f64) as i64;
+        let first = state.beat_at_time(start, quantum);
+        let last = state.beat_at_time(end, quantum);
+        let step = if frames == 0 {
+            0.0
+        } else {
+            (last - first) / frames as f64
+        };
+        (state.tempo(), first, step)
+    }
+
+    // Fuera del hilo de audio: el estado de aplicación sí puede reservar memoria
+    fn update(&self, change: impl FnOnce(&mut SessionState, i64)) {
+        let mut state = SessionState::new();
+        self.link.capture_app_session_state(&mut state);
+        change(&mut state, self.link.clock_micros());
+        self.link.commit_app_session_state(&state);
+    }
+
+    pub(crate) fn set_tempo(&self, bpm: f64) {
+        self.update(|state, now| state.set_tempo(bpm, now));
+    }
+
+    // El tiempo 0 cae en el próximo compás de la sesión, en fase con las demás apps
+    pub(crate) fn restart(&self, quantum: f64) {
+        self.update(|state, now| state.request_beat_at_time(0.0, now, quantum));
+    }
+}
+
+impl Drop for LinkClock {
+    fn drop(&mut self) {
+        self.link.enable(false);
+    }
+}
+
+fn status(audio: &AudioState) -> Result<LinkStatus, String> {
+    let clock = audio.shared().lock_transport()?;
+    Ok(LinkStatus {
+        enabled: clock.link().is_some(),
+        peers: clock.link().map(LinkClock::peers).unwrap_or_default(),
+        bpm: clock.bpm(),
+    })
+}
+
+// Con Link activo el transporte sigue el tempo y la fase de la sesión, y un cambio de tempo
+// local pasa a toda la red
+#[tauri::command]
+pub fn link_enable(audio: State<'_, AudioState>, enabled: bool) -> Result<LinkStatus, String> {
+    let (active, bpm) = {
+        let clock = audio.shared().lock_transport()?;
+        (clock.link().is_some(), clock.bpm())
+    };
+    if enabled == active {
+        return status(&audio);
+    }
+    // Link se abre y se cierra fuera del lock, que también toma el callback de audio
+    let link = enabled.then(|| LinkClock::new(bpm));
+    let previous = audio.shared().lock_transport()?.set_link(link);
+    drop(previous);
+    info!(
+        "Ableton Link {}",
+        if enabled { "activado" } else { "apagado" }
+    );
+    status(&audio)
+}
+
+#[tauri::command]
+pub fn link_status(audio: State<'_, AudioState>) -> Result<LinkStatus, String> {
+    status(&audio)
+}
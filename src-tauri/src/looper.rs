@@ -0,0 +1,1086 @@
+use crate::audio::{AudioBlock, AudioState, BeatMark};
+use crate::meters::{Meter, MeterLevel};
+use crate::plugin_host::ClapPlugin;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tauri::State;
+
+// Lo que se reserva al empezar a grabar, para no pedir memoria en el hilo de audio durante
+// una toma normal (s)
+const RESERVED_SECS: usize = 60;
+// Una toma más larga se cierra sola y pasa a reproducirse (s)
+const MAX_LOOP_SECS: usize = 600;
+// Pistas, contando la maestra
+const MAX_TRACKS: usize = 8;
+// Largos posibles de una pista, en vueltas del maestro
+const MULTIPLES: [u32; 3] = [1, 2, 4];
+// Estiramiento máximo, para abajo y para arriba
+const MIN_SPEED: f64 = 0.25;
+const MAX_SPEED: f64 = 4.0;
+const MAX_TRACK_VOLUME: f32 = 2.0;
+// Lo que tarda un cambio de volumen, paneo o mute en pasar de 0 a 1, para que no haga click
+// (ms)
+const MIX_RAMP_MS: u32 = 10;
+// Bloque del motor para el que se reservan los buffers de mezcla; uno más largo los agranda
+const MAX_BLOCK: usize = 8192;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopState {
+    #[default]
+    Empty,
+    Recording,
+    Playing,
+    /// Reproduce y suma la entrada a lo grabado
+    Overdubbing,
+    /// Con grabación, en silencio
+    Stopped,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopQuantize {
+    /// Las acciones se hacen al instante
+    #[default]
+    Off,
+    /// Esperan el próximo tiempo del transporte
+    Beat,
+    /// Esperan el próximo compás
+    Bar,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopAction {
+    Record,
+    Overdub,
+    Play,
+    Stop,
+}
+
+// Acciones para un controlador de pie: pocos botones, cada uno alterna entre dos cosas
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PedalAction {
+    /// Graba en una pista vacía; con grabación alterna el overdub
+    RecordOverdub,
+    /// Alterna entre reproducir y parar
+    PlayStop,
+    /// Deshace el último overdub
+    Undo,
+    /// Pasa a la pista siguiente; después de la última vuelve al maestro
+    NextTrack,
+    Clear,
+}
+
+#[derive(Clone, Serialize)]
+pub struct LoopStatus {
+    track: usize,
+    // Largo en vueltas del maestro (la pista 0 es el maestro)
+    multiple: u32,
+    state: LoopState,
+    length_samples: usize,
+    position: usize,
+    length_ms: u64,
+    quantize: LoopQuantize,
+    // Acción cuantizada que espera el próximo tiempo o compás
+    pending: Option<LoopAction>,
+    can_undo: bool,
+    // La que manejan los pedales sin pista fija
+    selected: bool,
+    volume: f32,
+    pan: f32,
+    muted: bool,
+    stretch: bool,
+    // Tempo del transporte al grabar el maestro
+    take_bpm: Option<f64>,
+}
+
+// Nivel de la pista en la mezcla
+#[derive(Clone, Copy)]
+struct TrackMix {
+    volume: f32,
+    // -1 todo a la izquierda, 1 todo a la derecha
+    pan: f32,
+    muted: bool,
+    // Ganancias que se aplican ahora; van en rampa hacia las pedidas
+    gains: [f32; 2],
+}
+
+impl Default for TrackMix {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            pan: 0.0,
+            muted: false,
+            gains: [1.0; 2],
+        }
+    }
+}
+
+impl TrackMix {
+    // Balance: al centro los dos lados quedan a pleno, así una pista sin tocar suena igual
+    fn target(&self) -> [f32; 2] {
+        if self.muted {
+            return [0.0; 2];
+        }
+        [
+            self.volume * (1.0 - self.pan).min(1.0),
+            self.volume * (1.0 + self.pan).min(1.0),
+        ]
+    }
+
+    fn next(&mut self, step: f32) -> [f32; 2] {
+        let target = self.target();
+        for (gain, target) in self.gains.iter_mut().zip(target) {
+            *gain += (target - *gain).clamp(-step, step);
+        }
+        self.gains
+    }
+}
+
+// Lo que una toma necesita antes de empezar
+#[derive(Clone, Copy, PartialEq)]
+enum Reserve {
+    // Pista sincronizada: el largo exacto, en silencio
+    Silence(usize),
+    // Capacidad sin contenido: la toma libre del maestro o la copia para deshacer
+    Capacity(usize),
+}
+
+impl Reserve {
+    fn allocate(self) -> Vec<f32> {
+        match self {
+            Reserve::Silence(len) => vec![0.0; len],
+            Reserve::Capacity(len) => Vec::with_capacity(len),
+        }
+    }
+}
+
+struct LoopTrack {
+    // Vueltas del maestro que dura; 0 en el maestro mismo
+    multiple: u32,
+    state: LoopState,
+    buffer: Vec<f32>,
+    // Próxima muestra a reproducir, con fracción cuando el loop va estirado; vuelve a 0 justo
+    // después de la última muestra grabada
+    position: f64,
+    // Muestras de la toma en curso
+    recorded: usize,
+    latency: usize,
+    pending: Option<LoopAction>,
+    // Copia de antes del último overdub; se reutiliza entre overdubs
+    undo: Vec<f32>,
+    can_undo: bool,
+    mix: TrackMix,
+    // Inserto; lo que suena de la pista pasa por él antes de la mezcla
+    plugin: Option<ClapPlugin>,
+    // Lo que suena en el bloque, antes y después del plugin; con capacidad reservada
+    dry: Vec<f32>,
+    wet: Vec<[f32; 2]>,
+}
+
+impl LoopTrack {
+    fn new(multiple: u32, latency: usize) -> Self {
+        Self {
+            multiple,
+            state: LoopState::Empty,
+            buffer: Vec::new(),
+            position: 0.0,
+            recorded: 0,
+            latency,
+            pending: None,
+            undo: Vec::new(),
+            can_undo: false,
+            mix: TrackMix::default(),
+            plugin: None,
+            dry: Vec::with_capacity(MAX_BLOCK),
+            wet: Vec::with_capacity(MAX_BLOCK),
+        }
+    }
+
+    fn record(&mut self) -> Result<(), String> {
+        match self.state {
+            // El buffer ya lo preparó `Looper::prepare`
+            LoopState::Empty => {
+                self.position = 0.0;
+                self.recorded = 0;
+                self.state = LoopState::Recording;
+            }
+            // Cerrar la toma: el loop empieza a sonar desde la primera muestra
+            LoopState::Recording => self.close(LoopState::Playing),
+            _ => return Err("El loop ya tiene grabación: usar overdub o borrarlo".to_string()),
+        }
+        Ok(())
+    }
+
+    fn overdub(&mut self) -> Result<(), String> {
+        self.state = match self.state {
+            LoopState::Playing | LoopState::Stopped => LoopState::Overdubbing,
+            LoopState::Overdubbing => LoopState::Playing,
+            LoopState::Recording => {
+                self.close(LoopState::Overdubbing);
+                return Ok(());
+            }
+            LoopState::Empty => return Err("El loop está vacío".to_string()),
+        };
+        Ok(())
+    }
+
+    fn play(&mut self) -> Result<(), String> {
+        match self.state {
+            LoopState::Empty => return Err("El loop está vacío".to_string()),
+            LoopState::Recording => self.close(LoopState::Playing),
+            LoopState::Stopped => {
+                self.position = 0.0;
+                self.state = LoopState::Playing;
+            }
+            LoopState::Playing | LoopState::Overdubbing => self.state = LoopState::Playing,
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        match self.state {
+            LoopState::Empty => {}
+            LoopState::Recording => self.close(LoopState::Stopped),
+            _ => self.state = LoopState::Stopped,
+        }
+        self.position = 0.0;
+    }
+
+    fn run(&mut self, action: LoopAction) -> Result<(), String> {
+        match action {
+            LoopAction::Record => self.record(),
+            LoopAction::Overdub => self.overdub(),
+            LoopAction::Play => self.play(),
+            LoopAction::Stop => {
+                self.stop();
+                Ok(())
+            }
+        }
+    }
+
+    fn run_pending(&mut self) {
+        if let Some(action) = self.pending.take() {
+            // Ya se validó al pedirla y el estado no cambió desde entonces
+            let _ = self.run(action);
+        }
+    }
+
+    // Una toma vacía (cerrada antes de que llegue audio) deja el loop vacío. La toma del
+    // maestro se corre la latencia, así su primera muestra es lo que se tocó al empezar a
+    // grabar; las demás ya escriben corridas.
+    fn close(&mut self, next: LoopState) {
+        if self.state == LoopState::Recording && self.multiple == 0 && !self.buffer.is_empty() {
+            let shift = self.latency % self.buffer.len();
+            self.buffer.rotate_left(shift);
+        }
+        self.position = 0.0;
+        self.state = if self.recorded == 0 {
+            LoopState::Empty
+        } else {
+            next
+        };
+    }
+
+    // Interpolación lineal entre las dos muestras vecinas
+    fn read(&self, at: f64) -> f32 {
+        let len = self.buffer.len();
+        let idx = at as usize % len;
+        let current = self.buffer[idx];
+        let next = self.buffer[(idx + 1) % len];
+        current + (next - current) * at.fract() as f32
+    }
+
+    // Donde se suma la entrada: lo que entra ahora se tocó sobre lo que sonó hace `latency`
+    // muestras
+    fn write_index(&self) -> usize {
+        let len = self.buffer.len();
+        (self.position as usize % len + len - self.latency % len) % len
+    }
+
+    // El maestro: graba hasta que se cierra la toma y marca el largo de las demás. `speed` es
+    // el estiramiento al tempo actual. Devuelve la muestra y si dio la vuelta.
+    fn tick_master(&mut self, input: f32, max_len: usize, speed: f64) -> (f32, bool) {
+        match self.state {
+            LoopState::Empty | LoopState::Stopped => (0.0, false),
+            LoopState::Recording => {
+                self.buffer.push(input);
+                self.recorded += 1;
+                if self.buffer.len() >= max_len {
+                    self.close(LoopState::Playing);
+                }
+                (0.0, false)
+            }
+            LoopState::Playing | LoopState::Overdubbing => {
+                let len = self.buffer.len() as f64;
+                let sample = self.read(self.position);
+                if self.state == LoopState::Overdubbing {
+                    // Más lento que la toma, varias entradas caen en la misma muestra
+                    let target = self.write_index();
+                    self.buffer[target] += input * speed.min(1.0) as f32;
+                }
+                self.position += speed;
+                let wrapped = self.position >= len;
+                if wrapped {
+                    self.position %= len;
+                }
+                (sample, wrapped)
+            }
+        }
+    }
+
+    // Una pista sincronizada: su posición sale de la del maestro, así no se corre nunca.
+    // La toma dura exactamente una vuelta de la pista y se cierra sola.
+    fn tick_synced(&mut self, input: f32, phase: f64, speed: f64) -> f32 {
+        let len = self.buffer.len();
+        if len == 0 {
+            return 0.0;
+        }
+        self.position = phase % len as f64;
+        let target = self.write_index();
+        match self.state {
+            LoopState::Empty | LoopState::Stopped => 0.0,
+            LoopState::Recording => {
+                self.buffer[target] = input;
+                self.recorded += 1;
+                if self.recorded as f64 * speed >= len as f64 {
+                    self.close(LoopState::Playing);
+                }
+                0.0
+            }
+            LoopState::Playing => self.read(self.position),
+            LoopState::Overdubbing => {
+                let sample = self.read(self.position);
+                self.buffer[target] += input * speed.min(1.0) as f32;
+                sample
+            }
+        }
+    }
+}
+
+// La pista 0 es el maestro: su toma fija el largo de las demás, que duran 1, 2 o 4 vueltas
+// suyas y siguen su posición
+pub(crate) struct Looper {
+    tracks: Vec<LoopTrack>,
+    // Vueltas del maestro desde que empezó a sonar; ubica a las pistas más largas
+    cycles: u64,
+    sample_rate: u32,
+    // Lo que suena del looper, todas las pistas juntas
+    meter: Meter,
+    // Latencia de ida y vuelta: lo que entra ahora se tocó sobre lo que sonó hace tantas
+    // muestras
+    latency: usize,
+    quantize: LoopQuantize,
+    // Pista de los pedales sin pista fija
+    selected: usize,
+    // Con `stretch`, las pistas siguen el tempo del transporte: suenan más rápido o más lento
+    // (y más agudas o más graves) en proporción con el tempo de la toma
+    stretch: bool,
+    bpm: Option<f64>,
+    take_bpm: Option<f64>,
+    // Mezcla de las pistas en el bloque
+    mixed: Vec<[f32; 2]>,
+}
+
+impl Default for Looper {
+    fn default() -> Self {
+        let mut tracks = Vec::with_capacity(MAX_TRACKS);
+        tracks.push(LoopTrack::new(0, 0));
+        Self {
+            tracks,
+            cycles: 0,
+            sample_rate: 0,
+            meter: Meter::default(),
+            latency: 0,
+            quantize: LoopQuantize::Off,
+            selected: 0,
+            stretch: false,
+            bpm: None,
+            take_bpm: None,
+            mixed: Vec::with_capacity(MAX_BLOCK),
+        }
+    }
+}
+
+impl Looper {
+    fn track(&self, track: usize) -> Result<&LoopTrack, String> {
+        self.tracks
+            .get(track)
+            .ok_or_else(|| format!("La pista de loop {track} no existe"))
+    }
+
+    fn status_of(&self, idx: usize, track: &LoopTrack) -> LoopStatus {
+        LoopStatus {
+            track: idx,
+            multiple: track.multiple.max(1),
+            state: track.state,
+            length_samples: track.buffer.len(),
+            position: track.position as usize,
+            length_ms: match self.sample_rate {
+                0 => 0,
+                rate => track.buffer.len() as u64 * 1000 / rate as u64,
+            },
+            quantize: self.quantize,
+            pending: track.pending,
+            can_undo: track.can_undo,
+            selected: idx == self.selected,
+            volume: track.mix.volume,
+            pan: track.mix.pan,
+            muted: track.mix.muted,
+            stretch: self.stretch,
+            take_bpm: self.take_bpm,
+        }
+    }
+
+    pub(crate) fn status(&self, track: usize) -> Result<LoopStatus, String> {
+        Ok(self.status_of(track, self.track(track)?))
+    }
+
+    fn statuses(&self) -> Vec<LoopStatus> {
+        self.tracks
+            .iter()
+            .enumerate()
+            .map(|(idx, track)| self.status_of(idx, track))
+            .collect()
+    }
+
+    // Copia de lo grabado, para escribirla fuera del lock. Una toma en curso todavía no tiene
+    // largo.
+    fn recording(&self, track: usize) -> Result<Option<(Vec<f32>, u32)>, String> {
+        let loop_track = self.track(track)?;
+        Ok(match loop_track.state {
+            LoopState::Empty | LoopState::Recording => None,
+            _ => Some((loop_track.buffer.clone(), self.sample_rate)),
+        })
+    }
+
+    pub(crate) fn set_latency(&mut self, samples: usize) {
+        self.latency = samples;
+        for track in &mut self.tracks {
+            track.latency = samples;
+        }
+    }
+
+    pub(crate) fn take_meter(&mut self) -> MeterLevel {
+        self.meter.take()
+    }
+
+    fn speed(&self) -> f64 {
+        match (self.stretch, self.bpm, self.take_bpm) {
+            (true, Some(bpm), Some(take_bpm)) if take_bpm > 0.0 => {
+                (bpm / take_bpm).clamp(MIN_SPEED, MAX_SPEED)
+            }
+            _ => 1.0,
+        }
+    }
+
+    // Largo del maestro, si ya tiene una toma cerrada
+    fn master_len(&self) -> Option<usize> {
+        let master = &self.tracks[0];
+        match master.state {
+            LoopState::Empty | LoopState::Recording => None,
+            _ => Some(master.buffer.len()),
+        }
+    }
+
+    // Los mismos errores que la acción, para rechazarla antes de dejarla esperando
+    fn check(&self, track: usize, action: LoopAction) -> Result<(), String> {
+        let state = self.track(track)?.state;
+        match (action, state) {
+            (LoopAction::Record, LoopState::Empty) if track > 0 && self.master_len().is_none() => {
+                Err("Primero hay que grabar el loop maestro (pista 0)".to_string())
+            }
+            (LoopAction::Record, LoopState::Empty | LoopState::Recording) => Ok(()),
+            (LoopAction::Record, _) => {
+                Err("El loop ya tiene grabación: usar overdub o borrarlo".to_string())
+            }
+            (LoopAction::Overdub | LoopAction::Play, LoopState::Empty) => {
+                Err("El loop está vacío".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Memoria que necesita una toma que está por empezar, aunque espere el próximo tiempo
+    fn reserve(&self, track: usize, action: LoopAction) -> Option<Reserve> {
+        let loop_track = self.tracks.get(track)?;
+        match (action, loop_track.state) {
+            (LoopAction::Record, LoopState::Empty) => match self.master_len() {
+                Some(master_len) if track > 0 => {
+                    Some(Reserve::Silence(master_len * loop_track.multiple as usize))
+                }
+                _ => {
+                    let reserved = RESERVED_SECS * self.sample_rate.max(48_000) as usize;
+                    (loop_track.buffer.capacity() < reserved).then_some(Reserve::Capacity(reserved))
+                }
+            },
+            // La copia para deshacer reutiliza la anterior si le alcanza
+            (LoopAction::Overdub, LoopState::Playing | LoopState::Stopped) => {
+                let len = loop_track.buffer.len();
+                (loop_track.undo.capacity() < len).then_some(Reserve::Capacity(len))
+            }
+            _ => None,
+        }
+    }
+
+    // Con el lock: pone lo que `reserve` pidió, ya reservado afuera. Devuelve el buffer
+    // reemplazado, para liberarlo también sin el lock.
+    fn prepare(
+        &mut self,
+        track: usize,
+        action: LoopAction,
+        reserved: Option<Vec<f32>>,
+    ) -> Option<Vec<f32>> {
+        let free_take = track == 0 || self.master_len().is_none();
+        let Some(loop_track) = self.tracks.get_mut(track) else {
+            return reserved;
+        };
+        match (action, loop_track.state) {
+            (LoopAction::Record, LoopState::Empty) => {
+                if free_take {
+                    self.take_bpm = self.bpm;
+                }
+                match reserved {
+                    Some(buffer) => Some(std::mem::replace(&mut loop_track.buffer, buffer)),
+                    None => {
+                        loop_track.buffer.clear();
+                        None
+                    }
+                }
+            }
+            // Lo que había antes del overdub, para poder deshacerlo
+            (LoopAction::Overdub, LoopState::Playing | LoopState::Stopped) => {
+                let LoopTrack {
+                    buffer,
+                    undo,
+                    can_undo,
+                    ..
+                } = loop_track;
+                let replaced = reserved.map(|spare| std::mem::replace(undo, spare));
+                undo.clear();
+                undo.extend_from_slice(buffer);
+                *can_undo = true;
+                replaced
+            }
+            _ => reserved,
+        }
+    }
+
+    // Intercambia el loop con la copia de antes del último overdub: deshacer otra vez lo
+    // rehace. Un overdub en curso termina.
+    fn undo(&mut self, track: usize) -> Result<(), String> {
+        let loop_track = self
+            .tracks
+            .get_mut(track)
+            .ok_or_else(|| format!("La pista de loop {track} no existe"))?;
+        if !loop_track.can_undo {
+            return Err(format!(
+                "La pista de loop {track} no tiene un overdub para deshacer"
+            ));
+        }
+        if loop_track.state == LoopState::Overdubbing {
+            loop_track.state = LoopState::Playing;
+        }
+        std::mem::swap(&mut loop_track.buffer, &mut loop_track.undo);
+        loop_track.pending = None;
+        Ok(())
+    }
+
+    pub(crate) fn run_pending(&mut self) {
+        for track in &mut self.tracks {
+            track.run_pending();
+        }
+    }
+
+    fn snaps_to(&self, mark: &BeatMark) -> bool {
+        match self.quantize {
+            LoopQuantize::Off => false,
+            LoopQuantize::Beat => true,
+            LoopQuantize::Bar => mark.bar,
+        }
+    }
+
+    // Borrar el maestro borra todas las pistas, porque su largo sale de él. La mezcla y el
+    // plugin quedan.
+    fn clear(&mut self, track: usize) {
+        for (idx, loop_track) in self.tracks.iter_mut().enumerate() {
+            if idx == track || track == 0 {
+                let mix = loop_track.mix;
+                let plugin = loop_track.plugin.take();
+                *loop_track = LoopTrack::new(loop_track.multiple, self.latency);
+                loop_track.mix = mix;
+                loop_track.plugin = plugin;
+            }
+        }
+        if track == 0 {
+            self.cycles = 0;
+            self.take_bpm = None;
+        }
+    }
+
+    // Lo llama el callback de salida en cada bloque. Una acción cuantizada se hace en la
+    // muestra exacta donde empieza el tiempo.
+    pub(crate) fn process(&mut self, block: &mut AudioBlock) {
+        self.sample_rate = block.sample_rate;
+        self.bpm = block.bpm.or(self.bpm);
+        let pending = self.tracks.iter().any(|track| track.pending.is_some());
+        let split = block
+            .beat
+            .filter(|mark| pending && self.snaps_to(mark))
+            .map(|mark| mark.offset.min(block.input.len()));
+
+        match split {
+            Some(offset) => {
+                let (input_before, input_after) = block.input.split_at(offset);
+                let (main_before, main_after) = block.main.split_at_mut(offset);
+                self.render(input_before, main_before);
+                self.run_pending();
+                self.render(input_after, main_after);
+            }
+            None => self.render(block.input, block.main),
+        }
+    }
+
+    fn render(&mut self, input: &[f32], main: &mut [[f32; 2]]) {
+        let max_len = MAX_LOOP_SECS * self.sample_rate as usize;
+        let speed = self.speed();
+        let step = 1.0 / (MIX_RAMP_MS * self.sample_rate / 1000).max(1) as f32;
+        let Some((master, synced)) = self.tracks.split_first_mut() else {
+            return;
+        };
+
+        master.dry.clear();
+        synced.iter_mut().for_each(|track| track.dry.clear());
+        for input in input {
+            // Con el maestro parado las demás pistas quedan en silencio: él lleva el tiempo
+            if master.state == LoopState::Stopped {
+                self.cycles = 0;
+            }
+            let playing = matches!(master.state, LoopState::Playing | LoopState::Overdubbing);
+            let master_len = master.buffer.len() as f64;
+            for track in synced.iter_mut() {
+                let turn = (self.cycles % track.multiple as u64) as f64;
+                let phase = turn * master_len + master.position;
+                let sample = match playing {
+                    true => track.tick_synced(*input, phase, speed),
+                    false => 0.0,
+                };
+                track.dry.push(sample);
+            }
+            let (master_sample, wrapped) = master.tick_master(*input, max_len, speed);
+            if wrapped {
+                self.cycles += 1;
+            }
+            master.dry.push(master_sample);
+        }
+
+        self.mixed.clear();
+        self.mixed.resize(input.len(), [0.0; 2]);
+        for track in &mut self.tracks {
+            // El plugin sigue procesando en silencio, así las colas (p. ej. de un reverb) suenan
+            let wet = match &mut track.plugin {
+                Some(plugin) => {
+                    track.wet.clear();
+                    track.wet.resize(track.dry.len(), [0.0; 2]);
+                    plugin.process(&track.dry, &mut track.wet, self.sample_rate)
+                }
+                None => false,
+            };
+            for (idx, mixed) in self.mixed.iter_mut().enumerate() {
+                let [left, right] = track.mix.next(step);
+                let frame = match wet {
+                    true => track.wet[idx],
+                    false => [track.dry[idx]; 2],
+                };
+                mixed[0] += frame[0] * left;
+                mixed[1] += frame[1] * right;
+            }
+        }
+        for (out, mixed) in main.iter_mut().zip(&self.mixed) {
+            out[0] += mixed[0];
+            out[1] += mixed[1];
+            self.meter.add((mixed[0] + mixed[1]) * 0.5);
+        }
+    }
+
+    // Al arrancar el motor, por si cambió la frecuencia
+    pub(crate) fn activate_plugins(&mut self, sample_rate: u32) {
+        for plugin in self
+            .tracks
+            .iter_mut()
+            .filter_map(|track| track.plugin.as_mut())
+        {
+            if let Err(err) = plugin.activate(sample_rate) {
+                warn!("{err}");
+            }
+        }
+    }
+
+    pub(crate) fn plugin_slot(&mut self, track: usize) -> Result<&mut Option<ClapPlugin>, String> {
+        self.track(track)?;
+        Ok(&mut self.tracks[track].plugin)
+    }
+
+    pub(crate) fn plugins(&self) -> impl Iterator<Item = (usize, &ClapPlugin)> {
+        self.tracks
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, track)| Some((idx, track.plugin.as_ref()?)))
+    }
+
+    // Para soltarlos fuera del lock
+    pub(crate) fn take_plugins(&mut self) -> Vec<ClapPlugin> {
+        self.tracks
+            .iter_mut()
+            .filter_map(|track| track.plugin.take())
+            .collect()
+    }
+}
+
+// Con cuantización y el transporte en marcha la acción queda esperando el próximo tiempo o
+// compás; si no, se hace ya
+fn apply(
+    audio: &AudioState,
+    track: Option<usize>,
+    action: LoopAction,
+) -> Result<LoopStatus, String> {
+    let track = track.unwrap_or(0);
+    let running = audio.shared().lock_transport()?.running();
+    // El callback de audio también toma el lock del looper: la memoria de la toma se reserva
+    // afuera, y si el looper cambió entretanto se vuelve a calcular
+    let (mut looper, reserved) = loop {
+        let reserve = {
+            let looper = audio.shared().lock_looper()?;
+            looper.check(track, action)?;
+            looper.reserve(track, action)
+        };
+        let reserved = reserve.map(Reserve::allocate);
+        let looper = audio.shared().lock_looper()?;
+        if looper.reserve(track, action) == reserve {
+            break (looper, reserved);
+        }
+    };
+    looper.check(track, action)?;
+    let replaced = looper.prepare(track, action, reserved);
+    let quantized = running && looper.quantize != LoopQuantize::Off;
+    let loop_track = &mut looper.tracks[track];
+    if quantized {
+        loop_track.pending = Some(action);
+    } else {
+        loop_track.pending = None;
+        loop_track.run(action)?;
+    }
+    let status = looper.status(track)?;
+    // El buffer reemplazado se libera sin el lock
+    drop(looper);
+    drop(replaced);
+    info!(
+        "Loop {track} {action:?}: {:?}, {} ms",
+        status.state, status.length_ms
+    );
+    Ok(status)
+}
+
+// En un loop vacío empieza a grabar; mientras graba cierra la toma y la reproduce. Sin
+// `track` los comandos van al maestro.
+#[tauri::command]
+pub fn loop_record(
+    audio: State<'_, AudioState>,
+    track: Option<usize>,
+) -> Result<LoopStatus, String> {
+    apply(&audio, track, LoopAction::Record)
+}
+
+// Alterna entre overdub y reproducción
+#[tauri::command]
+pub fn loop_overdub(
+    audio: State<'_, AudioState>,
+    track: Option<usize>,
+) -> Result<LoopStatus, String> {
+    apply(&audio, track, LoopAction::Overdub)
+}
+
+#[tauri::command]
+pub fn loop_play(audio: State<'_, AudioState>, track: Option<usize>) -> Result<LoopStatus, String> {
+    apply(&audio, track, LoopAction::Play)
+}
+
+#[tauri::command]
+pub fn loop_stop(audio: State<'_, AudioState>, track: Option<usize>) -> Result<LoopStatus, String> {
+    apply(&audio, track, LoopAction::Stop)
+}
+
+#[tauri::command]
+pub fn loop_clear(
+    audio: State<'_, AudioState>,
+    track: Option<usize>,
+) -> Result<LoopStatus, String> {
+    clear(&audio, track.unwrap_or(0))
+}
+
+fn clear(audio: &AudioState, track: usize) -> Result<LoopStatus, String> {
+    let mut looper = audio.shared().lock_looper()?;
+    looper.track(track)?;
+    looper.clear(track);
+    info!("Loop {track} borrado");
+    looper.status(track)
+}
+
+// Un nivel: deshacer dos veces vuelve a poner el overdub
+#[tauri::command]
+pub fn loop_undo(audio: State<'_, AudioState>, track: Option<usize>) -> Result<LoopStatus, String> {
+    undo(&audio, track.unwrap_or(0))
+}
+
+fn undo(audio: &AudioState, track: usize) -> Result<LoopStatus, String> {
+    let mut looper = audio.shared().lock_looper()?;
+    looper.undo(track)?;
+    info!("Overdub del loop {track} deshecho");
+    looper.status(track)
+}
+
+fn set_mix(
+    audio: &AudioState,
+    track: usize,
+    change: impl FnOnce(&mut TrackMix),
+) -> Result<LoopStatus, String> {
+    let mut looper = audio.shared().lock_looper()?;
+    let loop_track = looper
+        .tracks
+        .get_mut(track)
+        .ok_or_else(|| format!("La pista de loop {track} no existe"))?;
+    change(&mut loop_track.mix);
+    looper.status(track)
+}
+
+// De 0 a 2; los cambios de la mezcla van en una rampa corta en el callback de audio
+#[tauri::command]
+pub fn track_set_volume(
+    audio: State<'_, AudioState>,
+    track: usize,
+    volume: f32,
+) -> Result<LoopStatus, String> {
+    if !(0.0..=MAX_TRACK_VOLUME).contains(&volume) {
+        return Err(format!(
+            "El volumen de la pista va de 0 a {MAX_TRACK_VOLUME}"
+        ));
+    }
+    set_mix(&audio, track, |mix| mix.volume = volume)
+}
+
+// De -1 (izquierda) a 1 (derecha)
+#[tauri::command]
+pub fn track_set_pan(
+    audio: State<'_, AudioState>,
+    track: usize,
+    pan: f32,
+) -> Result<LoopStatus, String> {
+    if !(-1.0..=1.0).contains(&pan) {
+        return Err("El paneo va de -1 a 1".to_string());
+    }
+    set_mix(&audio, track, |mix| mix.pan = pan)
+}
+
+// Una pista muteada sigue avanzando y grabando; solo deja de sonar
+#[tauri::command]
+pub fn track_mute(
+    audio: State<'_, AudioState>,
+    track: usize,
+    muted: bool,
+) -> Result<LoopStatus, String> {
+    set_mix(&audio, track, |mix| mix.muted = muted)
+}
+
+// Elige la pista que manejan los pedales sin pista fija
+#[tauri::command]
+pub fn loop_select_track(audio: State<'_, AudioState>, track: usize) -> Result<LoopStatus, String> {
+    let mut looper = audio.shared().lock_looper()?;
+    looper.track(track)?;
+    looper.selected = track;
+    looper.status(track)
+}
+
+// Acción de un pedal mapeado por MIDI; sin `track` va a la pista elegida
+pub(crate) fn pedal(
+    audio: &AudioState,
+    action: PedalAction,
+    track: Option<usize>,
+) -> Result<(), String> {
+    let (track, state, count) = {
+        let looper = audio.shared().lock_looper()?;
+        let track = track.unwrap_or(looper.selected);
+        (track, looper.track(track)?.state, looper.tracks.len())
+    };
+    match action {
+        PedalAction::RecordOverdub => {
+            let action = match state {
+                LoopState::Empty => LoopAction::Record,
+                _ => LoopAction::Overdub,
+            };
+            apply(audio, Some(track), action)?;
+        }
+        PedalAction::PlayStop => {
+            let action = match state {
+                LoopState::Empty | LoopState::Stopped => LoopAction::Play,
+                _ => LoopAction::Stop,
+            };
+            apply(audio, Some(track), action)?;
+        }
+        PedalAction::Undo => {
+            undo(audio, track)?;
+        }
+        PedalAction::NextTrack => {
+            let next = (track + 1) % count;
+            audio.shared().lock_looper()?.selected = next;
+            info!("Pista de loop {next} elegida");
+        }
+        PedalAction::Clear => {
+            clear(audio, track)?;
+        }
+    }
+    Ok(())
+}
+
+// Agrega una pista de `multiple` (1, 2 o 4) vueltas del maestro
+#[tauri::command]
+pub fn loop_add_track(audio: State<'_, AudioState>, multiple: u32) -> Result<LoopStatus, String> {
+    if !MULTIPLES.contains(&multiple) {
+        return Err(format!(
+            "Una pista dura 1, 2 o 4 vueltas del maestro, no {multiple}"
+        ));
+    }
+    let mut looper = audio.shared().lock_looper()?;
+    if looper.tracks.len() == MAX_TRACKS {
+        return Err(format!("El looper admite hasta {MAX_TRACKS} pistas"));
+    }
+    let latency = looper.latency;
+    looper.tracks.push(LoopTrack::new(multiple, latency));
+    let track = looper.tracks.len() - 1;
+    info!("Pista de loop {track} agregada ({multiple}x)");
+    looper.status(track)
+}
+
+// Las pistas siguientes bajan un número
+#[tauri::command]
+pub fn loop_remove_track(audio: State<'_, AudioState>, track: usize) -> Result<(), String> {
+    if track == 0 {
+        return Err("La pista maestra no se puede quitar".to_string());
+    }
+    let removed = {
+        let mut looper = audio.shared().lock_looper()?;
+        looper.track(track)?;
+        if looper.selected >= track {
+            looper.selected -= 1;
+        }
+        looper.tracks.remove(track)
+    };
+    // El buffer se suelta fuera del lock
+    drop(removed);
+    Ok(())
+}
+
+// Mono, en coma flotante de 32 bits a la frecuencia del motor
+fn write_wav(path: &Path, samples: &[f32], sample_rate: u32) -> Result<(), String> {
+    let error = |e: hound::Error| format!("No se pudo escribir {}: {e}", path.display());
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(path, spec).map_err(error)?;
+    for sample in samples {
+        writer.write_sample(*sample).map_err(error)?;
+    }
+    writer.finalize().map_err(error)
+}
+
+#[tauri::command]
+pub fn loop_export(
+    audio: State<'_, AudioState>,
+    track: Option<usize>,
+    path: String,
+) -> Result<(), String> {
+    let track = track.unwrap_or(0);
+    let recording = audio.shared().lock_looper()?.recording(track)?;
+    let Some((samples, sample_rate)) = recording else {
+        return Err(format!(
+            "La pista de loop {track} no tiene una toma cerrada"
+        ));
+    };
+    write_wav(Path::new(&path), &samples, sample_rate)?;
+    info!("Loop {track} exportado a {path}");
+    Ok(())
+}
+
+// Escribe `loop_<pista>.wav` en `dir` por cada pista con grabación y devuelve los archivos
+#[tauri::command]
+pub fn session_export_all(
+    audio: State<'_, AudioState>,
+    dir: String,
+) -> Result<Vec<String>, String> {
+    let recordings = {
+        let looper = audio.shared().lock_looper()?;
+        (0..looper.tracks.len())
+            .map(|track| Ok((track, looper.recording(track)?)))
+            .collect::<Result<Vec<_>, String>>()?
+    };
+    let dir = Path::new(&dir);
+    fs::create_dir_all(dir).map_err(|e| format!("No se pudo crear {}: {e}", dir.display()))?;
+
+    let mut files = Vec::new();
+    for (track, recording) in recordings {
+        let Some((samples, sample_rate)) = recording else {
+            continue;
+        };
+        let path = dir.join(format!("loop_{track}.wav"));
+        write_wav(&path, &samples, sample_rate)?;
+        files.push(path.to_string_lossy().into_owned());
+    }
+    info!("{} loops exportados a {}", files.len(), dir.display());
+    Ok(files)
+}
+
+#[tauri::command]
+pub fn loop_tracks(audio: State<'_, AudioState>) -> Result<Vec<LoopStatus>, String> {
+    Ok(audio.shared().lock_looper()?.statuses())
+}
+
+#[tauri::command]
+pub fn loop_set_quantize(
+    audio: State<'_, AudioState>,
+    quantize: LoopQuantize,
+) -> Result<Vec<LoopStatus>, String> {
+    let mut looper = audio.shared().lock_looper()?;
+    looper.quantize = quantize;
+    if quantize == LoopQuantize::Off {
+        looper.run_pending();
+    }
+    Ok(looper.statuses())
+}
+
+#[tauri::command]
+pub fn loop_set_stretch(
+    audio: State<'_, AudioState>,
+    enabled: bool,
+) -> Result<Vec<LoopStatus>, String> {
+    let mut looper = audio.shared().lock_looper()?;
+    looper.stretch = enabled;
+    info!(
+        "Estiramiento de loops {}",
+        if enabled { "activado" } else { "apagado" }
+    );
+    Ok(looper.statuses())
+}
+
+#[tauri::command]
+pub fn loop_status(
+    audio: State<'_, AudioState>,
+    track: Option<usize>,
+) -> Result<LoopStatus, String> {
+    audio.shared().lock_looper()?.status(track.unwrap_or(0))
+}
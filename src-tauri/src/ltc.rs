@@ -0,0 +1,247 @@
+use crate::audio::AudioState;
+use crate::mtc::{self, MtcRate, Timecode};
+use crate::timeline;
+use log::{error, info};
+use serde::Serialize;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub const LTC_EVENT: &str = "audio://timecode";
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+// 80 bits por cuadro: de 1920 bits/s a 24 fps a 2400 bits/s a 30 fps
+const BITS_PER_FRAME: u32 = 80;
+const MAX_BIT_RATE: u32 = 30 * BITS_PER_FRAME;
+// Bits 64-79 de cada cuadro, en el orden en que llegan (el primero en el bit 0)
+const SYNC_WORD: u16 = 0xBFFC;
+// Histéresis del cruce por cero, para que el ruido no parezca una transición
+const THRESHOLD: f32 = 0.02;
+
+#[derive(Clone, Serialize)]
+pub struct LtcStatus {
+    enabled: bool,
+    channel: u16,
+    timecode: Option<Timecode>,
+    rate: MtcRate,
+    // Cuadros decodificados desde que se activó
+    frames: u64,
+}
+
+// Decodifica el código bifase de LTC en el callback de entrada: mide las muestras entre
+// transiciones, una larga es un 0 y dos cortas un 1. No reserva memoria.
+pub(crate) struct LtcDecoder {
+    enabled: bool,
+    channel: u16,
+    sample_rate: u32,
+    positive: bool,
+    since_edge: u32,
+    // Muestras por bit, ajustadas a lo que llega (la cinta puede ir más lenta o más rápida)
+    period: f32,
+    half_pending: bool,
+    bits: u128,
+    // Frecuencia deducida del último cuadro de cada segundo
+    rate: MtcRate,
+    max_frame: u8,
+    timecode: Option<Timecode>,
+    frames: u64,
+}
+
+impl Default for LtcDecoder {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            channel: 0,
+            sample_rate: 0,
+            positive: false,
+            since_edge: 0,
+            period: 0.0,
+            half_pending: false,
+            bits: 0,
+            rate: MtcRate::default(),
+            max_frame: 0,
+            timecode: None,
+            frames: 0,
+        }
+    }
+}
+
+impl LtcDecoder {
+    fn status(&self) -> LtcStatus {
+        LtcStatus {
+            enabled: self.enabled,
+            channel: self.channel,
+            timecode: self.timecode,
+            rate: self.rate,
+            frames: self.frames,
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Self {
+            enabled: self.enabled,
+            channel: self.channel,
+            ..Self::default()
+        };
+    }
+
+    fn nominal_period(&self) -> f32 {
+        self.sample_rate as f32 / MAX_BIT_RATE as f32
+    }
+
+    // Lo llama el callback de entrada con el bloque entrelazado de todos los canales
+    pub(crate) fn add_input(&mut self, data: &[f32], channels: usize, sample_rate: u32) {
+        let channel = self.channel as usize;
+        if !self.enabled || channel >= channels {
+            return;
+        }
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            self.period = self.nominal_period();
+        }
+        for frame in data.chunks(channels) {
+            let sample = frame[channel];
+            self.since_edge = self.since_edge.saturating_add(1);
+            let crossed = match self.positive {
+                true => sample < -THRESHOLD,
+                false => sample > THRESHOLD,
+            };
+            if crossed {
+                self.positive = !self.positive;
+                let interval = self.since_edge;
+                self.since_edge = 0;
+                self.edge(interval as f32);
+            }
+        }
+    }
+
+    fn edge(&mut self, interval: f32) {
+        let nominal = self.nominal_period();
+        if interval < self.period * 0.75 {
+            // Media celda: el segundo medio completa un 1
+            if self.half_pending {
+                self.push(true);
+            }
+            self.half_pending = !self.half_pending;
+            self.adapt(interval * 2.0, nominal);
+        } else if interval < self.period * 1.5 {
+            self.half_pending = false;
+            self.push(false);
+            self.adapt(interval, nominal);
+        } else {
+            // Silencio o señal que no es LTC: se empieza de nuevo
+            self.half_pending = false;
+            self.bits = 0;
+            self.period = nominal;
+        }
+    }
+
+    fn adapt(&mut self, period: f32, nominal: f32) {
+        self.period = (self.period * 0.9 + period * 0.1).clamp(nominal * 0.7, nominal * 1.6);
+    }
+
+    fn push(&mut self, bit: bool) {
+        self.bits = (self.bits >> 1) | (u128::from(bit) << (BITS_PER_FRAME - 1));
+        if (self.bits >> 64) as u16 == SYNC_WORD {
+            self.decode();
+        }
+    }
+
+    fn field(&self, start: u32, len: u32) -> u8 {
+        ((self.bits >> start) as u32 & ((1 << len) - 1)) as u8
+    }
+
+    fn decode(&mut self) {
+        let frames = self.field(0, 4) + 10 * self.field(8, 2);
+        let drop_frame = self.field(10, 1) == 1;
+        let timecode = Timecode::new(
+            self.field(48, 4) + 10 * self.field(56, 2),
+            self.field(32, 4) + 10 * self.field(40, 3),
+            self.field(16, 4) + 10 * self.field(24, 3),
+            frames,
+        );
+        if timecode.validate().is_err() {
+            return;
+        }
+
+        // Al empezar un segundo, el cuadro anterior fue el último del segundo
+        if drop_frame {
+            self.rate = MtcRate::Fps2997Drop;
+        } else if frames == 0 && self.max_frame >= 23 {
+            self.rate = match self.max_frame {
+                23 => MtcRate::Fps24,
+                24 => MtcRate::Fps25,
+                _ => MtcRate::Fps30,
+            };
+        }
+        self.max_frame = match frames {
+            0 => 0,
+            _ => self.max_frame.max(frames),
+        };
+        self.timecode = Some(timecode);
+        self.frames += 1;
+    }
+}
+
+// Sigue los cuadros decodificados y dispara las cues con timecode igual que el MIDI timecode
+pub fn start_ltc_follow(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    thread::spawn(move || {
+        let mut last_frames = 0;
+        let mut position: Option<u32> = None;
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let audio = app_handle.state::<AudioState>();
+            let status = match audio.shared().lock_ltc() {
+                Ok(decoder) => decoder.status(),
+                Err(err) => {
+                    error!("{err}");
+                    continue;
+                }
+            };
+            if !status.enabled || status.frames == last_frames {
+                continue;
+            }
+            last_frames = status.frames;
+            let Some(timecode) = status.timecode else {
+                continue;
+            };
+
+            let frames = timecode.to_frames(status.rate);
+            let located = mtc::is_locate(position, frames, status.rate);
+            mtc::chase_cues(&app_handle, position, frames, located, status.rate);
+            timeline::follow_timecode(&app_handle, frames, status.rate, located);
+            position = Some(frames);
+            if let Err(err) = app_handle.emit(LTC_EVENT, status) {
+                error!("No se pudo emitir el timecode LTC: {err}");
+            }
+        }
+    });
+}
+
+// `channel` es el canal de la entrada de audio (desde 0), no el del motor: el LTC suele
+// llegar por un canal aparte
+#[tauri::command]
+pub fn ltc_configure(
+    audio: State<'_, AudioState>,
+    enabled: bool,
+    channel: Option<u16>,
+) -> Result<LtcStatus, String> {
+    let mut decoder = audio.shared().lock_ltc()?;
+    if let Some(channel) = channel {
+        decoder.channel = channel;
+    }
+    decoder.enabled = enabled;
+    decoder.reset();
+    if enabled {
+        info!(
+            "Leyendo LTC del canal {} de la entrada",
+            decoder.channel + 1
+        );
+    }
+    Ok(decoder.status())
+}
+
+#[tauri::command]
+pub fn ltc_status(audio: State<'_, AudioState>) -> Result<LtcStatus, String> {
+    Ok(audio.shared().lock_ltc()?.status())
+}
@@ -0,0 +1,324 @@
+use crate::color::{self, ColorValue, Rgb};
+use crate::dmx::DmxState;
+use crate::fixture::{Attribute, FixtureState};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::f64::consts::TAU;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::{AppHandle, State};
+
+const MAX_SPEED_HZ: f64 = 25.0;
+// Fracción de pixels encendidos en cada destello de `Sparkle`
+const SPARKLE_DENSITY: f64 = 0.15;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatrixPattern {
+    /// Onda de brillo que recorre la matriz de izquierda a derecha
+    Wave,
+    /// Una columna encendida que avanza
+    Chase,
+    /// Pixels al azar que destellan
+    Sparkle,
+    /// Arcoíris que se desplaza (ignora el color)
+    Scroll,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MatrixEffectParams {
+    pattern: MatrixPattern,
+    // Ciclos por segundo del patrón
+    speed_hz: f64,
+    color: ColorValue,
+}
+
+// Grilla de fixtures en orden de filas; `None` en las celdas vacías
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MatrixLayout {
+    name: String,
+    width: u16,
+    height: u16,
+    pixels: Vec<Option<u32>>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct MatrixInfo {
+    #[serde(flatten)]
+    layout: MatrixLayout,
+    effect: Option<MatrixEffectParams>,
+}
+
+struct MatrixPixel {
+    x: u16,
+    index: usize,
+    attributes: Vec<Attribute>,
+    channels: Vec<u16>,
+}
+
+// Parte de una matriz que cae en un universo; la dibuja el hilo de escritura en cada frame
+pub(crate) struct MatrixRender {
+    pattern: MatrixPattern,
+    speed_hz: f64,
+    color: Rgb,
+    width: u16,
+    pixels: Vec<MatrixPixel>,
+    // El mismo instante en todos los universos de la matriz, para que vayan sincronizados
+    started: Instant,
+}
+
+impl MatrixRender {
+    fn pixel_color(&self, pixel: &MatrixPixel, elapsed: f64) -> Rgb {
+        let width = self.width.max(1) as f64;
+        let position = pixel.x as f64 / width;
+        let cycles = elapsed * self.speed_hz;
+
+        match self.pattern {
+            MatrixPattern::Wave => {
+                let phase = position - cycles;
+                self.color.scaled(0.5 - 0.5 * (phase * TAU).cos())
+            }
+            MatrixPattern::Chase => {
+                let column = (cycles.rem_euclid(1.0) * width) as u16;
+                if pixel.x == column {
+                    self.color
+                } else {
+                    Rgb::BLACK
+                }
+            }
+            MatrixPattern::Sparkle => {
+                if sparkle(pixel.index, cycles.floor() as u64) < SPARKLE_DENSITY {
+                    self.color
+                } else {
+                    Rgb::BLACK
+                }
+            }
+            MatrixPattern::Scroll => {
+                let hue = (position + cycles).rem_euclid(1.0) * 360.0;
+                color::hsv_to_rgb(hue, 1.0, 1.0).unwrap_or(Rgb::BLACK)
+            }
+        }
+    }
+
+    pub(crate) fn render(&self, now: Instant, levels: &mut Vec<Option<u8>>) {
+        let elapsed = now.saturating_duration_since(self.started).as_secs_f64();
+        levels.clear();
+        levels.resize(512, None);
+
+        for pixel in &self.pixels {
+            let rgb = self.pixel_color(pixel, elapsed);
+            let Ok(values) = color::emitter_levels(rgb, &pixel.attributes) else {
+                continue;
+            };
+            for (attribute, channel) in pixel.attributes.iter().zip(&pixel.channels) {
+                levels[*channel as usize - 1] = values.get(attribute).copied();
+            }
+        }
+    }
+}
+
+// Valor pseudoaleatorio estable entre 0 y 1 para el pixel en ese ciclo: todos los
+// universos calculan lo mismo sin compartir estado
+fn sparkle(index: usize, cycle: u64) -> f64 {
+    let mut hasher = DefaultHasher::new();
+    (index, cycle).hash(&mut hasher);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[derive(Default)]
+pub struct MatrixState {
+    matrices: Mutex<BTreeMap<String, MatrixInfo>>,
+}
+
+impl MatrixState {
+    fn lock_matrices(
+        &self,
+    ) -> Result<std::sync::MutexGuard<'_, BTreeMap<String, MatrixInfo>>, String> {
+        self.matrices
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear las matrices: {e}"))
+    }
+}
+
+fn validate_effect(effect: &MatrixEffectParams) -> Result<Rgb, String> {
+    if !(effect.speed_hz > 0.0 && effect.speed_hz <= MAX_SPEED_HZ) {
+        return Err(format!(
+            "La velocidad del efecto debe estar entre 0 y {MAX_SPEED_HZ} Hz"
+        ));
+    }
+    effect.color.to_rgb()
+}
+
+fn remove_renders(dmx: &DmxState, name: &str) {
+    dmx.for_each_universe(|_, shared| {
+        if let Ok(mut matrices) = shared.matrices.lock() {
+            matrices.remove(name);
+        }
+    });
+}
+
+// Crea la matriz o reemplaza su layout (detiene el efecto que tuviera)
+#[tauri::command]
+pub fn matrix_create(
+    matrices: State<'_, MatrixState>,
+    fixtures: State<'_, FixtureState>,
+    dmx: State<'_, DmxState>,
+    layout: MatrixLayout,
+) -> Result<(), String> {
+    if layout.width == 0 || layout.height == 0 {
+        return Err("La matriz debe tener al menos un pixel".to_string());
+    }
+    if layout.pixels.len() != layout.width as usize * layout.height as usize {
+        return Err(format!(
+            "La matriz de {}x{} necesita {} pixels",
+            layout.width,
+            layout.height,
+            layout.width as usize * layout.height as usize
+        ));
+    }
+    for id in layout.pixels.iter().flatten() {
+        fixtures.color_channels(*id)?;
+    }
+
+    remove_renders(&dmx, &layout.name);
+    matrices.lock_matrices()?.insert(
+        layout.name.clone(),
+        MatrixInfo {
+            layout,
+            effect: None,
+        },
+    );
+    Ok(())
+}
+
+#[tauri::command]
+pub fn matrix_delete(
+    matrices: State<'_, MatrixState>,
+    dmx: State<'_, DmxState>,
+    name: String,
+) -> Result<(), String> {
+    matrices
+        .lock_matrices()?
+        .remove(&name)
+        .ok_or_else(|| format!("La matriz \"{name}\" no existe"))?;
+    remove_renders(&dmx, &name);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn matrix_list(matrices: State<'_, MatrixState>) -> Result<Vec<MatrixInfo>, String> {
+    Ok(matrices.lock_matrices()?.values().cloned().collect())
+}
+
+// Arranca (o reemplaza) el efecto de la matriz. Los pixels se resuelven contra el patch actual.
+#[tauri::command]
+pub fn matrix_effect_start(
+    app_handle: AppHandle,
+    matrices: State<'_, MatrixState>,
+    fixtures: State<'_, FixtureState>,
+    dmx: State<'_, DmxState>,
+    name: String,
+    effect: MatrixEffectParams,
+) -> Result<(), String> {
+    let color = validate_effect(&effect)?;
+    let mut stored = matrices.lock_matrices()?;
+    let matrix = stored
+        .get_mut(&name)
+        .ok_or_else(|| format!("La matriz \"{name}\" no existe"))?;
+
+    let mut by_universe: BTreeMap<u16, Vec<MatrixPixel>> = BTreeMap::new();
+    for (index, id) in matrix.layout.pixels.iter().enumerate() {
+        let Some(id) = id else {
+            continue;
+        };
+        let (universe, channels) = fixtures.color_channels(*id)?;
+        let (attributes, channels) = channels.into_iter().unzip();
+        by_universe.entry(universe).or_default().push(MatrixPixel {
+            x: (index % matrix.layout.width as usize) as u16,
+            index,
+            attributes,
+            channels,
+        });
+    }
+
+    remove_renders(&dmx, &name);
+    let started = Instant::now();
+    for (universe, pixels) in by_universe {
+        let render = MatrixRender {
+            pattern: effect.pattern,
+            speed_hz: effect.speed_hz,
+            color,
+            width: matrix.layout.width,
+            pixels,
+            started,
+        };
+        dmx.with_universe(app_handle.clone(), universe, |shared| {
+            shared
+                .matrices
+                .lock()
+                .map_err(|e| format!("No se pudo bloquear las matrices: {e}"))?
+                .insert(name.clone(), render);
+            Ok(())
+        })?;
+    }
+
+    info!("Efecto {:?} en la matriz \"{name}\"", effect.pattern);
+    matrix.effect = Some(effect);
+    Ok(())
+}
+
+// Cambia velocidad y/o color del efecto en marcha sin reiniciarlo
+#[tauri::command]
+pub fn matrix_effect_update(
+    matrices: State<'_, MatrixState>,
+    dmx: State<'_, DmxState>,
+    name: String,
+    speed_hz: Option<f64>,
+    color: Option<ColorValue>,
+) -> Result<(), String> {
+    let mut stored = matrices.lock_matrices()?;
+    let effect = stored
+        .get_mut(&name)
+        .ok_or_else(|| format!("La matriz \"{name}\" no existe"))?
+        .effect
+        .as_mut()
+        .ok_or_else(|| format!("La matriz \"{name}\" no tiene un efecto en marcha"))?;
+
+    let mut updated = effect.clone();
+    if let Some(speed_hz) = speed_hz {
+        updated.speed_hz = speed_hz;
+    }
+    if let Some(color) = color {
+        updated.color = color;
+    }
+    let rgb = validate_effect(&updated)?;
+
+    dmx.for_each_universe(|_, shared| {
+        if let Ok(mut renders) = shared.matrices.lock() {
+            if let Some(render) = renders.get_mut(&name) {
+                render.speed_hz = updated.speed_hz;
+                render.color = rgb;
+            }
+        }
+    });
+    *effect = updated;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn matrix_effect_stop(
+    matrices: State<'_, MatrixState>,
+    dmx: State<'_, DmxState>,
+    name: String,
+) -> Result<(), String> {
+    matrices
+        .lock_matrices()?
+        .get_mut(&name)
+        .ok_or_else(|| format!("La matriz \"{name}\" no existe"))?
+        .effect = None;
+    remove_renders(&dmx, &name);
+    Ok(())
+}
@@ -0,0 +1,289 @@
+use crate::http_api::HttpApiState;
+use crate::osc::OscState;
+use crate::websocket::WsState;
+use log::{error, info, warn};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+
+const WS_SERVICE: &str = "_liveloop-ws._tcp.local.";
+const HTTP_SERVICE: &str = "_http._tcp.local.";
+const OSC_SERVICE: &str = "_osc._udp.local.";
+// En el TXT de cada servicio, para reconocer las sesiones entre los demás equipos que
+// anuncian HTTP u OSC
+const APP_ID: &str = "liveloop-studio";
+const DEFAULT_NAME: &str = "LiveLoop Studio";
+// Cada cuánto se revisa qué servidores están en marcha
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const DEFAULT_DISCOVER_MS: u64 = 1500;
+const MAX_DISCOVER_MS: u64 = 10_000;
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+pub struct MdnsService {
+    service_type: &'static str,
+    port: u16,
+}
+
+#[derive(Clone, Serialize)]
+pub struct MdnsStatus {
+    advertising: bool,
+    name: Option<String>,
+    // Lo que se anuncia: los servidores en marcha
+    services: Vec<MdnsService>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct DiscoveredSession {
+    name: String,
+    service_type: String,
+    host: String,
+    addresses: Vec<String>,
+    port: u16,
+    version: Option<String>,
+}
+
+struct Advertiser {
+    name: String,
+    stop: Sender<()>,
+    handle: thread::JoinHandle<()>,
+}
+
+#[derive(Default)]
+pub struct MdnsState {
+    advertiser: Mutex<Option<Advertiser>>,
+}
+
+impl MdnsState {
+    fn lock_advertiser(&self) -> Result<std::sync::MutexGuard<'_, Option<Advertiser>>, String> {
+        self.advertiser
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el anuncio mDNS: {e}"))
+    }
+}
+
+fn services(app_handle: &AppHandle) -> Vec<MdnsService> {
+    [
+        (WS_SERVICE, app_handle.state::<WsState>().port()),
+        (HTTP_SERVICE, app_handle.state::<HttpApiState>().port()),
+        (OSC_SERVICE, app_handle.state::<OscState>().port()),
+    ]
+    .into_iter()
+    .filter_map(|(service_type, port)| {
+        Some(MdnsService {
+            service_type,
+            port: port?,
+        })
+    })
+    .collect()
+}
+
+// El nombre del equipo en `.local`, armado con el de la sesión
+fn host_name(name: &str) -> String {
+    let label = name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_lowercase(),
+            false => '-',
+        })
+        .collect::<String>();
+    format!("{}.local.", label.trim_matches('-'))
+}
+
+fn register(daemon: &ServiceDaemon, name: &str, service: MdnsService) -> Result<String, String> {
+    let mut properties = HashMap::new();
+    properties.insert("app".to_string(), APP_ID.to_string());
+    properties.insert("version".to_string(), env!("CARGO_PKG_VERSION").to_string());
+    if service.service_type == HTTP_SERVICE {
+        properties.insert("path".to_string(), "/api".to_string());
+    }
+    let info = ServiceInfo::new(
+        service.service_type,
+        name,
+        &host_name(name),
+        "",
+        service.port,
+        properties,
+    )
+    .map_err(|e| format!("Servicio mDNS inválido ({}): {e}", service.service_type))?
+    .enable_addr_auto();
+    let fullname = info.get_fullname().to_string();
+    daemon
+        .register(info)
+        .map_err(|e| format!("No se pudo anunciar {}: {e}", service.service_type))?;
+    info!("Anunciado por mDNS: {fullname} (puerto {})", service.port);
+    Ok(fullname)
+}
+
+// Sigue a los servidores: uno que arranca se anuncia y uno que se detiene se retira
+fn run_advertiser(
+    app_handle: AppHandle,
+    daemon: ServiceDaemon,
+    name: String,
+    rx: mpsc::Receiver<()>,
+) {
+    let mut registered: Vec<(MdnsService, String)> = Vec::new();
+    loop {
+        let wanted = services(&app_handle);
+        registered.retain(|(service, fullname)| {
+            if wanted.contains(service) {
+                return true;
+            }
+            if let Err(err) = daemon.unregister(fullname) {
+                warn!("No se pudo retirar {fullname} de mDNS: {err}");
+            }
+            false
+        });
+        for service in wanted {
+            if registered.iter().any(|(current, _)| *current == service) {
+                continue;
+            }
+            match register(&daemon, &name, service) {
+                Ok(fullname) => registered.push((service, fullname)),
+                Err(err) => error!("{err}"),
+            }
+        }
+
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Err(RecvTimeoutError::Timeout) => {}
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    for (_, fullname) in registered {
+        let _ = daemon.unregister(&fullname);
+    }
+    if let Err(err) = daemon.shutdown() {
+        warn!("No se pudo cerrar mDNS: {err}");
+    }
+    info!("Anuncio mDNS detenido");
+}
+
+fn stop_advertiser(advertiser: Advertiser) {
+    let _ = advertiser.stop.send(());
+    if advertiser.handle.join().is_err() {
+        error!("El hilo del anuncio mDNS terminó con pánico");
+    }
+}
+
+// Anuncia el WebSocket, la API HTTP y OSC mientras estén en marcha, para que las apps de
+// control y otras instancias encuentren la sesión sin escribir la IP
+#[tauri::command]
+pub fn mdns_advertise_start(
+    app_handle: AppHandle,
+    state: State<'_, MdnsState>,
+    name: Option<String>,
+) -> Result<MdnsStatus, String> {
+    let name = name.unwrap_or_else(|| DEFAULT_NAME.to_string());
+    if name.trim().is_empty() {
+        return Err("El nombre de la sesión no puede estar vacío".to_string());
+    }
+
+    let mut advertiser = state.lock_advertiser()?;
+    if let Some(previous) = advertiser.take() {
+        stop_advertiser(previous);
+    }
+    let daemon = ServiceDaemon::new().map_err(|e| format!("No se pudo iniciar mDNS: {e}"))?;
+    let (tx, rx) = mpsc::channel();
+    let handle = {
+        let (app_handle, name) = (app_handle.clone(), name.clone());
+        thread::spawn(move || run_advertiser(app_handle, daemon, name, rx))
+    };
+    *advertiser = Some(Advertiser {
+        name: name.clone(),
+        stop: tx,
+        handle,
+    });
+    Ok(MdnsStatus {
+        advertising: true,
+        name: Some(name),
+        services: services(&app_handle),
+    })
+}
+
+#[tauri::command]
+pub fn mdns_advertise_stop(state: State<'_, MdnsState>) -> Result<(), String> {
+    let advertiser = state.lock_advertiser()?.take();
+    if let Some(advertiser) = advertiser {
+        stop_advertiser(advertiser);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn mdns_status(
+    app_handle: AppHandle,
+    state: State<'_, MdnsState>,
+) -> Result<MdnsStatus, String> {
+    let advertiser = state.lock_advertiser()?;
+    Ok(MdnsStatus {
+        advertising: advertiser.is_some(),
+        name: advertiser
+            .as_ref()
+            .map(|advertiser| advertiser.name.clone()),
+        services: match advertiser.is_some() {
+            true => services(&app_handle),
+            false => Vec::new(),
+        },
+    })
+}
+
+// Busca otras sesiones en la red durante `timeout_ms` (1,5 s por defecto); también aparece
+// la propia si se está anunciando
+#[tauri::command]
+pub fn mdns_discover(timeout_ms: Option<u64>) -> Result<Vec<DiscoveredSession>, String> {
+    let timeout = Duration::from_millis(
+        timeout_ms
+            .unwrap_or(DEFAULT_DISCOVER_MS)
+            .min(MAX_DISCOVER_MS),
+    );
+    let daemon = ServiceDaemon::new().map_err(|e| format!("No se pudo iniciar mDNS: {e}"))?;
+    let receivers = [WS_SERVICE, HTTP_SERVICE, OSC_SERVICE]
+        .into_iter()
+        .map(|service_type| {
+            daemon
+                .browse(service_type)
+                .map_err(|e| format!("No se pudo buscar {service_type}: {e}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let deadline = Instant::now() + timeout;
+    let mut found = Vec::<DiscoveredSession>::new();
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        // Se reparte la espera entre las búsquedas
+        let slice = (left / receivers.len() as u32).max(Duration::from_millis(10));
+        for receiver in &receivers {
+            let Ok(ServiceEvent::ServiceResolved(info)) = receiver.recv_timeout(slice) else {
+                continue;
+            };
+            if info.get_property_val_str("app") != Some(APP_ID) {
+                continue;
+            }
+            let name = info.get_fullname().to_string();
+            if found.iter().any(|session| session.name == name) {
+                continue;
+            }
+            found.push(DiscoveredSession {
+                name,
+                service_type: info.get_type().to_string(),
+                host: info.get_hostname().to_string(),
+                addresses: info
+                    .get_addresses()
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+                port: info.get_port(),
+                version: info.get_property_val_str("version").map(str::to_string),
+            });
+        }
+    }
+
+    if let Err(err) = daemon.shutdown() {
+        warn!("No se pudo cerrar mDNS: {err}");
+    }
+    Ok(found)
+}
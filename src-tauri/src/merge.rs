@@ -0,0 +1,263 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+const DEFAULT_PRIORITY: u8 = 100;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(tag = "kind", content = "id", rename_all = "snake_case")]
+pub enum MergeSource {
+    /// Buffer del universo (front, fades y escenas)
+    Programmer,
+    /// Reproducción de la lista de cues
+    Playback,
+    Chase(String),
+    /// Efecto generativo de una matriz de pixels
+    Matrix(String),
+    ArtNet(IpAddr),
+    /// Sound-to-light: bandas del espectro de la entrada de audio
+    Sound,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeSourceKind {
+    Programmer,
+    Playback,
+    Chase,
+    Matrix,
+    ArtNet,
+    Sound,
+}
+
+impl MergeSourceKind {
+    pub(crate) const ALL: [MergeSourceKind; 6] = [
+        MergeSourceKind::Programmer,
+        MergeSourceKind::Playback,
+        MergeSourceKind::Chase,
+        MergeSourceKind::Matrix,
+        MergeSourceKind::ArtNet,
+        MergeSourceKind::Sound,
+    ];
+}
+
+impl MergeSource {
+    pub(crate) fn kind(&self) -> MergeSourceKind {
+        match self {
+            MergeSource::Programmer => MergeSourceKind::Programmer,
+            MergeSource::Playback => MergeSourceKind::Playback,
+            MergeSource::Chase(_) => MergeSourceKind::Chase,
+            MergeSource::Matrix(_) => MergeSourceKind::Matrix,
+            MergeSource::ArtNet(_) => MergeSourceKind::ArtNet,
+            MergeSource::Sound => MergeSourceKind::Sound,
+        }
+    }
+
+    fn name(&self) -> Option<&str> {
+        match self {
+            MergeSource::Chase(name) | MergeSource::Matrix(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+// Niveles que aporta una fuente en este frame; `None` en los canales que no controla
+pub(crate) type Contribution = (MergeSource, Vec<Option<u8>>);
+
+// Contribuciones de un frame. El hilo de escritura la reutiliza: los buffers de cada fuente
+// se vacían y se vuelven a llenar sin pedir memoria nueva.
+#[derive(Default)]
+pub(crate) struct Contributions {
+    items: Vec<Contribution>,
+    len: usize,
+}
+
+impl Contributions {
+    pub(crate) fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    // Buffer vacío para la fuente, tomado de un frame anterior si lo hay
+    pub(crate) fn next(&mut self, source: MergeSource) -> &mut Vec<Option<u8>> {
+        if self.len == self.items.len() {
+            self.items.push((source, Vec::new()));
+        } else if self.items[self.len].0 != source {
+            self.items[self.len].0 = source;
+        }
+        self.slot()
+    }
+
+    // Igual que `next` para chases y matrices: el nombre solo se copia si el buffer era de
+    // otra fuente
+    pub(crate) fn next_named(
+        &mut self,
+        name: &str,
+        source: fn(String) -> MergeSource,
+    ) -> &mut Vec<Option<u8>> {
+        let kind = source(String::new()).kind();
+        let reusable = self
+            .items
+            .get(self.len)
+            .is_some_and(|(current, _)| current.kind() == kind && current.name() == Some(name));
+        if !reusable {
+            let source = source(name.to_string());
+            if self.len == self.items.len() {
+                self.items.push((source, Vec::new()));
+            } else {
+                self.items[self.len].0 = source;
+            }
+        }
+        self.slot()
+    }
+
+    fn slot(&mut self) -> &mut Vec<Option<u8>> {
+        self.len += 1;
+        let values = &mut self.items[self.len - 1].1;
+        values.clear();
+        values
+    }
+
+    // Descarta el último buffer pedido (la fuente no aportó nada este frame)
+    pub(crate) fn discard_last(&mut self) {
+        self.len = self.len.saturating_sub(1);
+    }
+
+    pub(crate) fn as_slice(&self) -> &[Contribution] {
+        &self.items[..self.len]
+    }
+
+    pub(crate) fn values_mut(&mut self) -> impl Iterator<Item = &mut Vec<Option<u8>>> {
+        self.items[..self.len].iter_mut().map(|(_, values)| values)
+    }
+}
+
+// Combina las fuentes canal por canal. Solo compiten las fuentes de mayor prioridad que
+// aportan al canal; entre ellas, HTP (gana el más alto) en los canales de intensidad y LTP
+// (gana la última fuente que cambió el canal) en los atributos.
+pub(crate) struct Merger {
+    previous: HashMap<MergeSource, Vec<Option<u8>>>,
+    owners: Vec<Option<MergeSource>>,
+    priorities: HashMap<MergeSourceKind, u8>,
+    // Prioridad de cada contribución del frame en curso (se reutiliza entre frames)
+    frame_priorities: Vec<u8>,
+}
+
+impl Merger {
+    pub(crate) fn new(channels: usize) -> Self {
+        Self {
+            previous: HashMap::new(),
+            owners: vec![None; channels],
+            priorities: HashMap::new(),
+            frame_priorities: Vec::new(),
+        }
+    }
+
+    pub(crate) fn priority(&self, kind: MergeSourceKind) -> u8 {
+        self.priorities
+            .get(&kind)
+            .copied()
+            .unwrap_or(DEFAULT_PRIORITY)
+    }
+
+    pub(crate) fn set_priority(&mut self, kind: MergeSourceKind, priority: u8) {
+        self.priorities.insert(kind, priority);
+    }
+
+    // Fuente que ganó cada canal en el último frame
+    pub(crate) fn owners(&self) -> &[Option<MergeSource>] {
+        &self.owners
+    }
+
+    pub(crate) fn merge(
+        &mut self,
+        contributions: &[Contribution],
+        is_intensity: impl Fn(usize) -> bool,
+        levels: &mut [u8],
+    ) {
+        let mut priorities = std::mem::take(&mut self.frame_priorities);
+        priorities.clear();
+        priorities.extend(
+            contributions
+                .iter()
+                .map(|(source, _)| self.priority(source.kind())),
+        );
+
+        for (idx, level) in levels.iter_mut().enumerate() {
+            let Some(owner_slot) = self.owners.get_mut(idx) else {
+                break;
+            };
+
+            // Una fuente toma el canal cuando su valor cambia respecto del frame anterior
+            for (source, values) in contributions {
+                let Some(value) = values.get(idx).copied().flatten() else {
+                    continue;
+                };
+                let previous = self
+                    .previous
+                    .get(source)
+                    .and_then(|values| values.get(idx).copied().flatten());
+                if previous != Some(value) && owner_slot.as_ref() != Some(source) {
+                    *owner_slot = Some(source.clone());
+                }
+            }
+
+            let top_priority = contributions
+                .iter()
+                .zip(&priorities)
+                .filter(|((_, values), _)| values.get(idx).copied().flatten().is_some())
+                .map(|(_, priority)| *priority)
+                .max();
+
+            let active = contributions
+                .iter()
+                .zip(&priorities)
+                .filter(|(_, priority)| Some(**priority) == top_priority)
+                .filter_map(|((source, values), _)| {
+                    Some((source, values.get(idx).copied().flatten()?))
+                });
+
+            let winner = if is_intensity(idx) {
+                active.max_by_key(|(_, value)| *value)
+            } else {
+                let mut fallback = None;
+                let mut owned = None;
+                for (source, value) in active {
+                    if owner_slot.as_ref() == Some(source) {
+                        owned = Some((source, value));
+                    }
+                    fallback = Some((source, value));
+                }
+                owned.or(fallback)
+            };
+
+            match winner {
+                Some((source, value)) => {
+                    *level = value;
+                    if owner_slot.as_ref() != Some(source) {
+                        *owner_slot = Some(source.clone());
+                    }
+                }
+                None => {
+                    *level = 0;
+                    *owner_slot = None;
+                }
+            }
+        }
+
+        // Se actualiza en el lugar para no copiar todo el mapa en cada frame
+        self.previous
+            .retain(|source, _| contributions.iter().any(|(current, _)| current == source));
+        for (source, values) in contributions {
+            match self.previous.get_mut(source) {
+                Some(previous) => {
+                    previous.clear();
+                    previous.extend_from_slice(values);
+                }
+                None => {
+                    self.previous.insert(source.clone(), values.clone());
+                }
+            }
+        }
+        self.frame_priorities = priorities;
+    }
+}
@@ -0,0 +1,128 @@
+use crate::audio::AudioState;
+use log::error;
+use serde::Serialize;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+pub const AUDIO_METERS_EVENT: &str = "audio://meters";
+// 20 veces por segundo alcanza para un vúmetro fluido
+const METER_INTERVAL: Duration = Duration::from_millis(50);
+
+// Lineales, de 0 a 1 (fondo de escala)
+#[derive(Clone, Copy, Default, Serialize)]
+pub struct MeterLevel {
+    peak: f32,
+    rms: f32,
+}
+
+// Acumula las muestras de un canal entre dos lecturas
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Meter {
+    peak: f32,
+    sum_squares: f64,
+    samples: u64,
+}
+
+impl Meter {
+    pub(crate) fn add(&mut self, sample: f32) {
+        self.peak = self.peak.max(sample.abs());
+        self.sum_squares += (sample * sample) as f64;
+        self.samples += 1;
+    }
+
+    // Devuelve lo acumulado desde la lectura anterior y empieza de nuevo
+    pub(crate) fn take(&mut self) -> MeterLevel {
+        let level = MeterLevel {
+            peak: self.peak,
+            rms: match self.samples {
+                0 => 0.0,
+                samples => (self.sum_squares / samples as f64).sqrt() as f32,
+            },
+        };
+        *self = Self::default();
+        level
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct AudioMeters {
+    // Un medidor por canal de la entrada
+    inputs: Vec<MeterLevel>,
+    looper: MeterLevel,
+    // Izquierda y derecha de la mezcla principal
+    main: [MeterLevel; 2],
+}
+
+#[derive(Default)]
+pub(crate) struct Meters {
+    inputs: Vec<Meter>,
+    main: [Meter; 2],
+}
+
+impl Meters {
+    // Al abrir la entrada, para no reservar memoria en el callback
+    pub(crate) fn set_input_channels(&mut self, channels: usize) {
+        self.inputs = vec![Meter::default(); channels];
+    }
+
+    // Lo llama el callback de entrada con los frames intercalados
+    pub(crate) fn add_input(&mut self, data: &[f32]) {
+        let channels = self.inputs.len();
+        if channels == 0 {
+            return;
+        }
+        for frame in data.chunks(channels) {
+            for (meter, sample) in self.inputs.iter_mut().zip(frame) {
+                meter.add(*sample);
+            }
+        }
+    }
+
+    // Lo llama el callback de salida con la mezcla ya procesada
+    pub(crate) fn add_main(&mut self, main: &[[f32; 2]]) {
+        for frame in main {
+            self.main[0].add(frame[0]);
+            self.main[1].add(frame[1]);
+        }
+    }
+
+    fn has_samples(&self) -> bool {
+        self.main
+            .iter()
+            .chain(&self.inputs)
+            .any(|meter| meter.samples > 0)
+    }
+}
+
+// Manda los niveles de la entrada, del looper y de la salida mientras el motor está en marcha
+pub fn start_meter_events(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    thread::spawn(move || loop {
+        thread::sleep(METER_INTERVAL);
+        let audio = app_handle.state::<AudioState>();
+        let shared = audio.shared();
+        let looper = match shared.lock_looper() {
+            Ok(mut looper) => looper.take_meter(),
+            Err(err) => {
+                error!("{err}");
+                continue;
+            }
+        };
+        let meters = match shared.lock_meters() {
+            Ok(mut meters) if meters.has_samples() => AudioMeters {
+                inputs: meters.inputs.iter_mut().map(Meter::take).collect(),
+                looper,
+                main: [meters.main[0].take(), meters.main[1].take()],
+            },
+            Ok(_) => continue,
+            Err(err) => {
+                error!("{err}");
+                continue;
+            }
+        };
+        if let Err(err) = app_handle.emit(AUDIO_METERS_EVENT, meters) {
+            error!("No se pudo emitir los niveles de audio: {err}");
+        }
+    });
+}
@@ -0,0 +1,90 @@
+use crate::dmx::DmxState;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tauri::State;
+
+// Cada cuánto se cierran las estadísticas; lo que devuelve `dmx_get_metrics` es la última ventana completa
+const WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Default, Serialize)]
+pub struct FrameStats {
+    fps: f64,
+    target_hz: u32,
+    // Intervalo entre frames, en milisegundos
+    mean_interval_ms: f64,
+    min_interval_ms: f64,
+    max_interval_ms: f64,
+    // Desvío estándar del intervalo
+    jitter_ms: f64,
+}
+
+// Lo alimenta el hilo de escritura al terminar cada frame
+#[derive(Default)]
+pub(crate) struct FrameMetrics {
+    last_frame: Option<Instant>,
+    window_start: Option<Instant>,
+    count: u32,
+    sum_ms: f64,
+    sum_sq_ms: f64,
+    min_ms: f64,
+    max_ms: f64,
+    stats: FrameStats,
+}
+
+impl FrameMetrics {
+    pub(crate) fn record(&mut self, now: Instant, target_hz: u32) {
+        let window_start = *self.window_start.get_or_insert(now);
+        if let Some(last) = self.last_frame.replace(now) {
+            let interval = now.saturating_duration_since(last).as_secs_f64() * 1000.0;
+            if self.count == 0 {
+                self.min_ms = interval;
+                self.max_ms = interval;
+            }
+            self.count += 1;
+            self.sum_ms += interval;
+            self.sum_sq_ms += interval * interval;
+            self.min_ms = self.min_ms.min(interval);
+            self.max_ms = self.max_ms.max(interval);
+        }
+
+        let elapsed = now.saturating_duration_since(window_start);
+        if elapsed < WINDOW || self.count == 0 {
+            return;
+        }
+
+        let count = self.count as f64;
+        let mean = self.sum_ms / count;
+        let variance = (self.sum_sq_ms / count - mean * mean).max(0.0);
+        self.stats = FrameStats {
+            fps: count / elapsed.as_secs_f64(),
+            target_hz,
+            mean_interval_ms: mean,
+            min_interval_ms: self.min_ms,
+            max_interval_ms: self.max_ms,
+            jitter_ms: variance.sqrt(),
+        };
+        *self = FrameMetrics {
+            last_frame: self.last_frame,
+            window_start: Some(now),
+            stats: self.stats,
+            ..FrameMetrics::default()
+        };
+    }
+
+    // Si el hilo se detuvo las estadísticas viejas no valen
+    pub(crate) fn reset(&mut self) {
+        *self = FrameMetrics::default();
+    }
+}
+
+// FPS real y jitter del último segundo de salida del universo
+#[tauri::command]
+pub fn dmx_get_metrics(state: State<'_, DmxState>, universe: u16) -> Result<FrameStats, String> {
+    state.read_universe(universe, |shared| {
+        shared
+            .metrics
+            .lock()
+            .map(|metrics| metrics.stats)
+            .map_err(|e| format!("No se pudo bloquear las métricas DMX: {e}"))
+    })
+}
@@ -0,0 +1,420 @@
+use crate::midi_clock::{self, ClockMessage};
+use crate::midi_map;
+use crate::msc;
+use crate::mtc::{self, MtcMessage};
+use log::{debug, error, info, warn};
+use midir::{Ignore, MidiInput, MidiInputConnection, MidiOutput, MidiOutputConnection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, State};
+
+pub const MIDI_EVENT: &str = "midi://message";
+const CLIENT_NAME: &str = "LiveLoop Studio";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MidiKind {
+    NoteOn,
+    /// También un note on con velocidad 0, como mandan muchos controladores
+    NoteOff,
+    ControlChange,
+    ProgramChange,
+}
+
+// Mensaje de canal ya decodificado; `number` es la nota, el CC o el programa
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MidiMessage {
+    pub(crate) kind: MidiKind,
+    // 1-16
+    pub(crate) channel: u8,
+    pub(crate) number: u8,
+    // Velocidad o valor del CC; 0 en program change
+    pub(crate) value: u8,
+}
+
+impl MidiMessage {
+    pub(crate) fn parse(bytes: &[u8]) -> Option<Self> {
+        let (&status, data) = bytes.split_first()?;
+        let channel = (status & 0x0F) + 1;
+        let byte = |idx: usize| data.get(idx).copied().filter(|b| *b < 0x80);
+
+        let (kind, number, value) = match status & 0xF0 {
+            0x90 if byte(1)? > 0 => (MidiKind::NoteOn, byte(0)?, byte(1)?),
+            0x80 | 0x90 => (MidiKind::NoteOff, byte(0)?, byte(1)?),
+            0xB0 => (MidiKind::ControlChange, byte(0)?, byte(1)?),
+            0xC0 => (MidiKind::ProgramChange, byte(0)?, 0),
+            _ => return None,
+        };
+        Some(Self {
+            kind,
+            channel,
+            number,
+            value,
+        })
+    }
+
+    pub(crate) fn new(kind: MidiKind, channel: u8, number: u8, value: u8) -> Self {
+        Self {
+            kind,
+            channel,
+            number,
+            value,
+        }
+    }
+
+    pub(crate) fn to_bytes(self) -> Vec<u8> {
+        let channel = self.channel.saturating_sub(1) & 0x0F;
+        let (number, value) = (self.number & 0x7F, self.value & 0x7F);
+        match self.kind {
+            MidiKind::NoteOn => vec![0x90 | channel, number, value],
+            MidiKind::NoteOff => vec![0x80 | channel, number, value],
+            MidiKind::ControlChange => vec![0xB0 | channel, number, value],
+            MidiKind::ProgramChange => vec![0xC0 | channel, number],
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct MidiEvent {
+    device: String,
+    #[serde(flatten)]
+    message: MidiMessage,
+}
+
+#[derive(Serialize)]
+pub struct MidiPortInfo {
+    name: String,
+    open: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MidiDirection {
+    Input,
+    Output,
+}
+
+// Entradas y salidas MIDI abiertas, por nombre del dispositivo. La conexión de entrada corre
+// en un hilo de midir; las dos se cierran al soltarlas.
+#[derive(Default)]
+pub struct MidiState {
+    inputs: Mutex<InputConnections>,
+    outputs: Mutex<OutputConnections>,
+    // Abiertos con los comandos y no cerrados: si se desconectan se reabren al volver
+    wanted: Mutex<BTreeSet<(MidiDirection, String)>>,
+}
+
+type InputConnections = HashMap<String, MidiInputConnection<()>>;
+type OutputConnections = HashMap<String, MidiOutputConnection>;
+
+impl MidiState {
+    fn lock_inputs(&self) -> Result<std::sync::MutexGuard<'_, InputConnections>, String> {
+        self.inputs
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear las entradas MIDI: {e}"))
+    }
+
+    fn lock_outputs(&self) -> Result<std::sync::MutexGuard<'_, OutputConnections>, String> {
+        self.outputs
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear las salidas MIDI: {e}"))
+    }
+
+    fn lock_wanted(
+        &self,
+    ) -> Result<std::sync::MutexGuard<'_, BTreeSet<(MidiDirection, String)>>, String> {
+        self.wanted
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear los dispositivos MIDI: {e}"))
+    }
+
+    fn open_input(&self, app_handle: &AppHandle, name: &str) -> Result<(), String> {
+        let mut inputs = self.lock_inputs()?;
+        if inputs.contains_key(name) {
+            return Ok(());
+        }
+
+        let mut input = new_input()?;
+        // El reloj hace falta; solo se descarta el active sensing
+        input.ignore(Ignore::ActiveSense);
+        let port = input
+            .ports()
+            .into_iter()
+            .find(|port| {
+                input
+                    .port_name(port)
+                    .is_ok_and(|port_name| port_name == name)
+            })
+            .ok_or_else(|| format!("No se encontró la entrada MIDI \"{name}\""))?;
+
+        let app_handle = app_handle.clone();
+        let device = name.to_string();
+        let connection = input
+            .connect(
+                &port,
+                "entrada",
+                move |stamp, bytes, _| dispatch(&app_handle, &device, stamp, bytes),
+                (),
+            )
+            .map_err(|e| format!("No se pudo abrir la entrada MIDI \"{name}\": {e}"))?;
+
+        info!("Entrada MIDI abierta: {name}");
+        inputs.insert(name.to_string(), connection);
+        Ok(())
+    }
+
+    fn open_output(&self, name: &str) -> Result<(), String> {
+        let mut outputs = self.lock_outputs()?;
+        if outputs.contains_key(name) {
+            return Ok(());
+        }
+
+        let output = new_output()?;
+        let port = output
+            .ports()
+            .into_iter()
+            .find(|port| {
+                output
+                    .port_name(port)
+                    .is_ok_and(|port_name| port_name == name)
+            })
+            .ok_or_else(|| format!("No se encontró la salida MIDI \"{name}\""))?;
+
+        let connection = output
+            .connect(&port, "salida")
+            .map_err(|e| format!("No se pudo abrir la salida MIDI \"{name}\": {e}"))?;
+
+        info!("Salida MIDI abierta: {name}");
+        outputs.insert(name.to_string(), connection);
+        Ok(())
+    }
+
+    // El dispositivo desapareció: se suelta la conexión, pero sigue en `wanted`
+    pub(crate) fn disconnected(&self, direction: MidiDirection, name: &str) {
+        let dropped = match direction {
+            MidiDirection::Input => self
+                .lock_inputs()
+                .is_ok_and(|mut inputs| inputs.remove(name).is_some()),
+            MidiDirection::Output => self
+                .lock_outputs()
+                .is_ok_and(|mut outputs| outputs.remove(name).is_some()),
+        };
+        if dropped {
+            warn!("Dispositivo MIDI desconectado con la conexión abierta: {name}");
+        }
+    }
+
+    // Reabre los dispositivos buscados que están conectados pero cerrados; devuelve los que
+    // se pudieron abrir
+    pub(crate) fn reopen(
+        &self,
+        app_handle: &AppHandle,
+        direction: MidiDirection,
+        present: &BTreeSet<String>,
+    ) -> Vec<String> {
+        let wanted = match self.lock_wanted() {
+            Ok(wanted) => wanted
+                .iter()
+                .filter(|(wanted, name)| *wanted == direction && present.contains(name))
+                .map(|(_, name)| name.clone())
+                .collect::<Vec<_>>(),
+            Err(err) => {
+                error!("{err}");
+                return Vec::new();
+            }
+        };
+
+        wanted
+            .into_iter()
+            .filter(|name| !self.is_open(direction, name))
+            .filter(|name| {
+                let opened = match direction {
+                    MidiDirection::Input => self.open_input(app_handle, name),
+                    MidiDirection::Output => self.open_output(name),
+                };
+                // Se reintenta en la próxima vuelta
+                opened.map_err(|err| debug!("{err}")).is_ok()
+            })
+            .collect()
+    }
+
+    fn is_open(&self, direction: MidiDirection, name: &str) -> bool {
+        match direction {
+            MidiDirection::Input => self
+                .lock_inputs()
+                .is_ok_and(|inputs| inputs.contains_key(name)),
+            MidiDirection::Output => self
+                .lock_outputs()
+                .is_ok_and(|outputs| outputs.contains_key(name)),
+        }
+    }
+
+    fn set_wanted(&self, direction: MidiDirection, name: Option<&str>, wanted: bool) {
+        let Ok(mut current) = self.lock_wanted() else {
+            return;
+        };
+        match (name, wanted) {
+            (Some(name), true) => {
+                current.insert((direction, name.to_string()));
+            }
+            (Some(name), false) => {
+                current.remove(&(direction, name.to_string()));
+            }
+            (None, _) => current.retain(|(current, _)| *current != direction),
+        }
+    }
+
+    pub(crate) fn open_outputs(&self) -> Vec<String> {
+        self.lock_outputs()
+            .map(|outputs| outputs.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn send(&self, device: &str, bytes: &[u8]) -> Result<(), String> {
+        let mut outputs = self.lock_outputs()?;
+        let connection = outputs
+            .get_mut(device)
+            .ok_or_else(|| format!("La salida MIDI \"{device}\" no está abierta"))?;
+        connection
+            .send(bytes)
+            .map_err(|e| format!("No se pudo enviar a la salida MIDI \"{device}\": {e}"))
+    }
+}
+
+fn new_input() -> Result<MidiInput, String> {
+    MidiInput::new(CLIENT_NAME).map_err(|e| format!("No se pudo iniciar MIDI: {e}"))
+}
+
+fn new_output() -> Result<MidiOutput, String> {
+    MidiOutput::new(CLIENT_NAME).map_err(|e| format!("No se pudo iniciar MIDI: {e}"))
+}
+
+// Nombres de los dispositivos conectados del sistema
+pub(crate) fn port_names(direction: MidiDirection) -> Result<BTreeSet<String>, String> {
+    Ok(match direction {
+        MidiDirection::Input => {
+            let input = new_input()?;
+            input
+                .ports()
+                .iter()
+                .filter_map(|port| input.port_name(port).ok())
+                .collect()
+        }
+        MidiDirection::Output => {
+            let output = new_output()?;
+            output
+                .ports()
+                .iter()
+                .filter_map(|port| output.port_name(port).ok())
+                .collect()
+        }
+    })
+}
+
+// Todo mensaje que llega pasa por acá: se avisa al front y a los destinos internos. El reloj
+// no se emite al front, son 24 mensajes por negra.
+fn dispatch(app_handle: &AppHandle, device: &str, stamp: u64, bytes: &[u8]) {
+    if let Some(clock) = ClockMessage::parse(bytes) {
+        midi_clock::handle(app_handle, device, stamp, clock);
+        return;
+    }
+    if let Some(timecode) = MtcMessage::parse(bytes) {
+        mtc::handle(app_handle, device, timecode);
+        return;
+    }
+    if bytes.first() == Some(&0xF0) {
+        msc::handle(app_handle, bytes);
+        return;
+    }
+    let Some(message) = MidiMessage::parse(bytes) else {
+        return;
+    };
+    // Primero los destinos internos: el canal DMX no espera al evento del front
+    midi_map::handle(app_handle, device, &message);
+    let event = MidiEvent {
+        device: device.to_string(),
+        message,
+    };
+    if let Err(err) = app_handle.emit(MIDI_EVENT, event) {
+        error!("No se pudo emitir el mensaje MIDI: {err}");
+    }
+}
+
+#[tauri::command]
+pub fn midi_list_inputs(state: State<'_, MidiState>) -> Result<Vec<MidiPortInfo>, String> {
+    let names = port_names(MidiDirection::Input)?;
+    let inputs = state.lock_inputs()?;
+    Ok(names
+        .into_iter()
+        .map(|name| MidiPortInfo {
+            open: inputs.contains_key(&name),
+            name,
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub fn midi_open_input(
+    app_handle: AppHandle,
+    state: State<'_, MidiState>,
+    name: String,
+) -> Result<(), String> {
+    state.open_input(&app_handle, &name)?;
+    state.set_wanted(MidiDirection::Input, Some(&name), true);
+    Ok(())
+}
+
+// Sin nombre cierra todas
+#[tauri::command]
+pub fn midi_close_input(state: State<'_, MidiState>, name: Option<String>) -> Result<(), String> {
+    state.set_wanted(MidiDirection::Input, name.as_deref(), false);
+    let mut inputs = state.lock_inputs()?;
+    match name {
+        Some(name) => {
+            inputs
+                .remove(&name)
+                .ok_or_else(|| format!("La entrada MIDI \"{name}\" no está abierta"))?;
+            info!("Entrada MIDI cerrada: {name}");
+        }
+        None => inputs.clear(),
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn midi_list_outputs(state: State<'_, MidiState>) -> Result<Vec<MidiPortInfo>, String> {
+    let names = port_names(MidiDirection::Output)?;
+    let outputs = state.lock_outputs()?;
+    Ok(names
+        .into_iter()
+        .map(|name| MidiPortInfo {
+            open: outputs.contains_key(&name),
+            name,
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub fn midi_open_output(state: State<'_, MidiState>, name: String) -> Result<(), String> {
+    state.open_output(&name)?;
+    state.set_wanted(MidiDirection::Output, Some(&name), true);
+    Ok(())
+}
+
+// Sin nombre cierra todas
+#[tauri::command]
+pub fn midi_close_output(state: State<'_, MidiState>, name: Option<String>) -> Result<(), String> {
+    state.set_wanted(MidiDirection::Output, name.as_deref(), false);
+    let mut outputs = state.lock_outputs()?;
+    match name {
+        Some(name) => {
+            outputs
+                .remove(&name)
+                .ok_or_else(|| format!("La salida MIDI \"{name}\" no está abierta"))?;
+            info!("Salida MIDI cerrada: {name}");
+        }
+        None => outputs.clear(),
+    }
+    Ok(())
+}
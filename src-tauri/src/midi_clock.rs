@@ -0,0 +1,180 @@
+use crate::chase::{apply_transport, clamp_bpm, set_running_bpm, Transport};
+use crate::dmx::DmxState;
+use log::{error, info};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub const MIDI_CLOCK_EVENT: &str = "midi://clock";
+const CLOCKS_PER_BEAT: usize = 24;
+// Cambios más chicos no se pasan a los chases, así el jitter del reloj no los mueve
+const BPM_EPSILON: f64 = 0.2;
+// Sin ticks durante este tiempo el promedio empieza de nuevo (µs)
+const CLOCK_TIMEOUT_US: u64 = 500_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ClockMessage {
+    /// 24 por negra
+    Tick,
+    Start,
+    Continue,
+    Stop,
+}
+
+impl ClockMessage {
+    pub(crate) fn parse(bytes: &[u8]) -> Option<Self> {
+        match bytes.first()? {
+            0xF8 => Some(Self::Tick),
+            0xFA => Some(Self::Start),
+            0xFB => Some(Self::Continue),
+            0xFC => Some(Self::Stop),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct ClockStatus {
+    enabled: bool,
+    device: Option<String>,
+    bpm: Option<f64>,
+    running: bool,
+}
+
+#[derive(Default)]
+struct ClockSync {
+    enabled: bool,
+    // Sin dispositivo se sigue el reloj de cualquier entrada abierta
+    device: Option<String>,
+    // Marcas de tiempo de midir (µs) del último beat de ticks
+    ticks: VecDeque<u64>,
+    bpm: Option<f64>,
+    running: bool,
+}
+
+impl ClockSync {
+    fn status(&self) -> ClockStatus {
+        ClockStatus {
+            enabled: self.enabled,
+            device: self.device.clone(),
+            bpm: self.bpm,
+            running: self.running,
+        }
+    }
+
+    // Devuelve el tempo nuevo si cambió lo suficiente
+    fn tick(&mut self, stamp: u64) -> Option<f64> {
+        if self
+            .ticks
+            .back()
+            .is_some_and(|last| stamp.saturating_sub(*last) > CLOCK_TIMEOUT_US)
+        {
+            self.ticks.clear();
+        }
+        self.ticks.push_back(stamp);
+        if self.ticks.len() > CLOCKS_PER_BEAT + 1 {
+            self.ticks.pop_front();
+        }
+        if self.ticks.len() <= CLOCKS_PER_BEAT {
+            return None;
+        }
+
+        let span = self.ticks.back()?.saturating_sub(*self.ticks.front()?);
+        if span == 0 {
+            return None;
+        }
+        let bpm = clamp_bpm(60_000_000.0 / span as f64);
+        if self
+            .bpm
+            .is_some_and(|current| (current - bpm).abs() < BPM_EPSILON)
+        {
+            return None;
+        }
+        self.bpm = Some(bpm);
+        Some(bpm)
+    }
+}
+
+// Clock MIDI entrante: el tempo pasa a los chases y efectos, y start/stop/continue los mueven
+#[derive(Default)]
+pub struct MidiClockState {
+    sync: Mutex<ClockSync>,
+}
+
+impl MidiClockState {
+    fn lock_sync(&self) -> Result<std::sync::MutexGuard<'_, ClockSync>, String> {
+        self.sync
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el clock MIDI: {e}"))
+    }
+}
+
+// Llamado por cada mensaje de reloj de una entrada abierta; `stamp` es la marca de midir
+pub(crate) fn handle(app_handle: &AppHandle, device: &str, stamp: u64, message: ClockMessage) {
+    let state = app_handle.state::<MidiClockState>();
+    let (bpm, transport, status) = {
+        let mut sync = match state.lock_sync() {
+            Ok(sync) => sync,
+            Err(err) => {
+                error!("{err}");
+                return;
+            }
+        };
+        if !sync.enabled || sync.device.as_deref().is_some_and(|name| name != device) {
+            return;
+        }
+
+        let (bpm, transport) = match message {
+            ClockMessage::Tick => (sync.tick(stamp), None),
+            ClockMessage::Start => (None, Some(Transport::Start)),
+            ClockMessage::Continue => (None, Some(Transport::Continue)),
+            ClockMessage::Stop => (None, Some(Transport::Stop)),
+        };
+        if let Some(transport) = transport {
+            sync.running = transport != Transport::Stop;
+        }
+        if bpm.is_none() && transport.is_none() {
+            return;
+        }
+        (bpm, transport, sync.status())
+    };
+
+    let dmx = app_handle.state::<DmxState>();
+    if let Some(bpm) = bpm {
+        set_running_bpm(&dmx, bpm);
+    }
+    if let Some(transport) = transport {
+        apply_transport(&dmx, transport);
+    }
+    if let Err(err) = app_handle.emit(MIDI_CLOCK_EVENT, status) {
+        error!("No se pudo emitir el estado del clock MIDI: {err}");
+    }
+}
+
+#[tauri::command]
+pub fn midi_clock_sync(
+    state: State<'_, MidiClockState>,
+    enabled: bool,
+    device: Option<String>,
+) -> Result<ClockStatus, String> {
+    let mut sync = state.lock_sync()?;
+    *sync = ClockSync {
+        enabled,
+        device,
+        ..ClockSync::default()
+    };
+
+    if enabled {
+        match &sync.device {
+            Some(device) => info!("Siguiendo el clock MIDI de {device}"),
+            None => info!("Siguiendo el clock MIDI de cualquier entrada"),
+        }
+    }
+    Ok(sync.status())
+}
+
+#[tauri::command]
+pub fn midi_clock_status(state: State<'_, MidiClockState>) -> Result<ClockStatus, String> {
+    Ok(state.lock_sync()?.status())
+}
@@ -0,0 +1,260 @@
+use crate::cues::CueState;
+use crate::dmx::{DmxState, DMX_CHANNELS};
+use crate::midi::{MidiKind, MidiMessage, MidiState};
+use crate::scenes::SceneState;
+use crate::storage;
+use log::{debug, error, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+const MIDI_FEEDBACK_FILE: &str = "midi_feedback.json";
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+const DEFAULT_ON_VALUE: u8 = 127;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FeedbackSource {
+    /// Nivel de salida del canal (0-255) llevado a 0-127, para faders motorizados o anillos
+    ChannelLevel { universe: u16, channel: u16 },
+    /// Encendido mientras la escena es la última recuperada
+    SceneActive { scene: String },
+    /// Encendido mientras la cue está en escena
+    CueActive { number: f64 },
+}
+
+// Control de una salida MIDI que refleja un estado del backend: la nota (su velocidad, que en
+// muchos controladores elige el color del LED) o el valor del CC
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MidiFeedback {
+    device: String,
+    kind: MidiKind,
+    channel: u8,
+    number: u8,
+    source: FeedbackSource,
+    // Valores para los estados encendido/apagado; no se usan con `channel_level`
+    #[serde(default)]
+    on_value: Option<u8>,
+    #[serde(default)]
+    off_value: Option<u8>,
+}
+
+type FeedbackControl = (String, MidiKind, u8, u8);
+
+impl MidiFeedback {
+    fn validate(&self) -> Result<(), String> {
+        if !matches!(self.kind, MidiKind::NoteOn | MidiKind::ControlChange) {
+            return Err("El feedback MIDI solo usa `note_on` o `control_change`".to_string());
+        }
+        if !(1..=16).contains(&self.channel) {
+            return Err(format!("Canal MIDI inválido: {}", self.channel));
+        }
+        if self.number > 127 {
+            return Err(format!("Número MIDI inválido: {}", self.number));
+        }
+        if [self.on_value, self.off_value]
+            .into_iter()
+            .flatten()
+            .any(|value| value > 127)
+        {
+            return Err("Los valores MIDI van de 0 a 127".to_string());
+        }
+        if let FeedbackSource::ChannelLevel { channel, .. } = &self.source {
+            if !(1..=DMX_CHANNELS as u16).contains(channel) {
+                return Err(format!("Canal DMX inválido: {channel}"));
+            }
+        }
+        Ok(())
+    }
+
+    fn control(&self) -> FeedbackControl {
+        (self.device.clone(), self.kind, self.channel, self.number)
+    }
+
+    fn switch(&self, on: bool) -> u8 {
+        if on {
+            self.on_value.unwrap_or(DEFAULT_ON_VALUE)
+        } else {
+            self.off_value.unwrap_or(0)
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct MidiFeedbackState {
+    feedback: Mutex<Vec<MidiFeedback>>,
+    path: Mutex<Option<PathBuf>>,
+}
+
+impl MidiFeedbackState {
+    pub fn load(&self, app_handle: &AppHandle) {
+        let Some(path) = storage::config_file(app_handle, MIDI_FEEDBACK_FILE) else {
+            return;
+        };
+
+        if let Some(mut feedback) = storage::load_json::<Vec<MidiFeedback>>(&path) {
+            // El archivo no pasó por `midi_feedback_add`
+            feedback.retain(|item| match item.validate() {
+                Ok(()) => true,
+                Err(err) => {
+                    warn!("Feedback MIDI descartado: {err}");
+                    false
+                }
+            });
+            if let Ok(mut current) = self.feedback.lock() {
+                *current = feedback;
+            }
+        }
+
+        if let Ok(mut current) = self.path.lock() {
+            *current = Some(path);
+        }
+    }
+
+    pub(crate) fn export(&self) -> Result<Vec<MidiFeedback>, String> {
+        Ok(self.lock_feedback()?.clone())
+    }
+
+    pub(crate) fn replace(&self, feedback: Vec<MidiFeedback>) -> Result<(), String> {
+        for item in &feedback {
+            item.validate()?;
+        }
+        let mut stored = self.lock_feedback()?;
+        *stored = feedback;
+        self.save(&stored)
+    }
+
+    fn lock_feedback(&self) -> Result<std::sync::MutexGuard<'_, Vec<MidiFeedback>>, String> {
+        self.feedback
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el feedback MIDI: {e}"))
+    }
+
+    fn save(&self, feedback: &[MidiFeedback]) -> Result<(), String> {
+        let path = self
+            .path
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el feedback MIDI: {e}"))?
+            .clone();
+
+        match path {
+            Some(path) => storage::save_json(&path, &feedback),
+            None => Ok(()),
+        }
+    }
+}
+
+// Valor que toca mandar ahora a cada control con feedback
+fn current_values(app_handle: &AppHandle, feedback: &[MidiFeedback]) -> Vec<(MidiFeedback, u8)> {
+    let dmx = app_handle.state::<DmxState>();
+    let active_scene = app_handle.state::<SceneState>().active();
+    let active_cue = app_handle.state::<CueState>().active_number();
+    // Un solo pedido de niveles por universo en cada vuelta
+    let mut outputs: BTreeMap<u16, Option<Vec<u8>>> = BTreeMap::new();
+
+    feedback
+        .iter()
+        .filter_map(|item| {
+            let value = match &item.source {
+                FeedbackSource::ChannelLevel { universe, channel } => {
+                    let levels = outputs.entry(*universe).or_insert_with(|| {
+                        dmx.read_universe(*universe, |shared| Ok(shared.output_levels()))
+                            .ok()
+                    });
+                    levels.as_ref()?.get((*channel as usize).checked_sub(1)?)? / 2
+                }
+                FeedbackSource::SceneActive { scene } => {
+                    item.switch(active_scene.as_ref() == Some(scene))
+                }
+                FeedbackSource::CueActive { number } => item.switch(active_cue == Some(*number)),
+            };
+            Some((item.clone(), value))
+        })
+        .collect()
+}
+
+// Cada 50 ms compara el estado con lo último enviado y solo manda los controles que cambiaron.
+// Una salida que se abre (o se vuelve a abrir) recibe todos sus valores.
+pub fn start_feedback(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    thread::spawn(move || {
+        let mut sent: HashMap<FeedbackControl, u8> = HashMap::new();
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let midi = app_handle.state::<MidiState>();
+            let open = midi.open_outputs();
+            sent.retain(|(device, ..), _| open.contains(device));
+            if open.is_empty() {
+                continue;
+            }
+
+            let feedback = match app_handle.state::<MidiFeedbackState>().export() {
+                Ok(feedback) => feedback,
+                Err(err) => {
+                    error!("{err}");
+                    continue;
+                }
+            };
+            for (item, value) in current_values(&app_handle, &feedback) {
+                if !open.contains(&item.device) {
+                    continue;
+                }
+                let control = item.control();
+                if sent.get(&control) == Some(&value) {
+                    continue;
+                }
+                let message = MidiMessage::new(item.kind, item.channel, item.number, value);
+                match midi.send(&item.device, &message.to_bytes()) {
+                    Ok(()) => {
+                        sent.insert(control, value);
+                    }
+                    // Se reintenta en la próxima vuelta
+                    Err(err) => debug!("{err}"),
+                }
+            }
+        }
+    });
+}
+
+// Un control de salida refleja un solo estado: el feedback nuevo reemplaza al anterior
+#[tauri::command]
+pub fn midi_feedback_add(
+    state: State<'_, MidiFeedbackState>,
+    feedback: MidiFeedback,
+) -> Result<(), String> {
+    feedback.validate()?;
+
+    let mut stored = state.lock_feedback()?;
+    stored.retain(|item| item.control() != feedback.control());
+    stored.push(feedback);
+    state.save(&stored)
+}
+
+#[tauri::command]
+pub fn midi_feedback_remove(
+    state: State<'_, MidiFeedbackState>,
+    device: String,
+    kind: MidiKind,
+    channel: u8,
+    number: u8,
+) -> Result<(), String> {
+    let control = (device, kind, channel, number);
+    let mut stored = state.lock_feedback()?;
+    let before = stored.len();
+    stored.retain(|item| item.control() != control);
+    if stored.len() == before {
+        return Err("No hay feedback MIDI para ese control".to_string());
+    }
+    state.save(&stored)
+}
+
+#[tauri::command]
+pub fn midi_feedback_list(
+    state: State<'_, MidiFeedbackState>,
+) -> Result<Vec<MidiFeedback>, String> {
+    state.export()
+}
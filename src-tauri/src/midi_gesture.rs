@@ -0,0 +1,129 @@
+use crate::midi::{MidiKind, MidiMessage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// Apretado más que esto cuenta como pulsación larga
+const LONG_PRESS: Duration = Duration::from_millis(600);
+// Un segundo toque dentro de esta ventana cuenta como doble
+const DOUBLE_TAP: Duration = Duration::from_millis(300);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MidiGesture {
+    /// Al apretar. Si el control también tiene pulsación larga o doble toque, cuando se sabe
+    /// que no lo fue: al soltar o al vencer la ventana del doble toque.
+    #[default]
+    Press,
+    /// Mantenido 600 ms; salta sin esperar a que se suelte
+    LongPress,
+    /// Dos toques en menos de 300 ms
+    DoubleTap,
+}
+
+// Gestos mapeados en un control
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Available {
+    pub(crate) long_press: bool,
+    pub(crate) double_tap: bool,
+}
+
+// Dispositivo, tipo (note off cuenta como note on), canal y número
+pub(crate) type ControlKey = (String, MidiKind, u8, u8);
+
+struct Hold {
+    // El mensaje que lo apretó, para buscar los mapeos cuando el gesto se resuelve más tarde
+    message: MidiMessage,
+    available: Available,
+    down: Option<Instant>,
+    // Toque corto que espera un posible segundo
+    tap: Option<Instant>,
+    long_fired: bool,
+}
+
+// Reconoce pulsaciones largas y dobles toques, para controladores de pie como la FCB1010
+// que solo tienen botones
+#[derive(Default)]
+pub(crate) struct Gestures {
+    holds: HashMap<ControlKey, Hold>,
+}
+
+impl Gestures {
+    // Devuelve el gesto si ya se sabe cuál es
+    pub(crate) fn press(
+        &mut self,
+        key: ControlKey,
+        message: MidiMessage,
+        available: Available,
+        now: Instant,
+    ) -> Option<MidiGesture> {
+        // Sin gestos mapeados el botón responde al instante
+        if !available.long_press && !available.double_tap {
+            self.holds.remove(&key);
+            return Some(MidiGesture::Press);
+        }
+        let second_tap = self
+            .holds
+            .get(&key)
+            .and_then(|hold| hold.tap)
+            .is_some_and(|tap| now.duration_since(tap) < DOUBLE_TAP);
+        if available.double_tap && second_tap {
+            self.holds.remove(&key);
+            return Some(MidiGesture::DoubleTap);
+        }
+        self.holds.insert(
+            key.clone(),
+            Hold {
+                message,
+                available,
+                down: Some(now),
+                tap: None,
+                long_fired: false,
+            },
+        );
+        // Un program change no se suelta: es siempre un toque corto
+        if message.kind == MidiKind::ProgramChange {
+            return self.release(&key, now);
+        }
+        None
+    }
+
+    pub(crate) fn release(&mut self, key: &ControlKey, now: Instant) -> Option<MidiGesture> {
+        let hold = self.holds.get_mut(key)?;
+        hold.down.take()?;
+        if hold.long_fired {
+            self.holds.remove(key);
+            return None;
+        }
+        if hold.available.double_tap {
+            hold.tap = Some(now);
+            return None;
+        }
+        self.holds.remove(key);
+        Some(MidiGesture::Press)
+    }
+
+    // Pulsaciones largas cumplidas y toques que ya no van a ser dobles
+    pub(crate) fn expired(&mut self, now: Instant) -> Vec<(ControlKey, MidiMessage, MidiGesture)> {
+        let mut fired = Vec::new();
+        for (key, hold) in self.holds.iter_mut() {
+            let long = hold
+                .down
+                .is_some_and(|down| now.duration_since(down) >= LONG_PRESS);
+            if hold.available.long_press && long && !hold.long_fired {
+                hold.long_fired = true;
+                fired.push((key.clone(), hold.message, MidiGesture::LongPress));
+            }
+            if hold
+                .tap
+                .is_some_and(|tap| now.duration_since(tap) >= DOUBLE_TAP)
+            {
+                hold.tap = None;
+                fired.push((key.clone(), hold.message, MidiGesture::Press));
+            }
+        }
+        self.holds
+            .retain(|_, hold| hold.down.is_some() || hold.tap.is_some());
+        fired
+    }
+}
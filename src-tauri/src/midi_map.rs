@@ -0,0 +1,534 @@
+use crate::audio::AudioState;
+use crate::chase::{set_running_bpm, TempoState};
+use crate::cues::{cue_go, CueState};
+use crate::dmx::{DmxState, DMX_CHANNELS};
+use crate::looper::{self, PedalAction};
+use crate::midi::{MidiKind, MidiMessage};
+use crate::midi_gesture::{Available, Gestures, MidiGesture};
+use crate::scenes::{scene_recall, SceneState};
+use crate::storage;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub const MIDI_LEARN_EVENT: &str = "midi://learned";
+const MIDI_MAP_FILE: &str = "midi_map.json";
+// Un CC cuenta como botón apretado desde la mitad de su recorrido
+const CC_PRESS_THRESHOLD: u8 = 64;
+// Revisión de las pulsaciones largas y los dobles toques pendientes
+const GESTURE_INTERVAL: Duration = Duration::from_millis(10);
+
+// Mensaje que dispara un mapeo. Una nota responde tanto al note on como al note off.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MidiTrigger {
+    // Sin dispositivo responde a cualquier entrada
+    #[serde(default)]
+    device: Option<String>,
+    kind: MidiKind,
+    channel: u8,
+    number: u8,
+}
+
+impl MidiTrigger {
+    fn from_message(device: &str, message: &MidiMessage) -> Self {
+        Self {
+            device: Some(device.to_string()),
+            kind: note_as_on(message.kind),
+            channel: message.channel,
+            number: message.number,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !(1..=16).contains(&self.channel) {
+            return Err(format!("Canal MIDI inválido: {}", self.channel));
+        }
+        if self.number > 127 {
+            return Err(format!("Número MIDI inválido: {}", self.number));
+        }
+        Ok(())
+    }
+
+    fn matches(&self, device: &str, message: &MidiMessage) -> bool {
+        self.device.iter().all(|name| name == device)
+            && note_as_on(self.kind) == note_as_on(message.kind)
+            && self.channel == message.channel
+            && self.number == message.number
+    }
+
+    // Mismo control en el mismo dispositivo (o los dos sin dispositivo)
+    fn same_control(&self, other: &Self) -> bool {
+        note_as_on(self.kind) == note_as_on(other.kind)
+            && self.channel == other.channel
+            && self.number == other.number
+            && self.device == other.device
+    }
+}
+
+fn note_as_on(kind: MidiKind) -> MidiKind {
+    match kind {
+        MidiKind::NoteOff => MidiKind::NoteOn,
+        kind => kind,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MidiAction {
+    /// El valor del mensaje mueve el canal: un CC de 0-127 a `min`-`max` (0-255 por defecto),
+    /// una nota a `max` o `min`. Con `min` mayor que `max` el control queda invertido.
+    ChannelLevel {
+        universe: u16,
+        channel: u16,
+        #[serde(default)]
+        min: Option<u8>,
+        #[serde(default)]
+        max: Option<u8>,
+    },
+    SceneRecall {
+        scene: String,
+        #[serde(default)]
+        fade_ms: Option<u64>,
+    },
+    CueGo,
+    TapTempo,
+    /// Sin `track` va a la pista elegida del looper
+    Looper {
+        action: PedalAction,
+        #[serde(default)]
+        track: Option<usize>,
+    },
+}
+
+impl MidiAction {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            MidiAction::ChannelLevel { channel, .. }
+                if !(1..=DMX_CHANNELS as u16).contains(channel) =>
+            {
+                Err(format!("Canal DMX inválido: {channel}"))
+            }
+            MidiAction::SceneRecall { scene, .. } if scene.trim().is_empty() => {
+                Err("Falta el nombre de la escena".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MidiMapping {
+    trigger: MidiTrigger,
+    action: MidiAction,
+    // Un control puede tener una acción por gesto
+    #[serde(default)]
+    gesture: MidiGesture,
+}
+
+// Canal DMX que sigue directo a un control, ya con su escala resuelta
+#[derive(Clone, Copy)]
+struct LevelTarget {
+    universe: u16,
+    channel: u16,
+    min: u8,
+    max: u8,
+}
+
+impl LevelTarget {
+    fn scale(&self, level: u8) -> u8 {
+        let (min, max) = (self.min as i32, self.max as i32);
+        (min + (max - min) * level as i32 / 255) as u8
+    }
+}
+
+// Tipo (note on por note off), canal MIDI y número -> dispositivo del mapeo y canal destino
+type LevelIndex = HashMap<(MidiKind, u8, u8), Vec<(Option<String>, LevelTarget)>>;
+
+#[derive(Default)]
+struct MidiMap {
+    mappings: Vec<MidiMapping>,
+    // Los mapeos a canales DMX, indexados por control: cada mensaje va directo al buffer del
+    // universo sin recorrer la tabla ni pasar por el front
+    levels: LevelIndex,
+    // Acción que espera el próximo mensaje para quedar mapeada
+    learning: Option<(MidiAction, MidiGesture)>,
+    // CC por encima del umbral, para disparar los botones solo al apretar
+    pressed: HashSet<(String, u8, u8)>,
+    gestures: Gestures,
+}
+
+impl MidiMap {
+    // Un control dispara una sola acción por gesto: el mapeo nuevo reemplaza al anterior
+    fn insert(&mut self, mapping: MidiMapping) {
+        self.mappings.retain(|current| {
+            !current.trigger.same_control(&mapping.trigger) || current.gesture != mapping.gesture
+        });
+        self.mappings.push(mapping);
+        self.reindex();
+    }
+
+    // Hay que llamarlo cada vez que cambian los mapeos
+    fn reindex(&mut self) {
+        self.levels.clear();
+        for mapping in &self.mappings {
+            let MidiAction::ChannelLevel {
+                universe,
+                channel,
+                min,
+                max,
+            } = mapping.action
+            else {
+                continue;
+            };
+            let trigger = &mapping.trigger;
+            let target = LevelTarget {
+                universe,
+                channel,
+                min: min.unwrap_or(0),
+                max: max.unwrap_or(255),
+            };
+            self.levels
+                .entry((note_as_on(trigger.kind), trigger.channel, trigger.number))
+                .or_default()
+                .push((trigger.device.clone(), target));
+        }
+    }
+
+    fn level_targets(&self, device: &str, message: &MidiMessage) -> Vec<LevelTarget> {
+        let key = (note_as_on(message.kind), message.channel, message.number);
+        self.levels.get(&key).map_or_else(Vec::new, |targets| {
+            targets
+                .iter()
+                .filter(|(name, _)| name.iter().all(|name| name == device))
+                .map(|(_, target)| *target)
+                .collect()
+        })
+    }
+
+    // `Some(true)` si el mensaje aprieta un botón y `Some(false)` si lo suelta; recuerda el
+    // estado de los CC
+    fn edge(&mut self, device: &str, message: &MidiMessage) -> Option<bool> {
+        match message.kind {
+            MidiKind::NoteOn | MidiKind::ProgramChange => Some(true),
+            MidiKind::NoteOff => Some(false),
+            MidiKind::ControlChange => {
+                let key = (device.to_string(), message.channel, message.number);
+                if message.value >= CC_PRESS_THRESHOLD {
+                    self.pressed.insert(key).then_some(true)
+                } else {
+                    self.pressed.remove(&key).then_some(false)
+                }
+            }
+        }
+    }
+
+    fn buttons<'a>(
+        &'a self,
+        device: &'a str,
+        message: &'a MidiMessage,
+    ) -> impl Iterator<Item = &'a MidiMapping> {
+        self.mappings
+            .iter()
+            .filter(|mapping| !matches!(mapping.action, MidiAction::ChannelLevel { .. }))
+            .filter(move |mapping| mapping.trigger.matches(device, message))
+    }
+
+    fn available(&self, device: &str, message: &MidiMessage) -> Available {
+        let mut available = Available::default();
+        for mapping in self.buttons(device, message) {
+            match mapping.gesture {
+                MidiGesture::Press => {}
+                MidiGesture::LongPress => available.long_press = true,
+                MidiGesture::DoubleTap => available.double_tap = true,
+            }
+        }
+        available
+    }
+
+    fn actions(
+        &self,
+        device: &str,
+        message: &MidiMessage,
+        gesture: MidiGesture,
+    ) -> Vec<MidiAction> {
+        self.buttons(device, message)
+            .filter(|mapping| mapping.gesture == gesture)
+            .map(|mapping| mapping.action.clone())
+            .collect()
+    }
+}
+
+#[derive(Default)]
+pub struct MidiMapState {
+    map: Mutex<MidiMap>,
+    path: Mutex<Option<PathBuf>>,
+}
+
+impl MidiMapState {
+    pub fn load(&self, app_handle: &AppHandle) {
+        let Some(path) = storage::config_file(app_handle, MIDI_MAP_FILE) else {
+            return;
+        };
+
+        if let Some(mappings) = storage::load_json::<Vec<MidiMapping>>(&path) {
+            if let Ok(mut map) = self.map.lock() {
+                map.mappings = mappings;
+                map.reindex();
+            }
+        }
+
+        if let Ok(mut current) = self.path.lock() {
+            *current = Some(path);
+        }
+    }
+
+    pub(crate) fn export(&self) -> Result<Vec<MidiMapping>, String> {
+        Ok(self.lock_map()?.mappings.clone())
+    }
+
+    pub(crate) fn replace(&self, mappings: Vec<MidiMapping>) -> Result<(), String> {
+        let mut map = self.lock_map()?;
+        map.mappings = mappings;
+        map.learning = None;
+        map.reindex();
+        self.save(&map)
+    }
+
+    fn lock_map(&self) -> Result<std::sync::MutexGuard<'_, MidiMap>, String> {
+        self.map
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el mapeo MIDI: {e}"))
+    }
+
+    fn save(&self, map: &MidiMap) -> Result<(), String> {
+        let path = self
+            .path
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el mapeo MIDI: {e}"))?
+            .clone();
+
+        match path {
+            Some(path) => storage::save_json(&path, &map.mappings),
+            None => Ok(()),
+        }
+    }
+
+    // Aprende el mensaje si hay una acción esperando; si no, devuelve lo que dispara
+    fn route(
+        &self,
+        app_handle: &AppHandle,
+        device: &str,
+        message: &MidiMessage,
+    ) -> Result<Routed, String> {
+        let mut map = self.lock_map()?;
+        let edge = map.edge(device, message);
+
+        // Se aprende al apretar, así soltar la tecla o bajar el fader no pisa el control
+        if edge == Some(true) {
+            if let Some((action, gesture)) = map.learning.take() {
+                let mapping = MidiMapping {
+                    trigger: MidiTrigger::from_message(device, message),
+                    action,
+                    gesture,
+                };
+                map.insert(mapping.clone());
+                self.save(&map)?;
+
+                info!("Mapeo MIDI aprendido en {device}");
+                if let Err(err) = app_handle.emit(MIDI_LEARN_EVENT, mapping) {
+                    error!("No se pudo emitir el mapeo MIDI aprendido: {err}");
+                }
+                return Ok(Routed::default());
+            }
+        }
+
+        let levels = map.level_targets(device, message);
+        // Las demás acciones solo se disparan al resolverse un gesto del botón
+        let key = (
+            device.to_string(),
+            note_as_on(message.kind),
+            message.channel,
+            message.number,
+        );
+        let now = Instant::now();
+        let gesture = match edge {
+            Some(true) => {
+                let available = map.available(device, message);
+                map.gestures.press(key, *message, available, now)
+            }
+            Some(false) => map.gestures.release(&key, now),
+            None => None,
+        };
+        let actions = gesture
+            .map(|gesture| map.actions(device, message, gesture))
+            .unwrap_or_default();
+        Ok(Routed { levels, actions })
+    }
+}
+
+#[derive(Default)]
+struct Routed {
+    levels: Vec<LevelTarget>,
+    // Acciones de botón, solo cuando el mensaje cuenta como botón apretado
+    actions: Vec<MidiAction>,
+}
+
+fn channel_level(message: &MidiMessage) -> Option<u8> {
+    match message.kind {
+        MidiKind::NoteOn => Some(255),
+        MidiKind::NoteOff => Some(0),
+        // 127 tiene que llegar a 255
+        MidiKind::ControlChange => Some(message.value * 2 + message.value / 64),
+        MidiKind::ProgramChange => None,
+    }
+}
+
+fn run(app_handle: &AppHandle, action: MidiAction) -> Result<(), String> {
+    match action {
+        // Van por el índice de niveles
+        MidiAction::ChannelLevel { .. } => Ok(()),
+        MidiAction::SceneRecall { scene, fade_ms } => scene_recall(
+            app_handle.clone(),
+            app_handle.state::<SceneState>(),
+            app_handle.state::<DmxState>(),
+            scene,
+            fade_ms,
+        ),
+        MidiAction::CueGo => cue_go(
+            app_handle.clone(),
+            app_handle.state::<CueState>(),
+            app_handle.state::<DmxState>(),
+        )
+        .map(|_| ()),
+        MidiAction::TapTempo => {
+            if let Some(bpm) = app_handle.state::<TempoState>().tap(Instant::now())? {
+                set_running_bpm(&app_handle.state::<DmxState>(), bpm);
+            }
+            Ok(())
+        }
+        MidiAction::Looper { action, track } => {
+            looper::pedal(&app_handle.state::<AudioState>(), action, track)
+        }
+    }
+}
+
+// Dispara las pulsaciones largas apenas se cumplen y los toques simples cuando vence la
+// ventana del doble toque, aunque no llegue otro mensaje
+pub fn start_gestures(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    thread::spawn(move || loop {
+        thread::sleep(GESTURE_INTERVAL);
+        let state = app_handle.state::<MidiMapState>();
+        let actions = match state.lock_map() {
+            Ok(mut map) => {
+                let fired = map.gestures.expired(Instant::now());
+                fired
+                    .into_iter()
+                    .flat_map(|((device, ..), message, gesture)| {
+                        map.actions(&device, &message, gesture)
+                    })
+                    .collect::<Vec<_>>()
+            }
+            Err(err) => {
+                error!("{err}");
+                continue;
+            }
+        };
+        for action in actions {
+            if let Err(err) = run(&app_handle, action) {
+                error!("Acción MIDI fallida: {err}");
+            }
+        }
+    });
+}
+
+// Llamado por cada mensaje de una entrada MIDI abierta
+pub(crate) fn handle(app_handle: &AppHandle, device: &str, message: &MidiMessage) {
+    let routed = app_handle
+        .state::<MidiMapState>()
+        .route(app_handle, device, message);
+    let routed = match routed {
+        Ok(routed) => routed,
+        Err(err) => {
+            error!("{err}");
+            return;
+        }
+    };
+
+    if let Some(level) = channel_level(message) {
+        let dmx = app_handle.state::<DmxState>();
+        for target in routed.levels {
+            let value = target.scale(level);
+            let written = dmx.with_universe(app_handle.clone(), target.universe, |shared| {
+                shared.patch_levels(target.channel, &[value])
+            });
+            if let Err(err) = written {
+                error!("Acción MIDI fallida: {err}");
+            }
+        }
+    }
+    for action in routed.actions {
+        if let Err(err) = run(app_handle, action) {
+            error!("Acción MIDI fallida: {err}");
+        }
+    }
+}
+
+// El próximo control que se apriete en una entrada abierta queda mapeado a `action`, con
+// `gesture` (al apretar si no se indica)
+#[tauri::command]
+pub fn midi_learn_start(
+    state: State<'_, MidiMapState>,
+    action: MidiAction,
+    gesture: Option<MidiGesture>,
+) -> Result<(), String> {
+    action.validate()?;
+    state.lock_map()?.learning = Some((action, gesture.unwrap_or_default()));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn midi_learn_cancel(state: State<'_, MidiMapState>) -> Result<(), String> {
+    state.lock_map()?.learning = None;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn midi_map_add(state: State<'_, MidiMapState>, mapping: MidiMapping) -> Result<(), String> {
+    mapping.trigger.validate()?;
+    mapping.action.validate()?;
+
+    let mut map = state.lock_map()?;
+    map.insert(mapping);
+    state.save(&map)
+}
+
+// Sin `gesture` quita todos los mapeos del control
+#[tauri::command]
+pub fn midi_map_remove(
+    state: State<'_, MidiMapState>,
+    trigger: MidiTrigger,
+    gesture: Option<MidiGesture>,
+) -> Result<(), String> {
+    let mut map = state.lock_map()?;
+    let before = map.mappings.len();
+    map.mappings.retain(|mapping| {
+        !mapping.trigger.same_control(&trigger)
+            || gesture.is_some_and(|gesture| gesture != mapping.gesture)
+    });
+    if map.mappings.len() == before {
+        return Err("No hay un mapeo MIDI para ese control".to_string());
+    }
+    map.reindex();
+    state.save(&map)
+}
+
+#[tauri::command]
+pub fn midi_map_list(state: State<'_, MidiMapState>) -> Result<Vec<MidiMapping>, String> {
+    state.export()
+}
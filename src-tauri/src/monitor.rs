@@ -0,0 +1,117 @@
+use crate::dmx::DmxState;
+use log::error;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, State};
+
+pub const LEVELS_EVENT: &str = "dmx://levels";
+const DEFAULT_INTERVAL_MS: u64 = 100;
+const MIN_INTERVAL_MS: u64 = 20;
+const MAX_INTERVAL_MS: u64 = 5000;
+
+#[derive(Clone, Serialize)]
+pub struct LevelsEvent {
+    universe: u16,
+    // Pares [canal (1-512), valor] que cambiaron desde el último evento
+    changes: Vec<(u16, u8)>,
+}
+
+// Monitor de salida: como mucho un evento por intervalo y solo con los canales que cambiaron
+pub(crate) struct OutputMonitor {
+    enabled: bool,
+    interval: Duration,
+    last_emit: Option<Instant>,
+    // Lo último que se informó al front, `None` hasta el primer evento
+    reported: Option<Vec<u8>>,
+}
+
+impl Default for OutputMonitor {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: Duration::from_millis(DEFAULT_INTERVAL_MS),
+            last_emit: None,
+            reported: None,
+        }
+    }
+}
+
+impl OutputMonitor {
+    // Lo llama el hilo de escritura con cada frame (sin start code)
+    pub(crate) fn observe(
+        &mut self,
+        app_handle: &AppHandle,
+        universe: u16,
+        levels: &[u8],
+        now: Instant,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        if self
+            .last_emit
+            .is_some_and(|last| now.saturating_duration_since(last) < self.interval)
+        {
+            return;
+        }
+
+        let changes = match &self.reported {
+            Some(reported) => levels
+                .iter()
+                .zip(reported)
+                .enumerate()
+                .filter(|(_, (level, reported))| level != reported)
+                .map(|(idx, (level, _))| (idx as u16 + 1, *level))
+                .collect::<Vec<_>>(),
+            None => levels
+                .iter()
+                .enumerate()
+                .map(|(idx, level)| (idx as u16 + 1, *level))
+                .collect(),
+        };
+        if changes.is_empty() {
+            return;
+        }
+
+        self.last_emit = Some(now);
+        self.reported = Some(levels.to_vec());
+        if let Err(err) = app_handle.emit(LEVELS_EVENT, LevelsEvent { universe, changes }) {
+            error!("No se pudo emitir el monitor DMX: {err}");
+        }
+    }
+}
+
+// Sin universo configura todos. Al reactivarlo el siguiente evento trae el frame completo.
+#[tauri::command]
+pub fn dmx_set_monitor(
+    state: State<'_, DmxState>,
+    universe: Option<u16>,
+    enabled: bool,
+    interval_ms: Option<u64>,
+) -> Result<(), String> {
+    if let Some(interval_ms) = interval_ms {
+        if !(MIN_INTERVAL_MS..=MAX_INTERVAL_MS).contains(&interval_ms) {
+            return Err(format!(
+                "El intervalo del monitor debe estar entre {MIN_INTERVAL_MS} y {MAX_INTERVAL_MS} ms"
+            ));
+        }
+    }
+
+    let mut result = Ok(());
+    state.for_each_universe(|id, shared| {
+        if universe.is_some_and(|universe| universe != id) {
+            return;
+        }
+        match shared.monitor.lock() {
+            Ok(mut monitor) => {
+                monitor.enabled = enabled;
+                monitor.reported = None;
+                if let Some(interval_ms) = interval_ms {
+                    monitor.interval = Duration::from_millis(interval_ms);
+                }
+            }
+            Err(err) => result = Err(format!("No se pudo bloquear el monitor DMX: {err}")),
+        }
+    });
+    result
+}
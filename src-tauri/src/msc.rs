@@ -0,0 +1,224 @@
+use crate::cues::CueState;
+use crate::dmx::DmxState;
+use crate::groups::{submaster_set_level, GroupState};
+use crate::midi::MidiState;
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+// F0 7F <device_id> 02 <command_format> <command> <datos> F7
+const SYSEX_START: u8 = 0xF0;
+const SYSEX_END: u8 = 0xF7;
+const UNIVERSAL_REAL_TIME: u8 = 0x7F;
+const MSC_SUB_ID: u8 = 0x02;
+const ALL_CALL: u8 = 0x7F;
+const MAX_DEVICE_ID: u8 = 0x6F;
+const LIGHTING_FORMAT: u8 = 0x01;
+const ALL_TYPES_FORMAT: u8 = 0x7F;
+const MAX_SET_VALUE: f64 = 16383.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum MscCommand {
+    Go,
+    Stop,
+    Resume,
+    /// Número de control y valor, de 14 bits cada uno
+    Set,
+}
+
+impl MscCommand {
+    fn code(self) -> u8 {
+        match self {
+            MscCommand::Go => 0x01,
+            MscCommand::Stop => 0x02,
+            MscCommand::Resume => 0x03,
+            MscCommand::Set => 0x06,
+        }
+    }
+
+    fn from_code(code: u8) -> Option<Self> {
+        [
+            MscCommand::Go,
+            MscCommand::Stop,
+            MscCommand::Resume,
+            MscCommand::Set,
+        ]
+        .into_iter()
+        .find(|command| command.code() == code)
+    }
+}
+
+struct MscMessage<'a> {
+    device_id: u8,
+    format: u8,
+    command: MscCommand,
+    data: &'a [u8],
+}
+
+impl<'a> MscMessage<'a> {
+    fn parse(bytes: &'a [u8]) -> Option<Self> {
+        let body = bytes.strip_prefix(&[SYSEX_START])?;
+        let body = body.strip_suffix(&[SYSEX_END]).unwrap_or(body);
+        match body {
+            [UNIVERSAL_REAL_TIME, device_id, MSC_SUB_ID, format, command, data @ ..] => {
+                Some(Self {
+                    device_id: *device_id,
+                    format: *format,
+                    command: MscCommand::from_code(*command)?,
+                    data,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    // El número de cue va en ASCII ("12.5") hasta el primer 00, que separa la lista
+    fn cue_number(&self) -> Option<f64> {
+        let end = self
+            .data
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(self.data.len());
+        std::str::from_utf8(&self.data[..end])
+            .ok()
+            .filter(|text| !text.is_empty())?
+            .parse()
+            .ok()
+    }
+
+    // (control, valor), en LSB-MSB de 7 bits
+    fn set_value(&self) -> Option<(u16, u16)> {
+        match self.data {
+            [control_lsb, control_msb, value_lsb, value_msb, ..] => Some((
+                *control_lsb as u16 | (*control_msb as u16) << 7,
+                *value_lsb as u16 | (*value_msb as u16) << 7,
+            )),
+            _ => None,
+        }
+    }
+}
+
+fn encode(device_id: u8, command: MscCommand, cue: Option<f64>) -> Vec<u8> {
+    let mut bytes = vec![
+        SYSEX_START,
+        UNIVERSAL_REAL_TIME,
+        device_id,
+        MSC_SUB_ID,
+        LIGHTING_FORMAT,
+        command.code(),
+    ];
+    if let Some(cue) = cue {
+        bytes.extend(cue.to_string().bytes());
+    }
+    bytes.push(SYSEX_END);
+    bytes
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MscConfig {
+    // 0-111; los mensajes a todos los dispositivos (0x7F) también se aceptan
+    device_id: u8,
+    receive: bool,
+    // Salida MIDI por la que se anuncian los cambios de cue; sin ella no se transmite
+    #[serde(default)]
+    transmit: Option<String>,
+}
+
+#[derive(Default)]
+pub struct MscState {
+    config: Mutex<MscConfig>,
+}
+
+impl MscState {
+    fn config(&self) -> MscConfig {
+        self.config
+            .lock()
+            .map(|config| config.clone())
+            .unwrap_or_default()
+    }
+}
+
+fn run(app_handle: &AppHandle, message: &MscMessage) -> Result<(), String> {
+    let cues = app_handle.state::<CueState>();
+    let dmx = app_handle.state::<DmxState>();
+    match message.command {
+        MscCommand::Go => cues
+            .go(app_handle, &dmx, message.cue_number(), None)
+            .map(|_| ()),
+        MscCommand::Stop => cues.pause(&dmx, true),
+        MscCommand::Resume => cues.pause(&dmx, false),
+        // El número de control es el id del grupo cuyo submaster se mueve
+        MscCommand::Set => {
+            let (control, value) = message
+                .set_value()
+                .ok_or_else(|| "SET de MSC incompleto".to_string())?;
+            let level = (value as f64 / MAX_SET_VALUE * 255.0).round().min(255.0) as u8;
+            submaster_set_level(
+                app_handle.clone(),
+                app_handle.state::<GroupState>(),
+                control as u32,
+                level,
+            )
+        }
+    }
+}
+
+// Llamado por cada SysEx de una entrada abierta; lo que no es MSC para este dispositivo se
+// ignora
+pub(crate) fn handle(app_handle: &AppHandle, bytes: &[u8]) {
+    let Some(message) = MscMessage::parse(bytes) else {
+        return;
+    };
+    let config = app_handle.state::<MscState>().config();
+    if !config.receive
+        || (message.device_id != config.device_id && message.device_id != ALL_CALL)
+        || (message.format != LIGHTING_FORMAT && message.format != ALL_TYPES_FORMAT)
+    {
+        return;
+    }
+
+    debug!("MSC recibido: {:?}", message.command);
+    if let Err(err) = run(app_handle, &message) {
+        error!("Comando MSC fallido: {err}");
+    }
+}
+
+// Anuncia un cambio de la lista de cues por la salida configurada, si hay una
+pub(crate) fn announce(app_handle: &AppHandle, command: MscCommand, cue: Option<f64>) {
+    let config = app_handle.state::<MscState>().config();
+    let Some(device) = config.transmit else {
+        return;
+    };
+    let bytes = encode(config.device_id, command, cue);
+    if let Err(err) = app_handle.state::<MidiState>().send(&device, &bytes) {
+        error!("No se pudo transmitir MSC: {err}");
+    }
+}
+
+#[tauri::command]
+pub fn msc_configure(state: State<'_, MscState>, config: MscConfig) -> Result<(), String> {
+    if config.device_id > MAX_DEVICE_ID {
+        return Err(format!(
+            "El device ID de MSC va de 0 a {MAX_DEVICE_ID}, no {}",
+            config.device_id
+        ));
+    }
+
+    info!(
+        "MSC: device ID {}, recepción {}, transmisión {}",
+        config.device_id,
+        if config.receive { "activa" } else { "apagada" },
+        config.transmit.as_deref().unwrap_or("apagada")
+    );
+    *state
+        .config
+        .lock()
+        .map_err(|e| format!("No se pudo bloquear la configuración MSC: {e}"))? = config;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn msc_get_config(state: State<'_, MscState>) -> MscConfig {
+    state.config()
+}
@@ -0,0 +1,321 @@
+use crate::cues::CueState;
+use crate::dmx::DmxState;
+use crate::timeline;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub const MTC_EVENT: &str = "midi://timecode";
+const QUARTER_FRAME: u8 = 0xF1;
+// F0 7F <device_id> 01 01 hh mm ss ff F7
+const SYSEX_START: u8 = 0xF0;
+const SYSEX_END: u8 = 0xF7;
+const UNIVERSAL_REAL_TIME: u8 = 0x7F;
+const MTC_SUB_ID: u8 = 0x01;
+const FULL_FRAME: u8 = 0x01;
+// Un salto mayor es un locate: no se disparan las cues del medio
+const MAX_ADVANCE_SECS: u32 = 1;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MtcRate {
+    Fps24,
+    Fps25,
+    /// 29,97 drop frame; los cuadros se cuentan como a 30
+    Fps2997Drop,
+    #[default]
+    Fps30,
+}
+
+impl MtcRate {
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0 => MtcRate::Fps24,
+            1 => MtcRate::Fps25,
+            2 => MtcRate::Fps2997Drop,
+            _ => MtcRate::Fps30,
+        }
+    }
+
+    pub(crate) fn fps(self) -> u32 {
+        match self {
+            MtcRate::Fps24 => 24,
+            MtcRate::Fps25 => 25,
+            MtcRate::Fps2997Drop | MtcRate::Fps30 => 30,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timecode {
+    hours: u8,
+    minutes: u8,
+    seconds: u8,
+    frames: u8,
+}
+
+impl Timecode {
+    pub(crate) fn new(hours: u8, minutes: u8, seconds: u8, frames: u8) -> Self {
+        Self {
+            hours,
+            minutes,
+            seconds,
+            frames,
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if self.hours > 23 || self.minutes > 59 || self.seconds > 59 || self.frames > 29 {
+            return Err(format!("Timecode inválido: {self}"));
+        }
+        Ok(())
+    }
+
+    // Cuadros desde 00:00:00:00 a la frecuencia dada
+    pub(crate) fn to_frames(self, rate: MtcRate) -> u32 {
+        let seconds = (self.hours as u32 * 60 + self.minutes as u32) * 60 + self.seconds as u32;
+        seconds * rate.fps() + self.frames as u32
+    }
+
+    pub(crate) fn from_frames(frames: u32, rate: MtcRate) -> Self {
+        let fps = rate.fps();
+        let seconds = frames / fps;
+        Self {
+            hours: (seconds / 3600 % 24) as u8,
+            minutes: (seconds / 60 % 60) as u8,
+            seconds: (seconds % 60) as u8,
+            frames: (frames % fps) as u8,
+        }
+    }
+}
+
+impl std::fmt::Display for Timecode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}:{:02}:{:02}",
+            self.hours, self.minutes, self.seconds, self.frames
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum MtcMessage {
+    /// Pieza (0-7) y su nibble
+    QuarterFrame(u8, u8),
+    /// Posición completa, mandada al hacer un locate
+    FullFrame(Timecode, MtcRate),
+}
+
+impl MtcMessage {
+    pub(crate) fn parse(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [QUARTER_FRAME, data] => Some(Self::QuarterFrame((data >> 4) & 0x07, data & 0x0F)),
+            [SYSEX_START, UNIVERSAL_REAL_TIME, _device_id, MTC_SUB_ID, FULL_FRAME, hours, minutes, seconds, frames, SYSEX_END] => {
+                Some(Self::FullFrame(
+                    Timecode {
+                        hours: hours & 0x1F,
+                        minutes: *minutes,
+                        seconds: *seconds,
+                        frames: *frames,
+                    },
+                    MtcRate::from_bits(hours >> 5),
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct MtcStatus {
+    enabled: bool,
+    device: Option<String>,
+    timecode: Option<Timecode>,
+    rate: MtcRate,
+}
+
+#[derive(Default)]
+struct MtcReader {
+    enabled: bool,
+    // Sin dispositivo se sigue el timecode de cualquier entrada abierta
+    device: Option<String>,
+    pieces: [u8; 8],
+    // Piezas recibidas desde la última 0, como máscara de bits
+    received: u8,
+    rate: MtcRate,
+    // Cuadros desde 00:00:00:00; `None` hasta armar la primera posición
+    position: Option<u32>,
+}
+
+impl MtcReader {
+    fn status(&self) -> MtcStatus {
+        MtcStatus {
+            enabled: self.enabled,
+            device: self.device.clone(),
+            timecode: self
+                .position
+                .map(|frames| Timecode::from_frames(frames, self.rate)),
+            rate: self.rate,
+        }
+    }
+
+    // Devuelve la posición nueva cuando cambia y si fue un locate
+    fn receive(&mut self, message: MtcMessage) -> Option<(u32, bool)> {
+        match message {
+            MtcMessage::FullFrame(timecode, rate) => {
+                self.rate = rate;
+                self.received = 0;
+                let frames = timecode.to_frames(rate);
+                self.position = Some(frames);
+                Some((frames, true))
+            }
+            MtcMessage::QuarterFrame(piece, value) => {
+                if piece == 0 {
+                    self.received = 0;
+                }
+                self.pieces[piece as usize] = value;
+                self.received |= 1 << piece;
+
+                match piece {
+                    // Cada cuatro piezas pasa un cuadro: en la mitad se avanza el que ya se
+                    // conocía
+                    3 => {
+                        let frames = self.position? + 1;
+                        self.position = Some(frames);
+                        Some((frames, false))
+                    }
+                    // Las ocho piezas describen el cuadro en que empezó la serie, que ya quedó
+                    // dos cuadros atrás
+                    7 if self.received == 0xFF => {
+                        let p = self.pieces;
+                        self.rate = MtcRate::from_bits(p[7] >> 1);
+                        let timecode = Timecode {
+                            hours: p[6] | (p[7] & 0x01) << 4,
+                            minutes: p[4] | (p[5] & 0x03) << 4,
+                            seconds: p[2] | (p[3] & 0x03) << 4,
+                            frames: p[0] | (p[1] & 0x01) << 4,
+                        };
+                        let frames = timecode.to_frames(self.rate) + 2;
+                        let located = is_locate(self.position, frames, self.rate);
+                        self.position = Some(frames);
+                        Some((frames, located))
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+// MIDI timecode entrante: las cues con timecode se disparan cuando la posición las cruza
+#[derive(Default)]
+pub struct MtcState {
+    reader: Mutex<MtcReader>,
+}
+
+impl MtcState {
+    fn lock_reader(&self) -> Result<std::sync::MutexGuard<'_, MtcReader>, String> {
+        self.reader
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el timecode MIDI: {e}"))
+    }
+}
+
+// Un salto hacia atrás o de más de un segundo no es avance: es un locate
+pub(crate) fn is_locate(previous: Option<u32>, frames: u32, rate: MtcRate) -> bool {
+    let max_advance = MAX_ADVANCE_SECS * rate.fps();
+    !previous.is_some_and(|previous| frames >= previous && frames - previous <= max_advance)
+}
+
+// Al avanzar se dispara la última cue cruzada desde el cuadro anterior. En un locate se salta
+// sin fade a la última cue antes de la posición nueva, así el estado queda como si la lista
+// se hubiera seguido desde el principio.
+pub(crate) fn chase_cues(
+    app_handle: &AppHandle,
+    previous: Option<u32>,
+    frames: u32,
+    located: bool,
+    rate: MtcRate,
+) {
+    let cues = app_handle.state::<CueState>();
+    let from = if located { None } else { previous };
+    let target = cues
+        .timecode_cues()
+        .into_iter()
+        .filter(|(timecode, _)| {
+            let at = timecode.to_frames(rate);
+            at <= frames && from.iter().all(|from| at > *from)
+        })
+        .max_by_key(|(timecode, _)| timecode.to_frames(rate));
+    let Some((timecode, number)) = target else {
+        return;
+    };
+    if located && cues.active_number() == Some(number) {
+        return;
+    }
+
+    let dmx = app_handle.state::<DmxState>();
+    let fade_ms = located.then_some(0);
+    match cues.go(app_handle, &dmx, Some(number), fade_ms) {
+        Ok(_) => info!("Cue {number} disparada por timecode {timecode}"),
+        Err(err) => error!("No se pudo disparar la cue {number} por timecode: {err}"),
+    }
+}
+
+// Llamado por cada mensaje de timecode de una entrada abierta
+pub(crate) fn handle(app_handle: &AppHandle, device: &str, message: MtcMessage) {
+    let state = app_handle.state::<MtcState>();
+    let (previous, update, status) = {
+        let mut reader = match state.lock_reader() {
+            Ok(reader) => reader,
+            Err(err) => {
+                error!("{err}");
+                return;
+            }
+        };
+        if !reader.enabled || reader.device.as_deref().is_some_and(|name| name != device) {
+            return;
+        }
+        let previous = reader.position;
+        let Some(update) = reader.receive(message) else {
+            return;
+        };
+        (previous, update, reader.status())
+    };
+
+    let (frames, located) = update;
+    chase_cues(app_handle, previous, frames, located, status.rate);
+    timeline::follow_timecode(app_handle, frames, status.rate, located);
+    if let Err(err) = app_handle.emit(MTC_EVENT, status) {
+        error!("No se pudo emitir el timecode MIDI: {err}");
+    }
+}
+
+#[tauri::command]
+pub fn mtc_configure(
+    state: State<'_, MtcState>,
+    enabled: bool,
+    device: Option<String>,
+) -> Result<MtcStatus, String> {
+    let mut reader = state.lock_reader()?;
+    *reader = MtcReader {
+        enabled,
+        device,
+        ..MtcReader::default()
+    };
+
+    if enabled {
+        match &reader.device {
+            Some(device) => info!("Siguiendo el timecode MIDI de {device}"),
+            None => info!("Siguiendo el timecode MIDI de cualquier entrada"),
+        }
+    }
+    Ok(reader.status())
+}
+
+#[tauri::command]
+pub fn mtc_status(state: State<'_, MtcState>) -> Result<MtcStatus, String> {
+    Ok(state.lock_reader()?.status())
+}
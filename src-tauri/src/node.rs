@@ -0,0 +1,429 @@
+use crate::artnet::{self, ArtNetConfig, ARTNET_PORT};
+use crate::dmx::{DmxState, ReplaySource, DMX_CHANNELS, DMX_FRAME_SIZE};
+use crate::sacn::{self, SACN_PORT};
+use crate::storage;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+
+const NODE_FILE: &str = "node.json";
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+// Una fuente sACN de más prioridad se pierde a los 2,5 s sin paquetes (E1.31)
+const SACN_SOURCE_TIMEOUT: Duration = Duration::from_millis(2500);
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "protocol", rename_all = "snake_case")]
+pub enum NodeSource {
+    /// Universo Art-Net por net, subnet y universo
+    ArtNet { net: u8, subnet: u8, universe: u8 },
+    /// Universo sACN (1-63999), recibido por multicast
+    Sacn { universe: u16 },
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NodeRoute {
+    source: NodeSource,
+    // Universo local, con el puerto DMX que saca lo recibido
+    universe: u16,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NodeConfig {
+    routes: Vec<NodeRoute>,
+    #[serde(default)]
+    bind_ip: Option<String>,
+    // Arranca solo al abrir la app
+    #[serde(default)]
+    auto_start: bool,
+    // Con `auto_start`, además esconde la ventana: la laptop queda como nodo
+    #[serde(default)]
+    headless: bool,
+}
+
+#[derive(Clone, Serialize)]
+pub struct NodeRouteStatus {
+    source: NodeSource,
+    universe: u16,
+    frames: u64,
+    idle_ms: Option<u64>,
+    sender: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct NodeStatus {
+    running: bool,
+    routes: Vec<NodeRouteStatus>,
+}
+
+#[derive(Default)]
+struct RouteStats {
+    frames: u64,
+    last_seen: Option<Instant>,
+    sender: Option<IpAddr>,
+    priority: u8,
+}
+
+struct NodeRunner {
+    config: NodeConfig,
+    stats: Arc<Mutex<Vec<RouteStats>>>,
+    stop: Vec<Sender<()>>,
+    handles: Vec<thread::JoinHandle<()>>,
+}
+
+#[derive(Default)]
+pub struct NodeState {
+    runner: Mutex<Option<NodeRunner>>,
+    path: Mutex<Option<PathBuf>>,
+}
+
+impl NodeState {
+    pub fn load(&self, app_handle: &AppHandle) {
+        let Some(path) = storage::config_file(app_handle, NODE_FILE) else {
+            return;
+        };
+
+        let stored = storage::load_json::<NodeConfig>(&path);
+        if let Ok(mut current) = self.path.lock() {
+            *current = Some(path);
+        }
+        let Some(config) = stored.filter(|config| config.auto_start) else {
+            return;
+        };
+        let headless = config.headless;
+        if let Err(err) = self.start(app_handle, config) {
+            error!("No se pudo arrancar el modo nodo: {err}");
+            return;
+        }
+        if headless {
+            let hidden = app_handle
+                .get_webview_window("main")
+                .map(|window| window.hide());
+            if let Some(Err(err)) = hidden {
+                error!("No se pudo esconder la ventana del modo nodo: {err}");
+            }
+        }
+    }
+
+    fn lock_runner(&self) -> Result<std::sync::MutexGuard<'_, Option<NodeRunner>>, String> {
+        self.runner
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el modo nodo: {e}"))
+    }
+
+    fn save(&self, config: &NodeConfig) -> Result<(), String> {
+        let path = self
+            .path
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la configuración del nodo: {e}"))?
+            .clone();
+
+        match path {
+            Some(path) => storage::save_json(&path, config),
+            None => Ok(()),
+        }
+    }
+
+    fn start(&self, app_handle: &AppHandle, config: NodeConfig) -> Result<(), String> {
+        let bind_ip = match config.bind_ip.as_deref() {
+            Some(ip) => ip
+                .parse::<Ipv4Addr>()
+                .map_err(|e| format!("IP de escucha del nodo inválida ({ip}): {e}"))?,
+            None => Ipv4Addr::UNSPECIFIED,
+        };
+        for route in &config.routes {
+            match route.source {
+                // Reutiliza la validación de direccionamiento de la salida
+                NodeSource::ArtNet {
+                    net,
+                    subnet,
+                    universe,
+                } => ArtNetConfig::new(Ipv4Addr::UNSPECIFIED, net, subnet, universe).map(|_| ())?,
+                NodeSource::Sacn { universe } => sacn::check_universe(universe)?,
+            }
+        }
+        if config.routes.is_empty() {
+            return Err("El modo nodo necesita al menos una ruta".to_string());
+        }
+
+        let mut runner = self.lock_runner()?;
+        if let Some(previous) = runner.take() {
+            stop_runner(app_handle, previous);
+        }
+
+        // Los sockets se abren antes de los hilos, así un error no deja ninguno corriendo
+        let mut sockets = Vec::new();
+        let artnet = config
+            .routes
+            .iter()
+            .any(|route| matches!(route.source, NodeSource::ArtNet { .. }));
+        if artnet {
+            sockets.push((
+                Protocol::ArtNet,
+                open_socket(bind_ip, ARTNET_PORT, "Art-Net")?,
+            ));
+        }
+        let sacn = config
+            .routes
+            .iter()
+            .filter_map(|route| match route.source {
+                NodeSource::Sacn { universe } => Some(universe),
+                NodeSource::ArtNet { .. } => None,
+            })
+            .collect::<Vec<_>>();
+        if !sacn.is_empty() {
+            let socket = open_socket(bind_ip, SACN_PORT, "sACN")?;
+            for universe in sacn {
+                let group = sacn::multicast_ip(universe);
+                socket
+                    .join_multicast_v4(&group, &bind_ip)
+                    .map_err(|e| format!("No se pudo unir al grupo sACN {group}: {e}"))?;
+            }
+            sockets.push((Protocol::Sacn, socket));
+        }
+
+        let routes = Arc::new(config.routes.clone());
+        let stats = Arc::new(Mutex::new(
+            config
+                .routes
+                .iter()
+                .map(|_| RouteStats::default())
+                .collect::<Vec<_>>(),
+        ));
+        let mut stop = Vec::new();
+        let mut handles = Vec::new();
+        for (protocol, socket) in sockets {
+            let (tx, rx) = mpsc::channel();
+            let (app_handle, routes, stats) = (app_handle.clone(), routes.clone(), stats.clone());
+            handles.push(thread::spawn(move || {
+                run_receiver(app_handle, socket, protocol, &routes, &stats, rx)
+            }));
+            stop.push(tx);
+        }
+
+        info!(
+            "Modo nodo en marcha: {} rutas hacia la salida DMX local",
+            config.routes.len()
+        );
+        *runner = Some(NodeRunner {
+            config,
+            stats,
+            stop,
+            handles,
+        });
+        Ok(())
+    }
+
+    fn status(&self) -> Result<NodeStatus, String> {
+        let runner = self.lock_runner()?;
+        let Some(runner) = runner.as_ref() else {
+            return Ok(NodeStatus {
+                running: false,
+                routes: Vec::new(),
+            });
+        };
+        let stats = runner
+            .stats
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el estado del nodo: {e}"))?;
+        Ok(NodeStatus {
+            running: true,
+            routes: runner
+                .config
+                .routes
+                .iter()
+                .zip(stats.iter())
+                .map(|(route, stats)| NodeRouteStatus {
+                    source: route.source.clone(),
+                    universe: route.universe,
+                    frames: stats.frames,
+                    idle_ms: stats
+                        .last_seen
+                        .map(|last| last.elapsed().as_millis() as u64),
+                    sender: stats.sender.map(|sender| sender.to_string()),
+                })
+                .collect(),
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Protocol {
+    ArtNet,
+    Sacn,
+}
+
+fn open_socket(bind_ip: Ipv4Addr, port: u16, label: &str) -> Result<UdpSocket, String> {
+    // Con el receptor de Art-Net del merge en marcha el puerto ya está tomado
+    let socket = UdpSocket::bind((bind_ip, port))
+        .map_err(|e| format!("No se pudo escuchar {label} en {bind_ip}:{port}: {e}"))?;
+    socket
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .map_err(|e| format!("No se pudo configurar el socket {label}: {e}"))?;
+    Ok(socket)
+}
+
+// Devuelve la fuente, la prioridad y los canales (sin start code) del paquete
+fn parse(protocol: Protocol, packet: &[u8]) -> Option<(NodeSource, u8, &[u8])> {
+    match protocol {
+        Protocol::ArtNet => {
+            let (net, sub_uni, data) = artnet::parse_art_dmx(packet)?;
+            let source = NodeSource::ArtNet {
+                net,
+                subnet: sub_uni >> 4,
+                universe: sub_uni & 0x0F,
+            };
+            Some((source, 0, data))
+        }
+        Protocol::Sacn => {
+            let (universe, priority, frame) = sacn::parse_data_packet(packet)?;
+            // Otros start codes (p. ej. prioridad por canal) no son niveles
+            let (&start_code, data) = frame.split_first()?;
+            if start_code != 0 {
+                return None;
+            }
+            Some((NodeSource::Sacn { universe }, priority, data))
+        }
+    }
+}
+
+// Se queda con la fuente de más prioridad mientras siga viva; con la misma, la última
+fn accept(stats: &Mutex<Vec<RouteStats>>, idx: usize, sender: IpAddr, priority: u8) -> bool {
+    let Ok(mut stats) = stats.lock() else {
+        return false;
+    };
+    let Some(stats) = stats.get_mut(idx) else {
+        return false;
+    };
+    let alive = match stats.last_seen {
+        Some(last) => last.elapsed() < SACN_SOURCE_TIMEOUT,
+        None => false,
+    };
+    if alive && priority < stats.priority {
+        return false;
+    }
+    stats.frames += 1;
+    stats.last_seen = Some(Instant::now());
+    stats.sender = Some(sender);
+    stats.priority = priority;
+    true
+}
+
+// Lo recibido sale como frame de reemplazo por el loop DMX del universo: tal cual, sin
+// merge, efectos ni master
+fn run_receiver(
+    app_handle: AppHandle,
+    socket: UdpSocket,
+    protocol: Protocol,
+    routes: &[NodeRoute],
+    stats: &Mutex<Vec<RouteStats>>,
+    rx: Receiver<()>,
+) {
+    let mut buffer = [0u8; 1024];
+    let mut frame = [0u8; DMX_FRAME_SIZE];
+
+    while rx.try_recv().is_err() {
+        let (len, from) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            // El timeout de lectura permite revisar la señal de parada
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                continue
+            }
+            Err(err) => {
+                error!("Error al recibir en el modo nodo: {err}");
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+        };
+        let Some((source, priority, data)) = parse(protocol, &buffer[..len]) else {
+            continue;
+        };
+        let channels = data.len().min(DMX_CHANNELS);
+        frame[1..=channels].copy_from_slice(&data[..channels]);
+
+        let dmx = app_handle.state::<DmxState>();
+        for (idx, route) in routes.iter().enumerate() {
+            if route.source != source || !accept(stats, idx, from.ip(), priority) {
+                continue;
+            }
+            let result = dmx.with_universe(app_handle.clone(), route.universe, |shared| {
+                shared.set_replay_frame(ReplaySource::Node, &frame[..=channels])
+            });
+            if let Err(err) = result {
+                warn!(
+                    "No se pudo sacar lo recibido por el universo {}: {err}",
+                    route.universe
+                );
+            }
+        }
+    }
+
+    info!("Receptor del modo nodo detenido");
+}
+
+// Los universos vuelven a su salida normal
+fn stop_runner(app_handle: &AppHandle, runner: NodeRunner) {
+    for stop in &runner.stop {
+        let _ = stop.send(());
+    }
+    for handle in runner.handles {
+        if handle.join().is_err() {
+            error!("Un hilo del modo nodo terminó con pánico");
+        }
+    }
+    let dmx = app_handle.state::<DmxState>();
+    for route in &runner.config.routes {
+        let _ = dmx.read_universe(route.universe, |shared| {
+            shared.clear_replay_frame(ReplaySource::Node)
+        });
+    }
+    info!("Modo nodo detenido");
+}
+
+// Recibe Art-Net o sACN de otra consola y lo saca por los puertos DMX locales; la
+// configuración se guarda y con `auto_start` el nodo arranca solo al abrir la app
+#[tauri::command]
+pub fn node_start(
+    app_handle: AppHandle,
+    state: State<'_, NodeState>,
+    config: NodeConfig,
+) -> Result<NodeStatus, String> {
+    state.start(&app_handle, config.clone())?;
+    state.save(&config)?;
+    state.status()
+}
+
+#[tauri::command]
+pub fn node_stop(app_handle: AppHandle, state: State<'_, NodeState>) -> Result<(), String> {
+    let runner = state.lock_runner()?.take();
+    if let Some(runner) = runner {
+        stop_runner(&app_handle, runner);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn node_status(state: State<'_, NodeState>) -> Result<NodeStatus, String> {
+    state.status()
+}
+
+// La configuración guardada, esté o no en marcha
+#[tauri::command]
+pub fn node_get_config(state: State<'_, NodeState>) -> Result<Option<NodeConfig>, String> {
+    let path = state
+        .path
+        .lock()
+        .map_err(|e| format!("No se pudo bloquear la configuración del nodo: {e}"))?
+        .clone();
+    Ok(path.and_then(|path| storage::load_json(&path)))
+}
@@ -0,0 +1,474 @@
+use crate::chase::chase_tap_tempo;
+use crate::color::{emitter_levels, ColorValue};
+use crate::cues::{cue_back, cue_go, cue_goto, cue_release, CueState};
+use crate::dmx::DmxState;
+use crate::fixture::FixtureState;
+use crate::groups::{submaster_set_level, GroupState};
+use crate::scenes::{scene_recall, SceneState};
+use log::{debug, error, info};
+use serde::Serialize;
+use std::net::{Ipv4Addr, UdpSocket};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub const OSC_EVENT: &str = "osc://message";
+pub const DEFAULT_OSC_PORT: u16 = 8000;
+const BUNDLE_ID: &[u8; 8] = b"#bundle\0";
+// Cabecera del bundle: identificador y time tag
+const BUNDLE_HEADER_LEN: usize = 16;
+// Time tag "inmediato"
+const IMMEDIATELY: u64 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum OscArg {
+    Int(i32),
+    Float(f32),
+    String(String),
+    Bool(bool),
+}
+
+impl OscArg {
+    fn tag(&self) -> char {
+        match self {
+            OscArg::Int(_) => 'i',
+            OscArg::Float(_) => 'f',
+            OscArg::String(_) => 's',
+            OscArg::Bool(true) => 'T',
+            OscArg::Bool(false) => 'F',
+        }
+    }
+
+    // TouchOSC y Open Stage Control mandan los faders como float 0-1; un entero se toma
+    // como el valor DMX
+    fn level(&self) -> Option<u8> {
+        match self {
+            OscArg::Int(value) => Some((*value).clamp(0, 255) as u8),
+            OscArg::Float(value) => Some((value.clamp(0.0, 1.0) * 255.0).round() as u8),
+            OscArg::Bool(on) => Some(if *on { 255 } else { 0 }),
+            OscArg::String(_) => None,
+        }
+    }
+
+    fn number(&self) -> Option<f64> {
+        match self {
+            OscArg::Int(value) => Some(*value as f64),
+            OscArg::Float(value) => Some(*value as f64),
+            OscArg::String(text) => text.parse().ok(),
+            OscArg::Bool(_) => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct OscMessage {
+    address: String,
+    args: Vec<OscArg>,
+}
+
+impl OscMessage {
+    pub(crate) fn new(address: impl Into<String>, args: Vec<OscArg>) -> Self {
+        Self {
+            address: address.into(),
+            args,
+        }
+    }
+
+    pub(crate) fn encode(&self, packet: &mut Vec<u8>) {
+        write_string(packet, &self.address);
+        let tags = std::iter::once(',')
+            .chain(self.args.iter().map(OscArg::tag))
+            .collect::<String>();
+        write_string(packet, &tags);
+        for arg in &self.args {
+            match arg {
+                OscArg::Int(value) => packet.extend_from_slice(&value.to_be_bytes()),
+                OscArg::Float(value) => packet.extend_from_slice(&value.to_be_bytes()),
+                OscArg::String(text) => write_string(packet, text),
+                OscArg::Bool(_) => {}
+            }
+        }
+    }
+
+    // Un paquete puede ser un mensaje o un bundle con varios (que a su vez pueden ser bundles)
+    fn parse_packet(packet: &[u8], messages: &mut Vec<OscMessage>) {
+        if !packet.starts_with(BUNDLE_ID) {
+            if let Some(message) = Self::parse(packet) {
+                messages.push(message);
+            }
+            return;
+        }
+
+        let mut rest = packet.get(BUNDLE_HEADER_LEN..).unwrap_or_default();
+        while let [a, b, c, d, tail @ ..] = rest {
+            let size = u32::from_be_bytes([*a, *b, *c, *d]) as usize;
+            let Some(element) = tail.get(..size) else {
+                return;
+            };
+            Self::parse_packet(element, messages);
+            rest = &tail[size..];
+        }
+    }
+
+    fn parse(packet: &[u8]) -> Option<Self> {
+        let (address, rest) = read_string(packet)?;
+        if !address.starts_with('/') {
+            return None;
+        }
+        // Los mensajes viejos pueden venir sin type tags
+        let (tags, mut rest) = match read_string(rest) {
+            Some((tags, rest)) if tags.starts_with(',') => (tags, rest),
+            _ => (String::from(","), rest),
+        };
+
+        let mut args = Vec::new();
+        for tag in tags.chars().skip(1) {
+            let arg = match tag {
+                'i' => {
+                    let (bytes, tail) = split_word(rest)?;
+                    rest = tail;
+                    OscArg::Int(i32::from_be_bytes(bytes))
+                }
+                'f' => {
+                    let (bytes, tail) = split_word(rest)?;
+                    rest = tail;
+                    OscArg::Float(f32::from_be_bytes(bytes))
+                }
+                's' => {
+                    let (text, tail) = read_string(rest)?;
+                    rest = tail;
+                    OscArg::String(text)
+                }
+                'T' => OscArg::Bool(true),
+                'F' => OscArg::Bool(false),
+                // Otros tipos (blobs, dobles, etc.) no los usa ninguna dirección
+                _ => return None,
+            };
+            args.push(arg);
+        }
+
+        Some(Self { address, args })
+    }
+
+    // Botones: sin argumentos se dispara siempre; con uno, al apretar (TouchOSC manda 1 al
+    // apretar y 0 al soltar)
+    fn pressed(&self) -> bool {
+        match self.args.first() {
+            None | Some(OscArg::String(_)) => true,
+            Some(arg) => arg.level().is_some_and(|level| level > 0),
+        }
+    }
+}
+
+// Cadena terminada en 0 y rellenada hasta múltiplo de 4
+fn read_string(bytes: &[u8]) -> Option<(String, &[u8])> {
+    let end = bytes.iter().position(|byte| *byte == 0)?;
+    let text = std::str::from_utf8(&bytes[..end]).ok()?.to_string();
+    let padded = (end + 4) & !3;
+    Some((text, bytes.get(padded..)?))
+}
+
+fn write_string(packet: &mut Vec<u8>, text: &str) {
+    packet.extend_from_slice(text.as_bytes());
+    let padded = (text.len() + 4) & !3;
+    packet.resize(packet.len() + padded - text.len(), 0);
+}
+
+// Varios mensajes en un solo paquete, para ejecutar de inmediato
+pub(crate) fn encode_bundle(messages: &[OscMessage], packet: &mut Vec<u8>) {
+    packet.clear();
+    packet.extend_from_slice(BUNDLE_ID);
+    packet.extend_from_slice(&IMMEDIATELY.to_be_bytes());
+    let mut element = Vec::new();
+    for message in messages {
+        element.clear();
+        message.encode(&mut element);
+        packet.extend_from_slice(&(element.len() as u32).to_be_bytes());
+        packet.extend_from_slice(&element);
+    }
+}
+
+fn split_word(bytes: &[u8]) -> Option<([u8; 4], &[u8])> {
+    match bytes {
+        [a, b, c, d, rest @ ..] => Some(([*a, *b, *c, *d], rest)),
+        _ => None,
+    }
+}
+
+fn set_channel(
+    app_handle: &AppHandle,
+    universe: u16,
+    channel: &str,
+    message: &OscMessage,
+) -> Result<(), String> {
+    let channel = channel
+        .parse::<u16>()
+        .map_err(|_| format!("Canal DMX inválido: {channel}"))?;
+    let value = message
+        .args
+        .first()
+        .and_then(OscArg::level)
+        .ok_or_else(|| format!("{} necesita un valor", message.address))?;
+    app_handle
+        .state::<DmxState>()
+        .with_universe(app_handle.clone(), universe, |shared| {
+            shared.patch_levels(channel, &[value])
+        })
+}
+
+fn recall_scene(app_handle: &AppHandle, name: String, fade: Option<&OscArg>) -> Result<(), String> {
+    scene_recall(
+        app_handle.clone(),
+        app_handle.state::<SceneState>(),
+        app_handle.state::<DmxState>(),
+        name,
+        fade.and_then(OscArg::number).map(|ms| ms.max(0.0) as u64),
+    )
+}
+
+fn set_fixture_intensity(
+    app_handle: &AppHandle,
+    id: &str,
+    message: &OscMessage,
+) -> Result<(), String> {
+    let id = id
+        .parse::<u32>()
+        .map_err(|_| format!("Fixture inválido: {id}"))?;
+    let value = message
+        .args
+        .first()
+        .and_then(OscArg::level)
+        .ok_or_else(|| format!("{} necesita un valor", message.address))?;
+    let (universe, channels) = app_handle.state::<FixtureState>().intensity_channels(id)?;
+    app_handle
+        .state::<DmxState>()
+        .with_universe(app_handle.clone(), universe, |shared| {
+            channels
+                .iter()
+                .try_for_each(|channel| shared.patch_levels(*channel, &[value]))
+        })
+}
+
+// Pone el color ("rrggbb") en todos los fixtures que tienen emisores de color
+fn set_all_colors(app_handle: &AppHandle, hex: &str) -> Result<(), String> {
+    let rgb = ColorValue::Hex(hex.to_string()).to_rgb()?;
+    let fixtures = app_handle.state::<FixtureState>();
+    let dmx = app_handle.state::<DmxState>();
+    for (id, _, has_color) in fixtures.overview()? {
+        if has_color {
+            let values = emitter_levels(rgb, &fixtures.attributes(id)?)?;
+            fixtures.write_attributes(app_handle, &dmx, id, &values, None)?;
+        }
+    }
+    Ok(())
+}
+
+fn run(app_handle: &AppHandle, universe: u16, message: &OscMessage) -> Result<(), String> {
+    let cues = || app_handle.state::<CueState>();
+    let dmx = || app_handle.state::<DmxState>();
+    let segments = message.address[1..].split('/').collect::<Vec<_>>();
+
+    match segments.as_slice() {
+        ["dmx", "channel", channel] => set_channel(app_handle, universe, channel, message),
+        ["dmx", universe, "channel", channel] => {
+            let universe = universe
+                .parse::<u16>()
+                .map_err(|_| format!("Universo inválido: {universe}"))?;
+            set_channel(app_handle, universe, channel, message)
+        }
+        // Con el nombre como argumento o, para un botón por escena, en la dirección
+        ["scene", "recall"] => match message.args.first() {
+            Some(OscArg::String(name)) => {
+                recall_scene(app_handle, name.clone(), message.args.get(1))
+            }
+            _ => Err("/scene/recall necesita el nombre de la escena".to_string()),
+        },
+        ["scene", "recall", name] if message.pressed() => {
+            recall_scene(app_handle, name.to_string(), message.args.get(1))
+        }
+        ["cue", "go"] if message.pressed() => cue_go(app_handle.clone(), cues(), dmx()).map(|_| ()),
+        ["cue", "back"] if message.pressed() => {
+            cue_back(app_handle.clone(), cues(), dmx()).map(|_| ())
+        }
+        ["cue", "release"] if message.pressed() => {
+            cue_release(app_handle.clone(), cues(), dmx()).map(|_| ())
+        }
+        ["cue", "goto"] => {
+            let number = message
+                .args
+                .first()
+                .and_then(OscArg::number)
+                .ok_or_else(|| "/cue/goto necesita el número de cue".to_string())?;
+            cue_goto(app_handle.clone(), cues(), dmx(), number, None).map(|_| ())
+        }
+        ["fixture", id, "intensity"] => set_fixture_intensity(app_handle, id, message),
+        ["color", hex] if message.pressed() => set_all_colors(app_handle, hex),
+        ["submaster", id] => {
+            let id = id
+                .parse::<u32>()
+                .map_err(|_| format!("Grupo inválido: {id}"))?;
+            let value = message
+                .args
+                .first()
+                .and_then(OscArg::level)
+                .ok_or_else(|| format!("{} necesita un valor", message.address))?;
+            submaster_set_level(
+                app_handle.clone(),
+                app_handle.state::<GroupState>(),
+                id,
+                value,
+            )
+        }
+        ["tempo", "tap"] if message.pressed() => {
+            chase_tap_tempo(dmx(), app_handle.state()).map(|_| ())
+        }
+        // Soltar un botón u otra dirección: no hace nada
+        _ => Ok(()),
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct OscStatus {
+    listening: bool,
+    port: Option<u16>,
+    universe: u16,
+}
+
+struct OscServer {
+    port: u16,
+    universe: u16,
+    stop: Sender<()>,
+    handle: thread::JoinHandle<()>,
+}
+
+#[derive(Default)]
+pub struct OscState {
+    server: Mutex<Option<OscServer>>,
+}
+
+impl OscState {
+    fn lock_server(&self) -> Result<std::sync::MutexGuard<'_, Option<OscServer>>, String> {
+        self.server
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el servidor OSC: {e}"))
+    }
+
+    pub(crate) fn port(&self) -> Option<u16> {
+        self.lock_server().ok()?.as_ref().map(|server| server.port)
+    }
+}
+
+fn run_server(app_handle: AppHandle, socket: UdpSocket, universe: u16, rx: Receiver<()>) {
+    let mut buffer = [0u8; 4096];
+    let mut messages = Vec::new();
+
+    while rx.try_recv().is_err() {
+        let (len, from) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            // El timeout de lectura permite revisar la señal de parada
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                continue
+            }
+            Err(err) => {
+                error!("Error al recibir OSC: {err}");
+                thread::sleep(Duration::from_millis(200));
+                continue;
+            }
+        };
+
+        messages.clear();
+        OscMessage::parse_packet(&buffer[..len], &mut messages);
+        for message in messages.drain(..) {
+            debug!("OSC de {from}: {} {:?}", message.address, message.args);
+            if let Err(err) = run(&app_handle, universe, &message) {
+                error!("Mensaje OSC fallido ({}): {err}", message.address);
+            }
+            if let Err(err) = app_handle.emit(OSC_EVENT, message) {
+                error!("No se pudo emitir el mensaje OSC: {err}");
+            }
+        }
+    }
+
+    info!("Servidor OSC detenido");
+}
+
+fn stop_server(server: OscServer) {
+    let _ = server.stop.send(());
+    if server.handle.join().is_err() {
+        error!("El hilo del servidor OSC terminó con pánico");
+    }
+}
+
+// `universe` es el que controla `/dmx/channel/<n>`; los demás se eligen con
+// `/dmx/<universo>/channel/<n>`
+#[tauri::command]
+pub fn osc_listen_start(
+    app_handle: AppHandle,
+    state: State<'_, OscState>,
+    bind_ip: Option<String>,
+    port: Option<u16>,
+    universe: Option<u16>,
+) -> Result<OscStatus, String> {
+    let bind_ip = match bind_ip {
+        Some(ip) => ip
+            .parse::<Ipv4Addr>()
+            .map_err(|e| format!("IP de escucha OSC inválida ({ip}): {e}"))?,
+        None => Ipv4Addr::UNSPECIFIED,
+    };
+    let port = port.unwrap_or(DEFAULT_OSC_PORT);
+    let universe = universe.unwrap_or(0);
+
+    let mut server = state.lock_server()?;
+    // Se reinicia con la configuración nueva
+    if let Some(previous) = server.take() {
+        stop_server(previous);
+    }
+
+    let socket = UdpSocket::bind((bind_ip, port))
+        .map_err(|e| format!("No se pudo escuchar OSC en {bind_ip}:{port}: {e}"))?;
+    socket
+        .set_read_timeout(Some(Duration::from_millis(200)))
+        .map_err(|e| format!("No se pudo configurar el socket OSC: {e}"))?;
+
+    info!("Escuchando OSC en {bind_ip}:{port} (universo {universe})");
+    let (tx, rx) = mpsc::channel();
+    let handle = thread::spawn(move || run_server(app_handle, socket, universe, rx));
+    *server = Some(OscServer {
+        port,
+        universe,
+        stop: tx,
+        handle,
+    });
+    Ok(OscStatus {
+        listening: true,
+        port: Some(port),
+        universe,
+    })
+}
+
+#[tauri::command]
+pub fn osc_listen_stop(state: State<'_, OscState>) -> Result<(), String> {
+    let server = state.lock_server()?.take();
+    if let Some(server) = server {
+        stop_server(server);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn osc_status(state: State<'_, OscState>) -> Result<OscStatus, String> {
+    let server = state.lock_server()?;
+    Ok(OscStatus {
+        listening: server.is_some(),
+        port: server.as_ref().map(|server| server.port),
+        universe: server.as_ref().map_or(0, |server| server.universe),
+    })
+}
@@ -0,0 +1,177 @@
+use crate::cues::CueState;
+use crate::dmx::DmxState;
+use crate::osc::{encode_bundle, OscArg, OscMessage};
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+// Mensajes por bundle, para que cada paquete quepa en un datagrama sin fragmentar
+const BUNDLE_SIZE: usize = 32;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OscOutputConfig {
+    // IP o nombre del equipo que recibe
+    host: String,
+    port: u16,
+}
+
+// Lo último enviado, para mandar solo lo que cambia
+#[derive(Default)]
+struct Sent {
+    levels: BTreeMap<u16, Vec<u8>>,
+    // `Some(None)` es "sin cue activa" ya enviado
+    cue: Option<Option<f64>>,
+    bpm: Option<f64>,
+}
+
+struct OscOutput {
+    config: OscOutputConfig,
+    target: SocketAddr,
+    socket: UdpSocket,
+    sent: Sent,
+}
+
+#[derive(Default)]
+pub struct OscOutputState {
+    output: Mutex<Option<OscOutput>>,
+}
+
+impl OscOutputState {
+    fn lock_output(&self) -> Result<std::sync::MutexGuard<'_, Option<OscOutput>>, String> {
+        self.output
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la salida OSC: {e}"))
+    }
+}
+
+fn level_messages(universe: u16, previous: Option<&Vec<u8>>, levels: &[u8]) -> Vec<OscMessage> {
+    levels
+        .iter()
+        .enumerate()
+        .filter(|(idx, value)| previous.and_then(|p| p.get(*idx)) != Some(*value))
+        .map(|(idx, value)| {
+            OscMessage::new(
+                format!("/dmx/{universe}/channel/{}", idx + 1),
+                vec![OscArg::Float(*value as f32 / 255.0)],
+            )
+        })
+        .collect()
+}
+
+// Mensajes con lo que cambió desde la última vuelta
+fn changes(app_handle: &AppHandle, sent: &mut Sent) -> Vec<OscMessage> {
+    let dmx = app_handle.state::<DmxState>();
+    let mut messages = Vec::new();
+    let mut bpm = None;
+    dmx.for_each_universe(|universe, shared| {
+        let levels = shared.output_levels();
+        messages.extend(level_messages(
+            universe,
+            sent.levels.get(&universe),
+            &levels,
+        ));
+        sent.levels.insert(universe, levels);
+        if let Ok(effects) = shared.effects.lock() {
+            bpm.get_or_insert(effects.bpm());
+        }
+    });
+
+    let cue = app_handle.state::<CueState>().active_number();
+    if sent.cue != Some(cue) {
+        // Sin cue activa el mensaje va sin argumentos
+        let args = cue.map(|number| OscArg::Float(number as f32));
+        messages.push(OscMessage::new("/cue/active", args.into_iter().collect()));
+        sent.cue = Some(cue);
+    }
+    if let Some(bpm) = bpm.filter(|bpm| sent.bpm != Some(*bpm)) {
+        messages.push(OscMessage::new(
+            "/tempo/bpm",
+            vec![OscArg::Float(bpm as f32)],
+        ));
+        sent.bpm = Some(bpm);
+    }
+    messages
+}
+
+// Cada 50 ms manda al equipo configurado los niveles de salida que cambiaron, la cue activa
+// y el tempo. Al configurar un destino nuevo se manda todo.
+pub fn start_output(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    thread::spawn(move || {
+        let mut packet = Vec::new();
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let state = app_handle.state::<OscOutputState>();
+            let mut output = match state.lock_output() {
+                Ok(output) => output,
+                Err(err) => {
+                    error!("{err}");
+                    continue;
+                }
+            };
+            let Some(output) = output.as_mut() else {
+                continue;
+            };
+
+            for chunk in changes(&app_handle, &mut output.sent).chunks(BUNDLE_SIZE) {
+                encode_bundle(chunk, &mut packet);
+                if let Err(err) = output.socket.send_to(&packet, output.target) {
+                    // Se vuelve a mandar todo en la próxima vuelta
+                    debug!("Error al enviar OSC a {}: {err}", output.target);
+                    output.sent = Sent::default();
+                    break;
+                }
+            }
+        }
+    });
+}
+
+// Sin `config` deja de enviar
+#[tauri::command]
+pub fn osc_output_configure(
+    state: State<'_, OscOutputState>,
+    config: Option<OscOutputConfig>,
+) -> Result<(), String> {
+    let Some(config) = config else {
+        if state.lock_output()?.take().is_some() {
+            info!("Salida OSC apagada");
+        }
+        return Ok(());
+    };
+
+    let target = (config.host.as_str(), config.port)
+        .to_socket_addrs()
+        .map_err(|e| format!("Destino OSC inválido ({}): {e}", config.host))?
+        .find(SocketAddr::is_ipv4)
+        .ok_or_else(|| format!("El destino OSC {} no tiene dirección IPv4", config.host))?;
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .map_err(|e| format!("No se pudo abrir el socket OSC: {e}"))?;
+    socket
+        .set_broadcast(true)
+        .map_err(|e| format!("No se pudo habilitar broadcast OSC: {e}"))?;
+
+    info!("Enviando el estado por OSC a {target}");
+    *state.lock_output()? = Some(OscOutput {
+        config,
+        target,
+        socket,
+        sent: Sent::default(),
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn osc_output_get_config(
+    state: State<'_, OscOutputState>,
+) -> Result<Option<OscOutputConfig>, String> {
+    Ok(state
+        .lock_output()?
+        .as_ref()
+        .map(|output| output.config.clone()))
+}
@@ -0,0 +1,755 @@
+// Insertos de las pistas de loop. Solo se alojan plugins CLAP: los VST3 no se cargan.
+// CLAP reparte las llamadas entre hilos: la carga, la activación, el estado, los parámetros y la
+// destrucción pasan por el hilo principal de la app; el callback de audio solo llama a `process`.
+use crate::audio::AudioState;
+use clap_sys::audio_buffer::clap_audio_buffer;
+use clap_sys::entry::clap_plugin_entry;
+use clap_sys::events::{
+    clap_event_header, clap_event_param_value, clap_input_events, clap_output_events,
+    CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_VALUE,
+};
+use clap_sys::ext::params::{clap_param_info, clap_plugin_params, CLAP_EXT_PARAMS};
+use clap_sys::ext::state::{clap_plugin_state, CLAP_EXT_STATE};
+use clap_sys::factory::plugin_factory::{clap_plugin_factory, CLAP_PLUGIN_FACTORY_ID};
+use clap_sys::host::clap_host;
+use clap_sys::plugin::clap_plugin;
+use clap_sys::process::{clap_process, CLAP_PROCESS_ERROR};
+use clap_sys::stream::{clap_istream, clap_ostream};
+use clap_sys::version::CLAP_VERSION;
+use libloading::Library;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::path::Path;
+use std::ptr;
+use std::sync::{mpsc, OnceLock};
+use std::thread::{self, ThreadId};
+use tauri::{AppHandle, Manager};
+
+static MAIN_THREAD: OnceLock<ThreadId> = OnceLock::new();
+
+// Bloque más largo que se le pasa al plugin; los más largos se parten
+const MAX_FRAMES: usize = 4096;
+// Cambios de parámetros que pueden esperar al próximo bloque
+const MAX_EVENTS: usize = 256;
+
+#[derive(Clone, Serialize)]
+pub struct PluginParam {
+    id: u32,
+    name: String,
+    min: f64,
+    max: f64,
+    default: f64,
+    value: f64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct PluginInfo {
+    track: usize,
+    path: String,
+    plugin_id: String,
+    name: String,
+    // Frecuencia a la que está activo; sin motor en marcha el plugin no procesa
+    sample_rate: Option<u32>,
+}
+
+// Lo que guarda el proyecto de cada inserto
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TrackPlugin {
+    track: usize,
+    path: String,
+    plugin_id: String,
+    // Lo que devuelve el propio plugin (extensión `state` de CLAP)
+    #[serde(default)]
+    state: Vec<u8>,
+}
+
+fn c_string(value: *const c_char) -> String {
+    if value.is_null() {
+        return String::new();
+    }
+    // SAFETY: CLAP garantiza strings terminados en cero
+    unsafe { CStr::from_ptr(value) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+unsafe extern "C" fn host_get_extension(
+    _host: *const clap_host,
+    _extension_id: *const c_char,
+) -> *const c_void {
+    ptr::null()
+}
+
+unsafe extern "C" fn host_request(_host: *const clap_host) {}
+
+unsafe extern "C" fn events_size(list: *const clap_input_events) -> u32 {
+    let events = &*((*list).ctx as *const Vec<clap_event_param_value>);
+    events.len() as u32
+}
+
+unsafe extern "C" fn events_get(
+    list: *const clap_input_events,
+    index: u32,
+) -> *const clap_event_header {
+    let events = &*((*list).ctx as *const Vec<clap_event_param_value>);
+    match events.get(index as usize) {
+        Some(event) => &event.header,
+        None => ptr::null(),
+    }
+}
+
+// Lo que manda el plugin (p. ej. parámetros que mueve solo) se descarta
+unsafe extern "C" fn events_push(
+    _list: *const clap_output_events,
+    _event: *const clap_event_header,
+) -> bool {
+    true
+}
+
+unsafe extern "C" fn stream_write(
+    stream: *const clap_ostream,
+    buffer: *const c_void,
+    size: u64,
+) -> i64 {
+    let data = &mut *((*stream).ctx as *mut Vec<u8>);
+    data.extend_from_slice(std::slice::from_raw_parts(
+        buffer as *const u8,
+        size as usize,
+    ));
+    size as i64
+}
+
+struct StreamReader<'a> {
+    data: &'a [u8],
+    read: usize,
+}
+
+unsafe extern "C" fn stream_read(
+    stream: *const clap_istream,
+    buffer: *mut c_void,
+    size: u64,
+) -> i64 {
+    let reader = &mut *((*stream).ctx as *mut StreamReader);
+    let count = (reader.data.len() - reader.read).min(size as usize);
+    ptr::copy_nonoverlapping(
+        reader.data[reader.read..].as_ptr(),
+        buffer as *mut u8,
+        count,
+    );
+    reader.read += count;
+    count as i64
+}
+
+// Plugin CLAP cargado como inserto de una pista de loop. Procesa en estéreo por su primer
+// puerto de entrada y de salida.
+pub(crate) struct ClapPlugin {
+    path: String,
+    plugin_id: String,
+    name: String,
+    // Se reserva al cargarlo; el plugin guarda el puntero
+    _host: Box<clap_host>,
+    plugin: *const clap_plugin,
+    params: *const clap_plugin_params,
+    state: *const clap_plugin_state,
+    entry: *const clap_plugin_entry,
+    active: Option<u32>,
+    processing: bool,
+    steady_time: i64,
+    // Cambios de parámetros para el próximo bloque, con capacidad reservada
+    events: Vec<clap_event_param_value>,
+    inputs: [Vec<f32>; 2],
+    outputs: [Vec<f32>; 2],
+    // Al final: la biblioteca se descarga después de destruir el plugin
+    _library: Library,
+}
+
+// SAFETY: el plugin se usa siempre con el looper bloqueado, desde un hilo por vez: el principal
+// (`on_main_thread`) o el de audio, que solo procesa.
+unsafe impl Send for ClapPlugin {}
+
+impl ClapPlugin {
+    // Sin `plugin_id` carga el primer plugin del archivo
+    fn load(path: &str, plugin_id: Option<&str>) -> Result<Self, String> {
+        let error = |detail: &str| format!("No se pudo cargar el plugin {path}: {detail}");
+        // Un bundle VST3 es una carpeta: sin esto el error sería el de la carga de la biblioteca
+        let is_vst3 = Path::new(path)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("vst3"));
+        if is_vst3 {
+            return Err(error("es un VST3; solo se cargan plugins CLAP (.clap)"));
+        }
+        // SAFETY: cargar un plugin ejecuta su código de inicialización; es lo que se pide
+        let library = unsafe { Library::new(path) }.map_err(|e| error(&e.to_string()))?;
+        let entry = unsafe { library.get::<*const clap_plugin_entry>(b"clap_entry\0") }
+            .map(|symbol| *symbol)
+            .map_err(|_| error("no es un plugin CLAP (los VST3 no están soportados)"))?;
+        let c_path = CString::new(path).map_err(|_| error("ruta inválida"))?;
+
+        unsafe {
+            let entry_ref = &*entry;
+            let init = entry_ref.init.ok_or_else(|| error("entrada incompleta"))?;
+            if !init(c_path.as_ptr()) {
+                return Err(error("falló la inicialización"));
+            }
+            let deinit = || {
+                if let Some(deinit) = entry_ref.deinit {
+                    deinit();
+                }
+            };
+            let factory = entry_ref
+                .get_factory
+                .map(|get_factory| get_factory(CLAP_PLUGIN_FACTORY_ID.as_ptr()))
+                .unwrap_or(ptr::null()) as *const clap_plugin_factory;
+            if factory.is_null() {
+                deinit();
+                return Err(error("no tiene fábrica de plugins"));
+            }
+            let factory_ref = &*factory;
+            let count = factory_ref
+                .get_plugin_count
+                .map_or(0, |count| count(factory));
+            let descriptor = (0..count)
+                .filter_map(|index| {
+                    let descriptor = factory_ref.get_plugin_descriptor?(factory, index);
+                    (!descriptor.is_null()).then_some(descriptor)
+                })
+                .find(|descriptor| {
+                    plugin_id.is_none() || plugin_id == Some(c_string((**descriptor).id).as_str())
+                });
+            let Some(descriptor) = descriptor else {
+                deinit();
+                return Err(match plugin_id {
+                    Some(id) => error(&format!("no contiene \"{id}\"")),
+                    None => error("no contiene plugins"),
+                });
+            };
+            let (id, name) = (c_string((*descriptor).id), c_string((*descriptor).name));
+
+            let host = Box::new(clap_host {
+                clap_version: CLAP_VERSION,
+                host_data: ptr::null_mut(),
+                name: c"LiveLoop Studio".as_ptr(),
+                vendor: c"LiveLoop".as_ptr(),
+                url: c"".as_ptr(),
+                version: c"0.1.0".as_ptr(),
+                get_extension: Some(host_get_extension),
+                request_restart: Some(host_request),
+                request_process: Some(host_request),
+                request_callback: Some(host_request),
+            });
+            let plugin = factory_ref
+                .create_plugin
+                .map(|create| create(factory, &*host, (*descriptor).id))
+                .unwrap_or(ptr::null());
+            if plugin.is_null() {
+                deinit();
+                return Err(error("no se pudo crear la instancia"));
+            }
+            if !(*plugin).init.is_some_and(|init| init(plugin)) {
+                if let Some(destroy) = (*plugin).destroy {
+                    destroy(plugin);
+                }
+                deinit();
+                return Err(error("falló la inicialización de la instancia"));
+            }
+            let extension = |id: &CStr| match (*plugin).get_extension {
+                Some(get_extension) => get_extension(plugin, id.as_ptr()),
+                None => ptr::null(),
+            };
+
+            Ok(Self {
+                path: path.to_string(),
+                plugin_id: id,
+                name,
+                _host: host,
+                plugin,
+                params: extension(CLAP_EXT_PARAMS) as *const clap_plugin_params,
+                state: extension(CLAP_EXT_STATE) as *const clap_plugin_state,
+                entry,
+                active: None,
+                processing: false,
+                steady_time: 0,
+                events: Vec::with_capacity(MAX_EVENTS),
+                inputs: [
+                    Vec::with_capacity(MAX_FRAMES),
+                    Vec::with_capacity(MAX_FRAMES),
+                ],
+                outputs: [
+                    Vec::with_capacity(MAX_FRAMES),
+                    Vec::with_capacity(MAX_FRAMES),
+                ],
+                _library: library,
+            })
+        }
+    }
+
+    fn info(&self, track: usize) -> PluginInfo {
+        PluginInfo {
+            track,
+            path: self.path.clone(),
+            plugin_id: self.plugin_id.clone(),
+            name: self.name.clone(),
+            sample_rate: self.active,
+        }
+    }
+
+    fn deactivate(&mut self) {
+        unsafe {
+            if self.processing {
+                if let Some(stop) = (*self.plugin).stop_processing {
+                    stop(self.plugin);
+                }
+                self.processing = false;
+            }
+            if self.active.take().is_some() {
+                if let Some(deactivate) = (*self.plugin).deactivate {
+                    deactivate(self.plugin);
+                }
+            }
+        }
+    }
+
+    // Fuera del hilo de audio; un cambio de frecuencia del motor lo reactiva
+    pub(crate) fn activate(&mut self, sample_rate: u32) -> Result<(), String> {
+        if self.active == Some(sample_rate) {
+            return Ok(());
+        }
+        self.deactivate();
+        let activated = unsafe {
+            (*self.plugin).activate.is_some_and(|activate| {
+                activate(self.plugin, sample_rate as f64, 1, MAX_FRAMES as u32)
+            })
+        };
+        if !activated {
+            return Err(format!(
+                "El plugin {} no se pudo activar a {sample_rate} Hz",
+                self.name
+            ));
+        }
+        self.active = Some(sample_rate);
+        Ok(())
+    }
+
+    // Lo llama el callback de salida con lo que suena de la pista. Devuelve si el plugin
+    // procesó; si no, la pista sigue sin efecto.
+    pub(crate) fn process(
+        &mut self,
+        input: &[f32],
+        output: &mut [[f32; 2]],
+        sample_rate: u32,
+    ) -> bool {
+        if self.active != Some(sample_rate) {
+            return false;
+        }
+        let Some(process) = (unsafe { (*self.plugin).process }) else {
+            return false;
+        };
+        if !self.processing {
+            self.processing = unsafe {
+                (*self.plugin)
+                    .start_processing
+                    .is_some_and(|start| start(self.plugin))
+            };
+            if !self.processing {
+                return false;
+            }
+        }
+
+        for (input, output) in input.chunks(MAX_FRAMES).zip(output.chunks_mut(MAX_FRAMES)) {
+            let frames = input.len();
+            for channel in &mut self.inputs {
+                channel.clear();
+                channel.extend_from_slice(input);
+            }
+            for channel in &mut self.outputs {
+                channel.clear();
+                channel.resize(frames, 0.0);
+            }
+            let mut input_channels = [self.inputs[0].as_mut_ptr(), self.inputs[1].as_mut_ptr()];
+            let mut output_channels = [self.outputs[0].as_mut_ptr(), self.outputs[1].as_mut_ptr()];
+            let audio_input = clap_audio_buffer {
+                data32: input_channels.as_mut_ptr(),
+                data64: ptr::null_mut(),
+                channel_count: 2,
+                latency: 0,
+                constant_mask: 0,
+            };
+            let mut audio_output = clap_audio_buffer {
+                data32: output_channels.as_mut_ptr(),
+                data64: ptr::null_mut(),
+                channel_count: 2,
+                latency: 0,
+                constant_mask: 0,
+            };
+            let in_events = clap_input_events {
+                ctx: &self.events as *const _ as *mut c_void,
+                size: Some(events_size),
+                get: Some(events_get),
+            };
+            let out_events = clap_output_events {
+                ctx: ptr::null_mut(),
+                try_push: Some(events_push),
+            };
+            let block = clap_process {
+                steady_time: self.steady_time,
+                frames_count: frames as u32,
+                transport: ptr::null(),
+                audio_inputs: &audio_input,
+                audio_outputs: &mut audio_output,
+                audio_inputs_count: 1,
+                audio_outputs_count: 1,
+                in_events: &in_events,
+                out_events: &out_events,
+            };
+            let status = unsafe { process(self.plugin, &block) };
+            self.events.clear();
+            self.steady_time += frames as i64;
+            if status == CLAP_PROCESS_ERROR {
+                return false;
+            }
+            for (frame, (left, right)) in output
+                .iter_mut()
+                .zip(self.outputs[0].iter().zip(&self.outputs[1]))
+            {
+                *frame = [*left, *right];
+            }
+        }
+        true
+    }
+
+    fn params(&self) -> Result<Vec<PluginParam>, String> {
+        if self.params.is_null() {
+            return Ok(Vec::new());
+        }
+        let params = unsafe { &*self.params };
+        let count = unsafe { params.count.map_or(0, |count| count(self.plugin)) };
+        let mut list = Vec::new();
+        for index in 0..count {
+            // SAFETY: `clap_param_info` es una estructura de C sin invariantes
+            let mut info: clap_param_info = unsafe { std::mem::zeroed() };
+            let found = unsafe {
+                params
+                    .get_info
+                    .is_some_and(|get_info| get_info(self.plugin, index, &mut info))
+            };
+            if !found {
+                continue;
+            }
+            list.push(PluginParam {
+                id: info.id,
+                name: c_string(info.name.as_ptr()),
+                min: info.min_value,
+                max: info.max_value,
+                default: info.default_value,
+                value: self.param(info.id).unwrap_or(info.default_value),
+            });
+        }
+        Ok(list)
+    }
+
+    fn param(&self, id: u32) -> Result<f64, String> {
+        let mut value = 0.0;
+        let found = !self.params.is_null()
+            && unsafe {
+                (*self.params)
+                    .get_value
+                    .is_some_and(|get_value| get_value(self.plugin, id, &mut value))
+            };
+        if !found {
+            return Err(format!(
+                "El plugin {} no tiene el parámetro {id}",
+                self.name
+            ));
+        }
+        Ok(value)
+    }
+
+    // Con el plugin procesando el cambio entra en el próximo bloque; si no, se le pasa directo
+    fn set_param(&mut self, id: u32, value: f64) -> Result<(), String> {
+        self.param(id)?;
+        if self.events.len() == MAX_EVENTS {
+            return Err("Demasiados cambios de parámetros pendientes".to_string());
+        }
+        self.events.push(clap_event_param_value {
+            header: clap_event_header {
+                size: std::mem::size_of::<clap_event_param_value>() as u32,
+                time: 0,
+                space_id: CLAP_CORE_EVENT_SPACE_ID,
+                type_: CLAP_EVENT_PARAM_VALUE,
+                flags: 0,
+            },
+            param_id: id,
+            cookie: ptr::null_mut(),
+            note_id: -1,
+            port_index: -1,
+            channel: -1,
+            key: -1,
+            value,
+        });
+        if !self.processing {
+            self.flush();
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) {
+        let Some(flush) = (unsafe { self.params.as_ref() }).and_then(|params| params.flush) else {
+            return;
+        };
+        let in_events = clap_input_events {
+            ctx: &self.events as *const _ as *mut c_void,
+            size: Some(events_size),
+            get: Some(events_get),
+        };
+        let out_events = clap_output_events {
+            ctx: ptr::null_mut(),
+            try_push: Some(events_push),
+        };
+        unsafe { flush(self.plugin, &in_events, &out_events) };
+        self.events.clear();
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        let Some(save) = (unsafe { self.state.as_ref() }).and_then(|state| state.save) else {
+            return data;
+        };
+        let stream = clap_ostream {
+            ctx: &mut data as *mut Vec<u8> as *mut c_void,
+            write: Some(stream_write),
+        };
+        if !unsafe { save(self.plugin, &stream) } {
+            warn!("El plugin {} no pudo guardar su estado", self.name);
+            data.clear();
+        }
+        data
+    }
+
+    fn load_state(&mut self, data: &[u8]) -> Result<(), String> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let Some(load) = (unsafe { self.state.as_ref() }).and_then(|state| state.load) else {
+            return Err(format!(
+                "El plugin {} no admite cargar un estado",
+                self.name
+            ));
+        };
+        let mut reader = StreamReader { data, read: 0 };
+        let stream = clap_istream {
+            ctx: &mut reader as *mut StreamReader as *mut c_void,
+            read: Some(stream_read),
+        };
+        if !unsafe { load(self.plugin, &stream) } {
+            return Err(format!("El plugin {} no pudo cargar su estado", self.name));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ClapPlugin {
+    fn drop(&mut self) {
+        self.deactivate();
+        unsafe {
+            if let Some(destroy) = (*self.plugin).destroy {
+                destroy(self.plugin);
+            }
+            if let Some(deinit) = (*self.entry).deinit {
+                deinit();
+            }
+        }
+    }
+}
+
+// Al principio de `setup`, que corre en el hilo principal
+pub(crate) fn mark_main_thread() {
+    let _ = MAIN_THREAD.set(thread::current().id());
+}
+
+// Corre `task` en el hilo principal y espera el resultado. Quien llama no puede tener tomado un
+// lock que el hilo principal necesite, como el del looper o el del motor de audio.
+fn on_main_thread<T: Send + 'static>(
+    app_handle: &AppHandle,
+    task: impl FnOnce(&AppHandle) -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    if MAIN_THREAD.get() == Some(&thread::current().id()) {
+        return task(app_handle);
+    }
+    let (tx, rx) = mpsc::channel();
+    let main_handle = app_handle.clone();
+    app_handle
+        .run_on_main_thread(move || {
+            let _ = tx.send(task(&main_handle));
+        })
+        .map_err(|e| format!("No se pudo pasar al hilo principal: {e}"))?;
+    rx.recv()
+        .map_err(|_| "El hilo principal no respondió".to_string())?
+}
+
+// En el hilo principal y fuera del lock del looper, que también toma el callback de audio
+fn instantiate(
+    audio: &AudioState,
+    path: &str,
+    plugin_id: Option<&str>,
+    state: &[u8],
+) -> Result<ClapPlugin, String> {
+    let mut plugin = ClapPlugin::load(path, plugin_id)?;
+    plugin.load_state(state)?;
+    if let Some(sample_rate) = audio.sample_rate()? {
+        plugin.activate(sample_rate)?;
+    }
+    Ok(plugin)
+}
+
+fn with_plugin<T: Send + 'static>(
+    app_handle: &AppHandle,
+    track: usize,
+    action: impl FnOnce(&mut ClapPlugin) -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    on_main_thread(app_handle, move |app_handle| {
+        let audio = app_handle.state::<AudioState>();
+        let mut looper = audio.shared().lock_looper()?;
+        let result = match looper.plugin_slot(track)? {
+            Some(plugin) => action(plugin),
+            None => Err(format!("La pista de loop {track} no tiene plugin")),
+        };
+        result
+    })
+}
+
+// Al arrancar el motor, por si cambió la frecuencia
+pub(crate) fn activate_all(app_handle: &AppHandle, sample_rate: u32) -> Result<(), String> {
+    on_main_thread(app_handle, move |app_handle| {
+        app_handle
+            .state::<AudioState>()
+            .shared()
+            .lock_looper()?
+            .activate_plugins(sample_rate);
+        Ok(())
+    })
+}
+
+pub(crate) fn export(app_handle: &AppHandle) -> Result<Vec<TrackPlugin>, String> {
+    on_main_thread(app_handle, |app_handle| {
+        let audio = app_handle.state::<AudioState>();
+        let looper = audio.shared().lock_looper()?;
+        let plugins = looper
+            .plugins()
+            .map(|(track, plugin)| TrackPlugin {
+                track,
+                path: plugin.path.clone(),
+                plugin_id: plugin.plugin_id.clone(),
+                state: plugin.save_state(),
+            })
+            .collect();
+        Ok(plugins)
+    })
+}
+
+// Reemplaza los insertos por los del proyecto. Un plugin que no carga (p. ej. no está
+// instalado en esta máquina) se salta con un aviso.
+pub(crate) fn replace(app_handle: &AppHandle, plugins: Vec<TrackPlugin>) -> Result<(), String> {
+    on_main_thread(app_handle, move |app_handle| {
+        let audio = app_handle.state::<AudioState>();
+        let previous = audio.shared().lock_looper()?.take_plugins();
+        drop(previous);
+
+        for saved in plugins {
+            let plugin =
+                match instantiate(&audio, &saved.path, Some(&saved.plugin_id), &saved.state) {
+                    Ok(plugin) => plugin,
+                    Err(err) => {
+                        warn!("{err}");
+                        continue;
+                    }
+                };
+            let mut looper = audio.shared().lock_looper()?;
+            match looper.plugin_slot(saved.track) {
+                Ok(slot) => *slot = Some(plugin),
+                Err(err) => warn!("{err}"),
+            }
+        }
+        Ok(())
+    })
+}
+
+// Inserto de la pista: lo que suena de ella pasa por el plugin antes de la mezcla. Solo CLAP
+// (`.clap`); sin `plugin_id` se usa el primero del archivo.
+#[tauri::command]
+pub fn plugin_load(
+    app_handle: AppHandle,
+    track: usize,
+    path: String,
+    plugin_id: Option<String>,
+) -> Result<PluginInfo, String> {
+    on_main_thread(&app_handle, move |app_handle| {
+        let audio = app_handle.state::<AudioState>();
+        audio.shared().lock_looper()?.plugin_slot(track)?;
+        let plugin = instantiate(&audio, &path, plugin_id.as_deref(), &[])?;
+        let info = plugin.info(track);
+        let previous = audio
+            .shared()
+            .lock_looper()?
+            .plugin_slot(track)?
+            .replace(plugin);
+        // El anterior se destruye fuera del lock
+        drop(previous);
+        info!("Plugin {} cargado en la pista {track}", info.name);
+        Ok(info)
+    })
+}
+
+#[tauri::command]
+pub fn plugin_unload(app_handle: AppHandle, track: usize) -> Result<(), String> {
+    on_main_thread(&app_handle, move |app_handle| {
+        let previous = app_handle
+            .state::<AudioState>()
+            .shared()
+            .lock_looper()?
+            .plugin_slot(track)?
+            .take();
+        match previous {
+            Some(plugin) => {
+                info!("Plugin {} quitado de la pista {track}", plugin.name);
+                drop(plugin);
+                Ok(())
+            }
+            None => Err(format!("La pista de loop {track} no tiene plugin")),
+        }
+    })
+}
+
+#[tauri::command]
+pub fn plugin_list(app_handle: AppHandle) -> Result<Vec<PluginInfo>, String> {
+    Ok(app_handle
+        .state::<AudioState>()
+        .shared()
+        .lock_looper()?
+        .plugins()
+        .map(|(track, plugin)| plugin.info(track))
+        .collect())
+}
+
+#[tauri::command]
+pub fn plugin_params(app_handle: AppHandle, track: usize) -> Result<Vec<PluginParam>, String> {
+    with_plugin(&app_handle, track, |plugin| plugin.params())
+}
+
+// En el rango `min`-`max` que informa `plugin_params`
+#[tauri::command]
+pub fn plugin_set_param(
+    app_handle: AppHandle,
+    track: usize,
+    id: u32,
+    value: f64,
+) -> Result<(), String> {
+    with_plugin(&app_handle, track, move |plugin| {
+        plugin.set_param(id, value)
+    })
+}
+
+#[tauri::command]
+pub fn plugin_get_param(app_handle: AppHandle, track: usize, id: u32) -> Result<f64, String> {
+    with_plugin(&app_handle, track, move |plugin| plugin.param(id))
+}
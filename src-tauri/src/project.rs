@@ -0,0 +1,187 @@
+use crate::cues::{Cue, CueState};
+use crate::dmx::{DmxState, UniverseSettings};
+use crate::fixture::{FixtureLibrary, FixtureState};
+use crate::groups::{self, Group, GroupState};
+use crate::midi_feedback::{MidiFeedback, MidiFeedbackState};
+use crate::midi_map::{MidiMapState, MidiMapping};
+use crate::plugin_host::{self, TrackPlugin};
+use crate::scenes::{Scene, SceneState};
+use crate::storage;
+use crate::timeline::{Timeline, TimelineState};
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+// Un proyecto se guarda siempre en la última versión y se lee de cualquiera anterior
+// pasándolo por las migraciones. Cambiar la forma de `Project` es subir la versión y agregar
+// la migración que lleva los archivos viejos a la nueva.
+const PROJECT_VERSION: u32 = 2;
+type Migration = fn(&mut Map<String, Value>) -> Result<(), String>;
+// La migración `i` lleva de la versión `i + 1` a la `i + 2`
+const MIGRATIONS: [Migration; PROJECT_VERSION as usize - 1] = [migrate_v1];
+
+// Archivo de show: todo lo que hace falta para reconstruir el estado del backend
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Project {
+    version: u32,
+    universes: Vec<UniverseSettings>,
+    scenes: Vec<Scene>,
+    cues: Vec<Cue>,
+    fixtures: FixtureLibrary,
+    groups: Vec<Group>,
+    midi_mappings: Vec<MidiMapping>,
+    midi_feedback: Vec<MidiFeedback>,
+    // Insertos de las pistas de loop, con su estado
+    loop_plugins: Vec<TrackPlugin>,
+    timelines: Vec<Timeline>,
+}
+
+// En la versión 1 las secciones que se fueron agregando podían faltar; desde la 2 están todas
+fn migrate_v1(project: &mut Map<String, Value>) -> Result<(), String> {
+    let fixtures = serde_json::to_value(FixtureLibrary::default())
+        .map_err(|e| format!("No se pudo migrar el patch de fixtures: {e}"))?;
+    project.entry("fixtures".to_string()).or_insert(fixtures);
+    for section in [
+        "groups",
+        "midi_mappings",
+        "midi_feedback",
+        "loop_plugins",
+        "timelines",
+    ] {
+        project
+            .entry(section.to_string())
+            .or_insert(Value::Array(Vec::new()));
+    }
+    Ok(())
+}
+
+// Lee un proyecto de cualquier versión hasta la actual
+pub(crate) fn from_value(value: Value) -> Result<Project, String> {
+    let Value::Object(mut project) = value else {
+        return Err("El proyecto no es un objeto JSON".to_string());
+    };
+    let version = project
+        .get("version")
+        .and_then(Value::as_u64)
+        .filter(|version| *version > 0)
+        .ok_or_else(|| "El proyecto no tiene una versión válida".to_string())?;
+    if version > PROJECT_VERSION as u64 {
+        return Err(format!(
+            "El proyecto es de una versión más nueva ({version}) que la soportada ({PROJECT_VERSION})"
+        ));
+    }
+
+    for migration in &MIGRATIONS[version as usize - 1..] {
+        migration(&mut project)?;
+    }
+    if version < PROJECT_VERSION as u64 {
+        info!("Proyecto migrado de la versión {version} a la {PROJECT_VERSION}");
+    }
+    project.insert("version".to_string(), Value::from(PROJECT_VERSION));
+    serde_json::from_value(Value::Object(project)).map_err(|e| e.to_string())
+}
+
+#[derive(Default)]
+pub struct ProjectState {
+    path: Mutex<Option<PathBuf>>,
+}
+
+impl ProjectState {
+    pub(crate) fn current_path(&self) -> Option<PathBuf> {
+        self.path.lock().ok().and_then(|path| path.clone())
+    }
+
+    fn set_current_path(&self, path: &Path) {
+        if let Ok(mut current) = self.path.lock() {
+            *current = Some(path.to_path_buf());
+        }
+    }
+}
+
+pub(crate) fn capture(app_handle: &AppHandle) -> Result<Project, String> {
+    Ok(Project {
+        version: PROJECT_VERSION,
+        universes: app_handle.state::<DmxState>().export_settings()?,
+        scenes: app_handle.state::<SceneState>().export()?,
+        cues: app_handle.state::<CueState>().export()?,
+        fixtures: app_handle.state::<FixtureState>().export()?,
+        groups: app_handle.state::<GroupState>().export()?,
+        midi_mappings: app_handle.state::<MidiMapState>().export()?,
+        midi_feedback: app_handle.state::<MidiFeedbackState>().export()?,
+        loop_plugins: plugin_host::export(app_handle)?,
+        timelines: app_handle.state::<TimelineState>().export()?,
+    })
+}
+
+pub(crate) fn apply(app_handle: &AppHandle, project: Project) -> Result<(), String> {
+    project.fixtures.validate()?;
+    groups::validate(&project.groups)?;
+    app_handle
+        .state::<DmxState>()
+        .restore_settings(app_handle, project.universes)?;
+    app_handle.state::<SceneState>().replace(project.scenes)?;
+    app_handle.state::<CueState>().replace(project.cues)?;
+    app_handle
+        .state::<MidiMapState>()
+        .replace(project.midi_mappings)?;
+    app_handle
+        .state::<MidiFeedbackState>()
+        .replace(project.midi_feedback)?;
+    plugin_host::replace(app_handle, project.loop_plugins)?;
+    app_handle
+        .state::<TimelineState>()
+        .replace(project.timelines)?;
+    app_handle
+        .state::<FixtureState>()
+        .replace(app_handle, project.fixtures)?;
+    // Después de los fixtures: los submasters se resuelven contra el patch
+    app_handle
+        .state::<GroupState>()
+        .replace(app_handle, project.groups)
+}
+
+#[tauri::command]
+pub fn project_save(
+    app_handle: AppHandle,
+    project: State<'_, ProjectState>,
+    path: String,
+) -> Result<(), String> {
+    let path = PathBuf::from(path);
+    let show = capture(&app_handle)?;
+    storage::save_json(&path, &show)?;
+
+    project.set_current_path(&path);
+    info!("Proyecto guardado en {}", path.display());
+    Ok(())
+}
+
+#[tauri::command]
+pub fn project_open(
+    app_handle: AppHandle,
+    project: State<'_, ProjectState>,
+    path: String,
+) -> Result<(), String> {
+    let path = PathBuf::from(path);
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| format!("No se pudo leer el proyecto {}: {e}", path.display()))?;
+    let show = serde_json::from_str::<Value>(&contents)
+        .map_err(|e| e.to_string())
+        .and_then(from_value)
+        .map_err(|e| format!("Proyecto inválido {}: {e}", path.display()))?;
+
+    apply(&app_handle, show)?;
+
+    project.set_current_path(&path);
+    info!("Proyecto abierto: {}", path.display());
+    Ok(())
+}
+
+#[tauri::command]
+pub fn project_current_path(project: State<'_, ProjectState>) -> Option<String> {
+    project
+        .current_path()
+        .map(|path| path.display().to_string())
+}
@@ -0,0 +1,699 @@
+use crate::dmx::{DmxDriver, DmxSharedState, DmxState, SerialBackend};
+use crate::enttec;
+use log::{debug, info, warn};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{State, Wry};
+use tauri_plugin_serialplugin::SerialPort;
+
+const RDM_START_CODE: u8 = 0xCC;
+const RDM_SUB_START_CODE: u8 = 0x01;
+const RDM_HEADER_LEN: usize = 24;
+
+// La clase de comando de la respuesta siempre es la de la petición + 1
+const DISCOVERY_COMMAND: u8 = 0x10;
+const GET_COMMAND: u8 = 0x20;
+const SET_COMMAND: u8 = 0x30;
+
+const RESPONSE_TYPE_ACK: u8 = 0x00;
+const RESPONSE_TYPE_ACK_TIMER: u8 = 0x01;
+const RESPONSE_TYPE_NACK_REASON: u8 = 0x02;
+
+const PID_DISC_UNIQUE_BRANCH: u16 = 0x0001;
+const PID_DISC_MUTE: u16 = 0x0002;
+const PID_DISC_UN_MUTE: u16 = 0x0003;
+const PID_DEVICE_INFO: u16 = 0x0060;
+const PID_DEVICE_MODEL_DESCRIPTION: u16 = 0x0080;
+const PID_MANUFACTURER_LABEL: u16 = 0x0081;
+const PID_DMX_PERSONALITY: u16 = 0x00E0;
+const PID_DMX_PERSONALITY_DESCRIPTION: u16 = 0x00E1;
+const PID_DMX_START_ADDRESS: u16 = 0x00F0;
+const PID_IDENTIFY_DEVICE: u16 = 0x1000;
+
+const LABEL_SEND_RDM_PACKET: u8 = 7;
+const LABEL_SEND_RDM_DISCOVERY: u8 = 11;
+const LABEL_RDM_TIMEOUT: u8 = 12;
+
+const RESPONSE_TIMEOUT: Duration = Duration::from_millis(100);
+// Cota de seguridad para la búsqueda binaria si un equipo responde mal de forma persistente
+const MAX_DISCOVERY_BRANCHES: usize = 4096;
+
+// Rango de fabricante ESTA reservado para prototipos/uso local
+const CONTROLLER_UID: Uid = Uid {
+    manufacturer: 0x7FF0,
+    device: 0x4C4C_0001,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Uid {
+    manufacturer: u16,
+    device: u32,
+}
+
+impl Uid {
+    const BROADCAST: Uid = Uid {
+        manufacturer: 0xFFFF,
+        device: 0xFFFF_FFFF,
+    };
+    const MAX: u64 = 0xFFFF_FFFF_FFFE;
+
+    fn from_u64(value: u64) -> Self {
+        Self {
+            manufacturer: (value >> 32) as u16,
+            device: value as u32,
+        }
+    }
+
+    fn to_bytes(self) -> [u8; 6] {
+        let mut bytes = [0u8; 6];
+        bytes[..2].copy_from_slice(&self.manufacturer.to_be_bytes());
+        bytes[2..].copy_from_slice(&self.device.to_be_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            manufacturer: u16::from_be_bytes([bytes[0], bytes[1]]),
+            device: u32::from_be_bytes([bytes[2], bytes[3], bytes[4], bytes[5]]),
+        }
+    }
+}
+
+impl FromStr for Uid {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (manufacturer, device) = value
+            .split_once(':')
+            .ok_or_else(|| format!("UID RDM inválido ({value}): se esperaba MMMM:DDDDDDDD"))?;
+
+        Ok(Self {
+            manufacturer: u16::from_str_radix(manufacturer, 16)
+                .map_err(|e| format!("UID RDM inválido ({value}): {e}"))?,
+            device: u32::from_str_radix(device, 16)
+                .map_err(|e| format!("UID RDM inválido ({value}): {e}"))?,
+        })
+    }
+}
+
+impl fmt::Display for Uid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04X}:{:08X}", self.manufacturer, self.device)
+    }
+}
+
+struct RdmResponse {
+    response_type: u8,
+    command_class: u8,
+    pid: u16,
+    data: Vec<u8>,
+}
+
+enum DiscoveryReply {
+    Silence,
+    Found(Uid),
+    Collision,
+}
+
+#[derive(Clone, Serialize)]
+pub struct RdmDevice {
+    uid: String,
+    manufacturer_id: u16,
+    device_model_id: Option<u16>,
+    software_version: Option<u32>,
+    dmx_footprint: Option<u16>,
+    dmx_start_address: Option<u16>,
+    personality: Option<u8>,
+    personality_count: Option<u8>,
+    manufacturer_label: Option<String>,
+    model_description: Option<String>,
+}
+
+#[derive(Default)]
+pub struct RdmState {
+    transaction: AtomicU8,
+    devices: Mutex<HashMap<u16, Vec<RdmDevice>>>,
+}
+
+fn build_request(
+    source: Uid,
+    dest: Uid,
+    transaction: u8,
+    command_class: u8,
+    pid: u16,
+    data: &[u8],
+) -> Vec<u8> {
+    let length = RDM_HEADER_LEN + data.len();
+
+    let mut packet = Vec::with_capacity(length + 2);
+    packet.push(RDM_START_CODE);
+    packet.push(RDM_SUB_START_CODE);
+    packet.push(length as u8);
+    packet.extend_from_slice(&dest.to_bytes());
+    packet.extend_from_slice(&source.to_bytes());
+    packet.push(transaction);
+    packet.push(0x01); // Port ID
+    packet.push(0x00); // Message count
+    packet.extend_from_slice(&0u16.to_be_bytes()); // Sub-device raíz
+    packet.push(command_class);
+    packet.extend_from_slice(&pid.to_be_bytes());
+    packet.push(data.len() as u8);
+    packet.extend_from_slice(data);
+
+    let checksum = packet
+        .iter()
+        .fold(0u16, |acc, b| acc.wrapping_add(*b as u16));
+    packet.extend_from_slice(&checksum.to_be_bytes());
+    packet
+}
+
+fn parse_response(bytes: &[u8]) -> Result<RdmResponse, String> {
+    if bytes.len() < RDM_HEADER_LEN + 2
+        || bytes[0] != RDM_START_CODE
+        || bytes[1] != RDM_SUB_START_CODE
+    {
+        return Err("Respuesta RDM inválida".to_string());
+    }
+
+    let length = bytes[2] as usize;
+    if length < RDM_HEADER_LEN || bytes.len() < length + 2 {
+        return Err("Respuesta RDM truncada".to_string());
+    }
+
+    let checksum = bytes[..length]
+        .iter()
+        .fold(0u16, |acc, b| acc.wrapping_add(*b as u16));
+    if checksum != u16::from_be_bytes([bytes[length], bytes[length + 1]]) {
+        return Err("Checksum RDM incorrecto".to_string());
+    }
+
+    let data_len = (bytes[23] as usize).min(length - RDM_HEADER_LEN);
+    Ok(RdmResponse {
+        response_type: bytes[16],
+        command_class: bytes[20],
+        pid: u16::from_be_bytes([bytes[21], bytes[22]]),
+        data: bytes[RDM_HEADER_LEN..RDM_HEADER_LEN + data_len].to_vec(),
+    })
+}
+
+// La respuesta a DISC_UNIQUE_BRANCH no lleva break: preámbulo 0xFE, separador 0xAA y
+// cada byte del UID/checksum codificado en dos (b | 0xAA, b | 0x55).
+fn decode_discovery_reply(bytes: &[u8]) -> DiscoveryReply {
+    if bytes.is_empty() {
+        return DiscoveryReply::Silence;
+    }
+
+    let Some(separator) = bytes.iter().take(8).position(|b| *b == 0xAA) else {
+        return DiscoveryReply::Collision;
+    };
+    let encoded = &bytes[separator + 1..];
+    if encoded.len() < 16 {
+        return DiscoveryReply::Collision;
+    }
+
+    let decoded = encoded[..16]
+        .chunks(2)
+        .map(|pair| pair[0] & pair[1])
+        .collect::<Vec<_>>();
+    let expected = u16::from_be_bytes([decoded[6], decoded[7]]);
+    let checksum = encoded[..12]
+        .iter()
+        .fold(0u16, |acc, b| acc.wrapping_add(*b as u16));
+
+    if checksum == expected {
+        DiscoveryReply::Found(Uid::from_bytes(&decoded[..6]))
+    } else {
+        DiscoveryReply::Collision
+    }
+}
+
+pub(crate) struct RdmPort<'a> {
+    serial: &'a SerialPort<Wry>,
+    shared: DmxSharedState,
+    port_path: String,
+    transaction: &'a AtomicU8,
+}
+
+impl<'a> RdmPort<'a> {
+    pub(crate) fn open(
+        serial: &'a SerialPort<Wry>,
+        dmx: &DmxState,
+        rdm: &'a RdmState,
+        universe: u16,
+    ) -> Result<Self, String> {
+        let shared = dmx.read_universe(universe, |shared| Ok(shared.clone()))?;
+
+        let port_path = shared
+            .port()
+            .ok_or_else(|| format!("El universo {universe} no tiene un puerto asignado"))?;
+        if shared.driver() != DmxDriver::EnttecPro {
+            return Err(
+                "RDM requiere una interfaz con protocolo Enttec Pro (driver enttec_pro)"
+                    .to_string(),
+            );
+        }
+        if shared.serial_backend() == SerialBackend::Direct {
+            return Err(
+                "RDM necesita el backend serie del plugin (serial_backend plugin)".to_string(),
+            );
+        }
+        if !shared.is_port_open(&port_path) {
+            return Err(format!("El puerto {port_path} todavía no está abierto"));
+        }
+
+        Ok(Self {
+            serial,
+            shared,
+            port_path,
+            transaction: &rdm.transaction,
+        })
+    }
+
+    fn transact(&self, label: u8, packet: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        // Comparte el puerto con el hilo DMX, que toma el mismo lock por frame
+        let _guard = self.shared.lock_output()?;
+
+        // Descarta lo que haya quedado pendiente de transacciones anteriores
+        let _ = self
+            .serial
+            .read_binary(self.port_path.clone(), Some(1), Some(1024));
+
+        self.serial
+            .write_binary(self.port_path.clone(), enttec::build_packet(label, packet))
+            .map_err(|e| format!("No se pudo enviar el paquete RDM: {e}"))?;
+
+        let deadline = Instant::now() + RESPONSE_TIMEOUT;
+        let mut buffer = Vec::new();
+        while Instant::now() < deadline {
+            if let Ok(chunk) = self
+                .serial
+                .read_binary(self.port_path.clone(), Some(5), Some(1024))
+            {
+                buffer.extend_from_slice(&chunk);
+            }
+
+            for (label, payload) in enttec::parse_packets(&mut buffer) {
+                match label {
+                    enttec::LABEL_RECEIVED_DMX_PACKET => {
+                        if let Some((_status, data)) = payload.split_first() {
+                            // Ignora frames DMX normales que pudiera estar recibiendo el widget
+                            if data.first() != Some(&0x00) {
+                                return Ok(Some(data.to_vec()));
+                            }
+                        }
+                    }
+                    LABEL_RDM_TIMEOUT => return Ok(None),
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn next_transaction(&self) -> u8 {
+        self.transaction.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn send_discovery(&self, pid: u16, dest: Uid, data: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        let packet = build_request(
+            CONTROLLER_UID,
+            dest,
+            self.next_transaction(),
+            DISCOVERY_COMMAND,
+            pid,
+            data,
+        );
+        let label = if pid == PID_DISC_UNIQUE_BRANCH {
+            LABEL_SEND_RDM_DISCOVERY
+        } else {
+            LABEL_SEND_RDM_PACKET
+        };
+        self.transact(label, &packet)
+    }
+
+    pub(crate) fn request(
+        &self,
+        dest: Uid,
+        command_class: u8,
+        pid: u16,
+        data: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        let packet = build_request(
+            CONTROLLER_UID,
+            dest,
+            self.next_transaction(),
+            command_class,
+            pid,
+            data,
+        );
+
+        let reply = self
+            .transact(LABEL_SEND_RDM_PACKET, &packet)?
+            .ok_or_else(|| format!("El equipo {dest} no respondió al PID 0x{pid:04X}"))?;
+        let response = parse_response(&reply)?;
+
+        if response.pid != pid || response.command_class != command_class + 1 {
+            return Err(format!("Respuesta RDM inesperada de {dest}"));
+        }
+
+        match response.response_type {
+            RESPONSE_TYPE_ACK => Ok(response.data),
+            RESPONSE_TYPE_ACK_TIMER => Err(format!(
+                "El equipo {dest} todavía está procesando el PID 0x{pid:04X}"
+            )),
+            RESPONSE_TYPE_NACK_REASON => {
+                let reason = response
+                    .data
+                    .get(..2)
+                    .map(|r| u16::from_be_bytes([r[0], r[1]]))
+                    .unwrap_or_default();
+                Err(format!(
+                    "El equipo {dest} rechazó el PID 0x{pid:04X} (motivo 0x{reason:04X})"
+                ))
+            }
+            other => Err(format!("Tipo de respuesta RDM no soportado: 0x{other:02X}")),
+        }
+    }
+
+    fn discover(&self) -> Result<Vec<Uid>, String> {
+        self.send_discovery(PID_DISC_UN_MUTE, Uid::BROADCAST, &[])?;
+
+        let mut found = Vec::new();
+        let mut pending = vec![(0u64, Uid::MAX)];
+        let mut branches = 0;
+
+        while let Some((lower, upper)) = pending.pop() {
+            branches += 1;
+            if branches > MAX_DISCOVERY_BRANCHES {
+                warn!("Descubrimiento RDM cortado tras {MAX_DISCOVERY_BRANCHES} ramas");
+                break;
+            }
+
+            let mut bounds = Uid::from_u64(lower).to_bytes().to_vec();
+            bounds.extend_from_slice(&Uid::from_u64(upper).to_bytes());
+
+            let reply = self.send_discovery(PID_DISC_UNIQUE_BRANCH, Uid::BROADCAST, &bounds)?;
+            match decode_discovery_reply(reply.as_deref().unwrap_or_default()) {
+                DiscoveryReply::Silence => {}
+                DiscoveryReply::Found(uid) => {
+                    debug!("Respondedor RDM encontrado: {uid}");
+                    let confirmed = self.send_discovery(PID_DISC_MUTE, uid, &[])?;
+                    if confirmed.is_some() {
+                        if !found.contains(&uid) {
+                            found.push(uid);
+                        }
+                        // Puede haber más equipos en la misma rama
+                        pending.push((lower, upper));
+                    } else if lower < upper {
+                        // Sin mute va a seguir respondiendo: se parte la rama para buscar a
+                        // los demás en lugar de repetirla
+                        warn!("El respondedor RDM {uid} no confirmó el mute");
+                        let mid = lower + (upper - lower) / 2;
+                        pending.push((mid + 1, upper));
+                        pending.push((lower, mid));
+                    }
+                }
+                DiscoveryReply::Collision if lower < upper => {
+                    let mid = lower + (upper - lower) / 2;
+                    pending.push((mid + 1, upper));
+                    pending.push((lower, mid));
+                }
+                DiscoveryReply::Collision => {
+                    warn!("Colisión RDM irresoluble en {}", Uid::from_u64(lower));
+                }
+            }
+        }
+
+        Ok(found)
+    }
+
+    fn describe(&self, uid: Uid) -> RdmDevice {
+        let mut device = RdmDevice {
+            uid: uid.to_string(),
+            manufacturer_id: uid.manufacturer,
+            device_model_id: None,
+            software_version: None,
+            dmx_footprint: None,
+            dmx_start_address: None,
+            personality: None,
+            personality_count: None,
+            manufacturer_label: None,
+            model_description: None,
+        };
+
+        match self.request(uid, GET_COMMAND, PID_DEVICE_INFO, &[]) {
+            Ok(info) if info.len() >= 19 => {
+                device.device_model_id = Some(u16::from_be_bytes([info[2], info[3]]));
+                device.software_version =
+                    Some(u32::from_be_bytes([info[6], info[7], info[8], info[9]]));
+                device.dmx_footprint = Some(u16::from_be_bytes([info[10], info[11]]));
+                device.personality = Some(info[12]);
+                device.personality_count = Some(info[13]);
+                device.dmx_start_address = Some(u16::from_be_bytes([info[14], info[15]]));
+            }
+            Ok(_) => warn!("DEVICE_INFO incompleto de {uid}"),
+            Err(err) => warn!("{err}"),
+        }
+
+        device.manufacturer_label = self.get_text(uid, PID_MANUFACTURER_LABEL);
+        device.model_description = self.get_text(uid, PID_DEVICE_MODEL_DESCRIPTION);
+        device
+    }
+
+    fn get_text(&self, uid: Uid, pid: u16) -> Option<String> {
+        match self.request(uid, GET_COMMAND, pid, &[]) {
+            Ok(data) => Some(
+                String::from_utf8_lossy(&data)
+                    .trim_end_matches('\0')
+                    .to_string(),
+            ),
+            Err(err) => {
+                debug!("{err}");
+                None
+            }
+        }
+    }
+}
+
+#[tauri::command(async)]
+pub fn rdm_discover(
+    serial: State<'_, SerialPort<Wry>>,
+    dmx: State<'_, DmxState>,
+    rdm: State<'_, RdmState>,
+    universe: u16,
+) -> Result<Vec<RdmDevice>, String> {
+    let port = RdmPort::open(&serial, &dmx, &rdm, universe)?;
+
+    let started = Instant::now();
+    let uids = port.discover()?;
+    let devices = uids
+        .into_iter()
+        .map(|uid| port.describe(uid))
+        .collect::<Vec<_>>();
+    info!(
+        "Descubrimiento RDM en el universo {universe}: {} equipos en {:?}",
+        devices.len(),
+        started.elapsed()
+    );
+
+    rdm.devices
+        .lock()
+        .map_err(|e| format!("No se pudo bloquear la lista de equipos RDM: {e}"))?
+        .insert(universe, devices.clone());
+    Ok(devices)
+}
+
+#[tauri::command]
+pub fn rdm_list_devices(rdm: State<'_, RdmState>, universe: u16) -> Result<Vec<RdmDevice>, String> {
+    Ok(rdm
+        .devices
+        .lock()
+        .map_err(|e| format!("No se pudo bloquear la lista de equipos RDM: {e}"))?
+        .get(&universe)
+        .cloned()
+        .unwrap_or_default())
+}
+
+#[derive(Serialize)]
+pub struct RdmPersonality {
+    current: u8,
+    count: u8,
+    footprint: Option<u16>,
+    description: Option<String>,
+}
+
+impl RdmState {
+    fn update_device(&self, universe: u16, uid: Uid, f: impl FnOnce(&mut RdmDevice)) {
+        let uid = uid.to_string();
+        if let Ok(mut devices) = self.devices.lock() {
+            if let Some(device) = devices
+                .get_mut(&universe)
+                .and_then(|list| list.iter_mut().find(|d| d.uid == uid))
+            {
+                f(device);
+            }
+        }
+    }
+}
+
+#[tauri::command(async)]
+pub fn rdm_get_start_address(
+    serial: State<'_, SerialPort<Wry>>,
+    dmx: State<'_, DmxState>,
+    rdm: State<'_, RdmState>,
+    universe: u16,
+    uid: String,
+) -> Result<u16, String> {
+    let uid = uid.parse::<Uid>()?;
+    let port = RdmPort::open(&serial, &dmx, &rdm, universe)?;
+
+    let data = port.request(uid, GET_COMMAND, PID_DMX_START_ADDRESS, &[])?;
+    let address = data
+        .get(..2)
+        .map(|d| u16::from_be_bytes([d[0], d[1]]))
+        .ok_or_else(|| format!("Respuesta DMX_START_ADDRESS incompleta de {uid}"))?;
+
+    rdm.update_device(universe, uid, |device| {
+        device.dmx_start_address = Some(address)
+    });
+    Ok(address)
+}
+
+#[tauri::command(async)]
+pub fn rdm_set_start_address(
+    serial: State<'_, SerialPort<Wry>>,
+    dmx: State<'_, DmxState>,
+    rdm: State<'_, RdmState>,
+    universe: u16,
+    uid: String,
+    address: u16,
+) -> Result<(), String> {
+    if !(1..=512).contains(&address) {
+        return Err("La dirección DMX debe estar entre 1 y 512".to_string());
+    }
+
+    let uid = uid.parse::<Uid>()?;
+    let port = RdmPort::open(&serial, &dmx, &rdm, universe)?;
+    port.request(
+        uid,
+        SET_COMMAND,
+        PID_DMX_START_ADDRESS,
+        &address.to_be_bytes(),
+    )?;
+
+    info!("RDM: {uid} direccionado en {address}");
+    rdm.update_device(universe, uid, |device| {
+        device.dmx_start_address = Some(address)
+    });
+    Ok(())
+}
+
+#[tauri::command(async)]
+pub fn rdm_get_personality(
+    serial: State<'_, SerialPort<Wry>>,
+    dmx: State<'_, DmxState>,
+    rdm: State<'_, RdmState>,
+    universe: u16,
+    uid: String,
+) -> Result<RdmPersonality, String> {
+    let uid = uid.parse::<Uid>()?;
+    let port = RdmPort::open(&serial, &dmx, &rdm, universe)?;
+
+    let data = port.request(uid, GET_COMMAND, PID_DMX_PERSONALITY, &[])?;
+    let [current, count] = data
+        .get(..2)
+        .map(|d| [d[0], d[1]])
+        .ok_or_else(|| format!("Respuesta DMX_PERSONALITY incompleta de {uid}"))?;
+
+    // La descripción es opcional en el estándar: si el equipo no la soporta se omite
+    let (footprint, description) = match port.request(
+        uid,
+        GET_COMMAND,
+        PID_DMX_PERSONALITY_DESCRIPTION,
+        &[current],
+    ) {
+        Ok(desc) if desc.len() >= 3 => (
+            Some(u16::from_be_bytes([desc[1], desc[2]])),
+            Some(
+                String::from_utf8_lossy(&desc[3..])
+                    .trim_end_matches('\0')
+                    .to_string(),
+            ),
+        ),
+        _ => (None, None),
+    };
+
+    rdm.update_device(universe, uid, |device| {
+        device.personality = Some(current);
+        device.personality_count = Some(count);
+    });
+
+    Ok(RdmPersonality {
+        current,
+        count,
+        footprint,
+        description,
+    })
+}
+
+#[tauri::command(async)]
+pub fn rdm_set_personality(
+    serial: State<'_, SerialPort<Wry>>,
+    dmx: State<'_, DmxState>,
+    rdm: State<'_, RdmState>,
+    universe: u16,
+    uid: String,
+    personality: u8,
+) -> Result<(), String> {
+    if personality == 0 {
+        return Err("Las personalidades RDM empiezan en 1".to_string());
+    }
+
+    let uid = uid.parse::<Uid>()?;
+    let port = RdmPort::open(&serial, &dmx, &rdm, universe)?;
+    port.request(uid, SET_COMMAND, PID_DMX_PERSONALITY, &[personality])?;
+
+    info!("RDM: {uid} cambiado a la personalidad {personality}");
+    rdm.update_device(universe, uid, |device| {
+        device.personality = Some(personality)
+    });
+    Ok(())
+}
+
+#[tauri::command(async)]
+pub fn rdm_get_identify(
+    serial: State<'_, SerialPort<Wry>>,
+    dmx: State<'_, DmxState>,
+    rdm: State<'_, RdmState>,
+    universe: u16,
+    uid: String,
+) -> Result<bool, String> {
+    let uid = uid.parse::<Uid>()?;
+    let port = RdmPort::open(&serial, &dmx, &rdm, universe)?;
+
+    let data = port.request(uid, GET_COMMAND, PID_IDENTIFY_DEVICE, &[])?;
+    data.first()
+        .map(|value| *value != 0)
+        .ok_or_else(|| format!("Respuesta IDENTIFY_DEVICE incompleta de {uid}"))
+}
+
+#[tauri::command(async)]
+pub fn rdm_set_identify(
+    serial: State<'_, SerialPort<Wry>>,
+    dmx: State<'_, DmxState>,
+    rdm: State<'_, RdmState>,
+    universe: u16,
+    uid: String,
+    enabled: bool,
+) -> Result<(), String> {
+    let uid = uid.parse::<Uid>()?;
+    let port = RdmPort::open(&serial, &dmx, &rdm, universe)?;
+    port.request(uid, SET_COMMAND, PID_IDENTIFY_DEVICE, &[enabled as u8])?;
+    Ok(())
+}
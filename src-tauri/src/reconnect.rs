@@ -0,0 +1,127 @@
+use crate::errors::{self, DmxErrorEvent};
+use log::{error, info, warn};
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+pub const RECONNECT_FAILED_EVENT: &str = "dmx://reconnect-failed";
+const INITIAL_DELAY: Duration = Duration::from_millis(500);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+// Con la espera duplicándose son unos dos minutos antes de rendirse
+const MAX_ATTEMPTS: u32 = 10;
+
+#[derive(Clone, Serialize)]
+pub struct ReconnectFailed {
+    universe: u16,
+    port: String,
+    attempts: u32,
+}
+
+// Reintentos de apertura/escritura de la salida física de un universo. Mientras espera, el
+// hilo de escritura sigue mandando Art-Net/sACN y solo se salta la salida física.
+#[derive(Default)]
+pub(crate) struct Reconnect {
+    port: Option<String>,
+    attempts: u32,
+    retry_at: Option<Instant>,
+    gave_up: bool,
+    // Milisegundos desde epoch del último frame que salió bien
+    last_frame_at: Option<u64>,
+    last_error: Option<DmxErrorEvent>,
+}
+
+#[derive(Clone, Default, Serialize)]
+pub struct OutputHealth {
+    last_frame_at: Option<u64>,
+    last_error: Option<DmxErrorEvent>,
+    attempts: u32,
+    gave_up: bool,
+}
+
+impl Reconnect {
+    // Un puerto distinto empieza de cero
+    pub(crate) fn may_attempt(&mut self, port: &str, now: Instant) -> bool {
+        if self.port.as_deref() != Some(port) {
+            *self = Reconnect {
+                port: Some(port.to_string()),
+                ..Reconnect::default()
+            };
+        }
+        !self.gave_up && !matches!(self.retry_at, Some(retry_at) if now < retry_at)
+    }
+
+    // Reinicia los reintentos; el último frame y el último error se conservan para el estado
+    pub(crate) fn reset(&mut self) {
+        self.attempts = 0;
+        self.retry_at = None;
+        self.gave_up = false;
+    }
+
+    pub(crate) fn health(&self) -> OutputHealth {
+        OutputHealth {
+            last_frame_at: self.last_frame_at,
+            last_error: self.last_error.clone(),
+            attempts: self.attempts,
+            gave_up: self.gave_up,
+        }
+    }
+}
+
+// El frame salió bien: si venía fallando, se avisa en el log que se recuperó
+pub(crate) fn report_success(reconnect: &Mutex<Reconnect>, universe: u16, port: &str) {
+    let Ok(mut reconnect) = reconnect.lock() else {
+        return;
+    };
+    reconnect.last_frame_at = Some(errors::now_ms());
+    if reconnect.attempts > 0 {
+        info!(
+            "Salida DMX recuperada en {port} (universo {universe}) tras {} intentos",
+            reconnect.attempts
+        );
+        reconnect.attempts = 0;
+        reconnect.retry_at = None;
+    }
+}
+
+pub(crate) fn report_failure(
+    app_handle: &AppHandle,
+    reconnect: &Mutex<Reconnect>,
+    universe: u16,
+    port: &str,
+    error: DmxErrorEvent,
+) {
+    let Ok(mut reconnect) = reconnect.lock() else {
+        return;
+    };
+    reconnect.last_error = Some(error);
+    reconnect.attempts += 1;
+
+    if reconnect.attempts >= MAX_ATTEMPTS {
+        reconnect.gave_up = true;
+        reconnect.retry_at = None;
+        error!(
+            "Se abandonó la reconexión de {port} (universo {universe}) tras {} intentos; usar dmx_reconnect",
+            reconnect.attempts
+        );
+        let payload = ReconnectFailed {
+            universe,
+            port: port.to_string(),
+            attempts: reconnect.attempts,
+        };
+        if let Err(err) = app_handle.emit(RECONNECT_FAILED_EVENT, payload) {
+            error!("No se pudo avisar del fallo de reconexión: {err}");
+        }
+        return;
+    }
+
+    let delay = INITIAL_DELAY
+        .saturating_mul(1 << (reconnect.attempts - 1).min(16))
+        .min(MAX_DELAY);
+    reconnect.retry_at = Some(Instant::now() + delay);
+    warn!(
+        "Reintentando {port} (universo {universe}) en {} ms (intento {} de {MAX_ATTEMPTS})",
+        delay.as_millis(),
+        reconnect.attempts
+    );
+}
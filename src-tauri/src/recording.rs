@@ -0,0 +1,410 @@
+use crate::dmx::{DmxState, ReplaySource};
+use log::{error, info};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+
+// Formato del archivo de grabación (little endian):
+//   cabecera: "LLDMXREC" + versión (u8)
+//   registros: tiempo desde el inicio en ms (u32), universo (u16), largo (u16), frame
+// Solo se guarda un frame cuando cambia respecto del anterior de su universo.
+const RECORDING_MAGIC: &[u8; 8] = b"LLDMXREC";
+const RECORDING_VERSION: u8 = 1;
+const RECORD_HEADER_LEN: usize = 8;
+const PLAYER_TICK: Duration = Duration::from_millis(2);
+
+struct DmxRecorder {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    started: Instant,
+    last_frames: HashMap<u16, Vec<u8>>,
+    frames: u64,
+}
+
+impl DmxRecorder {
+    fn create(path: PathBuf) -> Result<Self, String> {
+        let file = File::create(&path)
+            .map_err(|e| format!("No se pudo crear la grabación {}: {e}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        writer
+            .write_all(RECORDING_MAGIC)
+            .and_then(|_| writer.write_all(&[RECORDING_VERSION]))
+            .map_err(|e| format!("No se pudo escribir la grabación {}: {e}", path.display()))?;
+
+        Ok(Self {
+            path,
+            writer,
+            started: Instant::now(),
+            last_frames: HashMap::new(),
+            frames: 0,
+        })
+    }
+
+    fn write_frame(&mut self, universe: u16, frame: &[u8]) -> std::io::Result<()> {
+        if self.last_frames.get(&universe).map(Vec::as_slice) == Some(frame) {
+            return Ok(());
+        }
+
+        let elapsed = self.started.elapsed().as_millis().min(u32::MAX as u128) as u32;
+        self.writer.write_all(&elapsed.to_le_bytes())?;
+        self.writer.write_all(&universe.to_le_bytes())?;
+        self.writer.write_all(&(frame.len() as u16).to_le_bytes())?;
+        self.writer.write_all(frame)?;
+
+        self.last_frames.insert(universe, frame.to_vec());
+        self.frames += 1;
+        Ok(())
+    }
+}
+
+struct RecordedFrame {
+    time_ms: u64,
+    universe: u16,
+    frame: Vec<u8>,
+}
+
+fn read_recording(path: &str) -> Result<Vec<RecordedFrame>, String> {
+    let data = fs::read(path).map_err(|e| format!("No se pudo leer la grabación {path}: {e}"))?;
+
+    let header_len = RECORDING_MAGIC.len() + 1;
+    if data.len() < header_len || &data[..RECORDING_MAGIC.len()] != RECORDING_MAGIC {
+        return Err(format!("{path} no es una grabación DMX"));
+    }
+    let version = data[RECORDING_MAGIC.len()];
+    if version != RECORDING_VERSION {
+        return Err(format!(
+            "Versión de grabación DMX no soportada ({version}) en {path}"
+        ));
+    }
+
+    let mut frames = Vec::new();
+    let mut offset = header_len;
+    while offset + RECORD_HEADER_LEN <= data.len() {
+        let header = &data[offset..offset + RECORD_HEADER_LEN];
+        let time_ms = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as u64;
+        let universe = u16::from_le_bytes([header[4], header[5]]);
+        let length = u16::from_le_bytes([header[6], header[7]]) as usize;
+
+        let start = offset + RECORD_HEADER_LEN;
+        // Un registro cortado (p. ej. grabación interrumpida) cierra el archivo
+        let Some(frame) = data.get(start..start + length) else {
+            break;
+        };
+        frames.push(RecordedFrame {
+            time_ms,
+            universe,
+            frame: frame.to_vec(),
+        });
+        offset = start + length;
+    }
+
+    Ok(frames)
+}
+
+enum PlayerCommand {
+    Pause(bool),
+    Seek(u64),
+}
+
+#[derive(Clone, Serialize)]
+pub struct PlaybackStatus {
+    path: String,
+    position_ms: u64,
+    duration_ms: u64,
+    paused: bool,
+    looping: bool,
+}
+
+struct DmxPlayer {
+    commands: Sender<PlayerCommand>,
+    stop_tx: Sender<()>,
+    handle: thread::JoinHandle<()>,
+    status: Arc<Mutex<PlaybackStatus>>,
+}
+
+// Reloj de reproducción que se puede pausar y mover
+struct PlaybackClock {
+    base_ms: u64,
+    resumed: Option<Instant>,
+}
+
+impl PlaybackClock {
+    fn position_ms(&self) -> u64 {
+        self.base_ms
+            + self
+                .resumed
+                .map_or(0, |resumed| resumed.elapsed().as_millis() as u64)
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        match (paused, self.resumed) {
+            (true, Some(_)) => {
+                self.base_ms = self.position_ms();
+                self.resumed = None;
+            }
+            (false, None) => self.resumed = Some(Instant::now()),
+            _ => {}
+        }
+    }
+
+    fn seek(&mut self, position_ms: u64) {
+        self.base_ms = position_ms;
+        if self.resumed.is_some() {
+            self.resumed = Some(Instant::now());
+        }
+    }
+}
+
+fn run_player(
+    app_handle: AppHandle,
+    frames: Vec<RecordedFrame>,
+    status: Arc<Mutex<PlaybackStatus>>,
+    commands: Receiver<PlayerCommand>,
+    stop_rx: Receiver<()>,
+) {
+    let dmx = app_handle.state::<DmxState>();
+    let duration_ms = frames.last().map_or(0, |frame| frame.time_ms);
+    let looping = status.lock().map(|status| status.looping).unwrap_or(false);
+    let universes = frames
+        .iter()
+        .map(|frame| frame.universe)
+        .collect::<BTreeSet<_>>();
+
+    let send = |frame: &RecordedFrame| {
+        if let Err(err) = dmx.with_universe(app_handle.clone(), frame.universe, |shared| {
+            shared.set_replay_frame(ReplaySource::Recording, &frame.frame)
+        }) {
+            error!("No se pudo reproducir un frame DMX grabado: {err}");
+        }
+    };
+
+    let mut clock = PlaybackClock {
+        base_ms: 0,
+        resumed: Some(Instant::now()),
+    };
+    let mut next = 0;
+
+    while stop_rx.try_recv().is_err() {
+        while let Ok(command) = commands.try_recv() {
+            match command {
+                PlayerCommand::Pause(paused) => clock.set_paused(paused),
+                PlayerCommand::Seek(position_ms) => {
+                    clock.seek(position_ms.min(duration_ms));
+                    next = frames.partition_point(|frame| frame.time_ms < clock.base_ms);
+                    // Al saltar se restaura el último frame de cada universo antes de ese punto
+                    for universe in &universes {
+                        if let Some(frame) = frames[..next]
+                            .iter()
+                            .rev()
+                            .find(|frame| frame.universe == *universe)
+                        {
+                            send(frame);
+                        }
+                    }
+                }
+            }
+        }
+
+        let position_ms = clock.position_ms();
+        while let Some(frame) = frames
+            .get(next)
+            .filter(|frame| frame.time_ms <= position_ms)
+        {
+            send(frame);
+            next += 1;
+        }
+
+        if next >= frames.len() {
+            if !looping {
+                break;
+            }
+            clock.seek(0);
+            next = 0;
+        }
+
+        if let Ok(mut status) = status.lock() {
+            status.position_ms = clock.position_ms().min(duration_ms);
+            status.paused = clock.resumed.is_none();
+        }
+        thread::sleep(PLAYER_TICK);
+    }
+
+    for universe in &universes {
+        let _ = dmx.read_universe(*universe, |shared| {
+            shared.clear_replay_frame(ReplaySource::Recording)
+        });
+    }
+    info!("Reproducción de grabación DMX terminada");
+}
+
+#[derive(Default)]
+pub struct RecordingState {
+    recorder: Mutex<Option<DmxRecorder>>,
+    player: Mutex<Option<DmxPlayer>>,
+}
+
+impl RecordingState {
+    // Lo llama el hilo de escritura de cada universo con el frame que sale por el puerto
+    pub(crate) fn capture(&self, universe: u16, frame: &[u8]) {
+        let Ok(mut recorder) = self.recorder.lock() else {
+            return;
+        };
+        let Some(active) = recorder.as_mut() else {
+            return;
+        };
+
+        if let Err(err) = active.write_frame(universe, frame) {
+            error!(
+                "Error al grabar DMX en {}, se detiene la grabación: {err}",
+                active.path.display()
+            );
+            *recorder = None;
+        }
+    }
+
+    fn lock_player(&self) -> Result<std::sync::MutexGuard<'_, Option<DmxPlayer>>, String> {
+        self.player
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la reproducción de grabaciones: {e}"))
+    }
+
+    fn send_player_command(&self, command: PlayerCommand) -> Result<(), String> {
+        let player = self.lock_player()?;
+        let player = player
+            .as_ref()
+            .filter(|player| !player.handle.is_finished())
+            .ok_or_else(|| "No hay una grabación DMX reproduciéndose".to_string())?;
+        player
+            .commands
+            .send(command)
+            .map_err(|_| "La reproducción de la grabación ya terminó".to_string())
+    }
+}
+
+fn stop_player(player: DmxPlayer) {
+    let _ = player.stop_tx.send(());
+    if player.handle.join().is_err() {
+        error!("El hilo de reproducción DMX terminó con pánico");
+    }
+}
+
+#[tauri::command]
+pub fn dmx_record_start(state: State<'_, RecordingState>, path: String) -> Result<(), String> {
+    let mut recorder = state
+        .recorder
+        .lock()
+        .map_err(|e| format!("No se pudo bloquear la grabación DMX: {e}"))?;
+    if recorder.is_some() {
+        return Err("Ya hay una grabación DMX en curso".to_string());
+    }
+
+    *recorder = Some(DmxRecorder::create(PathBuf::from(&path))?);
+    info!("Grabando DMX en {path}");
+    Ok(())
+}
+
+// Devuelve la cantidad de frames guardados
+#[tauri::command]
+pub fn dmx_record_stop(state: State<'_, RecordingState>) -> Result<u64, String> {
+    let mut active = state
+        .recorder
+        .lock()
+        .map_err(|e| format!("No se pudo bloquear la grabación DMX: {e}"))?
+        .take()
+        .ok_or_else(|| "No hay una grabación DMX en curso".to_string())?;
+
+    active.writer.flush().map_err(|e| {
+        format!(
+            "No se pudo cerrar la grabación {}: {e}",
+            active.path.display()
+        )
+    })?;
+    info!(
+        "Grabación DMX terminada: {} ({} frames)",
+        active.path.display(),
+        active.frames
+    );
+    Ok(active.frames)
+}
+
+// Reproduce la grabación por los universos grabados, con su temporización original.
+// Mientras dura, la salida de esos universos es la de la grabación.
+#[tauri::command]
+pub fn dmx_play_recording(
+    app_handle: AppHandle,
+    state: State<'_, RecordingState>,
+    path: String,
+    looping: Option<bool>,
+) -> Result<PlaybackStatus, String> {
+    let frames = read_recording(&path)?;
+    if frames.is_empty() {
+        return Err(format!("La grabación {path} no tiene frames"));
+    }
+
+    let mut player = state.lock_player()?;
+    if let Some(previous) = player.take() {
+        stop_player(previous);
+    }
+
+    let status = PlaybackStatus {
+        path: path.clone(),
+        position_ms: 0,
+        duration_ms: frames.last().map_or(0, |frame| frame.time_ms),
+        paused: false,
+        looping: looping.unwrap_or(false),
+    };
+    let shared_status = Arc::new(Mutex::new(status.clone()));
+
+    let (commands_tx, commands_rx) = mpsc::channel();
+    let (stop_tx, stop_rx) = mpsc::channel();
+    let thread_status = shared_status.clone();
+    let handle =
+        thread::spawn(move || run_player(app_handle, frames, thread_status, commands_rx, stop_rx));
+
+    *player = Some(DmxPlayer {
+        commands: commands_tx,
+        stop_tx,
+        handle,
+        status: shared_status,
+    });
+    info!("Reproduciendo grabación DMX {path}");
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn dmx_playback_pause(state: State<'_, RecordingState>, paused: bool) -> Result<(), String> {
+    state.send_player_command(PlayerCommand::Pause(paused))
+}
+
+#[tauri::command]
+pub fn dmx_playback_seek(state: State<'_, RecordingState>, position_ms: u64) -> Result<(), String> {
+    state.send_player_command(PlayerCommand::Seek(position_ms))
+}
+
+#[tauri::command]
+pub fn dmx_playback_stop(state: State<'_, RecordingState>) -> Result<(), String> {
+    let player = state
+        .lock_player()?
+        .take()
+        .ok_or_else(|| "No hay una grabación DMX reproduciéndose".to_string())?;
+    stop_player(player);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn dmx_playback_status(
+    state: State<'_, RecordingState>,
+) -> Result<Option<PlaybackStatus>, String> {
+    let player = state.lock_player()?;
+    Ok(player
+        .as_ref()
+        .filter(|player| !player.handle.is_finished())
+        .and_then(|player| player.status.lock().ok().map(|status| status.clone())))
+}
@@ -0,0 +1,212 @@
+use crate::audio::AudioState;
+use crate::cues::{cue_back, cue_go, cue_goto, cue_release, cue_status, CueState};
+use crate::dmx::{
+    dmx_blackout, dmx_get_levels, dmx_set_channel, dmx_set_levels, dmx_status, DmxState,
+};
+use crate::groups::{submaster_set_level, GroupState};
+use crate::looper::{
+    loop_clear, loop_overdub, loop_play, loop_record, loop_status, loop_stop, loop_tracks,
+    loop_undo, track_mute, track_set_pan, track_set_volume,
+};
+use crate::scenes::{scene_list, scene_recall, SceneState};
+use crate::transport::{transport_set_tempo, transport_start, transport_status, transport_stop};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+// Comandos que se pueden mandar desde un control remoto; los nombres y los argumentos son los
+// de los comandos de Tauri
+#[derive(Deserialize)]
+#[serde(tag = "command", content = "args", rename_all = "snake_case")]
+pub(crate) enum RemoteCommand {
+    DmxSetChannel {
+        universe: u16,
+        channel: u16,
+        value: u8,
+    },
+    DmxSetLevels {
+        universe: u16,
+        levels: Vec<u8>,
+        fade_ms: Option<u64>,
+    },
+    DmxGetLevels {
+        universe: u16,
+    },
+    DmxBlackout {
+        universe: Option<u16>,
+        fade_ms: Option<u64>,
+    },
+    DmxStatus,
+    SceneRecall {
+        name: String,
+        fade_ms: Option<u64>,
+    },
+    SceneList,
+    CueGo,
+    CueBack,
+    CueRelease,
+    CueGoto {
+        number: f64,
+        fade_ms: Option<u64>,
+    },
+    CueStatus,
+    SubmasterSetLevel {
+        id: u32,
+        value: u8,
+    },
+    LoopRecord {
+        track: Option<usize>,
+    },
+    LoopOverdub {
+        track: Option<usize>,
+    },
+    LoopPlay {
+        track: Option<usize>,
+    },
+    LoopStop {
+        track: Option<usize>,
+    },
+    LoopClear {
+        track: Option<usize>,
+    },
+    LoopUndo {
+        track: Option<usize>,
+    },
+    LoopStatus {
+        track: Option<usize>,
+    },
+    LoopTracks,
+    TrackSetVolume {
+        track: usize,
+        volume: f32,
+    },
+    TrackSetPan {
+        track: usize,
+        pan: f32,
+    },
+    TrackMute {
+        track: usize,
+        muted: bool,
+    },
+    TransportSetTempo {
+        bpm: f64,
+    },
+    TransportStart,
+    TransportStop,
+    TransportStatus,
+}
+
+// Pedido de un control remoto; `id` vuelve tal cual en la respuesta para emparejarlas
+#[derive(Deserialize)]
+struct RemoteRequest {
+    #[serde(default)]
+    id: Option<Value>,
+    #[serde(flatten)]
+    command: RemoteCommand,
+}
+
+#[derive(Serialize)]
+pub(crate) struct RemoteResponse {
+    id: Option<Value>,
+    ok: bool,
+    result: Option<Value>,
+    error: Option<String>,
+}
+
+impl RemoteResponse {
+    pub(crate) fn new(id: Option<Value>, result: Result<Value, String>) -> Self {
+        match result {
+            Ok(result) => Self {
+                id,
+                ok: true,
+                result: Some(result),
+                error: None,
+            },
+            Err(error) => Self {
+                id,
+                ok: false,
+                result: None,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+fn json<T: Serialize>(result: Result<T, String>) -> Result<Value, String> {
+    serde_json::to_value(result?).map_err(|e| format!("No se pudo serializar la respuesta: {e}"))
+}
+
+pub(crate) fn run(app_handle: &AppHandle, command: RemoteCommand) -> Result<Value, String> {
+    let app = || app_handle.clone();
+    let dmx = || app_handle.state::<DmxState>();
+    let cues = || app_handle.state::<CueState>();
+    let audio = || app_handle.state::<AudioState>();
+
+    match command {
+        RemoteCommand::DmxSetChannel {
+            universe,
+            channel,
+            value,
+        } => json(dmx_set_channel(app(), dmx(), universe, channel, value)),
+        RemoteCommand::DmxSetLevels {
+            universe,
+            levels,
+            fade_ms,
+        } => json(dmx_set_levels(app(), dmx(), universe, levels, fade_ms)),
+        RemoteCommand::DmxGetLevels { universe } => json(dmx_get_levels(dmx(), universe)),
+        RemoteCommand::DmxBlackout { universe, fade_ms } => {
+            json(dmx_blackout(dmx(), universe, fade_ms))
+        }
+        RemoteCommand::DmxStatus => json(dmx_status(dmx())),
+        RemoteCommand::SceneRecall { name, fade_ms } => json(scene_recall(
+            app(),
+            app_handle.state::<SceneState>(),
+            dmx(),
+            name,
+            fade_ms,
+        )),
+        RemoteCommand::SceneList => json(scene_list(app_handle.state::<SceneState>())),
+        RemoteCommand::CueGo => json(cue_go(app(), cues(), dmx())),
+        RemoteCommand::CueBack => json(cue_back(app(), cues(), dmx())),
+        RemoteCommand::CueRelease => json(cue_release(app(), cues(), dmx())),
+        RemoteCommand::CueGoto { number, fade_ms } => {
+            json(cue_goto(app(), cues(), dmx(), number, fade_ms))
+        }
+        RemoteCommand::CueStatus => json(cue_status(cues())),
+        RemoteCommand::SubmasterSetLevel { id, value } => json(submaster_set_level(
+            app(),
+            app_handle.state::<GroupState>(),
+            id,
+            value,
+        )),
+        RemoteCommand::LoopRecord { track } => json(loop_record(audio(), track)),
+        RemoteCommand::LoopOverdub { track } => json(loop_overdub(audio(), track)),
+        RemoteCommand::LoopPlay { track } => json(loop_play(audio(), track)),
+        RemoteCommand::LoopStop { track } => json(loop_stop(audio(), track)),
+        RemoteCommand::LoopClear { track } => json(loop_clear(audio(), track)),
+        RemoteCommand::LoopUndo { track } => json(loop_undo(audio(), track)),
+        RemoteCommand::LoopStatus { track } => json(loop_status(audio(), track)),
+        RemoteCommand::LoopTracks => json(loop_tracks(audio())),
+        RemoteCommand::TrackSetVolume { track, volume } => {
+            json(track_set_volume(audio(), track, volume))
+        }
+        RemoteCommand::TrackSetPan { track, pan } => json(track_set_pan(audio(), track, pan)),
+        RemoteCommand::TrackMute { track, muted } => json(track_mute(audio(), track, muted)),
+        RemoteCommand::TransportSetTempo { bpm } => json(transport_set_tempo(audio(), bpm)),
+        RemoteCommand::TransportStart => json(transport_start(audio())),
+        RemoteCommand::TransportStop => json(transport_stop(audio())),
+        RemoteCommand::TransportStatus => json(transport_status(audio())),
+    }
+}
+
+// Para los servidores de texto: parsea el pedido, lo ejecuta y arma la respuesta en JSON
+pub(crate) fn handle_text(app_handle: &AppHandle, text: &str) -> String {
+    let response = match serde_json::from_str::<RemoteRequest>(text) {
+        Ok(request) => RemoteResponse::new(request.id, run(app_handle, request.command)),
+        Err(err) => RemoteResponse::new(None, Err(format!("Pedido inválido: {err}"))),
+    };
+    serde_json::to_string(&response).unwrap_or_else(|_| {
+        r#"{"id":null,"ok":false,"result":null,"error":"No se pudo serializar la respuesta"}"#
+            .to_string()
+    })
+}
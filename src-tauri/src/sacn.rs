@@ -0,0 +1,330 @@
+use crate::dmx::{DmxSharedState, DmxState};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, State};
+
+pub const SACN_PORT: u16 = 5568;
+const ACN_PACKET_ID: &[u8; 12] = b"ASC-E1.17\0\0\0";
+const VECTOR_ROOT_E131_DATA: u32 = 0x0000_0004;
+const VECTOR_E131_DATA_PACKET: u32 = 0x0000_0002;
+const VECTOR_DMP_SET_PROPERTY: u8 = 0x02;
+const SOURCE_NAME_LEN: usize = 64;
+// Bit de opciones de los paquetes para monitores de previsualización, no para la salida
+const PREVIEW_DATA: u8 = 0x80;
+const DEFAULT_PRIORITY: u8 = 100;
+const MAX_PRIORITY: u8 = 200;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SacnConfig {
+    universe: u16,
+    source_name: String,
+    priority: u8,
+    cid: String,
+    enabled: bool,
+    #[serde(skip)]
+    cid_bytes: [u8; 16],
+}
+
+impl SacnConfig {
+    fn new(
+        universe: u16,
+        source_name: Option<String>,
+        priority: Option<u8>,
+        cid: Option<String>,
+    ) -> Result<Self, String> {
+        check_universe(universe)?;
+
+        let priority = priority.unwrap_or(DEFAULT_PRIORITY);
+        if priority > MAX_PRIORITY {
+            return Err(format!(
+                "La prioridad sACN debe estar entre 0 y {MAX_PRIORITY}"
+            ));
+        }
+
+        let source_name = source_name.unwrap_or_else(|| "liveloop-studio".to_string());
+        if source_name.len() >= SOURCE_NAME_LEN {
+            return Err(format!(
+                "El nombre de fuente sACN debe tener menos de {SOURCE_NAME_LEN} bytes"
+            ));
+        }
+
+        let cid_bytes = match cid {
+            Some(cid) => parse_cid(&cid)?,
+            None => generate_cid(),
+        };
+
+        Ok(Self {
+            universe,
+            source_name,
+            priority,
+            cid: format_cid(&cid_bytes),
+            enabled: true,
+            cid_bytes,
+        })
+    }
+
+    fn multicast_addr(&self) -> SocketAddrV4 {
+        SocketAddrV4::new(multicast_ip(self.universe), SACN_PORT)
+    }
+}
+
+pub(crate) fn check_universe(universe: u16) -> Result<(), String> {
+    if !(1..=63999).contains(&universe) {
+        return Err("El universo sACN debe estar entre 1 y 63999".to_string());
+    }
+    Ok(())
+}
+
+// Grupo multicast del universo: 239.255.<alto>.<bajo>
+pub(crate) fn multicast_ip(universe: u16) -> Ipv4Addr {
+    let [hi, lo] = universe.to_be_bytes();
+    Ipv4Addr::new(239, 255, hi, lo)
+}
+
+pub(crate) fn export_config(shared: &DmxSharedState) -> Option<SacnConfig> {
+    shared
+        .sacn
+        .lock()
+        .ok()
+        .and_then(|sacn| sacn.as_ref().map(|sender| sender.config.clone()))
+}
+
+// `cid_bytes` no se serializa, así que se reconstruye desde el CID en texto
+pub(crate) fn restore_config(shared: &DmxSharedState, config: SacnConfig) -> Result<(), String> {
+    let mut restored = SacnConfig::new(
+        config.universe,
+        Some(config.source_name),
+        Some(config.priority),
+        Some(config.cid),
+    )?;
+    restored.enabled = config.enabled;
+
+    let mut sacn = shared
+        .sacn
+        .lock()
+        .map_err(|e| format!("No se pudo bloquear la salida sACN: {e}"))?;
+    *sacn = Some(SacnSender::new(restored));
+    Ok(())
+}
+
+pub(crate) struct SacnSender {
+    config: SacnConfig,
+    socket: Option<UdpSocket>,
+    sequence: u8,
+    // Se reutiliza en cada frame
+    packet: Vec<u8>,
+}
+
+impl SacnSender {
+    fn new(config: SacnConfig) -> Self {
+        Self {
+            config,
+            socket: None,
+            sequence: 0,
+            packet: Vec::new(),
+        }
+    }
+
+    pub(crate) fn send(&mut self, frame: &[u8]) -> Result<(), String> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        if self.socket.is_none() {
+            let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+                .map_err(|e| format!("No se pudo abrir el socket sACN: {e}"))?;
+            self.socket = Some(socket);
+        }
+
+        self.sequence = self.sequence.wrapping_add(1);
+        build_data_packet(&mut self.packet, &self.config, self.sequence, frame);
+
+        let target = self.config.multicast_addr();
+        if let Some(socket) = &self.socket {
+            if let Err(err) = socket.send_to(&self.packet, target) {
+                self.socket = None;
+                return Err(format!("Error al enviar sACN a {target}: {err}"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// `frame` incluye el start code seguido de hasta 512 canales
+fn build_data_packet(packet: &mut Vec<u8>, config: &SacnConfig, sequence: u8, frame: &[u8]) {
+    let values = frame.len().min(513);
+    let total_len = 125 + values;
+
+    packet.clear();
+
+    // Root layer
+    packet.extend_from_slice(&0x0010u16.to_be_bytes());
+    packet.extend_from_slice(&0x0000u16.to_be_bytes());
+    packet.extend_from_slice(ACN_PACKET_ID);
+    packet.extend_from_slice(&flags_and_length(total_len - 16));
+    packet.extend_from_slice(&VECTOR_ROOT_E131_DATA.to_be_bytes());
+    packet.extend_from_slice(&config.cid_bytes);
+
+    // Framing layer
+    packet.extend_from_slice(&flags_and_length(total_len - 38));
+    packet.extend_from_slice(&VECTOR_E131_DATA_PACKET.to_be_bytes());
+    let mut source_name = [0u8; SOURCE_NAME_LEN];
+    source_name[..config.source_name.len()].copy_from_slice(config.source_name.as_bytes());
+    packet.extend_from_slice(&source_name);
+    packet.push(config.priority);
+    packet.extend_from_slice(&0u16.to_be_bytes()); // Synchronization address
+    packet.push(sequence);
+    packet.push(0); // Options
+    packet.extend_from_slice(&config.universe.to_be_bytes());
+
+    // DMP layer
+    packet.extend_from_slice(&flags_and_length(total_len - 115));
+    packet.push(VECTOR_DMP_SET_PROPERTY);
+    packet.push(0xA1); // Address type & data type
+    packet.extend_from_slice(&0u16.to_be_bytes()); // First property address
+    packet.extend_from_slice(&1u16.to_be_bytes()); // Address increment
+    packet.extend_from_slice(&(values as u16).to_be_bytes());
+    packet.extend_from_slice(&frame[..values]);
+}
+
+// Devuelve (universo, prioridad, start code y canales) si el paquete es de datos E1.31; los de
+// vista previa se descartan
+pub(crate) fn parse_data_packet(packet: &[u8]) -> Option<(u16, u8, &[u8])> {
+    if packet.len() < 126 || &packet[4..16] != ACN_PACKET_ID {
+        return None;
+    }
+    let root_vector = u32::from_be_bytes([packet[18], packet[19], packet[20], packet[21]]);
+    let framing_vector = u32::from_be_bytes([packet[40], packet[41], packet[42], packet[43]]);
+    if root_vector != VECTOR_ROOT_E131_DATA
+        || framing_vector != VECTOR_E131_DATA_PACKET
+        || packet[117] != VECTOR_DMP_SET_PROPERTY
+    {
+        return None;
+    }
+    if packet[112] & PREVIEW_DATA != 0 {
+        return None;
+    }
+
+    let priority = packet[108];
+    let universe = u16::from_be_bytes([packet[113], packet[114]]);
+    let values = u16::from_be_bytes([packet[123], packet[124]]) as usize;
+    let end = (125 + values).min(packet.len());
+    Some((universe, priority, &packet[125..end]))
+}
+
+fn flags_and_length(length: usize) -> [u8; 2] {
+    (0x7000 | (length as u16 & 0x0FFF)).to_be_bytes()
+}
+
+fn generate_cid() -> [u8; 16] {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+
+    let mut cid = [0u8; 16];
+    for chunk in cid.chunks_mut(8) {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(nanos);
+        chunk.copy_from_slice(&hasher.finish().to_le_bytes());
+    }
+
+    // UUID v4
+    cid[6] = (cid[6] & 0x0F) | 0x40;
+    cid[8] = (cid[8] & 0x3F) | 0x80;
+    cid
+}
+
+fn parse_cid(cid: &str) -> Result<[u8; 16], String> {
+    let hex = cid.chars().filter(|c| *c != '-').collect::<String>();
+    if hex.len() != 32 {
+        return Err(format!("CID sACN inválido ({cid}): se esperaba un UUID"));
+    }
+
+    let mut bytes = [0u8; 16];
+    for (idx, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[idx * 2..idx * 2 + 2], 16)
+            .map_err(|e| format!("CID sACN inválido ({cid}): {e}"))?;
+    }
+    Ok(bytes)
+}
+
+fn format_cid(bytes: &[u8; 16]) -> String {
+    let hex = bytes.iter().map(|b| format!("{b:02x}")).collect::<String>();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+#[tauri::command]
+pub fn sacn_configure(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    sacn_universe: u16,
+    source_name: Option<String>,
+    priority: Option<u8>,
+    cid: Option<String>,
+) -> Result<SacnConfig, String> {
+    state.with_universe(app_handle, universe, |shared| {
+        let mut sacn = shared
+            .sacn
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la salida sACN: {e}"))?;
+
+        // Conserva el CID anterior para que los receptores no vean una fuente nueva
+        let cid = cid.or_else(|| sacn.as_ref().map(|sender| sender.config.cid.clone()));
+        let config = SacnConfig::new(sacn_universe, source_name, priority, cid)?;
+
+        info!(
+            "Universo {universe} enviando sACN al universo {} (prioridad {})",
+            config.universe, config.priority
+        );
+        *sacn = Some(SacnSender::new(config.clone()));
+        Ok(config)
+    })
+}
+
+#[tauri::command]
+pub fn sacn_set_enabled(
+    app_handle: AppHandle,
+    state: State<'_, DmxState>,
+    universe: u16,
+    enabled: bool,
+) -> Result<(), String> {
+    state.with_universe(app_handle, universe, |shared| {
+        let mut sacn = shared
+            .sacn
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la salida sACN: {e}"))?;
+        let sender = sacn
+            .as_mut()
+            .ok_or_else(|| format!("El universo {universe} no tiene salida sACN configurada"))?;
+        sender.config.enabled = enabled;
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn sacn_get_config(
+    state: State<'_, DmxState>,
+    universe: u16,
+) -> Result<Option<SacnConfig>, String> {
+    state.read_universe(universe, |shared| {
+        shared
+            .sacn
+            .lock()
+            .map(|sacn| sacn.as_ref().map(|sender| sender.config.clone()))
+            .map_err(|e| format!("No se pudo bloquear la salida sACN: {e}"))
+    })
+}
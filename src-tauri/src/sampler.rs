@@ -0,0 +1,292 @@
+use crate::audio::{AudioBlock, AudioState};
+use log::info;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use tauri::State;
+
+// Voces que suenan a la vez; con más, un disparo nuevo corta la más vieja
+const MAX_VOICES: usize = 32;
+// Corte de una voz ahogada por su grupo, para que no haga click (ms)
+const CHOKE_FADE_MS: u32 = 5;
+const MAX_GAIN: f32 = 4.0;
+
+#[derive(Clone, Serialize)]
+pub struct SampleInfo {
+    id: u32,
+    name: String,
+    path: String,
+    length_ms: u64,
+    gain: f32,
+    // Un disparo corta lo que esté sonando del mismo grupo (p. ej. hi-hat abierto y cerrado)
+    choke_group: Option<u8>,
+}
+
+struct Sample {
+    info: SampleInfo,
+    // Estéreo, a la frecuencia del archivo
+    frames: Arc<Vec<[f32; 2]>>,
+    sample_rate: u32,
+}
+
+struct Voice {
+    sample: u32,
+    frames: Arc<Vec<[f32; 2]>>,
+    // Frame del archivo, con fracción cuando la frecuencia no coincide con la del motor
+    position: f64,
+    sample_rate: u32,
+    gain: f32,
+    choke_group: Option<u8>,
+    // Muestras que le quedan al corte; `None` suena hasta el final
+    fade: Option<u32>,
+}
+
+impl Voice {
+    // Interpolación lineal entre los dos frames vecinos
+    fn frame(&self) -> Option<[f32; 2]> {
+        let idx = self.position as usize;
+        let current = self.frames.get(idx)?;
+        let next = self.frames.get(idx + 1).unwrap_or(current);
+        let frac = self.position.fract() as f32;
+        Some([
+            current[0] + (next[0] - current[0]) * frac,
+            current[1] + (next[1] - current[1]) * frac,
+        ])
+    }
+}
+
+pub(crate) struct Sampler {
+    samples: BTreeMap<u32, Sample>,
+    next_id: u32,
+    voices: Vec<Voice>,
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Self {
+            samples: BTreeMap::new(),
+            next_id: 1,
+            voices: Vec::with_capacity(MAX_VOICES),
+        }
+    }
+}
+
+impl Sampler {
+    fn sample(&self, id: u32) -> Result<&Sample, String> {
+        self.samples
+            .get(&id)
+            .ok_or_else(|| format!("El sample {id} no existe"))
+    }
+
+    fn trigger(&mut self, id: u32) -> Result<(), String> {
+        let sample = self.sample(id)?;
+        let voice = Voice {
+            sample: id,
+            frames: sample.frames.clone(),
+            position: 0.0,
+            sample_rate: sample.sample_rate,
+            gain: sample.info.gain,
+            choke_group: sample.info.choke_group,
+            fade: None,
+        };
+        if let Some(group) = voice.choke_group {
+            self.voices
+                .iter_mut()
+                .filter(|playing| playing.choke_group == Some(group) && playing.fade.is_none())
+                .for_each(|playing| playing.fade = Some(0));
+        }
+        if self.voices.len() == MAX_VOICES {
+            self.voices.remove(0);
+        }
+        self.voices.push(voice);
+        Ok(())
+    }
+
+    // Lo llama el callback de salida en cada bloque
+    pub(crate) fn process(&mut self, block: &mut AudioBlock) {
+        let fade_len = (CHOKE_FADE_MS * block.sample_rate / 1000).max(1);
+        for voice in &mut self.voices {
+            let step = voice.sample_rate as f64 / block.sample_rate as f64;
+            for out in block.main.iter_mut() {
+                let Some(frame) = voice.frame() else {
+                    break;
+                };
+                let envelope = match &mut voice.fade {
+                    None => 1.0,
+                    // Recién ahogada: el corte arranca en esta muestra
+                    Some(0) => {
+                        voice.fade = Some(fade_len);
+                        1.0
+                    }
+                    Some(1) => {
+                        voice.position = voice.frames.len() as f64;
+                        break;
+                    }
+                    Some(remaining) => {
+                        *remaining -= 1;
+                        *remaining as f32 / fade_len as f32
+                    }
+                };
+                out[0] += frame[0] * voice.gain * envelope;
+                out[1] += frame[1] * voice.gain * envelope;
+                voice.position += step;
+            }
+        }
+        // Las voces terminadas se quitan sin soltar memoria: los buffers siguen en `samples`
+        self.voices
+            .retain(|voice| (voice.position as usize) < voice.frames.len());
+    }
+}
+
+// WAV entero o en coma flotante, mono o estéreo (con más canales se usan los dos primeros)
+fn read_wav(path: &Path) -> Result<(Vec<[f32; 2]>, u32), String> {
+    let error = |e: hound::Error| format!("No se pudo leer {}: {e}", path.display());
+    let mut reader = hound::WavReader::open(path).map_err(error)?;
+    let spec = reader.spec();
+    let values = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .samples::<f32>()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(error)?,
+        hound::SampleFormat::Int if (1..=32).contains(&spec.bits_per_sample) => {
+            let scale = (1u64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|value| value.map(|value| value as f32 / scale))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(error)?
+        }
+        hound::SampleFormat::Int => {
+            return Err(format!(
+                "{}: {} bits por muestra no es un formato válido",
+                path.display(),
+                spec.bits_per_sample
+            ));
+        }
+    };
+    let channels = spec.channels as usize;
+    if channels == 0 {
+        return Err(format!("{} no tiene canales", path.display()));
+    }
+    let frames = values
+        .chunks_exact(channels)
+        .map(|frame| match frame {
+            [mono] => [*mono, *mono],
+            [left, right, ..] => [*left, *right],
+            [] => [0.0; 2],
+        })
+        .collect();
+    Ok((frames, spec.sample_rate))
+}
+
+fn validate_gain(gain: f32) -> Result<(), String> {
+    if !(0.0..=MAX_GAIN).contains(&gain) {
+        return Err(format!("La ganancia del sample va de 0 a {MAX_GAIN}"));
+    }
+    Ok(())
+}
+
+// Carga el archivo entero en memoria, así el disparo no toca el disco
+#[tauri::command]
+pub fn sample_load(
+    audio: State<'_, AudioState>,
+    path: String,
+    gain: Option<f32>,
+    choke_group: Option<u8>,
+) -> Result<SampleInfo, String> {
+    let gain = gain.unwrap_or(1.0);
+    validate_gain(gain)?;
+    let file = Path::new(&path);
+    let (frames, sample_rate) = read_wav(file)?;
+    if frames.is_empty() || sample_rate == 0 {
+        return Err(format!("{path} no tiene audio"));
+    }
+
+    let mut sampler = audio.shared().lock_sampler()?;
+    let id = sampler.next_id;
+    sampler.next_id += 1;
+    let info = SampleInfo {
+        id,
+        name: file
+            .file_stem()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.clone()),
+        path: path.clone(),
+        length_ms: frames.len() as u64 * 1000 / sample_rate as u64,
+        gain,
+        choke_group,
+    };
+    sampler.samples.insert(
+        id,
+        Sample {
+            info: info.clone(),
+            frames: Arc::new(frames),
+            sample_rate,
+        },
+    );
+    info!("Sample {id} cargado: {path} ({} ms)", info.length_ms);
+    Ok(info)
+}
+
+#[tauri::command]
+pub fn sample_trigger(audio: State<'_, AudioState>, id: u32) -> Result<(), String> {
+    audio.shared().lock_sampler()?.trigger(id)
+}
+
+// Sin `id` corta todas las voces
+#[tauri::command]
+pub fn sample_stop(audio: State<'_, AudioState>, id: Option<u32>) -> Result<(), String> {
+    let mut sampler = audio.shared().lock_sampler()?;
+    sampler
+        .voices
+        .iter_mut()
+        .filter(|voice| id.is_none() || id == Some(voice.sample))
+        .filter(|voice| voice.fade.is_none())
+        .for_each(|voice| voice.fade = Some(0));
+    Ok(())
+}
+
+// Los cambios valen para los próximos disparos
+#[tauri::command]
+pub fn sample_set(
+    audio: State<'_, AudioState>,
+    id: u32,
+    gain: f32,
+    choke_group: Option<u8>,
+) -> Result<SampleInfo, String> {
+    validate_gain(gain)?;
+    let mut sampler = audio.shared().lock_sampler()?;
+    let sample = sampler
+        .samples
+        .get_mut(&id)
+        .ok_or_else(|| format!("El sample {id} no existe"))?;
+    sample.info.gain = gain;
+    sample.info.choke_group = choke_group;
+    Ok(sample.info.clone())
+}
+
+#[tauri::command]
+pub fn sample_unload(audio: State<'_, AudioState>, id: u32) -> Result<(), String> {
+    let removed = {
+        let mut sampler = audio.shared().lock_sampler()?;
+        // Las voces se quitan acá, así el buffer no se libera en el hilo de audio
+        sampler.voices.retain(|voice| voice.sample != id);
+        sampler.samples.remove(&id)
+    };
+    match removed {
+        Some(_) => Ok(()),
+        None => Err(format!("El sample {id} no existe")),
+    }
+}
+
+#[tauri::command]
+pub fn sample_list(audio: State<'_, AudioState>) -> Result<Vec<SampleInfo>, String> {
+    Ok(audio
+        .shared()
+        .lock_sampler()?
+        .samples
+        .values()
+        .map(|sample| sample.info.clone())
+        .collect())
+}
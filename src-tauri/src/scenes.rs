@@ -0,0 +1,177 @@
+use crate::dmx::DmxState;
+use crate::storage;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, State};
+
+const SCENES_FILE: &str = "scenes.json";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Scene {
+    name: String,
+    // Niveles (512 canales, sin start code) por universo
+    universes: BTreeMap<u16, Vec<u8>>,
+}
+
+#[derive(Default)]
+pub struct SceneState {
+    scenes: Mutex<BTreeMap<String, Scene>>,
+    path: Mutex<Option<PathBuf>>,
+    // Última escena recuperada
+    active: Mutex<Option<String>>,
+}
+
+impl SceneState {
+    pub fn load(&self, app_handle: &AppHandle) {
+        let Some(path) = storage::config_file(app_handle, SCENES_FILE) else {
+            return;
+        };
+
+        if let Some(scenes) = storage::load_json::<Vec<Scene>>(&path) {
+            if let Ok(mut current) = self.scenes.lock() {
+                *current = scenes
+                    .into_iter()
+                    .map(|scene| (scene.name.clone(), scene))
+                    .collect();
+            }
+        }
+
+        if let Ok(mut current) = self.path.lock() {
+            *current = Some(path);
+        }
+    }
+
+    pub(crate) fn export(&self) -> Result<Vec<Scene>, String> {
+        Ok(self.lock_scenes()?.values().cloned().collect())
+    }
+
+    pub(crate) fn replace(&self, scenes: Vec<Scene>) -> Result<(), String> {
+        let mut stored = self.lock_scenes()?;
+        *stored = scenes
+            .into_iter()
+            .map(|scene| (scene.name.clone(), scene))
+            .collect();
+        self.set_active(None);
+        self.save(&stored)
+    }
+
+    pub(crate) fn names(&self) -> Result<Vec<String>, String> {
+        Ok(self.lock_scenes()?.keys().cloned().collect())
+    }
+
+    pub(crate) fn active(&self) -> Option<String> {
+        self.active.lock().ok().and_then(|active| active.clone())
+    }
+
+    fn set_active(&self, name: Option<String>) {
+        if let Ok(mut active) = self.active.lock() {
+            *active = name;
+        }
+    }
+
+    fn lock_scenes(&self) -> Result<std::sync::MutexGuard<'_, BTreeMap<String, Scene>>, String> {
+        self.scenes
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la tabla de escenas: {e}"))
+    }
+
+    fn save(&self, scenes: &BTreeMap<String, Scene>) -> Result<(), String> {
+        let path = self
+            .path
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la tabla de escenas: {e}"))?
+            .clone();
+
+        match path {
+            Some(path) => storage::save_json(&path, &scenes.values().collect::<Vec<_>>()),
+            None => Ok(()),
+        }
+    }
+}
+
+// Sin universo se capturan todos los universos configurados
+#[tauri::command]
+pub fn scene_store(
+    scenes: State<'_, SceneState>,
+    dmx: State<'_, DmxState>,
+    name: String,
+    universe: Option<u16>,
+) -> Result<Scene, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("La escena necesita un nombre".to_string());
+    }
+
+    let mut universes = BTreeMap::new();
+    dmx.for_each_universe(|id, shared| {
+        if universe.is_some_and(|universe| universe != id) {
+            return;
+        }
+        universes.insert(id, shared.channel_levels());
+    });
+    if universes.is_empty() {
+        return Err("No hay universos DMX para guardar en la escena".to_string());
+    }
+
+    let scene = Scene {
+        name: name.clone(),
+        universes,
+    };
+
+    let mut stored = scenes.lock_scenes()?;
+    stored.insert(name.clone(), scene.clone());
+    scenes.save(&stored)?;
+
+    info!(
+        "Escena \"{name}\" guardada ({} universos)",
+        scene.universes.len()
+    );
+    Ok(scene)
+}
+
+#[tauri::command]
+pub fn scene_recall(
+    app_handle: AppHandle,
+    scenes: State<'_, SceneState>,
+    dmx: State<'_, DmxState>,
+    name: String,
+    fade_ms: Option<u64>,
+) -> Result<(), String> {
+    let scene = scenes
+        .lock_scenes()?
+        .get(&name)
+        .cloned()
+        .ok_or_else(|| format!("La escena \"{name}\" no existe"))?;
+
+    let duration = Duration::from_millis(fade_ms.unwrap_or(0));
+    for (universe, levels) in &scene.universes {
+        dmx.with_universe(app_handle.clone(), *universe, |shared| {
+            shared.start_fade(levels, duration)
+        })?;
+    }
+
+    info!("Escena \"{name}\" recuperada ({} ms)", duration.as_millis());
+    scenes.set_active(Some(name));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn scene_delete(scenes: State<'_, SceneState>, name: String) -> Result<(), String> {
+    let mut stored = scenes.lock_scenes()?;
+    stored
+        .remove(&name)
+        .ok_or_else(|| format!("La escena \"{name}\" no existe"))?;
+    if scenes.active().as_ref() == Some(&name) {
+        scenes.set_active(None);
+    }
+    scenes.save(&stored)
+}
+
+#[tauri::command]
+pub fn scene_list(scenes: State<'_, SceneState>) -> Result<Vec<Scene>, String> {
+    Ok(scenes.lock_scenes()?.values().cloned().collect())
+}
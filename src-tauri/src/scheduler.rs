@@ -0,0 +1,40 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Los últimos microsegundos se esperan activamente: `thread::sleep` puede pasarse por más de
+// un milisegundo según el sistema
+const SPIN_MARGIN: Duration = Duration::from_micros(1500);
+
+// Marca el ritmo del hilo de escritura con instantes absolutos: el tiempo que tarda cada
+// escritura no se suma al período, así la frecuencia no deriva
+pub(crate) struct FrameScheduler {
+    next: Instant,
+}
+
+impl FrameScheduler {
+    pub(crate) fn new() -> Self {
+        Self {
+            next: Instant::now(),
+        }
+    }
+
+    // Bloquea hasta el próximo frame. Si el hilo se atrasó más de un período (un write
+    // bloqueado, por ejemplo) se resincroniza en lugar de mandar una ráfaga de frames.
+    pub(crate) fn wait(&mut self, period: Duration) {
+        self.next += period;
+        let now = Instant::now();
+        if now > self.next + period {
+            self.next = now;
+            return;
+        }
+
+        if let Some(remaining) = self.next.checked_duration_since(now) {
+            if remaining > SPIN_MARGIN {
+                thread::sleep(remaining - SPIN_MARGIN);
+            }
+        }
+        while Instant::now() < self.next {
+            thread::yield_now();
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use crate::dmx::{DmxDriver, SerialLink, DMX_BAUD_RATE};
+use crate::enttec;
+use std::io::Write;
+use std::time::Duration;
+
+const WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+// Handle de `serialport` propio del hilo de escritura: evita el lock y la copia del plugin en
+// cada frame. Se cierra al soltarlo.
+pub(crate) struct DirectSerial {
+    path: String,
+    driver: DmxDriver,
+    port: Box<dyn serialport::SerialPort>,
+    // Buffer de los paquetes Enttec, reutilizado entre frames
+    packet: Vec<u8>,
+}
+
+impl DirectSerial {
+    pub(crate) fn open(path: &str, driver: DmxDriver) -> Result<Self, String> {
+        // Mismos parámetros que `open_serial_port`: el Pro es un puerto virtual
+        let (baud_rate, stop_bits) = match driver {
+            DmxDriver::OpenDmx | DmxDriver::PiUart => (DMX_BAUD_RATE, serialport::StopBits::Two),
+            DmxDriver::EnttecPro => (57600, serialport::StopBits::One),
+        };
+
+        let port = serialport::new(path, baud_rate)
+            .data_bits(serialport::DataBits::Eight)
+            .flow_control(serialport::FlowControl::None)
+            .parity(serialport::Parity::None)
+            .stop_bits(stop_bits)
+            .timeout(WRITE_TIMEOUT)
+            .open()
+            .map_err(|e| format!("No se pudo abrir el puerto DMX {path}: {e}"))?;
+
+        Ok(Self {
+            path: path.to_string(),
+            driver,
+            port,
+            packet: Vec::new(),
+        })
+    }
+
+    // Un cambio de driver necesita reabrir con otros parámetros
+    pub(crate) fn matches(&self, path: &str, driver: DmxDriver) -> bool {
+        self.path == path && self.driver == driver
+    }
+}
+
+impl SerialLink for DirectSerial {
+    fn set_break(&mut self) -> Result<(), String> {
+        self.port.set_break().map_err(|e| e.to_string())
+    }
+
+    fn clear_break(&mut self) -> Result<(), String> {
+        self.port.clear_break().map_err(|e| e.to_string())
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), String> {
+        self.port
+            .set_baud_rate(baud_rate)
+            .map_err(|e| e.to_string())
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), String> {
+        self.port.write_all(data).map_err(|e| e.to_string())
+    }
+
+    fn write_enttec_packet(&mut self, label: u8, payload: &[u8]) -> Result<(), String> {
+        enttec::build_packet_into(&mut self.packet, label, payload);
+        self.port.write_all(&self.packet).map_err(|e| e.to_string())
+    }
+}
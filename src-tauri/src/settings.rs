@@ -0,0 +1,237 @@
+use crate::audio::AudioStartup;
+use crate::companion::{companion_listen_start, CompanionState};
+use crate::dmx::{DmxState, UniverseSettings, MAX_REFRESH_HZ, MIN_REFRESH_HZ};
+use crate::http_api::{http_api_start, HttpApiState};
+use crate::osc::{osc_listen_start, OscState};
+use crate::storage;
+use crate::websocket::{ws_listen_start, WsState};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+const SETTINGS_FILE: &str = "settings.json";
+// Los universos con que se cerró la app, aparte de la configuración
+const LAST_UNIVERSES_FILE: &str = "last_universes.json";
+
+// Un servidor de control que se levanta al arrancar
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ListenSettings {
+    #[serde(default)]
+    bind_ip: Option<String>,
+    #[serde(default)]
+    port: Option<u16>,
+    // Solo para OSC: universo que mueven los mensajes de canal
+    #[serde(default)]
+    universe: Option<u16>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Settings {
+    // Frecuencia DMX de un puerto que no tiene la suya guardada
+    #[serde(default)]
+    refresh_hz: Option<u32>,
+    // Al arrancar vuelven los universos de la última sesión: puertos, drivers, frecuencias y
+    // salidas Art-Net/sACN
+    #[serde(default)]
+    restore_universes: bool,
+    #[serde(default)]
+    websocket: Option<ListenSettings>,
+    #[serde(default)]
+    http_api: Option<ListenSettings>,
+    #[serde(default)]
+    companion: Option<ListenSettings>,
+    #[serde(default)]
+    osc: Option<ListenSettings>,
+    // Backend, dispositivos y tamaño de bloque con que arranca el audio; sin valor, los
+    // últimos usados
+    #[serde(default)]
+    audio: Option<AudioStartup>,
+}
+
+impl Settings {
+    fn validate(&self) -> Result<(), String> {
+        if let Some(hz) = self.refresh_hz {
+            if !(MIN_REFRESH_HZ..=MAX_REFRESH_HZ).contains(&hz) {
+                return Err(format!(
+                    "La frecuencia DMX debe estar entre {MIN_REFRESH_HZ} y {MAX_REFRESH_HZ} Hz"
+                ));
+            }
+        }
+        if let Some(audio) = &self.audio {
+            audio.validate()?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct SettingsState {
+    settings: Mutex<Settings>,
+    path: Mutex<Option<PathBuf>>,
+    last_universes_path: Mutex<Option<PathBuf>>,
+}
+
+impl SettingsState {
+    pub fn load(&self, app_handle: &AppHandle) {
+        if let Some(path) = storage::config_file(app_handle, SETTINGS_FILE) {
+            if let Some(settings) = storage::load_json::<Settings>(&path) {
+                if let Ok(mut current) = self.settings.lock() {
+                    *current = settings;
+                }
+            }
+            if let Ok(mut current) = self.path.lock() {
+                *current = Some(path);
+            }
+        }
+
+        if let Ok(mut current) = self.last_universes_path.lock() {
+            *current = storage::config_file(app_handle, LAST_UNIVERSES_FILE);
+        }
+    }
+
+    // Después de cargar el resto del estado: levanta lo que la configuración pide al arrancar
+    pub fn restore(&self, app_handle: &AppHandle) {
+        let Ok(settings) = self.lock_settings().map(|settings| settings.clone()) else {
+            return;
+        };
+
+        if settings.restore_universes {
+            let universes = self
+                .last_universes_path()
+                .and_then(|path| storage::load_json::<Vec<UniverseSettings>>(&path));
+            if let Some(universes) = universes {
+                let count = universes.len();
+                match app_handle
+                    .state::<DmxState>()
+                    .restore_settings(app_handle, universes)
+                {
+                    Ok(()) => info!("{count} universos de la última sesión restaurados"),
+                    Err(err) => error!("No se pudo restaurar los universos: {err}"),
+                }
+            }
+        }
+
+        let app = || app_handle.clone();
+        if let Some(listen) = settings.websocket {
+            let result = ws_listen_start(
+                app(),
+                app_handle.state::<WsState>(),
+                listen.bind_ip,
+                listen.port,
+            );
+            if let Err(err) = result {
+                error!("{err}");
+            }
+        }
+        if let Some(listen) = settings.http_api {
+            let result = http_api_start(
+                app(),
+                app_handle.state::<HttpApiState>(),
+                listen.bind_ip,
+                listen.port,
+            );
+            if let Err(err) = result {
+                error!("{err}");
+            }
+        }
+        if let Some(listen) = settings.companion {
+            let result = companion_listen_start(
+                app(),
+                app_handle.state::<CompanionState>(),
+                listen.bind_ip,
+                listen.port,
+            );
+            if let Err(err) = result {
+                error!("{err}");
+            }
+        }
+        if let Some(listen) = settings.osc {
+            let result = osc_listen_start(
+                app(),
+                app_handle.state::<OscState>(),
+                listen.bind_ip,
+                listen.port,
+                listen.universe,
+            );
+            if let Err(err) = result {
+                error!("{err}");
+            }
+        }
+    }
+
+    // Al cerrar, antes de detener la salida DMX: guarda los universos para el próximo arranque
+    pub fn shutdown(&self, app_handle: &AppHandle) {
+        let Some(path) = self.last_universes_path() else {
+            return;
+        };
+        let result = app_handle
+            .state::<DmxState>()
+            .export_settings()
+            .and_then(|universes| storage::save_json(&path, &universes));
+        if let Err(err) = result {
+            error!("No se pudo guardar los universos de la sesión: {err}");
+        }
+    }
+
+    // Lo pide el audio al cargar, que viene después de la configuración
+    pub(crate) fn audio_startup(&self) -> Option<AudioStartup> {
+        self.settings
+            .lock()
+            .ok()
+            .and_then(|settings| settings.audio.clone())
+    }
+
+    pub(crate) fn refresh_hz(&self) -> Option<u32> {
+        self.settings
+            .lock()
+            .ok()
+            .and_then(|settings| settings.refresh_hz)
+    }
+
+    fn last_universes_path(&self) -> Option<PathBuf> {
+        self.last_universes_path
+            .lock()
+            .ok()
+            .and_then(|path| path.clone())
+    }
+
+    fn lock_settings(&self) -> Result<std::sync::MutexGuard<'_, Settings>, String> {
+        self.settings
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la configuración: {e}"))
+    }
+
+    fn save(&self, settings: &Settings) -> Result<(), String> {
+        let path = self
+            .path
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la configuración: {e}"))?
+            .clone();
+
+        match path {
+            Some(path) => storage::save_json(&path, settings),
+            None => Ok(()),
+        }
+    }
+}
+
+#[tauri::command]
+pub fn settings_get(state: State<'_, SettingsState>) -> Result<Settings, String> {
+    Ok(state.lock_settings()?.clone())
+}
+
+// Reemplaza la configuración entera. La frecuencia vale para los próximos puertos abiertos; los
+// servidores, los universos y el audio, para el próximo arranque.
+#[tauri::command]
+pub fn settings_set(
+    state: State<'_, SettingsState>,
+    settings: Settings,
+) -> Result<Settings, String> {
+    settings.validate()?;
+    let mut current = state.lock_settings()?;
+    state.save(&settings)?;
+    *current = settings;
+    Ok(current.clone())
+}
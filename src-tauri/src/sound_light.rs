@@ -0,0 +1,233 @@
+use crate::audio::AudioState;
+use crate::dmx::{DmxState, DMX_CHANNELS};
+use crate::spectrum::{SoundBand, SpectrumBands};
+use crate::storage;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+const SOUND_LIGHT_FILE: &str = "sound_light.json";
+// Más rápido que el refresco DMX no se nota
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SoundTarget {
+    /// Entra al merge como una fuente más (`sound`)
+    Channel { universe: u16, channel: u16 },
+    /// Escala el tamaño del efecto del canal
+    Effect { universe: u16, channel: u16 },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SoundMapping {
+    band: SoundBand,
+    target: SoundTarget,
+    // Valor con la banda en silencio y a pleno (sin `max`, 255); en un efecto, 255 es el
+    // tamaño completo
+    #[serde(default)]
+    min: u8,
+    #[serde(default)]
+    max: Option<u8>,
+}
+
+impl SoundMapping {
+    fn validate(&self) -> Result<(), String> {
+        let (SoundTarget::Channel { channel, .. } | SoundTarget::Effect { channel, .. }) =
+            self.target;
+        if !(1..=512).contains(&channel) {
+            return Err(format!("Canal DMX inválido: {channel}"));
+        }
+        Ok(())
+    }
+
+    fn value(&self, bands: &SpectrumBands) -> u8 {
+        let level = bands.level(self.band) as f64;
+        let (min, max) = (self.min as f64, self.max.unwrap_or(255) as f64);
+        (min + (max - min) * level).round() as u8
+    }
+}
+
+#[derive(Default)]
+pub struct SoundLightState {
+    mappings: Mutex<BTreeMap<String, SoundMapping>>,
+    path: Mutex<Option<PathBuf>>,
+}
+
+impl SoundLightState {
+    pub fn load(&self, app_handle: &AppHandle) {
+        let Some(path) = storage::config_file(app_handle, SOUND_LIGHT_FILE) else {
+            return;
+        };
+
+        if let Some(mut stored) = storage::load_json::<BTreeMap<String, SoundMapping>>(&path) {
+            // Un canal fuera de rango en el archivo no llega a `targets`
+            stored.retain(|name, mapping| match mapping.validate() {
+                Ok(()) => true,
+                Err(err) => {
+                    warn!("Mapeo de sound-to-light \"{name}\" descartado: {err}");
+                    false
+                }
+            });
+            if let Ok(mut mappings) = self.mappings.lock() {
+                *mappings = stored;
+            }
+        }
+
+        if let Ok(mut current) = self.path.lock() {
+            *current = Some(path);
+        }
+    }
+
+    fn lock_mappings(
+        &self,
+    ) -> Result<std::sync::MutexGuard<'_, BTreeMap<String, SoundMapping>>, String> {
+        self.mappings
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el sound-to-light: {e}"))
+    }
+
+    fn save(&self, mappings: &BTreeMap<String, SoundMapping>) -> Result<(), String> {
+        let path = self
+            .path
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el sound-to-light: {e}"))?
+            .clone();
+
+        match path {
+            Some(path) => storage::save_json(&path, mappings),
+            None => Ok(()),
+        }
+    }
+}
+
+type ChannelLevels = BTreeMap<u16, Vec<Option<u8>>>;
+type EffectScales = BTreeMap<u16, BTreeMap<u16, f64>>;
+
+fn targets(
+    mappings: &BTreeMap<String, SoundMapping>,
+    bands: &SpectrumBands,
+) -> (ChannelLevels, EffectScales) {
+    let mut channels = ChannelLevels::new();
+    let mut effects = EffectScales::new();
+    for mapping in mappings.values() {
+        let value = mapping.value(bands);
+        match mapping.target {
+            SoundTarget::Channel { universe, channel } => {
+                let levels = channels
+                    .entry(universe)
+                    .or_insert_with(|| vec![None; DMX_CHANNELS]);
+                // Dos bandas en el mismo canal: gana la más alta
+                let level = &mut levels[channel as usize - 1];
+                *level = Some(level.unwrap_or(0).max(value));
+            }
+            SoundTarget::Effect { universe, channel } => {
+                let scale = effects
+                    .entry(universe)
+                    .or_default()
+                    .entry(channel)
+                    .or_insert(0.0);
+                *scale = scale.max(value as f64 / 255.0);
+            }
+        }
+    }
+    (channels, effects)
+}
+
+// Pasa los niveles de las bandas a los canales y efectos mapeados. Sin mapeos el análisis de
+// espectro queda apagado.
+pub fn start_sound_light(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    thread::spawn(move || {
+        let mut active = false;
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let state = app_handle.state::<SoundLightState>();
+            let (channels, effects) = {
+                let mappings = match state.lock_mappings() {
+                    Ok(mappings) => mappings,
+                    Err(err) => {
+                        error!("{err}");
+                        continue;
+                    }
+                };
+                // Al quitar el último mapeo se limpia una vez lo que quedó aplicado
+                if mappings.is_empty() && !active {
+                    continue;
+                }
+                active = !mappings.is_empty();
+
+                let audio = app_handle.state::<AudioState>();
+                let bands = match audio.shared().lock_spectrum() {
+                    Ok(mut spectrum) => {
+                        spectrum.set_enabled(active);
+                        spectrum.bands()
+                    }
+                    Err(err) => {
+                        error!("{err}");
+                        continue;
+                    }
+                };
+                targets(&mappings, &bands)
+            };
+
+            let dmx = app_handle.state::<DmxState>();
+            let result = dmx
+                .distribute(&app_handle, channels, |shared, levels| {
+                    shared.set_sound(levels)
+                })
+                .and_then(|_| {
+                    dmx.distribute(&app_handle, effects, |shared, scales| {
+                        if let Ok(mut effects) = shared.effects.lock() {
+                            effects.set_intensities(scales.unwrap_or_default());
+                        }
+                    })
+                });
+            if let Err(err) = result {
+                error!("No se pudo aplicar el sound-to-light: {err}");
+            }
+        }
+    });
+}
+
+// Crea o reemplaza el mapeo `name`
+#[tauri::command]
+pub fn sound_light_set(
+    state: State<'_, SoundLightState>,
+    name: String,
+    mapping: SoundMapping,
+) -> Result<(), String> {
+    mapping.validate()?;
+    let mut mappings = state.lock_mappings()?;
+    mappings.insert(name.clone(), mapping);
+    state.save(&mappings)?;
+    info!("Sound-to-light \"{name}\" configurado");
+    Ok(())
+}
+
+#[tauri::command]
+pub fn sound_light_remove(state: State<'_, SoundLightState>, name: String) -> Result<(), String> {
+    let mut mappings = state.lock_mappings()?;
+    if mappings.remove(&name).is_none() {
+        return Err(format!("El mapeo de sound-to-light \"{name}\" no existe"));
+    }
+    state.save(&mappings)
+}
+
+#[tauri::command]
+pub fn sound_light_list(
+    state: State<'_, SoundLightState>,
+) -> Result<BTreeMap<String, SoundMapping>, String> {
+    Ok(state.lock_mappings()?.clone())
+}
+
+// Nivel actual de cada banda, de 0 a 1
+#[tauri::command]
+pub fn sound_light_bands(audio: State<'_, AudioState>) -> Result<SpectrumBands, String> {
+    Ok(audio.shared().lock_spectrum()?.bands())
+}
@@ -0,0 +1,180 @@
+use crate::audio::AudioBlock;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+// Ventana de la FFT; a 48 kHz son 21 ms y bins de 47 Hz
+const FFT_SIZE: usize = 1024;
+// Se analiza cada media ventana
+const HOP: usize = FFT_SIZE / 2;
+// Límites de las bandas (Hz)
+const LOW_HZ: f32 = 20.0;
+const MID_HZ: f32 = 250.0;
+const HIGH_HZ: f32 = 4000.0;
+const TOP_HZ: f32 = 16000.0;
+// Memoria del pico de cada banda con el que se normaliza: baja a la mitad en unos 5 s
+const PEAK_DECAY: f32 = 0.9985;
+// Por debajo de este pico la banda no se amplifica (silencio o ruido de fondo)
+const PEAK_FLOOR: f32 = 0.005;
+// Caída del nivel entre análisis; la subida es inmediata
+const RELEASE: f32 = 0.85;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SoundBand {
+    /// 20-250 Hz: bombo y bajo
+    Low,
+    /// 250 Hz-4 kHz: voces, guitarras, caja
+    Mid,
+    /// 4-16 kHz: platillos y brillo
+    High,
+}
+
+#[derive(Clone, Copy, Default, Serialize)]
+pub struct SpectrumBands {
+    low: f32,
+    mid: f32,
+    high: f32,
+}
+
+impl SpectrumBands {
+    pub(crate) fn level(&self, band: SoundBand) -> f32 {
+        match band {
+            SoundBand::Low => self.low,
+            SoundBand::Mid => self.mid,
+            SoundBand::High => self.high,
+        }
+    }
+}
+
+// Analiza la entrada en bandas grave/medio/agudo, cada una normalizada de 0 a 1 contra su
+// propio pico reciente. Los buffers se reservan al crearla: corre en el callback de audio.
+pub(crate) struct Spectrum {
+    enabled: bool,
+    // Anillo con las últimas muestras; `write` es la más vieja
+    samples: Vec<f32>,
+    write: usize,
+    // Muestras nuevas desde el último análisis
+    pending: usize,
+    window: Vec<f32>,
+    cos: Vec<f32>,
+    sin: Vec<f32>,
+    re: Vec<f32>,
+    im: Vec<f32>,
+    peaks: [f32; 3],
+    levels: [f32; 3],
+}
+
+impl Default for Spectrum {
+    fn default() -> Self {
+        let window = (0..FFT_SIZE)
+            .map(|idx| 0.5 - 0.5 * (TAU * idx as f32 / FFT_SIZE as f32).cos())
+            .collect();
+        let angle = |idx: usize| TAU * idx as f32 / FFT_SIZE as f32;
+        Self {
+            enabled: false,
+            samples: vec![0.0; FFT_SIZE],
+            write: 0,
+            pending: 0,
+            window,
+            cos: (0..FFT_SIZE / 2).map(|idx| angle(idx).cos()).collect(),
+            sin: (0..FFT_SIZE / 2).map(|idx| -angle(idx).sin()).collect(),
+            re: vec![0.0; FFT_SIZE],
+            im: vec![0.0; FFT_SIZE],
+            peaks: [0.0; 3],
+            levels: [0.0; 3],
+        }
+    }
+}
+
+impl Spectrum {
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        if !enabled {
+            self.levels = [0.0; 3];
+        }
+        self.enabled = enabled;
+    }
+
+    pub(crate) fn bands(&self) -> SpectrumBands {
+        SpectrumBands {
+            low: self.levels[0],
+            mid: self.levels[1],
+            high: self.levels[2],
+        }
+    }
+
+    // Lo llama el callback de salida en cada bloque
+    pub(crate) fn process(&mut self, block: &AudioBlock) {
+        if !self.enabled {
+            return;
+        }
+        for sample in block.input {
+            self.samples[self.write] = *sample;
+            self.write = (self.write + 1) % FFT_SIZE;
+            self.pending += 1;
+            if self.pending == HOP {
+                self.pending = 0;
+                self.analyze(block.sample_rate);
+            }
+        }
+    }
+
+    fn analyze(&mut self, sample_rate: u32) {
+        for (idx, (re, im)) in self.re.iter_mut().zip(self.im.iter_mut()).enumerate() {
+            *re = self.samples[(self.write + idx) % FFT_SIZE] * self.window[idx];
+            *im = 0.0;
+        }
+        self.fft();
+
+        let bin_hz = sample_rate as f32 / FFT_SIZE as f32;
+        let limits = [(LOW_HZ, MID_HZ), (MID_HZ, HIGH_HZ), (HIGH_HZ, TOP_HZ)];
+        for (band, (from, to)) in limits.iter().enumerate() {
+            let first = ((from / bin_hz).ceil() as usize).max(1);
+            let last = ((to / bin_hz) as usize).min(FFT_SIZE / 2);
+            if first > last {
+                continue;
+            }
+            // Energía de la banda: suma de las magnitudes de sus bins
+            let sum: f32 = (first..=last)
+                .map(|bin| (self.re[bin] * self.re[bin] + self.im[bin] * self.im[bin]).sqrt())
+                .sum();
+            let magnitude = sum / FFT_SIZE as f32;
+
+            let peak = &mut self.peaks[band];
+            *peak = magnitude.max(*peak * PEAK_DECAY);
+            let level = (magnitude / peak.max(PEAK_FLOOR)).min(1.0);
+            let current = &mut self.levels[band];
+            *current = level.max(*current * RELEASE);
+        }
+    }
+
+    // Radix-2 iterativa, en el lugar
+    fn fft(&mut self) {
+        let bits = FFT_SIZE.trailing_zeros();
+        for idx in 0..FFT_SIZE {
+            let reversed = idx.reverse_bits() >> (usize::BITS - bits);
+            if reversed > idx {
+                self.re.swap(idx, reversed);
+                self.im.swap(idx, reversed);
+            }
+        }
+
+        let mut size = 2;
+        while size <= FFT_SIZE {
+            let half = size / 2;
+            let stride = FFT_SIZE / size;
+            for start in (0..FFT_SIZE).step_by(size) {
+                for k in 0..half {
+                    let (w_re, w_im) = (self.cos[k * stride], self.sin[k * stride]);
+                    let (a, b) = (start + k, start + k + half);
+                    let t_re = self.re[b] * w_re - self.im[b] * w_im;
+                    let t_im = self.re[b] * w_im + self.im[b] * w_re;
+                    self.re[b] = self.re[a] - t_re;
+                    self.im[b] = self.im[a] - t_im;
+                    self.re[a] += t_re;
+                    self.im[a] += t_im;
+                }
+            }
+            size *= 2;
+        }
+    }
+}
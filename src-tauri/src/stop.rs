@@ -0,0 +1,138 @@
+use crate::dmx::{DmxState, DMX_FRAME_SIZE};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tauri::State;
+
+pub(crate) const STOP_POLICY_FILE: &str = "dmx_stop_policy.json";
+const MAX_HOLD_SECS: u32 = 600;
+const MAX_FADE_MS: u64 = 30_000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopMode {
+    /// Sigue mandando el último frame durante `hold_secs` (0 = deja de transmitir en el acto)
+    #[default]
+    Hold,
+    /// Manda un frame en negro antes de cerrar el puerto
+    Blackout,
+    /// Baja los canales de intensidad a 0 en `fade_ms` antes de cerrar el puerto
+    Fade,
+}
+
+// Qué hace la interfaz cuando se detiene la salida (dmx_stop o al cerrar la app)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StopPolicy {
+    mode: StopMode,
+    #[serde(default)]
+    hold_secs: u32,
+    #[serde(default)]
+    fade_ms: u64,
+}
+
+impl StopPolicy {
+    fn validate(&self) -> Result<(), String> {
+        if self.hold_secs > MAX_HOLD_SECS {
+            return Err(format!(
+                "El hold de parada no puede superar los {MAX_HOLD_SECS} s"
+            ));
+        }
+        if self.fade_ms > MAX_FADE_MS {
+            return Err(format!(
+                "El fade de parada no puede superar los {MAX_FADE_MS} ms"
+            ));
+        }
+        Ok(())
+    }
+
+    // Cuánto sigue transmitiendo el hilo después de la señal de parada
+    pub(crate) fn duration(&self) -> Duration {
+        match self.mode {
+            StopMode::Hold => Duration::from_secs(self.hold_secs as u64),
+            StopMode::Blackout => Duration::ZERO,
+            StopMode::Fade => Duration::from_millis(self.fade_ms),
+        }
+    }
+
+    // Al cerrar la app no se espera: el hold termina en el acto y el fade salta al final
+    pub(crate) fn on_exit(&self) -> StopPolicy {
+        StopPolicy {
+            hold_secs: 0,
+            fade_ms: 0,
+            ..*self
+        }
+    }
+
+    // Sin nada que transmitir el hilo termina sin escribir otro frame
+    pub(crate) fn is_immediate(&self) -> bool {
+        self.mode == StopMode::Hold && self.hold_secs == 0
+    }
+}
+
+// Los últimos frames de un hilo de escritura que recibió la señal de parada
+pub(crate) struct StopRamp {
+    policy: StopPolicy,
+    started: Instant,
+    last: [u8; DMX_FRAME_SIZE],
+    // Canales (0-511) que baja el fade; el resto queda como estaba
+    intensity: Vec<bool>,
+}
+
+impl StopRamp {
+    // `intensity_channels` vacío significa que todos los canales son de intensidad
+    pub(crate) fn new(
+        policy: StopPolicy,
+        last: &[u8; DMX_FRAME_SIZE],
+        intensity_channels: &[u16],
+        now: Instant,
+    ) -> Self {
+        let mut intensity = vec![intensity_channels.is_empty(); DMX_FRAME_SIZE - 1];
+        for channel in intensity_channels {
+            intensity[*channel as usize - 1] = true;
+        }
+        Self {
+            policy,
+            started: now,
+            last: *last,
+            intensity,
+        }
+    }
+
+    // Escribe en `frame` lo que toca mandar ahora; devuelve `true` si es el último frame
+    pub(crate) fn render(&self, frame: &mut [u8; DMX_FRAME_SIZE], now: Instant) -> bool {
+        frame.copy_from_slice(&self.last);
+        let elapsed = now.saturating_duration_since(self.started);
+        let duration = self.policy.duration();
+
+        match self.policy.mode {
+            StopMode::Hold => elapsed >= duration,
+            StopMode::Blackout => {
+                frame[1..].fill(0);
+                true
+            }
+            StopMode::Fade => {
+                let progress = if duration.is_zero() {
+                    1.0
+                } else {
+                    (elapsed.as_secs_f64() / duration.as_secs_f64()).min(1.0)
+                };
+                for (level, intensity) in frame[1..].iter_mut().zip(&self.intensity) {
+                    if *intensity {
+                        *level = (*level as f64 * (1.0 - progress)).round() as u8;
+                    }
+                }
+                progress >= 1.0
+            }
+        }
+    }
+}
+
+#[tauri::command]
+pub fn dmx_set_stop_policy(state: State<'_, DmxState>, policy: StopPolicy) -> Result<(), String> {
+    policy.validate()?;
+    state.set_stop_policy(policy)
+}
+
+#[tauri::command]
+pub fn dmx_get_stop_policy(state: State<'_, DmxState>) -> Result<StopPolicy, String> {
+    Ok(state.stop_policy())
+}
@@ -0,0 +1,38 @@
+use log::error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+// Archivo dentro de la carpeta de configuración de la app
+pub(crate) fn config_file(app_handle: &AppHandle, name: &str) -> Option<PathBuf> {
+    match app_handle.path().app_config_dir() {
+        Ok(dir) => Some(dir.join(name)),
+        Err(err) => {
+            error!("No se pudo resolver la carpeta de configuración: {err}");
+            None
+        }
+    }
+}
+
+// Un archivo que todavía no existe no es un error: se empieza con la configuración vacía
+pub(crate) fn load_json<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let contents = fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&contents) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            error!("No se pudo leer {}: {err}", path.display());
+            None
+        }
+    }
+}
+
+pub(crate) fn save_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("No se pudo crear {}: {e}", dir.display()))?;
+    }
+    let contents = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("No se pudo serializar {}: {e}", path.display()))?;
+    fs::write(path, contents).map_err(|e| format!("No se pudo guardar {}: {e}", path.display()))
+}
@@ -0,0 +1,642 @@
+use crate::dmx::{DmxState, DMX_CHANNELS};
+use crate::fixture::{Attribute, FixtureState};
+use crate::mtc::{MtcRate, Timecode};
+use crate::scenes::{scene_recall, SceneState};
+use crate::storage;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub const TIMELINE_EVENT: &str = "timeline://state";
+const TIMELINES_FILE: &str = "timelines.json";
+// 40 cuadros por segundo, más que suficiente para un fade
+const TICK: Duration = Duration::from_millis(25);
+// Siguiendo timecode, sin cuadros nuevos durante este tiempo la línea de tiempo se detiene
+const TIMECODE_DROPOUT: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "target", rename_all = "snake_case")]
+pub enum TrackTarget {
+    /// Canal DMX (1-512) de un universo
+    Channel { universe: u16, channel: u16 },
+    /// Atributo de un fixture patcheado; el canal se resuelve al cargar la línea de tiempo
+    Fixture { fixture: u32, attribute: Attribute },
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Keyframe {
+    time_ms: u64,
+    value: u8,
+    // Salta a este valor al llegar, sin interpolar desde el anterior
+    #[serde(default)]
+    step: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TimelineTrack {
+    #[serde(flatten)]
+    target: TrackTarget,
+    keyframes: Vec<Keyframe>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SceneTrigger {
+    time_ms: u64,
+    scene: String,
+    #[serde(default)]
+    fade_ms: Option<u64>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Timeline {
+    name: String,
+    duration_ms: u64,
+    #[serde(default)]
+    looped: bool,
+    #[serde(default)]
+    tracks: Vec<TimelineTrack>,
+    #[serde(default)]
+    scenes: Vec<SceneTrigger>,
+    // Cuadro de timecode que corresponde al 0 de la línea de tiempo, para seguir MTC o LTC
+    #[serde(default)]
+    timecode_start: Option<Timecode>,
+}
+
+impl Timeline {
+    fn validate(&mut self) -> Result<(), String> {
+        self.name = self.name.trim().to_string();
+        if self.name.is_empty() {
+            return Err("La línea de tiempo necesita un nombre".to_string());
+        }
+        if self.duration_ms == 0 {
+            return Err("La duración de la línea de tiempo tiene que ser mayor que 0".to_string());
+        }
+        if let Some(timecode) = &self.timecode_start {
+            timecode.validate()?;
+        }
+        for track in &mut self.tracks {
+            if let TrackTarget::Channel { channel, .. } = track.target {
+                if channel == 0 || channel as usize > DMX_CHANNELS {
+                    return Err(format!("Canal DMX inválido: {channel}"));
+                }
+            }
+            if track.keyframes.is_empty() {
+                return Err("Cada pista necesita al menos un keyframe".to_string());
+            }
+            track.keyframes.sort_by_key(|keyframe| keyframe.time_ms);
+        }
+        self.scenes.sort_by_key(|trigger| trigger.time_ms);
+        Ok(())
+    }
+}
+
+impl TimelineTrack {
+    fn value_at(&self, position_ms: u64) -> u8 {
+        let next = self
+            .keyframes
+            .iter()
+            .position(|keyframe| keyframe.time_ms > position_ms);
+        let (from, to) = match next {
+            Some(0) => return self.keyframes[0].value,
+            Some(idx) => (&self.keyframes[idx - 1], &self.keyframes[idx]),
+            None => return self.keyframes.last().map_or(0, |keyframe| keyframe.value),
+        };
+        if to.step {
+            return from.value;
+        }
+        let span = (to.time_ms - from.time_ms) as f64;
+        let t = (position_ms - from.time_ms) as f64 / span;
+        (from.value as f64 + (to.value as f64 - from.value as f64) * t).round() as u8
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaybackState {
+    Stopped,
+    Playing,
+    Paused,
+}
+
+#[derive(Clone, Serialize)]
+pub struct TimelineStatus {
+    name: Option<String>,
+    state: PlaybackState,
+    position_ms: u64,
+    duration_ms: Option<u64>,
+    chase: bool,
+}
+
+struct Player {
+    timeline: Option<Timeline>,
+    // Universo y canal de cada pista, resueltos al cargar
+    channels: Vec<(u16, u16)>,
+    // Lo último escrito por pista: lo que no cambia no se reescribe y se puede tocar a mano
+    written: Vec<Option<u8>>,
+    state: PlaybackState,
+    // Posición en `origin`; mientras se reproduce avanza con el reloj
+    offset_ms: u64,
+    origin: Instant,
+    // Hasta dónde ya se dispararon las escenas
+    triggered_ms: Option<u64>,
+    // Después de un salto: la escena del lugar nuevo entra sin fade
+    located: bool,
+    // Los niveles se escriben en el próximo cuadro aunque esté en pausa
+    dirty: bool,
+    chase: bool,
+    last_timecode: Option<Instant>,
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Self {
+            timeline: None,
+            channels: Vec::new(),
+            written: Vec::new(),
+            state: PlaybackState::Stopped,
+            offset_ms: 0,
+            origin: Instant::now(),
+            triggered_ms: None,
+            located: false,
+            dirty: false,
+            chase: false,
+            last_timecode: None,
+        }
+    }
+}
+
+impl Player {
+    fn position(&self) -> u64 {
+        match self.state {
+            PlaybackState::Playing => self.offset_ms + self.origin.elapsed().as_millis() as u64,
+            PlaybackState::Stopped | PlaybackState::Paused => self.offset_ms,
+        }
+    }
+
+    fn set_position(&mut self, position_ms: u64) {
+        self.offset_ms = position_ms;
+        self.origin = Instant::now();
+    }
+
+    fn locate(&mut self, position_ms: u64) {
+        self.set_position(position_ms);
+        self.triggered_ms = None;
+        self.located = true;
+        self.dirty = true;
+    }
+
+    fn status(&self) -> TimelineStatus {
+        TimelineStatus {
+            name: self.timeline.as_ref().map(|timeline| timeline.name.clone()),
+            state: self.state,
+            position_ms: self.position(),
+            duration_ms: self.timeline.as_ref().map(|timeline| timeline.duration_ms),
+            chase: self.chase,
+        }
+    }
+
+    // Lo que hay que hacer en este cuadro: niveles que cambiaron, escena cruzada y si cambió el
+    // estado de reproducción
+    fn advance(&mut self) -> Option<Frame> {
+        let (duration, looped) = self
+            .timeline
+            .as_ref()
+            .map(|timeline| (timeline.duration_ms, timeline.looped))?;
+        let mut state_changed = false;
+
+        let timecode_lost = match self.last_timecode {
+            Some(last) => last.elapsed() > TIMECODE_DROPOUT,
+            None => false,
+        };
+        if self.chase && self.state == PlaybackState::Playing && timecode_lost {
+            let position = self.position();
+            self.state = PlaybackState::Paused;
+            self.set_position(position);
+            state_changed = true;
+        }
+        if self.state != PlaybackState::Playing && !self.dirty {
+            return state_changed.then(Frame::default);
+        }
+        self.dirty = false;
+
+        let mut position = self.position();
+        let mut wrapped = false;
+        if position >= duration && self.state == PlaybackState::Playing {
+            if looped && !self.chase {
+                position %= duration;
+                self.set_position(position);
+                wrapped = true;
+            } else {
+                position = duration;
+                self.state = PlaybackState::Stopped;
+                self.set_position(duration);
+                state_changed = true;
+            }
+        }
+
+        // Como con las cues por timecode: la última escena cruzada desde el cuadro anterior
+        let until = if wrapped { duration } else { position };
+        let from = self.triggered_ms;
+        let timeline = self.timeline.as_ref()?;
+        let scene = timeline
+            .scenes
+            .iter()
+            .rev()
+            .find(|trigger| {
+                trigger.time_ms <= until && from.iter().all(|from| trigger.time_ms > *from)
+            })
+            .map(|trigger| {
+                let fade_ms = match self.located {
+                    true => Some(0),
+                    false => trigger.fade_ms,
+                };
+                (trigger.scene.clone(), fade_ms)
+            });
+        self.triggered_ms = if wrapped { None } else { Some(position) };
+        self.located = false;
+
+        let mut levels = Vec::new();
+        for ((track, channel), written) in timeline
+            .tracks
+            .iter()
+            .zip(&self.channels)
+            .zip(self.written.iter_mut())
+        {
+            let value = track.value_at(position);
+            if *written != Some(value) {
+                *written = Some(value);
+                levels.push((*channel, value));
+            }
+        }
+        Some(Frame {
+            levels,
+            scene,
+            state_changed,
+        })
+    }
+}
+
+#[derive(Default)]
+struct Frame {
+    levels: Vec<((u16, u16), u8)>,
+    scene: Option<(String, Option<u64>)>,
+    state_changed: bool,
+}
+
+#[derive(Default)]
+pub struct TimelineState {
+    timelines: Mutex<BTreeMap<String, Timeline>>,
+    path: Mutex<Option<PathBuf>>,
+    player: Mutex<Player>,
+}
+
+impl TimelineState {
+    pub fn load(&self, app_handle: &AppHandle) {
+        let Some(path) = storage::config_file(app_handle, TIMELINES_FILE) else {
+            return;
+        };
+
+        if let Some(timelines) = storage::load_json::<Vec<Timeline>>(&path) {
+            if let Ok(mut current) = self.timelines.lock() {
+                *current = timelines
+                    .into_iter()
+                    .map(|timeline| (timeline.name.clone(), timeline))
+                    .collect();
+            }
+        }
+
+        if let Ok(mut current) = self.path.lock() {
+            *current = Some(path);
+        }
+    }
+
+    pub(crate) fn export(&self) -> Result<Vec<Timeline>, String> {
+        Ok(self.lock_timelines()?.values().cloned().collect())
+    }
+
+    pub(crate) fn replace(&self, timelines: Vec<Timeline>) -> Result<(), String> {
+        let mut stored = self.lock_timelines()?;
+        *stored = timelines
+            .into_iter()
+            .map(|timeline| (timeline.name.clone(), timeline))
+            .collect();
+        *self.lock_player()? = Player::default();
+        self.save(&stored)
+    }
+
+    fn lock_timelines(
+        &self,
+    ) -> Result<std::sync::MutexGuard<'_, BTreeMap<String, Timeline>>, String> {
+        self.timelines
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear las líneas de tiempo: {e}"))
+    }
+
+    fn lock_player(&self) -> Result<std::sync::MutexGuard<'_, Player>, String> {
+        self.player
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear la reproducción de la línea de tiempo: {e}"))
+    }
+
+    fn save(&self, timelines: &BTreeMap<String, Timeline>) -> Result<(), String> {
+        let path = self
+            .path
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear las líneas de tiempo: {e}"))?
+            .clone();
+
+        match path {
+            Some(path) => storage::save_json(&path, &timelines.values().collect::<Vec<_>>()),
+            None => Ok(()),
+        }
+    }
+}
+
+fn emit_status(app_handle: &AppHandle, status: TimelineStatus) {
+    if let Err(err) = app_handle.emit(TIMELINE_EVENT, status) {
+        error!("No se pudo emitir el estado de la línea de tiempo: {err}");
+    }
+}
+
+// Los niveles se escriben fuera del lock de la reproducción
+fn apply(app_handle: &AppHandle, frame: Frame) {
+    let dmx = app_handle.state::<DmxState>();
+    for ((universe, channel), value) in frame.levels {
+        let result = dmx.with_universe(app_handle.clone(), universe, |shared| {
+            shared.patch_levels(channel, &[value])
+        });
+        if let Err(err) = result {
+            error!("No se pudo escribir la línea de tiempo en el universo {universe}: {err}");
+        }
+    }
+    if let Some((name, fade_ms)) = frame.scene {
+        let result = scene_recall(
+            app_handle.clone(),
+            app_handle.state::<SceneState>(),
+            dmx,
+            name.clone(),
+            fade_ms,
+        );
+        if let Err(err) = result {
+            warn!("No se pudo recuperar la escena \"{name}\" de la línea de tiempo: {err}");
+        }
+    }
+}
+
+pub fn start_playback(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    thread::spawn(move || loop {
+        thread::sleep(TICK);
+        let state = app_handle.state::<TimelineState>();
+        let (frame, status) = match state.lock_player() {
+            Ok(mut player) => (player.advance(), player.status()),
+            Err(err) => {
+                error!("{err}");
+                continue;
+            }
+        };
+        let Some(frame) = frame else {
+            continue;
+        };
+        if frame.state_changed {
+            emit_status(&app_handle, status);
+        }
+        apply(&app_handle, frame);
+    });
+}
+
+// Llamado con cada cuadro de MTC o LTC: con el seguimiento activo, la posición es la del
+// timecode desde `timecode_start`
+pub(crate) fn follow_timecode(app_handle: &AppHandle, frames: u32, rate: MtcRate, located: bool) {
+    let state = app_handle.state::<TimelineState>();
+    let status = {
+        let Ok(mut player) = state.lock_player() else {
+            return;
+        };
+        if !player.chase {
+            return;
+        }
+        let Some((start, duration)) = player.timeline.as_ref().and_then(|timeline| {
+            timeline
+                .timecode_start
+                .map(|start| (start, timeline.duration_ms))
+        }) else {
+            return;
+        };
+        // Antes del cuadro de inicio queda esperando en 0; pasado el final, detenida al final
+        let elapsed = frames.saturating_sub(start.to_frames(rate));
+        let position_ms = (elapsed as u64 * 1000 / rate.fps() as u64).min(duration);
+        player.last_timecode = Some(Instant::now());
+        if located {
+            player.locate(position_ms);
+        } else {
+            player.set_position(position_ms);
+        }
+        if player.state == PlaybackState::Playing || position_ms == duration {
+            return;
+        }
+        player.state = PlaybackState::Playing;
+        player.status()
+    };
+    emit_status(app_handle, status);
+}
+
+// Con el mismo nombre reemplaza la línea de tiempo guardada
+#[tauri::command]
+pub fn timeline_save(
+    state: State<'_, TimelineState>,
+    timeline: Timeline,
+) -> Result<Timeline, String> {
+    let mut timeline = timeline;
+    timeline.validate()?;
+    let mut stored = state.lock_timelines()?;
+    stored.insert(timeline.name.clone(), timeline.clone());
+    state.save(&stored)?;
+    info!(
+        "Línea de tiempo \"{}\" guardada ({} pistas, {} escenas)",
+        timeline.name,
+        timeline.tracks.len(),
+        timeline.scenes.len()
+    );
+    Ok(timeline)
+}
+
+#[tauri::command]
+pub fn timeline_delete(state: State<'_, TimelineState>, name: String) -> Result<(), String> {
+    let mut stored = state.lock_timelines()?;
+    stored
+        .remove(&name)
+        .ok_or_else(|| format!("La línea de tiempo \"{name}\" no existe"))?;
+    state.save(&stored)
+}
+
+#[tauri::command]
+pub fn timeline_list(state: State<'_, TimelineState>) -> Result<Vec<Timeline>, String> {
+    Ok(state.lock_timelines()?.values().cloned().collect())
+}
+
+// Deja la línea de tiempo lista en 0, detenida; las pistas de fixtures se resuelven contra el
+// patch actual
+#[tauri::command]
+pub fn timeline_load(
+    app_handle: AppHandle,
+    state: State<'_, TimelineState>,
+    fixtures: State<'_, FixtureState>,
+    name: String,
+) -> Result<TimelineStatus, String> {
+    let timeline = state
+        .lock_timelines()?
+        .get(&name)
+        .cloned()
+        .ok_or_else(|| format!("La línea de tiempo \"{name}\" no existe"))?;
+    let channels = timeline
+        .tracks
+        .iter()
+        .map(|track| match track.target {
+            TrackTarget::Channel { universe, channel } => Ok((universe, channel)),
+            TrackTarget::Fixture { fixture, attribute } => {
+                fixtures.attribute_channel(fixture, attribute)
+            }
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let status = {
+        let mut player = state.lock_player()?;
+        let chase = player.chase;
+        *player = Player {
+            written: vec![None; channels.len()],
+            channels,
+            timeline: Some(timeline),
+            chase,
+            ..Player::default()
+        };
+        player.status()
+    };
+    info!("Línea de tiempo \"{name}\" cargada");
+    emit_status(&app_handle, status.clone());
+    Ok(status)
+}
+
+// Detenida arranca desde 0; en pausa sigue desde donde quedó
+#[tauri::command]
+pub fn timeline_play(
+    app_handle: AppHandle,
+    state: State<'_, TimelineState>,
+) -> Result<TimelineStatus, String> {
+    let status = {
+        let mut player = state.lock_player()?;
+        if player.timeline.is_none() {
+            return Err("No hay una línea de tiempo cargada".to_string());
+        }
+        match player.state {
+            PlaybackState::Playing => return Ok(player.status()),
+            PlaybackState::Stopped => {
+                player.set_position(0);
+                player.triggered_ms = None;
+            }
+            PlaybackState::Paused => {
+                let position = player.offset_ms;
+                player.set_position(position);
+            }
+        }
+        player.state = PlaybackState::Playing;
+        player.status()
+    };
+    emit_status(&app_handle, status.clone());
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn timeline_pause(
+    app_handle: AppHandle,
+    state: State<'_, TimelineState>,
+) -> Result<TimelineStatus, String> {
+    let status = {
+        let mut player = state.lock_player()?;
+        if player.state == PlaybackState::Playing {
+            let position = player.position();
+            player.set_position(position);
+            player.state = PlaybackState::Paused;
+        }
+        player.status()
+    };
+    emit_status(&app_handle, status.clone());
+    Ok(status)
+}
+
+// Los niveles quedan como estaban; el próximo play arranca desde 0
+#[tauri::command]
+pub fn timeline_stop(
+    app_handle: AppHandle,
+    state: State<'_, TimelineState>,
+) -> Result<TimelineStatus, String> {
+    let status = {
+        let mut player = state.lock_player()?;
+        player.state = PlaybackState::Stopped;
+        player.set_position(0);
+        player.status()
+    };
+    emit_status(&app_handle, status.clone());
+    Ok(status)
+}
+
+// Salta a `position_ms`: los niveles pasan al valor de ese punto y la escena anterior entra sin
+// fade. Detenida queda en pausa ahí.
+#[tauri::command]
+pub fn timeline_locate(
+    app_handle: AppHandle,
+    state: State<'_, TimelineState>,
+    position_ms: u64,
+) -> Result<TimelineStatus, String> {
+    let status = {
+        let mut player = state.lock_player()?;
+        let duration = player
+            .timeline
+            .as_ref()
+            .map(|timeline| timeline.duration_ms)
+            .ok_or_else(|| "No hay una línea de tiempo cargada".to_string())?;
+        player.locate(position_ms.min(duration));
+        if player.state == PlaybackState::Stopped {
+            player.state = PlaybackState::Paused;
+        }
+        player.status()
+    };
+    emit_status(&app_handle, status.clone());
+    Ok(status)
+}
+
+// Con `enabled` la línea de tiempo cargada sigue al MTC o LTC entrante desde su
+// `timecode_start`; sin timecode durante medio segundo queda en pausa
+#[tauri::command]
+pub fn timeline_chase(
+    state: State<'_, TimelineState>,
+    enabled: bool,
+) -> Result<TimelineStatus, String> {
+    let mut player = state.lock_player()?;
+    if enabled {
+        let start = player
+            .timeline
+            .as_ref()
+            .map(|timeline| timeline.timecode_start);
+        match start {
+            None => return Err("No hay una línea de tiempo cargada".to_string()),
+            Some(None) => {
+                return Err("La línea de tiempo no tiene timecode de inicio".to_string());
+            }
+            Some(Some(_)) => {}
+        }
+    }
+    player.chase = enabled;
+    player.last_timecode = None;
+    Ok(player.status())
+}
+
+#[tauri::command]
+pub fn timeline_status(state: State<'_, TimelineState>) -> Result<TimelineStatus, String> {
+    Ok(state.lock_player()?.status())
+}
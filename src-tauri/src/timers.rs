@@ -0,0 +1,202 @@
+use crate::clock_schedule::{self, ScheduleAction};
+use log::{error, info};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub const TIMER_TICK_EVENT: &str = "timer://tick";
+pub const TIMER_EXPIRED_EVENT: &str = "timer://expired";
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+struct Timer {
+    duration_ms: u64,
+    deadline: Instant,
+    // En pausa, lo que faltaba al pausar
+    paused: Option<u64>,
+    action: Option<ScheduleAction>,
+    // Último segundo avisado, para mandar un tick por segundo
+    last_second: Option<u64>,
+}
+
+impl Timer {
+    fn remaining_ms(&self) -> u64 {
+        self.paused.unwrap_or_else(|| {
+            self.deadline
+                .saturating_duration_since(Instant::now())
+                .as_millis() as u64
+        })
+    }
+
+    fn status(&self, id: &str) -> TimerStatus {
+        TimerStatus {
+            id: id.to_string(),
+            duration_ms: self.duration_ms,
+            remaining_ms: self.remaining_ms(),
+            paused: self.paused.is_some(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct TimerStatus {
+    id: String,
+    duration_ms: u64,
+    remaining_ms: u64,
+    paused: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct TimerExpired {
+    id: String,
+    error: Option<String>,
+}
+
+// Cuentas regresivas con nombre: al llegar a 0 disparan su acción y desaparecen
+#[derive(Default)]
+pub struct TimerState {
+    timers: Mutex<BTreeMap<String, Timer>>,
+}
+
+impl TimerState {
+    fn lock_timers(&self) -> Result<std::sync::MutexGuard<'_, BTreeMap<String, Timer>>, String> {
+        self.timers
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear los temporizadores: {e}"))
+    }
+
+    // Los que cambiaron de segundo y los que vencieron, sacados de la tabla
+    fn poll(&self) -> (Vec<TimerStatus>, Vec<(String, Timer)>) {
+        let Ok(mut timers) = self.lock_timers() else {
+            return (Vec::new(), Vec::new());
+        };
+        let mut ticks = Vec::new();
+        for (id, timer) in timers.iter_mut() {
+            if timer.paused.is_some() {
+                continue;
+            }
+            // Redondeado hacia arriba, como un reloj de cuenta regresiva: 0 es el final
+            let second = timer.remaining_ms().div_ceil(1000);
+            if timer.last_second != Some(second) {
+                timer.last_second = Some(second);
+                ticks.push(timer.status(id));
+            }
+        }
+
+        let expired: Vec<String> = timers
+            .iter()
+            .filter(|(_, timer)| timer.paused.is_none() && timer.remaining_ms() == 0)
+            .map(|(id, _)| id.clone())
+            .collect();
+        let expired = expired
+            .into_iter()
+            .filter_map(|id| timers.remove_entry(&id))
+            .collect();
+        (ticks, expired)
+    }
+}
+
+pub fn start_timers(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    thread::spawn(move || loop {
+        thread::sleep(POLL_INTERVAL);
+        let (ticks, expired) = app_handle.state::<TimerState>().poll();
+        for status in ticks {
+            if let Err(err) = app_handle.emit(TIMER_TICK_EVENT, status) {
+                error!("No se pudo emitir el temporizador: {err}");
+            }
+        }
+
+        // Las acciones corren fuera del lock: pueden tardar (una escena, una línea de tiempo)
+        for (id, timer) in expired {
+            let result = match &timer.action {
+                Some(action) => clock_schedule::run_action(&app_handle, action),
+                None => Ok(()),
+            };
+            match &result {
+                Ok(()) => info!("Temporizador \"{id}\" vencido"),
+                Err(err) => error!("No se pudo disparar el temporizador \"{id}\": {err}"),
+            }
+            let expired = TimerExpired {
+                id,
+                error: result.err(),
+            };
+            if let Err(err) = app_handle.emit(TIMER_EXPIRED_EVENT, expired) {
+                error!("No se pudo emitir el temporizador: {err}");
+            }
+        }
+    });
+}
+
+// Con un `id` que ya corre, lo reinicia. `action` es la misma que en la programación horaria;
+// sin acción solo avisa.
+#[tauri::command]
+pub fn timer_start(
+    state: State<'_, TimerState>,
+    id: String,
+    duration_ms: u64,
+    action: Option<ScheduleAction>,
+) -> Result<TimerStatus, String> {
+    let id = id.trim().to_string();
+    if id.is_empty() {
+        return Err("El temporizador necesita un nombre".to_string());
+    }
+    if duration_ms == 0 {
+        return Err("La duración del temporizador tiene que ser mayor que 0".to_string());
+    }
+    let timer = Timer {
+        duration_ms,
+        deadline: Instant::now() + Duration::from_millis(duration_ms),
+        paused: None,
+        action,
+        last_second: None,
+    };
+    let status = timer.status(&id);
+    state.lock_timers()?.insert(id, timer);
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn timer_pause(state: State<'_, TimerState>, id: String) -> Result<TimerStatus, String> {
+    let mut timers = state.lock_timers()?;
+    let timer = timers
+        .get_mut(&id)
+        .ok_or_else(|| format!("El temporizador \"{id}\" no existe"))?;
+    if timer.paused.is_none() {
+        timer.paused = Some(timer.remaining_ms());
+    }
+    Ok(timer.status(&id))
+}
+
+#[tauri::command]
+pub fn timer_resume(state: State<'_, TimerState>, id: String) -> Result<TimerStatus, String> {
+    let mut timers = state.lock_timers()?;
+    let timer = timers
+        .get_mut(&id)
+        .ok_or_else(|| format!("El temporizador \"{id}\" no existe"))?;
+    if let Some(remaining) = timer.paused.take() {
+        timer.deadline = Instant::now() + Duration::from_millis(remaining);
+    }
+    Ok(timer.status(&id))
+}
+
+// Lo saca sin disparar la acción
+#[tauri::command]
+pub fn timer_cancel(state: State<'_, TimerState>, id: String) -> Result<(), String> {
+    state
+        .lock_timers()?
+        .remove(&id)
+        .map(|_| ())
+        .ok_or_else(|| format!("El temporizador \"{id}\" no existe"))
+}
+
+#[tauri::command]
+pub fn timer_list(state: State<'_, TimerState>) -> Result<Vec<TimerStatus>, String> {
+    Ok(state
+        .lock_timers()?
+        .iter()
+        .map(|(id, timer)| timer.status(id))
+        .collect())
+}
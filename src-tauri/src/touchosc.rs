@@ -0,0 +1,278 @@
+use crate::fixture::FixtureState;
+use crate::scenes::SceneState;
+use log::{info, warn};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tauri::State;
+use zip::write::SimpleFileOptions;
+
+// Un .touchosc es un zip con el layout en este XML; TouchOSC Mk2 también lo importa
+const LAYOUT_FILE: &str = "index.xml";
+const LAYOUT_VERSION: u32 = 17;
+// Tamaño propio (mode 3) de un iPad en vertical
+const PAGE_WIDTH: u32 = 768;
+const PAGE_HEIGHT: u32 = 1024;
+const MARGIN: u32 = 24;
+const GAP: u32 = 12;
+const FADER_WIDTH: u32 = 60;
+const FADER_HEIGHT: u32 = 300;
+const FADERS_PER_ROW: u32 = 10;
+const FADER_ROWS: u32 = 2;
+const LABEL_HEIGHT: u32 = 30;
+const PAD_SIZE: u32 = 78;
+const BUTTON_WIDTH: u32 = 168;
+const BUTTON_HEIGHT: u32 = 90;
+const BUTTONS_PER_ROW: u32 = 4;
+const BUTTON_ROWS: u32 = 6;
+// Separador y comodines de las direcciones OSC
+const RESERVED: &[char] = &['#', '*', ',', '/', '?', '[', ']', '{', '}'];
+
+// (color en `/color/<rrggbb>`, color del pad en TouchOSC)
+const COLOR_PADS: [(&str, &str); 8] = [
+    ("ff0000", "red"),
+    ("ff8000", "orange"),
+    ("ffff00", "yellow"),
+    ("00ff00", "green"),
+    ("00ffff", "blue"),
+    ("0000ff", "blue"),
+    ("ff00ff", "purple"),
+    ("ffffff", "gray"),
+];
+
+enum ControlKind {
+    Fader,
+    Push,
+    Label,
+}
+
+struct Control {
+    kind: ControlKind,
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+    color: &'static str,
+    // Dirección OSC para faders y botones; texto para etiquetas
+    value: String,
+}
+
+impl Control {
+    fn xml(&self, idx: usize) -> String {
+        let kind = match self.kind {
+            ControlKind::Fader => {
+                r#"type="faderv" response="absolute" inverted="false" centered="false""#
+            }
+            ControlKind::Push => r#"type="push" local_off="false" sp="true" sr="true""#,
+            ControlKind::Label => r#"type="labelh" size="14" background="true" outline="false""#,
+        };
+        let value = match self.kind {
+            ControlKind::Label => format!(r#"text="{}""#, base64(&self.value)),
+            _ => format!(r#"osc_cs="{}""#, base64(&self.value)),
+        };
+        format!(
+            r#"<control name="{}" x="{}" y="{}" w="{}" h="{}" color="{}" scalef="0.0" scalet="1.0" {kind} {value}></control>"#,
+            base64(&format!("control{idx}")),
+            self.x,
+            self.y,
+            self.w,
+            self.h,
+            self.color
+        )
+    }
+}
+
+// En el formato los nombres, textos y direcciones van en base64
+fn base64(text: &str) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in text.as_bytes().chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * idx) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn page_xml(name: &str, controls: &[Control]) -> String {
+    let controls = controls
+        .iter()
+        .enumerate()
+        .map(|(idx, control)| control.xml(idx))
+        .collect::<String>();
+    format!(
+        r#"<tabpage name="{}" scalef="0.0" scalet="1.0">{controls}</tabpage>"#,
+        base64(name)
+    )
+}
+
+fn label(x: u32, y: u32, w: u32, text: &str) -> Control {
+    Control {
+        kind: ControlKind::Label,
+        x,
+        y,
+        w,
+        h: LABEL_HEIGHT,
+        color: "gray",
+        value: text.to_string(),
+    }
+}
+
+// Hasta 20 faders de intensidad por página, con los pads de color abajo
+fn fixture_pages(fixtures: &[(u32, String, bool)]) -> Vec<String> {
+    let per_page = (FADERS_PER_ROW * FADER_ROWS) as usize;
+    let row_height = LABEL_HEIGHT + FADER_HEIGHT + GAP * 2;
+    let pads_y = MARGIN + row_height * FADER_ROWS + GAP;
+    let with_color = fixtures.iter().any(|(_, _, has_color)| *has_color);
+
+    fixtures
+        .chunks(per_page)
+        .enumerate()
+        .map(|(page, chunk)| {
+            let mut controls = Vec::new();
+            for (idx, (id, name, _)) in chunk.iter().enumerate() {
+                let x = MARGIN + (idx as u32 % FADERS_PER_ROW) * (FADER_WIDTH + GAP);
+                let y = MARGIN + (idx as u32 / FADERS_PER_ROW) * row_height;
+                controls.push(label(x, y, FADER_WIDTH, name));
+                controls.push(Control {
+                    kind: ControlKind::Fader,
+                    x,
+                    y: y + LABEL_HEIGHT + GAP,
+                    w: FADER_WIDTH,
+                    h: FADER_HEIGHT,
+                    color: "yellow",
+                    value: format!("/fixture/{id}/intensity"),
+                });
+            }
+            if with_color {
+                for (idx, (hex, color)) in COLOR_PADS.iter().enumerate() {
+                    controls.push(Control {
+                        kind: ControlKind::Push,
+                        x: MARGIN + idx as u32 * (PAD_SIZE + GAP),
+                        y: pads_y,
+                        w: PAD_SIZE,
+                        h: PAD_SIZE,
+                        color,
+                        value: format!("/color/{hex}"),
+                    });
+                }
+            }
+            page_xml(&format!("Fixtures {}", page + 1), &controls)
+        })
+        .collect()
+}
+
+// Una fila con GO, BACK y RELEASE y debajo un botón por escena
+fn scene_pages(scenes: &[String]) -> Vec<String> {
+    let per_page = (BUTTONS_PER_ROW * BUTTON_ROWS) as usize;
+    let row_height = LABEL_HEIGHT + BUTTON_HEIGHT + GAP;
+    let cue_buttons = [
+        ("GO", "/cue/go", "green"),
+        ("BACK", "/cue/back", "orange"),
+        ("RELEASE", "/cue/release", "red"),
+    ];
+
+    let pages = scenes.chunks(per_page).collect::<Vec<_>>();
+    // Sin escenas queda igual una página con los botones de cues
+    let pages = if pages.is_empty() {
+        vec![&[][..]]
+    } else {
+        pages
+    };
+    pages
+        .iter()
+        .enumerate()
+        .map(|(page, chunk)| {
+            let mut controls = Vec::new();
+            for (idx, (text, address, color)) in cue_buttons.iter().enumerate() {
+                let x = MARGIN + idx as u32 * (BUTTON_WIDTH + GAP);
+                controls.push(label(x, MARGIN, BUTTON_WIDTH, text));
+                controls.push(Control {
+                    kind: ControlKind::Push,
+                    x,
+                    y: MARGIN + LABEL_HEIGHT,
+                    w: BUTTON_WIDTH,
+                    h: BUTTON_HEIGHT,
+                    color,
+                    value: address.to_string(),
+                });
+            }
+            for (idx, name) in chunk.iter().enumerate() {
+                let x = MARGIN + (idx as u32 % BUTTONS_PER_ROW) * (BUTTON_WIDTH + GAP);
+                let y = MARGIN + (idx as u32 / BUTTONS_PER_ROW + 1) * row_height + GAP;
+                controls.push(label(x, y, BUTTON_WIDTH, name));
+                controls.push(Control {
+                    kind: ControlKind::Push,
+                    x,
+                    y: y + LABEL_HEIGHT,
+                    w: BUTTON_WIDTH,
+                    h: BUTTON_HEIGHT,
+                    color: "blue",
+                    value: format!("/scene/recall/{name}"),
+                });
+            }
+            page_xml(&format!("Escenas {}", page + 1), &controls)
+        })
+        .collect()
+}
+
+fn write_layout(path: &Path, xml: &str) -> Result<(), String> {
+    let file =
+        File::create(path).map_err(|e| format!("No se pudo crear {}: {e}", path.display()))?;
+    let error = |e: &dyn std::fmt::Display| {
+        format!("No se pudo escribir el layout {}: {e}", path.display())
+    };
+    let mut archive = zip::ZipWriter::new(file);
+    archive
+        .start_file(LAYOUT_FILE, SimpleFileOptions::default())
+        .map_err(|e| error(&e))?;
+    archive.write_all(xml.as_bytes()).map_err(|e| error(&e))?;
+    archive.finish().map_err(|e| error(&e))?;
+    Ok(())
+}
+
+// Layout de TouchOSC con el patch actual, para el servidor OSC del backend
+#[tauri::command]
+pub fn touchosc_export(
+    fixtures: State<'_, FixtureState>,
+    scenes: State<'_, SceneState>,
+    path: String,
+) -> Result<(), String> {
+    let path = PathBuf::from(path);
+    let fixtures = fixtures.overview()?;
+    // Una escena con caracteres que OSC no acepta no puede ir en la dirección del botón
+    let (scenes, skipped): (Vec<_>, Vec<_>) = scenes
+        .names()?
+        .into_iter()
+        .partition(|name| !name.contains(RESERVED));
+    for name in &skipped {
+        warn!("La escena \"{name}\" no entra en el layout: su nombre no es válido en OSC");
+    }
+
+    let pages = fixture_pages(&fixtures)
+        .into_iter()
+        .chain(scene_pages(&scenes))
+        .collect::<String>();
+    let xml = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?><layout version="{LAYOUT_VERSION}" mode="3" w="{PAGE_WIDTH}" h="{PAGE_HEIGHT}" orientation="vertical">{pages}</layout>"#
+    );
+    write_layout(&path, &xml)?;
+
+    info!(
+        "Layout de TouchOSC con {} fixtures y {} escenas guardado en {}",
+        fixtures.len(),
+        scenes.len(),
+        path.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,306 @@
+use crate::audio::{AudioBlock, AudioState, BeatMark};
+use crate::chase::{set_running_bpm, sync_to_beat};
+use crate::dmx::DmxState;
+use crate::link::LinkClock;
+use log::{error, info};
+use serde::Serialize;
+use std::f32::consts::TAU;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub const TRANSPORT_BEAT_EVENT: &str = "transport://beat";
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+const MIN_BPM: f64 = 20.0;
+const MAX_BPM: f64 = 300.0;
+const DEFAULT_BPM: f64 = 120.0;
+const CLICK_MS: f32 = 30.0;
+// El primer tiempo del compás suena más agudo
+const ACCENT_HZ: f32 = 1500.0;
+const BEAT_HZ: f32 = 1000.0;
+const DEFAULT_CLICK_VOLUME: f32 = 0.5;
+
+#[derive(Clone, Serialize)]
+pub struct TransportStatus {
+    running: bool,
+    bpm: f64,
+    numerator: u8,
+    denominator: u8,
+    // Desde 1, como en un DAW
+    bar: u64,
+    beat: u8,
+    metronome: bool,
+    click_volume: f32,
+    // Sincronizado con una sesión de Ableton Link
+    link: bool,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Click {
+    // Muestras que le quedan; 0 es silencio
+    remaining: usize,
+    length: usize,
+    freq: f32,
+    phase: f32,
+}
+
+// Tempo, compás y posición del show, que avanzan muestra a muestra en el callback de audio
+pub(crate) struct TransportClock {
+    running: bool,
+    bpm: f64,
+    numerator: u8,
+    denominator: u8,
+    // Tiempos (en la figura del denominador) desde el arranque
+    position: f64,
+    metronome: bool,
+    click_volume: f32,
+    click: Click,
+    link: Option<LinkClock>,
+}
+
+impl Default for TransportClock {
+    fn default() -> Self {
+        Self {
+            running: false,
+            bpm: DEFAULT_BPM,
+            numerator: 4,
+            denominator: 4,
+            position: 0.0,
+            metronome: false,
+            click_volume: DEFAULT_CLICK_VOLUME,
+            click: Click::default(),
+            link: None,
+        }
+    }
+}
+
+impl TransportClock {
+    pub(crate) fn status(&self) -> TransportStatus {
+        let beats = self.beat_count();
+        TransportStatus {
+            running: self.running,
+            bpm: self.bpm,
+            numerator: self.numerator,
+            denominator: self.denominator,
+            bar: beats / self.numerator as u64 + 1,
+            beat: self.beat_in_bar(beats),
+            metronome: self.metronome,
+            click_volume: self.click_volume,
+            link: self.link.is_some(),
+        }
+    }
+
+    pub(crate) fn running(&self) -> bool {
+        self.running
+    }
+
+    pub(crate) fn bpm(&self) -> f64 {
+        self.bpm
+    }
+
+    pub(crate) fn link(&self) -> Option<&LinkClock> {
+        self.link.as_ref()
+    }
+
+    // Devuelve la sesión anterior para cerrarla fuera del lock
+    pub(crate) fn set_link(&mut self, link: Option<LinkClock>) -> Option<LinkClock> {
+        std::mem::replace(&mut self.link, link)
+    }
+
+    fn beat_count(&self) -> u64 {
+        self.position.floor() as u64
+    }
+
+    // Desde 1
+    fn beat_in_bar(&self, beats: u64) -> u8 {
+        (beats % self.numerator as u64) as u8 + 1
+    }
+
+    fn set_bpm(&mut self, bpm: f64) -> Result<(), String> {
+        if !(MIN_BPM..=MAX_BPM).contains(&bpm) {
+            return Err(format!(
+                "El tempo debe estar entre {MIN_BPM} y {MAX_BPM} BPM"
+            ));
+        }
+        self.bpm = bpm;
+        if let Some(link) = &self.link {
+            link.set_tempo(bpm);
+        }
+        Ok(())
+    }
+
+    fn start_click(&mut self, beat: u64, sample_rate: u32) {
+        let accent = self.beat_in_bar(beat) == 1;
+        let length = (CLICK_MS / 1000.0 * sample_rate as f32) as usize;
+        self.click = Click {
+            remaining: length,
+            length,
+            freq: if accent { ACCENT_HZ } else { BEAT_HZ },
+            phase: 0.0,
+        };
+    }
+
+    // Lo llama el callback de salida en cada bloque, antes que el looper. El click va a su
+    // propio bus, que según el ruteo sale por otros canales o se suma a la mezcla.
+    pub(crate) fn process(&mut self, block: &mut AudioBlock) {
+        if !self.running {
+            block.bpm = Some(self.bpm);
+            return;
+        }
+        let rate = block.sample_rate as f32;
+        let step = match self.link.as_mut() {
+            // Con Link, tempo y posición salen de la sesión en cada bloque
+            Some(link) => {
+                let quantum = self.numerator as f64;
+                let (bpm, position, step) =
+                    link.block(block.main.len(), block.sample_rate, quantum);
+                self.bpm = bpm;
+                self.position = position;
+                step
+            }
+            None => self.bpm / 60.0 / block.sample_rate as f64,
+        };
+        block.bpm = Some(self.bpm);
+
+        for (offset, out) in block.click.iter_mut().enumerate() {
+            // Un tiempo nuevo arranca en la muestra donde la posición cruza el entero. Al
+            // entrar a una sesión de Link la posición es negativa hasta el próximo compás.
+            if self.position >= 0.0 && self.position.fract() < step {
+                let beat = self.beat_count();
+                // Un bloque dura mucho menos que un tiempo: se marca el primero
+                block.beat.get_or_insert(BeatMark {
+                    offset,
+                    bar: self.beat_in_bar(beat) == 1,
+                });
+                if self.metronome {
+                    self.start_click(beat, block.sample_rate);
+                }
+            }
+            if self.click.remaining > 0 {
+                let click = &mut self.click;
+                let envelope = click.remaining as f32 / click.length as f32;
+                let sample = (click.phase * TAU).sin() * envelope * envelope * self.click_volume;
+                click.phase = (click.phase + click.freq / rate).fract();
+                click.remaining -= 1;
+                out[0] += sample;
+                out[1] += sample;
+            }
+            self.position += step;
+        }
+    }
+}
+
+// Avisa cada tiempo nuevo del transporte para que el front parpadee y pasa los cambios de
+// tempo a los chases y efectos en marcha
+pub fn start_beat_events(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    thread::spawn(move || {
+        let mut last_beat = None;
+        let mut last_bpm = None;
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let audio = app_handle.state::<AudioState>();
+            let (beat, status) = match audio.shared().lock_transport() {
+                Ok(clock) => (clock.running.then(|| clock.beat_count()), clock.status()),
+                Err(err) => {
+                    error!("{err}");
+                    continue;
+                }
+            };
+
+            if last_bpm != Some(status.bpm) {
+                if last_bpm.is_some() {
+                    set_running_bpm(&app_handle.state::<DmxState>(), status.bpm);
+                }
+                last_bpm = Some(status.bpm);
+            }
+            if beat.is_some() && beat != last_beat {
+                // Con Link los chases quedan en fase con los tiempos de la sesión
+                if status.link {
+                    sync_to_beat(&app_handle.state::<DmxState>());
+                }
+                if let Err(err) = app_handle.emit(TRANSPORT_BEAT_EVENT, status) {
+                    error!("No se pudo emitir el tiempo del transporte: {err}");
+                }
+            }
+            last_beat = beat;
+        }
+    });
+}
+
+#[tauri::command]
+pub fn transport_set_tempo(
+    audio: State<'_, AudioState>,
+    bpm: f64,
+) -> Result<TransportStatus, String> {
+    let mut clock = audio.shared().lock_transport()?;
+    clock.set_bpm(bpm)?;
+    Ok(clock.status())
+}
+
+#[tauri::command]
+pub fn transport_set_signature(
+    audio: State<'_, AudioState>,
+    numerator: u8,
+    denominator: u8,
+) -> Result<TransportStatus, String> {
+    if !(1..=16).contains(&numerator) {
+        return Err(format!("Compás inválido: {numerator} tiempos"));
+    }
+    if ![2, 4, 8, 16].contains(&denominator) {
+        return Err(format!("Compás inválido: /{denominator}"));
+    }
+    let mut clock = audio.shared().lock_transport()?;
+    clock.numerator = numerator;
+    clock.denominator = denominator;
+    Ok(clock.status())
+}
+
+// Arranca desde el compás 1
+#[tauri::command]
+pub fn transport_start(audio: State<'_, AudioState>) -> Result<TransportStatus, String> {
+    let mut clock = audio.shared().lock_transport()?;
+    clock.position = 0.0;
+    clock.running = true;
+    if let Some(link) = &clock.link {
+        link.restart(clock.numerator as f64);
+    }
+    info!("Transporte en marcha a {} BPM", clock.bpm);
+    Ok(clock.status())
+}
+
+#[tauri::command]
+pub fn transport_stop(audio: State<'_, AudioState>) -> Result<TransportStatus, String> {
+    let status = {
+        let mut clock = audio.shared().lock_transport()?;
+        clock.running = false;
+        clock.position = 0.0;
+        clock.click = Click::default();
+        clock.status()
+    };
+    // Sin tiempos que esperar, lo cuantizado se hace ya
+    audio.shared().lock_looper()?.run_pending();
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn transport_status(audio: State<'_, AudioState>) -> Result<TransportStatus, String> {
+    Ok(audio.shared().lock_transport()?.status())
+}
+
+#[tauri::command]
+pub fn metronome_set(
+    audio: State<'_, AudioState>,
+    enabled: bool,
+    volume: Option<f32>,
+) -> Result<TransportStatus, String> {
+    if volume.is_some_and(|volume| !(0.0..=1.0).contains(&volume)) {
+        return Err("El volumen del metrónomo va de 0 a 1".to_string());
+    }
+    let mut clock = audio.shared().lock_transport()?;
+    clock.metronome = enabled;
+    if let Some(volume) = volume {
+        clock.click_volume = volume;
+    }
+    Ok(clock.status())
+}
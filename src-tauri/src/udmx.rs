@@ -0,0 +1,117 @@
+use rusb::{Device, DeviceHandle, Direction, GlobalContext, Recipient, RequestType};
+use std::time::Duration;
+
+// uDMX (Anyma) usa el par VID/PID compartido de V-USB, por eso se filtra también por producto
+const UDMX_VENDOR_ID: u16 = 0x16C0;
+const UDMX_PRODUCT_ID: u16 = 0x05DC;
+const UDMX_PRODUCT_NAME: &str = "uDMX";
+const CMD_SET_CHANNEL_RANGE: u8 = 0x02;
+const USB_TIMEOUT: Duration = Duration::from_millis(100);
+
+pub(crate) const PORT_PREFIX: &str = "udmx:";
+
+pub(crate) struct UdmxDeviceInfo {
+    pub(crate) path: String,
+    pub(crate) manufacturer: Option<String>,
+    pub(crate) product: Option<String>,
+    pub(crate) serial_number: Option<String>,
+}
+
+pub(crate) fn is_udmx_path(path: &str) -> bool {
+    path.starts_with(PORT_PREFIX)
+}
+
+pub(crate) fn list_devices() -> Result<Vec<UdmxDeviceInfo>, String> {
+    let devices =
+        rusb::devices().map_err(|e| format!("No se pudieron listar los dispositivos USB: {e}"))?;
+
+    let mut found = Vec::new();
+    for device in devices.iter() {
+        let Ok(descriptor) = device.device_descriptor() else {
+            continue;
+        };
+        if descriptor.vendor_id() != UDMX_VENDOR_ID || descriptor.product_id() != UDMX_PRODUCT_ID {
+            continue;
+        }
+
+        // Sin permisos de acceso no se pueden leer los strings; se reporta igual
+        let (manufacturer, product, serial_number) = match device.open() {
+            Ok(handle) => (
+                handle.read_manufacturer_string_ascii(&descriptor).ok(),
+                handle.read_product_string_ascii(&descriptor).ok(),
+                handle.read_serial_number_string_ascii(&descriptor).ok(),
+            ),
+            Err(_) => (None, None, None),
+        };
+
+        if product.as_deref().is_some_and(|p| p != UDMX_PRODUCT_NAME) {
+            continue;
+        }
+
+        found.push(UdmxDeviceInfo {
+            path: device_path(&device),
+            manufacturer,
+            product,
+            serial_number,
+        });
+    }
+
+    Ok(found)
+}
+
+fn device_path(device: &Device<GlobalContext>) -> String {
+    format!(
+        "{PORT_PREFIX}{:03}:{:03}",
+        device.bus_number(),
+        device.address()
+    )
+}
+
+pub(crate) struct UdmxOutput {
+    path: String,
+    handle: DeviceHandle<GlobalContext>,
+}
+
+impl UdmxOutput {
+    pub(crate) fn open(path: &str) -> Result<Self, String> {
+        let devices = rusb::devices()
+            .map_err(|e| format!("No se pudieron listar los dispositivos USB: {e}"))?;
+
+        let device = devices
+            .iter()
+            .find(|device| device_path(device) == path)
+            .ok_or_else(|| format!("No se encontró el dispositivo uDMX {path}"))?;
+
+        let handle = device
+            .open()
+            .map_err(|e| format!("No se pudo abrir el dispositivo uDMX {path}: {e}"))?;
+
+        Ok(Self {
+            path: path.to_string(),
+            handle,
+        })
+    }
+
+    pub(crate) fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub(crate) fn write(&self, channels: &[u8]) -> Result<(), String> {
+        let request_type =
+            rusb::request_type(Direction::Out, RequestType::Vendor, Recipient::Device);
+
+        // wValue = cantidad de canales, wIndex = primer canal (base 0)
+        self.handle
+            .write_control(
+                request_type,
+                CMD_SET_CHANNEL_RANGE,
+                channels.len() as u16,
+                0,
+                channels,
+                USB_TIMEOUT,
+            )
+            .map_err(|e| format!("Error al escribir en el uDMX {}: {e}", self.path))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+use crate::remote;
+use log::{debug, error, info, warn};
+use serde::Serialize;
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, State};
+use tungstenite::{Error, Message};
+
+pub const DEFAULT_WS_PORT: u16 = 9000;
+// Controles conectados a la vez; uno más se rechaza
+const MAX_CLIENTS: usize = 16;
+// Cada cuánto revisan la señal de parada el listener y las conexiones
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+// El handshake lleva varias lecturas; con el intervalo de sondeo una red lenta no llegaría
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Serialize)]
+pub struct WsStatus {
+    listening: bool,
+    port: Option<u16>,
+    clients: usize,
+}
+
+struct WsServer {
+    port: u16,
+    stopped: Arc<AtomicBool>,
+    clients: Arc<AtomicUsize>,
+    handle: thread::JoinHandle<()>,
+}
+
+#[derive(Default)]
+pub struct WsState {
+    server: Mutex<Option<WsServer>>,
+}
+
+impl WsState {
+    fn lock_server(&self) -> Result<std::sync::MutexGuard<'_, Option<WsServer>>, String> {
+        self.server
+            .lock()
+            .map_err(|e| format!("No se pudo bloquear el servidor WebSocket: {e}"))
+    }
+
+    pub(crate) fn port(&self) -> Option<u16> {
+        self.lock_server().ok()?.as_ref().map(|server| server.port)
+    }
+}
+
+fn timed_out(err: &std::io::Error) -> bool {
+    matches!(
+        err.kind(),
+        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+    )
+}
+
+// Un mensaje de texto por pedido, con la misma forma que el resto de los controles remotos
+fn run_client(app_handle: AppHandle, stream: TcpStream, from: SocketAddr, stopped: &AtomicBool) {
+    if let Err(err) = stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)) {
+        error!("No se pudo configurar la conexión WebSocket de {from}: {err}");
+        return;
+    }
+    let mut socket = match tungstenite::accept(stream) {
+        Ok(socket) => socket,
+        Err(err) => {
+            warn!("Conexión WebSocket rechazada de {from}: {err}");
+            return;
+        }
+    };
+    if let Err(err) = socket.get_ref().set_read_timeout(Some(POLL_INTERVAL)) {
+        error!("No se pudo configurar la conexión WebSocket de {from}: {err}");
+        return;
+    }
+    info!("Control remoto conectado por WebSocket: {from}");
+
+    while !stopped.load(Ordering::Relaxed) {
+        let text = match socket.read() {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) => break,
+            // Los ping los contesta la biblioteca
+            Ok(_) => continue,
+            Err(Error::Io(err)) if timed_out(&err) => continue,
+            Err(Error::ConnectionClosed | Error::AlreadyClosed) => break,
+            Err(err) => {
+                warn!("Error en la conexión WebSocket de {from}: {err}");
+                break;
+            }
+        };
+        debug!("WebSocket de {from}: {text}");
+        let response = remote::handle_text(&app_handle, &text);
+        if let Err(err) = socket.send(Message::Text(response)) {
+            warn!("No se pudo responder por WebSocket a {from}: {err}");
+            break;
+        }
+    }
+
+    let _ = socket.close(None);
+    info!("Control remoto desconectado: {from}");
+}
+
+fn run_server(
+    app_handle: AppHandle,
+    listener: TcpListener,
+    stopped: Arc<AtomicBool>,
+    clients: Arc<AtomicUsize>,
+) {
+    let mut connections = Vec::new();
+
+    while !stopped.load(Ordering::Relaxed) {
+        let (stream, from) = match listener.accept() {
+            Ok(accepted) => accepted,
+            Err(err) if timed_out(&err) => {
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+            Err(err) => {
+                error!("Error al aceptar una conexión WebSocket: {err}");
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+        };
+        connections.retain(|connection: &thread::JoinHandle<()>| !connection.is_finished());
+        if connections.len() >= MAX_CLIENTS {
+            warn!("Conexión WebSocket de {from} rechazada: ya hay {MAX_CLIENTS} controles");
+            continue;
+        }
+        // El listener es no bloqueante; la conexión no
+        if let Err(err) = stream.set_nonblocking(false) {
+            error!("No se pudo configurar la conexión WebSocket de {from}: {err}");
+            continue;
+        }
+        let app_handle = app_handle.clone();
+        let stopped = stopped.clone();
+        let clients = clients.clone();
+        connections.push(thread::spawn(move || {
+            clients.fetch_add(1, Ordering::Relaxed);
+            run_client(app_handle, stream, from, &stopped);
+            clients.fetch_sub(1, Ordering::Relaxed);
+        }));
+    }
+
+    for connection in connections {
+        if connection.join().is_err() {
+            error!("Un hilo de conexión WebSocket terminó con pánico");
+        }
+    }
+    info!("Servidor WebSocket detenido");
+}
+
+fn stop_server(server: WsServer) {
+    server.stopped.store(true, Ordering::Relaxed);
+    if server.handle.join().is_err() {
+        error!("El hilo del servidor WebSocket terminó con pánico");
+    }
+}
+
+// Los teléfonos y las laptops de la red se conectan a `ws://<ip>:<puerto>` y mandan los
+// pedidos en JSON
+#[tauri::command]
+pub fn ws_listen_start(
+    app_handle: AppHandle,
+    state: State<'_, WsState>,
+    bind_ip: Option<String>,
+    port: Option<u16>,
+) -> Result<WsStatus, String> {
+    let bind_ip = match bind_ip {
+        Some(ip) => ip
+            .parse::<Ipv4Addr>()
+            .map_err(|e| format!("IP de escucha WebSocket inválida ({ip}): {e}"))?,
+        None => Ipv4Addr::UNSPECIFIED,
+    };
+    let port = port.unwrap_or(DEFAULT_WS_PORT);
+
+    let mut server = state.lock_server()?;
+    // Se reinicia con la configuración nueva
+    if let Some(previous) = server.take() {
+        stop_server(previous);
+    }
+
+    let listener = TcpListener::bind((bind_ip, port))
+        .map_err(|e| format!("No se pudo escuchar WebSocket en {bind_ip}:{port}: {e}"))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| format!("No se pudo configurar el socket WebSocket: {e}"))?;
+
+    info!("Escuchando WebSocket en {bind_ip}:{port}");
+    let stopped = Arc::new(AtomicBool::new(false));
+    let clients = Arc::new(AtomicUsize::new(0));
+    let handle = {
+        let stopped = stopped.clone();
+        let clients = clients.clone();
+        thread::spawn(move || run_server(app_handle, listener, stopped, clients))
+    };
+    *server = Some(WsServer {
+        port,
+        stopped,
+        clients,
+        handle,
+    });
+    Ok(WsStatus {
+        listening: true,
+        port: Some(port),
+        clients: 0,
+    })
+}
+
+#[tauri::command]
+pub fn ws_listen_stop(state: State<'_, WsState>) -> Result<(), String> {
+    let server = state.lock_server()?.take();
+    if let Some(server) = server {
+        stop_server(server);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn ws_status(state: State<'_, WsState>) -> Result<WsStatus, String> {
+    let server = state.lock_server()?;
+    Ok(WsStatus {
+        listening: server.is_some(),
+        port: server.as_ref().map(|server| server.port),
+        clients: server
+            .as_ref()
+            .map_or(0, |server| server.clients.load(Ordering::Relaxed)),
+    })
+}
@@ -1,7 +1,7 @@
 import React, { useCallback, useEffect, useMemo, useState } from 'react';
 import { RefreshCw, Send, Zap, Usb } from 'lucide-react';
 import type { DmxPortInfo } from '../types';
-import { listDmxPorts, sendDmxLevels } from '../utils/dmx';
+import { assignDmxPort, DEFAULT_DMX_UNIVERSE, listDmxPorts, sendDmxLevels } from '../utils/dmx';
 
 const createEmptyLevels = () => new Array(512).fill(0);
 
@@ -54,7 +54,8 @@ const DmxPanel: React.FC = () => {
 
     setIsLoading(true);
     try {
-      await sendDmxLevels(selectedPort, dmxLevels);
+      await assignDmxPort(DEFAULT_DMX_UNIVERSE, selectedPort);
+      await sendDmxLevels(DEFAULT_DMX_UNIVERSE, dmxLevels);
       setStatus('Reenviando frames DMX a 40Hz');
     } catch (error) {
       console.error('Error sending DMX levels', error);
@@ -47,3 +47,12 @@ export interface DmxPortInfo {
   product?: string;
   serial_number?: string;
 }
+
+export interface DmxUniverseInfo {
+  universe: number;
+  port_path?: string;
+  refresh_hz: number;
+  timing: { break_us: number; mab_us: number };
+  master: number;
+  running: boolean;
+}
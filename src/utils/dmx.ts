@@ -1,10 +1,28 @@
 import { invoke } from '@tauri-apps/api/core';
-import type { DmxPortInfo } from '../types';
+import type { DmxPortInfo, DmxUniverseInfo } from '../types';
+
+export const DEFAULT_DMX_UNIVERSE = 1;
 
 export async function listDmxPorts(): Promise<DmxPortInfo[]> {
   return invoke('dmx_list_ports');
 }
 
-export async function sendDmxLevels(portPath: string, levels: number[]): Promise<void> {
-  return invoke('dmx_set_levels', { port_path: portPath, levels });
+export async function assignDmxPort(universe: number, portPath: string): Promise<void> {
+  return invoke('dmx_set_port', { universe, port_path: portPath });
+}
+
+export async function sendDmxLevels(
+  universe: number,
+  levels: number[],
+  fadeMs?: number,
+): Promise<void> {
+  return invoke('dmx_set_levels', { universe, levels, fade_ms: fadeMs });
+}
+
+export async function removeDmxUniverse(universe: number): Promise<void> {
+  return invoke('dmx_remove_universe', { universe });
+}
+
+export async function listDmxUniverses(): Promise<DmxUniverseInfo[]> {
+  return invoke('dmx_list_universes');
 }